@@ -0,0 +1,15 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Migrations are built into the crate by `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=migrations");
+
+    // Code of gRPC service is generated only if it's requested
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/notebook.proto");
+
+        let fds = protox::compile(["proto/notebook.proto"], ["proto"])?;
+        tonic_build::configure().compile_fds(fds)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS templates (
+    id SERIAL,
+    template_name TEXT UNIQUE NOT NULL,
+    template TEXT NOT NULL,
+    PRIMARY KEY (id)
+)
@@ -0,0 +1 @@
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS due_at TIMESTAMPTZ
@@ -0,0 +1,58 @@
+-- Bodies of revisions addressed by SHA-256 of the content.
+-- If `base_hash` is set, `body` is a delta against the body of `base_hash`.
+CREATE TABLE IF NOT EXISTS note_bodies (
+    hash TEXT NOT NULL,
+    base_hash TEXT REFERENCES note_bodies (hash),
+    body TEXT NOT NULL,
+    size INT8 NOT NULL,
+    PRIMARY KEY (hash)
+);
+
+CREATE TABLE IF NOT EXISTS note_revisions (
+    id SERIAL,
+    note_id INT4 NOT NULL REFERENCES notebook (id) ON DELETE CASCADE,
+    body_hash TEXT NOT NULL REFERENCES note_bodies (hash),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS note_revisions_note_id ON note_revisions (note_id, id);
+
+CREATE OR REPLACE FUNCTION record_note_revision() RETURNS trigger AS $$
+DECLARE
+    content TEXT := COALESCE(NEW.note, '');
+    content_hash TEXT := encode(sha256(convert_to(content, 'UTF8')), 'hex');
+BEGIN
+    INSERT INTO note_bodies (hash, body, size)
+    VALUES (content_hash, content, octet_length(content))
+    ON CONFLICT (hash) DO NOTHING;
+
+    INSERT INTO note_revisions (note_id, body_hash)
+    VALUES (NEW.id, content_hash);
+
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER notebook_insert_revision
+AFTER INSERT ON notebook
+FOR EACH ROW EXECUTE FUNCTION record_note_revision();
+
+CREATE OR REPLACE TRIGGER notebook_update_revision
+AFTER UPDATE OF note ON notebook
+FOR EACH ROW WHEN (OLD.note IS DISTINCT FROM NEW.note)
+EXECUTE FUNCTION record_note_revision();
+
+-- Existing notes start their history with the current content
+INSERT INTO note_bodies (hash, body, size)
+SELECT DISTINCT
+    encode(sha256(convert_to(COALESCE(note, ''), 'UTF8')), 'hex'),
+    COALESCE(note, ''),
+    octet_length(COALESCE(note, ''))
+FROM notebook
+ON CONFLICT (hash) DO NOTHING;
+
+INSERT INTO note_revisions (note_id, body_hash)
+SELECT id, encode(sha256(convert_to(COALESCE(note, ''), 'UTF8')), 'hex')
+FROM notebook
+WHERE NOT EXISTS (SELECT 1 FROM note_revisions WHERE note_revisions.note_id = notebook.id);
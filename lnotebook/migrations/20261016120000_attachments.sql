@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS attachments (
+    id SERIAL,
+    note_id INT NOT NULL REFERENCES notebook (id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    data BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id),
+    UNIQUE (note_id, file_name)
+)
@@ -0,0 +1,38 @@
+-- Every change of notebook is sent to channel `notebook_changes` as JSON:
+-- {"event": "insert" | "update" | "delete", "id": 1, "name": "note", "version": 3, "actor": "user"}
+-- `version` is the number of revisions of the note (NULL for deleted notes).
+-- Payload of NOTIFY must be shorter than 8000 bytes, so if it's too long (because of long name),
+-- only event and ID are sent with `"refetch": true`, and listener should read the note itself.
+CREATE OR REPLACE FUNCTION notify_note_change() RETURNS trigger AS $$
+DECLARE
+    changed notebook := COALESCE(NEW, OLD);
+    payload TEXT;
+BEGIN
+    payload := json_build_object(
+        'event', lower(TG_OP),
+        'id', changed.id,
+        'name', changed.note_name,
+        'version', CASE WHEN TG_OP = 'DELETE' THEN NULL
+            ELSE (SELECT COUNT(*) FROM note_revisions WHERE note_id = changed.id) END,
+        'actor', current_user
+    )::TEXT;
+
+    IF octet_length(payload) >= 8000 THEN
+        payload := json_build_object(
+            'event', lower(TG_OP),
+            'id', changed.id,
+            'refetch', true
+        )::TEXT;
+    END IF;
+
+    PERFORM pg_notify('notebook_changes', payload);
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+-- Triggers are fired in order of their names, so this one is fired after
+-- `notebook_*_revision` triggers and `version` includes the new revision
+CREATE OR REPLACE TRIGGER notify_note_change
+AFTER INSERT OR UPDATE OR DELETE ON notebook
+FOR EACH ROW EXECUTE FUNCTION notify_note_change();
@@ -0,0 +1 @@
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS passphrase_hash TEXT
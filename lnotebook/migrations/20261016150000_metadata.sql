@@ -0,0 +1 @@
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}'
@@ -0,0 +1,11 @@
+-- Every mutating command executed by the notebook: who (database and OS user), when and what.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id SERIAL PRIMARY KEY,
+    at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    db_user TEXT NOT NULL DEFAULT session_user,
+    os_user TEXT,
+    command TEXT NOT NULL,
+    target TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_at_idx ON audit_log (at);
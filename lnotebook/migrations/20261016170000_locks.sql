@@ -0,0 +1,8 @@
+-- Locks of notes taken by `lock-note`. They are kept until `unlock-note`,
+-- so they outlive the session (and process) that took them.
+CREATE TABLE IF NOT EXISTS note_locks (
+    note_id INT4 NOT NULL REFERENCES notebook (id) ON DELETE CASCADE,
+    locked_by TEXT NOT NULL,
+    locked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (note_id)
+);
@@ -0,0 +1,3 @@
+-- When reminder about deadline of note was sent by `remind-daemon`.
+-- It's reset when deadline is changed, so every deadline is reminded once.
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS reminded_at TIMESTAMPTZ;
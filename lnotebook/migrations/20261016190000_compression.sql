@@ -0,0 +1,52 @@
+-- Contents of revisions larger than the threshold of `compact` are compressed with zstd.
+-- If `compressed` is set, `body_zstd` is compressed `body` and `body` is empty.
+ALTER TABLE note_bodies ADD COLUMN IF NOT EXISTS compressed BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE note_bodies ADD COLUMN IF NOT EXISTS body_zstd BYTEA;
+
+-- Content of notes larger than the threshold is compressed with zstd when it's written.
+-- If `compressed` is set, `note_bytes` is compressed `note` and `note` is NULL.
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS compressed BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS note_bytes BYTEA;
+ALTER TABLE notebook DROP CONSTRAINT IF EXISTS notebook_compressed;
+ALTER TABLE notebook ADD CONSTRAINT notebook_compressed
+    CHECK (NOT compressed OR (note IS NULL AND note_bytes IS NOT NULL));
+
+-- Content of compressed note is decompressed before it's changed and compressed again after it,
+-- so changes of `compressed` never change content: they don't add revisions and are not sent
+-- to listeners.
+CREATE OR REPLACE TRIGGER notebook_update_revision
+AFTER UPDATE OF note ON notebook
+FOR EACH ROW WHEN (OLD.note IS DISTINCT FROM NEW.note AND OLD.compressed = NEW.compressed)
+EXECUTE FUNCTION record_note_revision();
+
+CREATE OR REPLACE FUNCTION notify_note_change() RETURNS trigger AS $$
+DECLARE
+    changed notebook := COALESCE(NEW, OLD);
+    payload TEXT;
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.compressed <> OLD.compressed THEN
+        RETURN NULL;
+    END IF;
+
+    payload := json_build_object(
+        'event', lower(TG_OP),
+        'id', changed.id,
+        'name', changed.note_name,
+        'version', CASE WHEN TG_OP = 'DELETE' THEN NULL
+            ELSE (SELECT COUNT(*) FROM note_revisions WHERE note_id = changed.id) END,
+        'actor', current_user
+    )::TEXT;
+
+    IF octet_length(payload) >= 8000 THEN
+        payload := json_build_object(
+            'event', lower(TG_OP),
+            'id', changed.id,
+            'refetch', true
+        )::TEXT;
+    END IF;
+
+    PERFORM pg_notify('notebook_changes', payload);
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
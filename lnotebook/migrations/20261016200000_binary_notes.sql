@@ -0,0 +1,4 @@
+-- Binary notes added with `add_binary` keep their content in `note_bytes` (like compressed notes),
+-- and `note` of binary notes is NULL.
+ALTER TABLE notebook DROP CONSTRAINT IF EXISTS notebook_text_or_binary;
+ALTER TABLE notebook ADD CONSTRAINT notebook_text_or_binary CHECK (note IS NULL OR note_bytes IS NULL);
@@ -0,0 +1,64 @@
+-- Time of the last change of note, used by `sync` to find which notes changed since the last sync.
+-- It's set to the current time on every change, unless the change sets it itself
+-- (like `sync` copying note from the other notebook), or restored dump has no such column.
+-- Compressing and decompressing content of note are not changes.
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE OR REPLACE FUNCTION touch_note() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        NEW.updated_at := COALESCE(NEW.updated_at, now());
+    ELSIF NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+        AND NEW.compressed = OLD.compressed
+        AND (NEW.note_name, NEW.note, NEW.note_bytes) IS DISTINCT FROM (OLD.note_name, OLD.note, OLD.note_bytes) THEN
+        NEW.updated_at := now();
+    END IF;
+
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER notebook_touch
+BEFORE INSERT OR UPDATE ON notebook
+FOR EACH ROW EXECUTE FUNCTION touch_note();
+
+-- Names of deleted (or renamed) notes, so `sync` deletes them in the other notebook
+-- instead of copying them back. Tombstone is removed when note with the name is added again.
+CREATE TABLE IF NOT EXISTS note_tombstones (
+    note_name TEXT NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (note_name)
+);
+
+CREATE OR REPLACE FUNCTION record_note_tombstone() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('DELETE', 'UPDATE') THEN
+        INSERT INTO note_tombstones (note_name)
+        VALUES (OLD.note_name)
+        ON CONFLICT (note_name) DO UPDATE SET deleted_at = now();
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        DELETE FROM note_tombstones WHERE note_name = NEW.note_name;
+    END IF;
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER notebook_tombstone
+AFTER INSERT OR DELETE ON notebook
+FOR EACH ROW EXECUTE FUNCTION record_note_tombstone();
+
+CREATE OR REPLACE TRIGGER notebook_rename_tombstone
+AFTER UPDATE OF note_name ON notebook
+FOR EACH ROW WHEN (OLD.note_name IS DISTINCT FROM NEW.note_name)
+EXECUTE FUNCTION record_note_tombstone();
+
+-- Time of the last sync with every remote notebook, by clocks of both databases.
+CREATE TABLE IF NOT EXISTS sync_state (
+    remote TEXT NOT NULL,
+    local_synced_at TIMESTAMPTZ NOT NULL,
+    remote_synced_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (remote)
+);
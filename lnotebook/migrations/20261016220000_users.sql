@@ -0,0 +1,14 @@
+-- Users of notebook shared by several people, created by `add-user`.
+CREATE TABLE IF NOT EXISTS users (
+    name TEXT NOT NULL,
+    admin BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (name)
+);
+
+-- Owner of note, only the owner (and admins) can see and change it.
+-- Notes without owner are shared by everyone, so notes added before users keep working.
+ALTER TABLE notebook ADD COLUMN IF NOT EXISTS owner TEXT
+    REFERENCES users (name) ON UPDATE CASCADE ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS notebook_owner_idx ON notebook (owner);
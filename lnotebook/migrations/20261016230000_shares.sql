@@ -0,0 +1,11 @@
+-- Notes shared by their owners with other users by `share-note`.
+-- Users can read shared notes, and change them unless they are shared read-only.
+CREATE TABLE IF NOT EXISTS note_shares (
+    note_id INT4 NOT NULL REFERENCES notebook (id) ON DELETE CASCADE,
+    user_name TEXT NOT NULL REFERENCES users (name) ON UPDATE CASCADE ON DELETE CASCADE,
+    read_only BOOLEAN NOT NULL DEFAULT false,
+    shared_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (note_id, user_name)
+);
+
+CREATE INDEX IF NOT EXISTS note_shares_user_name_idx ON note_shares (user_name);
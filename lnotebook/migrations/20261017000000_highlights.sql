@@ -0,0 +1,12 @@
+-- Excerpts of notes highlighted by `highlight`, kept even if the note is changed later.
+CREATE TABLE IF NOT EXISTS highlights (
+    id SERIAL,
+    note_id INT4 NOT NULL REFERENCES notebook (id) ON DELETE CASCADE,
+    start_line INT4 NOT NULL,
+    end_line INT4 NOT NULL,
+    excerpt TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS highlights_note_id_idx ON highlights (note_id);
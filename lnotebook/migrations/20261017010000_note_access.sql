@@ -0,0 +1,14 @@
+-- Returns `true` if user `$3` can read note with ID `$1` owned by `$2`, or change it if `$4` is set.
+-- Commands that list or change many notes filter them with it (read more in `users` module).
+-- NULL user means the notebook doesn't act as any user, so it can access every note.
+CREATE OR REPLACE FUNCTION can_access_note(INT4, TEXT, TEXT, BOOLEAN) RETURNS BOOLEAN AS $$
+    SELECT $3 IS NULL
+        OR $2 IS NULL
+        OR $2 = $3
+        OR EXISTS (SELECT 1 FROM users WHERE name = $3 AND admin)
+        OR EXISTS (
+            SELECT 1
+            FROM note_shares
+            WHERE note_id = $1 AND user_name = $3 AND NOT ($4 AND read_only)
+        )
+$$ LANGUAGE sql STABLE;
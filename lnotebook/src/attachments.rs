@@ -0,0 +1,239 @@
+//! Files attached to notes.
+//!
+//! Any file (screenshot, PDF and so on) can be attached to note with [`attach`].
+//! Files are stored in the database together with notes, so they are deleted with the note.
+//! Every file attached to note must have its own name.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::attachments::*;
+//! # use lnotebook::commands::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! # use std::path::Path;
+//! async fn attachments_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("receipts", "Receipts for July", pool).await?;
+//!     attach("receipts", Path::new("scans/shop.pdf"), pool).await?;
+//!
+//!     for attachment in attachments("receipts", pool).await? {
+//!         println!("{}", attachment);
+//!     }
+//!
+//!     save_attachment("receipts", "shop.pdf", Path::new("/tmp"), pool).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::{self, NotebookError};
+use crate::protect;
+use crate::sql::{query_as, query_scalar};
+use crate::users::{self, Access};
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{self, PgPool};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use tracing::{event, Level};
+
+/// This is a `struct` that containing information about file attached to note.
+pub struct Attachment {
+    pub id: i32,
+    pub file_name: String,
+    /// Size of file in bytes
+    pub size: i64,
+    pub created_at: DateTime<Local>,
+}
+
+impl fmt::Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} bytes  {}",
+            self.file_name,
+            self.size,
+            self.created_at.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+/// Attaches file `path` to note `notename`. Attachment is named as the file.
+/// ### Returns
+/// * Ok
+///     * [`Attachment`] that was added
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::AlreadyTaken`] error if file with the same name is already attached to note
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn attach(
+    notename: &str,
+    path: &Path,
+    pool: &PgPool,
+) -> Result<Attachment, NotebookError> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| {
+            NotebookError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("`{}` is not a file", path.display()),
+            ))
+        })?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+    let data = fs::read(path)?;
+
+    let row = query_as!(
+        { id: i32, file_name: String, size: i64, created_at: DateTime<Utc> },
+        r#"
+INSERT INTO attachments (note_id, file_name, data)
+SELECT id, $2, $3
+FROM notebook
+WHERE note_name = $1
+RETURNING id, file_name, octet_length(data)::INT8 AS "size!", created_at
+        "#,
+        notename,
+        file_name,
+        data
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| {
+        if errors::is_unique_violation(&err) {
+            NotebookError::AlreadyTaken {
+                notename: file_name.to_owned(),
+            }
+        } else {
+            err.into()
+        }
+    })?;
+
+    event!(
+        Level::INFO,
+        "Attach `{}` ({} bytes) to note `{}`",
+        row.file_name,
+        row.size,
+        notename
+    );
+
+    Ok(Attachment {
+        id: row.id,
+        file_name: row.file_name,
+        size: row.size,
+        created_at: row.created_at.with_timezone(&Local),
+    })
+}
+
+/// Returns files attached to note `notename` ordered by name.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`Attachment`]
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn attachments(notename: &str, pool: &PgPool) -> Result<Vec<Attachment>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let rows = query_as!(
+        { id: i32, file_name: String, size: i64, created_at: DateTime<Utc> },
+        r#"
+SELECT attachments.id, file_name, octet_length(data)::INT8 AS "size!", created_at
+FROM attachments
+JOIN notebook ON notebook.id = attachments.note_id
+WHERE notebook.note_name = $1
+ORDER BY file_name
+        "#,
+        notename
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Attachment {
+            id: row.id,
+            file_name: row.file_name,
+            size: row.size,
+            created_at: row.created_at.with_timezone(&Local),
+        })
+        .collect())
+}
+
+/// Returns content of file `file_name` attached to note `notename`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_attachment(
+    notename: &str,
+    file_name: &str,
+    pool: &PgPool,
+) -> Result<Vec<u8>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let data = query_scalar!(
+        Vec<u8>,
+        "
+SELECT data
+FROM attachments
+JOIN notebook ON notebook.id = attachments.note_id
+WHERE notebook.note_name = $1 AND file_name = $2
+        ",
+        notename,
+        file_name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(data)
+}
+
+/// Saves file `file_name` attached to note `notename` into `dest`.
+///
+/// If `dest` is a directory, file is saved into it with its name.
+/// ### Returns
+/// * Ok
+///     * Path of saved file
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be written
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn save_attachment(
+    notename: &str,
+    file_name: &str,
+    dest: &Path,
+    pool: &PgPool,
+) -> Result<PathBuf, NotebookError> {
+    let data = select_attachment(notename, file_name, pool).await?;
+
+    let path = if dest.is_dir() {
+        dest.join(file_name)
+    } else {
+        dest.to_owned()
+    };
+    fs::write(&path, &data)?;
+
+    event!(
+        Level::INFO,
+        "Save `{}` of note `{}` into `{}`",
+        file_name,
+        notename,
+        path.display()
+    );
+
+    Ok(path)
+}
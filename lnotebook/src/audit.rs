@@ -0,0 +1,170 @@
+//! Audit log of changes.
+//!
+//! Every mutating command executed by [`Notebook`][crate::notebook::Notebook] (so every command
+//! of CLI that changes notes, and changes made over HTTP server, gRPC service,
+//! terminal interface and by replaying [offline queue][crate::offline]) is recorded in table
+//! `audit_log` of the notebook database:
+//! when it was executed, by whom (database user and user of operating system) and what it was
+//! (name of command and the note, pattern or file it was given). Content of notes is never
+//! recorded. Commands that failed are not recorded.
+//!
+//! CLI displays the log with `audit [--since <date>]`, where `date` is written like deadlines
+//! (read about them in [`due` module][crate::due]), but `2024-07-20` means the start of the day.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::audit::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn audit_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     for entry in audit(Some(parse_since("2024-07-20")?), pool).await? {
+//!         println!("{entry}");
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::config;
+use crate::due;
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use sqlx::{self, PgPool};
+use std::fmt;
+
+/// This is a `struct` that containing one recorded command.
+///
+/// It implements [`fmt::Display`], which renders it as `date  user  command  target`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub at: DateTime<Local>,
+    /// Database user that executed command
+    pub db_user: String,
+    /// User that executed command (`LNOTEBOOK_USER` or user of operating system), if it's known
+    pub os_user: Option<String>,
+    /// Name of CLI command, like `upd-note`
+    pub command: String,
+    /// Note, pattern or file that command was given
+    pub target: Option<String>,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let user = match &self.os_user {
+            Some(os_user) => format!("{os_user} ({})", self.db_user),
+            None => self.db_user.to_owned(),
+        };
+
+        write!(
+            f,
+            "{}  {}  {}",
+            self.at.format("%Y-%m-%d %H:%M:%S"),
+            user,
+            self.command
+        )?;
+        if let Some(target) = &self.target {
+            write!(f, "  {target}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses date from which [`audit`] returns entries.
+///
+/// It's parsed like deadline (read about formats in [`due` module][crate::due]),
+/// but date without time means the start of the day.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::InvalidDate`] error if date can't be parsed
+/// ### Example
+/// ```
+/// # use lnotebook::audit::parse_since;
+/// use chrono::{Local, TimeZone};
+///
+/// let since = parse_since("2024-07-20").unwrap();
+///
+/// assert_eq!(Local.with_ymd_and_hms(2024, 7, 20, 0, 0, 0).unwrap(), since);
+/// ```
+pub fn parse_since(date: &str) -> Result<DateTime<Local>, NotebookError> {
+    match NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") {
+        Ok(day) => Local
+            .from_local_datetime(&day.and_time(Default::default()))
+            .earliest()
+            .ok_or_else(|| NotebookError::InvalidDate {
+                date: date.trim().to_owned(),
+            }),
+        Err(_) => due::parse_due(date),
+    }
+}
+
+/// Records that `command` was executed with `target`.
+///
+/// [`Notebook`][crate::notebook::Notebook] records commands itself, so use it only for changes
+/// that are made without it.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn record(
+    command: &str,
+    target: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    query!(
+        "
+INSERT INTO audit_log (os_user, command, target)
+VALUES ($1, $2, $3)
+        ",
+        config::user(),
+        command,
+        target
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns recorded commands from the oldest to the newest, only ones executed
+/// since `since` if it's `Some`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn audit(
+    since: Option<DateTime<Local>>,
+    pool: &PgPool,
+) -> Result<Vec<AuditEntry>, NotebookError> {
+    let rows = query_as!(
+        {
+            id: i32,
+            at: DateTime<Utc>,
+            db_user: String,
+            os_user: Option<String>,
+            command: String,
+            target: Option<String>,
+        },
+        "
+SELECT id, at, db_user, os_user, command, target
+FROM audit_log
+WHERE $1::TIMESTAMPTZ IS NULL OR at >= $1
+ORDER BY at, id
+        ",
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| AuditEntry {
+            id: row.id,
+            at: row.at.with_timezone(&Local),
+            db_user: row.db_user,
+            os_user: row.os_user,
+            command: row.command,
+            target: row.target,
+        })
+        .collect())
+}
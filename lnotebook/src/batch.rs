@@ -0,0 +1,176 @@
+//! Operations on many notes at once.
+//!
+//! Batch operations don't stop on the first note that can't be processed. Instead they
+//! return [`BatchReport`] that says what happened to every note:
+//! * succeeded - note was processed
+//! * skipped - nothing had to be done, for example the same note already exists
+//! * failed - note can't be processed, with the error why
+//!
+//! If you need the whole batch to be applied or not applied at all, use `fail_fast`:
+//! batch is processed in one transaction and the first error is returned, so no notes are changed.
+
+use crate::compression;
+use crate::config;
+use crate::errors::NotebookError;
+use crate::notename::NoteName;
+use crate::sql::query_as;
+use crate::users;
+
+use sqlx::{self, PgConnection, PgPool};
+use std::fmt;
+use tracing::{event, Level};
+
+/// This is a `struct` that containing result of batch operation for every note.
+///
+/// Read about batch operations [here][crate::batch].
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Notenames of notes that were processed
+    pub succeeded: Vec<String>,
+    /// Notenames of notes that didn't have to be processed
+    pub skipped: Vec<String>,
+    /// Notenames of notes that can't be processed with the errors why
+    pub failed: Vec<(String, NotebookError)>,
+}
+
+impl BatchReport {
+    /// Returns `true` if no notes failed.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} succeeded, {} skipped, {} failed",
+            self.succeeded.len(),
+            self.skipped.len(),
+            self.failed.len()
+        )?;
+
+        for (notename, err) in &self.failed {
+            write!(f, "\n  `{notename}`: {err}")?;
+        }
+
+        Ok(())
+    }
+}
+
+enum Outcome {
+    Added,
+    Exists,
+}
+
+async fn add_one(
+    notename: &str,
+    note: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<Outcome, NotebookError> {
+    NoteName::new(notename)?;
+    config::check_size(note.map_or(0, str::len))?;
+
+    let added = query_as!(
+        { id: i32 },
+        "
+INSERT INTO notebook (note_name, note, owner)
+VALUES ( $1, $2, $3 )
+ON CONFLICT (note_name) DO NOTHING
+RETURNING id
+        ",
+        notename,
+        note,
+        users::acting_user()
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(added) = added {
+        compression::pack(added.id, note, conn).await?;
+        return Ok(Outcome::Added);
+    }
+
+    let existing = query_as!(
+        { note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $1
+        ",
+        notename
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if compression::content(notename, existing.note, existing.packed)?.as_deref() == note {
+        Ok(Outcome::Exists)
+    } else {
+        Err(NotebookError::AlreadyTaken {
+            notename: notename.to_owned(),
+        })
+    }
+}
+
+/// Adds many notes into notebook.
+///
+/// Notes which already exist with the same content are skipped. Read more about batch operations
+/// and `fail_fast` [here][crate::batch].
+/// ### Returns
+/// * Ok
+///     * [`BatchReport`] about every note
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `fail_fast` is `true` and notename is not valid
+///     * [`NotebookError::NoteTooLarge`] error if `fail_fast` is `true` and note is larger than
+///       [`max_note_size`][crate::config::max_note_size]
+///     * [`NotebookError::AlreadyTaken`] error if `fail_fast` is `true` and note with the same name,
+///       but other content already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::batch::add_many;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn add_many_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let notes = [("milk", Some("2 bottles")), ("bread", None)];
+///
+///     let report = add_many(notes, false, pool).await?;
+///     println!("{report}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn add_many<'a>(
+    notes: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    fail_fast: bool,
+    pool: &PgPool,
+) -> Result<BatchReport, NotebookError> {
+    // Iterator is collected before anything is awaited, so the future stays `Send` whatever
+    // closures the iterator is built with
+    let notes: Vec<_> = notes.into_iter().collect();
+    let mut report = BatchReport::default();
+
+    if fail_fast {
+        let mut tx = pool.begin().await?;
+        for (notename, note) in notes {
+            match add_one(notename, note, &mut tx).await? {
+                Outcome::Added => report.succeeded.push(notename.to_owned()),
+                Outcome::Exists => report.skipped.push(notename.to_owned()),
+            }
+        }
+        tx.commit().await?;
+    } else {
+        let mut conn = pool.acquire().await?;
+        for (notename, note) in notes {
+            match add_one(notename, note, &mut conn).await {
+                Ok(Outcome::Added) => report.succeeded.push(notename.to_owned()),
+                Ok(Outcome::Exists) => report.skipped.push(notename.to_owned()),
+                Err(err) => report.failed.push((notename.to_owned(), err)),
+            }
+        }
+    }
+
+    event!(Level::INFO, "Add notes: {}", report);
+
+    Ok(report)
+}
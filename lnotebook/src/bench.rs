@@ -0,0 +1,237 @@
+//! Benchmark of the notebook on the connected database.
+//!
+//! [`bench`] adds synthetic notes named with prefix [`BENCH_PREFIX`], measures how long
+//! operations take on them and deletes them at the end:
+//! * `add` - adding of every note with [`commands::add`]
+//! * `list` - [`commands::list`] of all notes
+//! * `search` - [`search::search`] that matches every synthetic note
+//! * `export` - reading of all notes and rendering them into JSON, as `--format json` does
+//!
+//! Result is [`BenchReport`] with number of operations, throughput and percentiles of latency
+//! for every operation. It can be used to check setup of the database, and to compare results
+//! before and after changes of the notebook.
+//!
+//! CLI runs benchmark with `bench [--notes <n>] [--runs <n>]`.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::bench::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn bench_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let report = bench(1000, 20, pool).await?;
+//!
+//!     println!("{report}");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{self, NamePattern};
+use crate::errors::NotebookError;
+use crate::render::{Json, NoteRenderer};
+use crate::search;
+
+use sqlx::PgPool;
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+/// Prefix of names of synthetic notes.
+pub const BENCH_PREFIX: &str = "lnotebook-bench-";
+
+/// Word contained in every synthetic note, used by `search`.
+const BENCH_WORD: &str = "benchmark";
+
+/// This is a `struct` that containing results of one benchmarked operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationStats {
+    /// Name of operation
+    pub name: String,
+    /// Number of measured operations
+    pub count: usize,
+    /// Total duration of all operations
+    pub total: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl OperationStats {
+    /// Returns statistics of operation `name` from durations of its executions.
+    /// ### Example
+    /// ```
+    /// # use lnotebook::bench::*;
+    /// # use std::time::Duration;
+    /// let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+    /// let stats = OperationStats::from_samples("add", samples);
+    ///
+    /// assert_eq!(Duration::from_millis(50), stats.p50);
+    /// assert_eq!(Duration::from_millis(95), stats.p95);
+    /// assert_eq!(Duration::from_millis(100), stats.max);
+    /// assert_eq!(20.0, stats.ops_per_sec().round());
+    /// ```
+    pub fn from_samples(name: &str, mut samples: Vec<Duration>) -> OperationStats {
+        samples.sort_unstable();
+
+        // Nearest-rank percentile
+        let percentile = |p: usize| {
+            let rank = (samples.len() * p).div_ceil(100).max(1);
+            samples.get(rank - 1).copied().unwrap_or_default()
+        };
+
+        OperationStats {
+            name: name.to_owned(),
+            count: samples.len(),
+            total: samples.iter().sum(),
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Returns number of operations per second.
+    pub fn ops_per_sec(&self) -> f64 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+
+        self.count as f64 / self.total.as_secs_f64()
+    }
+}
+
+/// This is a `struct` that containing results of [`bench`].
+///
+/// It implements [`fmt::Display`], which renders results as a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchReport {
+    /// Number of synthetic notes
+    pub notes: usize,
+    /// Results of operations in the order they were run
+    pub operations: Vec<OperationStats>,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        writeln!(f, "Notes: {}", self.notes)?;
+        write!(
+            f,
+            "{:<9}  {:>6}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+            "Operation", "Count", "Ops/sec", "p50", "p95", "p99", "Max"
+        )?;
+        for op in &self.operations {
+            write!(
+                f,
+                "\n{:<9}  {:>6}  {:>10.1}  {:>8.2}ms  {:>8.2}ms  {:>8.2}ms  {:>8.2}ms",
+                op.name,
+                op.count,
+                op.ops_per_sec(),
+                ms(op.p50),
+                ms(op.p95),
+                ms(op.p99),
+                ms(op.max)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns content of synthetic note number `i`: about 1 KB of text in several lines.
+fn synthetic_note(i: usize) -> String {
+    let mut note = format!("Synthetic {BENCH_WORD} note {i}\n");
+    for line in 0..16 {
+        note += &format!("Line {line} of note {i}: the quick brown fox jumps over the lazy dog.\n");
+    }
+
+    note
+}
+
+/// Runs `op` `runs` times and returns duration of every run.
+async fn measure<F, Fut, T>(runs: usize, mut op: F) -> Result<Vec<Duration>, NotebookError>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<T, NotebookError>>,
+{
+    let mut samples = Vec::with_capacity(runs);
+    for i in 0..runs {
+        let start = Instant::now();
+        op(i).await?;
+        samples.push(start.elapsed());
+    }
+
+    Ok(samples)
+}
+
+/// Adds `notes` synthetic notes, measures operations on them and deletes them.
+///
+/// `add` is measured once for every note, other operations are measured `runs` times.
+/// Synthetic notes are deleted even if benchmark fails.
+/// ### Returns
+/// * Ok
+///     * [`BenchReport`] with results of all operations
+/// * Errors
+///     * [`NotebookError::AlreadyTaken`] error if notebook already has notes
+///       with prefix [`BENCH_PREFIX`] (for example, left by interrupted benchmark)
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn bench(notes: usize, runs: usize, pool: &PgPool) -> Result<BenchReport, NotebookError> {
+    let pattern = NamePattern::Glob(format!("{BENCH_PREFIX}*"));
+
+    if let Some(notename) = commands::select_matching(&pattern, pool)
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Err(NotebookError::AlreadyTaken { notename });
+    }
+
+    event!(Level::INFO, "Run benchmark with {} notes", notes);
+
+    let res = run(notes, runs, pool).await;
+
+    let deleted = commands::del_matching(&pattern, pool).await?;
+    event!(
+        Level::INFO,
+        "Benchmark is finished, {} synthetic notes are deleted",
+        deleted.len()
+    );
+
+    Ok(BenchReport {
+        notes,
+        operations: res?,
+    })
+}
+
+async fn run(
+    notes: usize,
+    runs: usize,
+    pool: &PgPool,
+) -> Result<Vec<OperationStats>, NotebookError> {
+    let add = measure(notes, |i| async move {
+        commands::add(&format!("{BENCH_PREFIX}{i:06}"), &synthetic_note(i), pool).await
+    })
+    .await?;
+
+    let list = measure(runs, |_| commands::list(pool)).await?;
+
+    let search = measure(runs, |_| search::search(BENCH_WORD, pool)).await?;
+
+    let export = measure(runs, |_| async {
+        let notes = commands::select_all(pool).await?;
+        Ok(Json.render_notes(&notes))
+    })
+    .await?;
+
+    Ok(vec![
+        OperationStats::from_samples("add", add),
+        OperationStats::from_samples("list", list),
+        OperationStats::from_samples("search", search),
+        OperationStats::from_samples("export", export),
+    ])
+}
@@ -0,0 +1,201 @@
+//! Blocking API for programs that are not asynchronous.
+//!
+//! [`Notebook`] of this module owns its own `tokio` runtime and runs async functions
+//! of the notebook on it, so small CLI tools and GUI apps can use the notebook without setting up
+//! runtime themselves. It has blocking versions of the most used functions of
+//! [`commands` module][crate::commands] and of [`Notebook::execute`][crate::notebook::Notebook::execute]
+//! for all other commands. Any other async function can be run with [`Notebook::block_on`]
+//! and database of the notebook ([`Notebook::pool`]).
+//!
+//! Runtime runs on the thread that calls the notebook, and only while it's called. Methods panic
+//! if they are called from async code (inside other runtime), use the async API there.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::blocking::{self, Notebook};
+//! # use lnotebook::{ConnectOptions, NotebookError};
+//! fn blocking_example() -> Result<(), NotebookError> {
+//!     let notebook = Notebook::connect(&blocking::get_db_url()?, ConnectOptions::default())?;
+//!
+//!     notebook.add("shopping", "milk")?;
+//!     let note = notebook.append("shopping", "\nbread")?;
+//!
+//!     assert_eq!("milk\nbread", note.body());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{self, Note, NoteSummary};
+use crate::errors::NotebookError;
+use crate::notebook::{self, CommandRequest, CommandResponse};
+use crate::pool::{self, ConnectOptions};
+use crate::users;
+
+use sqlx::PgPool;
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+/// Blocking version of [`get_db_url`][crate::get_db_url].
+pub fn get_db_url() -> Result<String, NotebookError> {
+    runtime()?.block_on(crate::get_db_url())
+}
+
+fn runtime() -> Result<Runtime, NotebookError> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
+
+/// Notebook with blocking methods, read more [here][crate::blocking].
+pub struct Notebook {
+    notebook: notebook::Notebook,
+    user: Option<String>,
+    // Declared after the notebook, so connections are closed before runtime is shut down
+    runtime: Runtime,
+}
+
+impl Notebook {
+    /// Connects to database `url` like [`connect`][crate::connect] and creates notebook that
+    /// stores notes in it.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Io`][NotebookError] error from [`std::io::Error`] if runtime
+    ///       can't be created
+    ///     * Errors of [`connect`][crate::connect]
+    pub fn connect(url: &str, options: ConnectOptions) -> Result<Notebook, NotebookError> {
+        let runtime = runtime()?;
+        let pool = runtime.block_on(pool::connect(url, options))?;
+
+        Ok(Notebook {
+            notebook: notebook::Notebook::new(pool),
+            user: None,
+            runtime,
+        })
+    }
+
+    /// Returns database of notebook.
+    pub fn pool(&self) -> &PgPool {
+        self.notebook.pool()
+    }
+
+    /// Adds hook that is called after every request executed by [`Notebook::execute`],
+    /// like [`on_command`][crate::notebook::Notebook::on_command] does.
+    pub fn on_command(
+        mut self,
+        hook: impl Fn(&CommandRequest, &Result<CommandResponse, NotebookError>) + Send + Sync + 'static,
+    ) -> Notebook {
+        self.notebook = self.notebook.on_command(hook);
+        self
+    }
+
+    /// Makes notebook act as user `user` in all its methods, read more [here][crate::users].
+    pub fn as_user(mut self, user: impl Into<String>) -> Notebook {
+        let user = user.into();
+        self.notebook = self.notebook.as_user(user.clone());
+        self.user = Some(user);
+        self
+    }
+
+    /// Runs `future` to completion and returns its output.
+    ///
+    /// Future acts as the user of notebook if it's set by [`Notebook::as_user`].
+    /// ### Example
+    /// ```rust,no_run
+    /// # use lnotebook::blocking::Notebook;
+    /// # use lnotebook::{search, NotebookError};
+    /// fn block_on_example(notebook: &Notebook) -> Result<(), NotebookError> {
+    ///     let found = notebook.block_on(search::search("milk", notebook.pool()))?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match &self.user {
+            Some(user) => self.runtime.block_on(users::as_user(user, future)),
+            None => self.runtime.block_on(future),
+        }
+    }
+
+    /// Blocking version of [`Notebook::execute`][crate::notebook::Notebook::execute].
+    pub fn execute(&self, request: CommandRequest) -> Result<CommandResponse, NotebookError> {
+        self.runtime.block_on(self.notebook.execute(request))
+    }
+
+    /// Blocking version of [`Notebook::name`][crate::notebook::Notebook::name].
+    pub fn name(&self) -> Result<String, NotebookError> {
+        self.runtime.block_on(self.notebook.name())
+    }
+
+    /// Blocking version of [`commands::add`].
+    pub fn add(&self, notename: &str, note: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::add(notename, note, self.pool()))
+    }
+
+    /// Blocking version of [`commands::upd`].
+    pub fn upd(&self, notename: &str, new_note: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::upd(notename, new_note, self.pool()))
+    }
+
+    /// Blocking version of [`commands::append`].
+    pub fn append(&self, notename: &str, text: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::append(notename, text, self.pool()))
+    }
+
+    /// Blocking version of [`commands::prepend`].
+    pub fn prepend(&self, notename: &str, text: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::prepend(notename, text, self.pool()))
+    }
+
+    /// Blocking version of [`commands::insert_at_line`].
+    pub fn insert_at_line(
+        &self,
+        notename: &str,
+        line_no: u32,
+        text: &str,
+    ) -> Result<Note, NotebookError> {
+        self.block_on(commands::insert_at_line(
+            notename,
+            line_no,
+            text,
+            self.pool(),
+        ))
+    }
+
+    /// Blocking version of [`commands::upd_notename`].
+    pub fn upd_notename(&self, notename: &str, new_notename: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::upd_notename(notename, new_notename, self.pool()))
+    }
+
+    /// Blocking version of [`commands::clear`].
+    pub fn clear(&self, notename: &str) -> Result<(), NotebookError> {
+        self.block_on(commands::clear(notename, self.pool()))
+    }
+
+    /// Blocking version of [`commands::del`].
+    pub fn del(&self, notename: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::del(notename, self.pool()))
+    }
+
+    /// Blocking version of [`commands::select_one`].
+    pub fn select_one(&self, notename: &str) -> Result<Note, NotebookError> {
+        self.block_on(commands::select_one(notename, self.pool()))
+    }
+
+    /// Blocking version of [`commands::select_all`].
+    pub fn select_all(&self) -> Result<Vec<Note>, NotebookError> {
+        self.block_on(commands::select_all(self.pool()))
+    }
+
+    /// Blocking version of [`commands::list`].
+    pub fn list(&self) -> Result<Vec<NoteSummary>, NotebookError> {
+        self.block_on(commands::list(self.pool()))
+    }
+
+    /// Blocking version of [`commands::exists`].
+    pub fn exists(&self, notename: &str) -> Result<bool, NotebookError> {
+        self.block_on(commands::exists(notename, self.pool()))
+    }
+
+    /// Blocking version of [`commands::count`].
+    pub fn count(&self) -> Result<i64, NotebookError> {
+        self.block_on(commands::count(self.pool()))
+    }
+}
@@ -0,0 +1,347 @@
+//! Client of the HTTP server mode (only with `client` feature).
+//!
+//! [`Client`] sends requests to REST API of notebook served by [`server` module][crate::server]
+//! and implements [`NoteStore`], so programs can work with remote notebook the same way as with
+//! local one, and give it to [`Notebook::with_store`][crate::notebook::Notebook::with_store].
+//! Notes are returned as [`Note`]s and errors as [`NotebookError`]s, like the server sends them:
+//! * `401 Unauthorized` - [`NotebookError::Unauthorized`], set token with [`Client::with_token`]
+//! * `429 Too Many Requests` - [`NotebookError::RateLimited`]
+//! * `404 Not Found` - [`NotebookError::Sqlx`][NotebookError] error with
+//!   `sqlx::Error::RowNotFound`, or [`NotebookError::NotFound`] if the notebook is built
+//!   without `postgres` feature
+//! * `409 Conflict` when note is added - [`NotebookError::AlreadyTaken`]
+//! * other errors - [`NotebookError::Client`] with message of the server
+//!
+//! Server doesn't search notes, so [`NoteStore::search`] fetches all notes and searches them
+//! like [`MemoryStore`][crate::store::MemoryStore] does. Server doesn't return deleted note,
+//! so [`NoteStore::delete`] fetches it before deleting.
+//!
+//! Client doesn't need the database, so web frontends can build the notebook for
+//! `wasm32-unknown-unknown` with only `client` feature (read more [here][crate]).
+//! Requests are sent by the transport of the target:
+//! * natively - by `hyper` over TCP connection of `tokio`. Only `http://` URLs are supported,
+//!   to reach server behind HTTPS proxy use a local proxy that forwards requests to it
+//! * on wasm32 - by `fetch` of the browser, so `https://` URLs are supported too. Browser sends
+//!   requests only to the origin of the page unless the server allows others with CORS,
+//!   so serve the frontend and the notebook behind the same proxy
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::client::Client;
+//! # use lnotebook::store::NoteStore;
+//! # use lnotebook::NotebookError;
+//! async fn client_example() -> Result<(), NotebookError> {
+//!     let client = Client::new("http://127.0.0.1:8080")?.with_token("secret");
+//!
+//!     client.add("shopping", "milk").await?;
+//!
+//!     assert_eq!("milk", client.get("shopping").await?.body());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::{self, NotebookError};
+use crate::note::{Note, NoteSummary};
+use crate::store::{self, NoteStore};
+
+use futures_util::future::BoxFuture;
+use http::{header, HeaderName, Method, StatusCode, Uri};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+mod fetch;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+
+#[cfg(target_arch = "wasm32")]
+use fetch as transport;
+#[cfg(not(target_arch = "wasm32"))]
+use native as transport;
+
+/// How long one request to the server can take.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client of notebook served over HTTP, read more [here][crate::client].
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: String,
+    token: Option<String>,
+}
+
+/// Note as it is sent by the server.
+#[derive(Deserialize)]
+struct NoteJson {
+    id: i32,
+    note_name: String,
+    note: String,
+}
+
+impl From<NoteJson> for Note {
+    fn from(note: NoteJson) -> Self {
+        Note {
+            id: note.id,
+            note: Some(note.note),
+            note_name: note.note_name,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct NewNote<'a> {
+    note_name: &'a str,
+    note: &'a str,
+}
+
+#[derive(Serialize)]
+struct UpdNote<'a> {
+    note: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ErrorJson {
+    error: String,
+}
+
+/// Response of the server.
+struct Response {
+    status: StatusCode,
+    /// Value of `Retry-After` header
+    retry_after: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Client {
+    /// Creates client of the server at `url`, like `http://127.0.0.1:8080`.
+    ///
+    /// URL can have path, if the server is served under it by proxy.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Client`] error if URL is not a valid `http://` URL
+    ///       (or `https://` URL on wasm32)
+    /// ### Example
+    /// ```
+    /// # use lnotebook::client::Client;
+    /// assert_eq!("http://notes.lan/api", Client::new("http://notes.lan/api/").unwrap().url());
+    /// assert!(Client::new("https://notes.lan").is_err());
+    /// ```
+    pub fn new(url: &str) -> Result<Client, NotebookError> {
+        let error = |reason: &str| NotebookError::Client {
+            url: url.to_owned(),
+            reason: reason.to_owned(),
+        };
+
+        let uri: Uri = url.parse().map_err(|_| error("URL is not valid"))?;
+        // `fetch` of the browser sends requests over HTTPS too
+        let https = cfg!(target_arch = "wasm32") && uri.scheme_str() == Some("https");
+        if uri.scheme_str() != Some("http") && !https {
+            return Err(error("only `http://` URLs are supported"));
+        }
+        if uri.host().is_none() {
+            return Err(error("URL doesn't have host"));
+        }
+
+        Ok(Client {
+            url: url.trim_end_matches('/').to_owned(),
+            token: None,
+        })
+    }
+
+    /// Makes client send `token` in `Authorization: Bearer <token>` header,
+    /// read more [here][crate::server#authentication-and-rate-limiting].
+    pub fn with_token(mut self, token: impl Into<String>) -> Client {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Returns URL of the server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> NotebookError {
+        NotebookError::Client {
+            url: self.url.to_owned(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Returns headers of every request, with `Content-Type` if request has JSON `body`.
+    fn headers(&self, body: bool) -> Vec<(HeaderName, String)> {
+        let mut headers = vec![
+            (header::ACCEPT, "application/json".to_owned()),
+            (
+                header::USER_AGENT,
+                concat!("lnotebook/", env!("CARGO_PKG_VERSION")).to_owned(),
+            ),
+        ];
+        if let Some(token) = &self.token {
+            headers.push((header::AUTHORIZATION, format!("Bearer {token}")));
+        }
+        if body {
+            headers.push((header::CONTENT_TYPE, "application/json".to_owned()));
+        }
+
+        headers
+    }
+
+    /// Sends request with `method` to `path` of API and returns response with any status.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<Response, NotebookError> {
+        let uri: Uri = format!("{}{}", self.url, path)
+            .parse()
+            .map_err(|err| self.error(err))?;
+
+        transport::send(self, method, uri, body).await
+    }
+
+    /// Returns error sent by the server in `response`, read more [here][crate::client].
+    fn response_error(&self, response: &Response) -> NotebookError {
+        match response.status {
+            StatusCode::UNAUTHORIZED => NotebookError::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => NotebookError::RateLimited {
+                retry_after: response
+                    .retry_after
+                    .as_deref()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(1),
+            },
+            StatusCode::NOT_FOUND => errors::not_found(),
+            status => {
+                let message = serde_json::from_slice::<ErrorJson>(&response.body)
+                    .map(|err| err.error)
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&response.body).into_owned());
+
+                self.error(format!("response status is {status}: {message}"))
+            }
+        }
+    }
+
+    /// Returns value of type `T` from body of successful `response`.
+    fn parse<T: DeserializeOwned>(&self, response: Response) -> Result<T, NotebookError> {
+        if !response.status.is_success() {
+            return Err(self.response_error(&response));
+        }
+
+        serde_json::from_slice(&response.body)
+            .map_err(|err| self.error(format!("invalid response: {err}")))
+    }
+
+    fn json(&self, value: &impl Serialize) -> Result<String, NotebookError> {
+        serde_json::to_string(value).map_err(|err| self.error(err))
+    }
+}
+
+/// Returns path of note `notename` in API, with notename percent-encoded.
+fn note_path(notename: &str) -> String {
+    let mut path = "/notes/".to_owned();
+    for byte in notename.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                path.push(byte as char)
+            }
+            _ => path += &format!("%{byte:02X}"),
+        }
+    }
+
+    path
+}
+
+impl NoteStore for Client {
+    fn add<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(async move {
+            let body = self.json(&NewNote {
+                note_name: notename,
+                note,
+            })?;
+            let response = self.send(Method::POST, "/notes", Some(body)).await?;
+
+            if response.status == StatusCode::CONFLICT {
+                return Err(NotebookError::AlreadyTaken {
+                    notename: notename.to_owned(),
+                });
+            }
+
+            Ok(self.parse::<NoteJson>(response)?.into())
+        })
+    }
+
+    fn get<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(async move {
+            let response = self.send(Method::GET, &note_path(notename), None).await?;
+
+            Ok(self.parse::<NoteJson>(response)?.into())
+        })
+    }
+
+    fn update<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(async move {
+            let body = self.json(&UpdNote { note })?;
+            let response = self
+                .send(Method::PUT, &note_path(notename), Some(body))
+                .await?;
+
+            Ok(self.parse::<NoteJson>(response)?.into())
+        })
+    }
+
+    fn delete<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(async move {
+            let note = self.get(notename).await?;
+
+            let response = self
+                .send(Method::DELETE, &note_path(notename), None)
+                .await?;
+            if !response.status.is_success() {
+                return Err(self.response_error(&response));
+            }
+
+            Ok(note)
+        })
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<Note>, NotebookError>> {
+        Box::pin(async move {
+            let response = self.send(Method::GET, "/notes", None).await?;
+            let notes: Vec<NoteJson> = self.parse(response)?;
+
+            Ok(notes.into_iter().map(Note::from).collect())
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<NoteSummary>, NotebookError>> {
+        Box::pin(async move {
+            let query = query.to_lowercase();
+
+            let mut found: Vec<NoteSummary> = self
+                .list()
+                .await?
+                .into_iter()
+                .filter(|note| {
+                    note.note_name.to_lowercase().contains(&query)
+                        || note.body().to_lowercase().contains(&query)
+                })
+                .map(|note| store::summary(note.id, note.note_name.to_owned(), note.body()))
+                .collect();
+            // Notes are ordered by name, like in `search`
+            found.sort_by(|a, b| a.note_name.cmp(&b.note_name));
+
+            Ok(found)
+        })
+    }
+}
@@ -0,0 +1,74 @@
+//! Transport of [`Client`] in the browser: requests are sent by `fetch`, so the client works
+//! in web frontends built for wasm32.
+
+use super::{Client, Response, TIMEOUT};
+use crate::errors::NotebookError;
+
+use http::{header, Method, StatusCode, Uri};
+use js_sys::{Promise, Uint8Array};
+use send_wrapper::SendWrapper;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AbortSignal, DomException, Request, RequestInit};
+
+#[wasm_bindgen]
+extern "C" {
+    /// `fetch` of the global scope, so requests are sent both from pages and from workers.
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_with_request(request: &Request) -> Promise;
+}
+
+/// Sends request with `method` to `uri` and returns response with any status.
+pub(super) async fn send(
+    client: &Client,
+    method: Method,
+    uri: Uri,
+    body: Option<String>,
+) -> Result<Response, NotebookError> {
+    let error = |err: JsValue| match err.dyn_ref::<DomException>() {
+        Some(err) if err.name() == "TimeoutError" => client.error("timed out"),
+        Some(err) => client.error(err.message()),
+        None => client.error(format!("{err:?}")),
+    };
+
+    let exchange = async move {
+        let init = RequestInit::new();
+        init.set_method(method.as_str());
+        init.set_signal(Some(&AbortSignal::timeout_with_u32(
+            TIMEOUT.as_millis() as u32
+        )));
+        if let Some(body) = &body {
+            init.set_body(&JsValue::from_str(body));
+        }
+
+        let request = Request::new_with_str_and_init(&uri.to_string(), &init).map_err(error)?;
+        for (name, value) in client.headers(body.is_some()) {
+            request
+                .headers()
+                .set(name.as_str(), &value)
+                .map_err(error)?;
+        }
+
+        let response: web_sys::Response = JsFuture::from(fetch_with_request(&request))
+            .await
+            .map_err(error)?
+            .unchecked_into();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER.as_str())
+            .map_err(error)?;
+        let body = JsFuture::from(response.array_buffer().map_err(error)?)
+            .await
+            .map_err(error)?;
+
+        Ok(Response {
+            status: StatusCode::from_u16(response.status()).map_err(|err| client.error(err))?,
+            retry_after,
+            body: Uint8Array::new(&body).to_vec(),
+        })
+    };
+
+    // Futures of JavaScript can't be sent to other threads, but `NoteStore` returns futures
+    // that can. The browser runs them in the only thread, so they are never sent anywhere
+    SendWrapper::new(exchange).await
+}
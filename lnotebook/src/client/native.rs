@@ -0,0 +1,79 @@
+//! Transport of [`Client`] outside of the browser: requests are sent by `hyper` over TCP
+//! connection of `tokio`.
+
+use super::{Client, Response, TIMEOUT};
+use crate::errors::NotebookError;
+
+use http::{header, Method, Request, Uri};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+
+/// Sends request with `method` to `uri` and returns response with any status.
+pub(super) async fn send(
+    client: &Client,
+    method: Method,
+    uri: Uri,
+    body: Option<String>,
+) -> Result<Response, NotebookError> {
+    let host = uri
+        .host()
+        .ok_or_else(|| client.error("URL doesn't have host"))?
+        // Host of IPv6 address is in brackets
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(80);
+
+    let exchange = async {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .map_err(|err| client.error(err))?;
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|err| client.error(err))?;
+        tokio::spawn(conn);
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri.path_and_query().map_or("/", |path| path.as_str()))
+            .header(
+                header::HOST,
+                uri.authority().map_or("", |authority| authority.as_str()),
+            );
+        for (name, value) in client.headers(body.is_some()) {
+            request = request.header(name, value);
+        }
+        let request = request
+            .body(Full::new(Bytes::from(body.unwrap_or_default())))
+            .map_err(|err| client.error(err))?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|err| client.error(err))?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|err| client.error(err))?
+            .to_bytes();
+
+        Ok(Response {
+            status,
+            retry_after,
+            body: body.to_vec(),
+        })
+    };
+
+    match tokio::time::timeout(TIMEOUT, exchange).await {
+        Ok(res) => res,
+        Err(_) => Err(client.error("timed out")),
+    }
+}
@@ -0,0 +1,52 @@
+//! Reading and writing system clipboard (only with `clipboard` feature).
+//!
+//! CLI uses it in `add-from-clipboard`, which adds note with text copied into clipboard,
+//! and `copy-to-clipboard`, which copies content of note into clipboard.
+//!
+//! On Linux content copied into clipboard belongs to the program that copied it, so it's
+//! available after the notebook exits only if clipboard manager is running (most desktop
+//! environments run one).
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::clipboard;
+//! # use lnotebook::NotebookError;
+//! fn clipboard_example() -> Result<(), NotebookError> {
+//!     clipboard::copy("milk")?;
+//!
+//!     assert_eq!("milk", clipboard::paste()?);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+
+use arboard::Clipboard;
+
+fn clipboard_error(err: arboard::Error) -> NotebookError {
+    NotebookError::Clipboard {
+        reason: err.to_string(),
+    }
+}
+
+/// Returns text copied into clipboard.
+/// ### Returns
+/// * Ok
+///     * Text from clipboard
+/// * Errors
+///     * [`NotebookError::Clipboard`] error if clipboard can't be opened or it has no text
+pub fn paste() -> Result<String, NotebookError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(clipboard_error)
+}
+
+/// Copies `text` into clipboard.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Clipboard`] error if clipboard can't be opened or written
+pub fn copy(text: &str) -> Result<(), NotebookError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(clipboard_error)
+}
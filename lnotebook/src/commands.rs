@@ -1,93 +1,196 @@
 //! This module contains functions used to control a notebook.
 
+#[cfg(feature = "cli")]
 pub mod execute_commands;
+use crate::compression::{self, StoredNote};
+use crate::config::{self, logged};
 use crate::errors;
+use crate::lock;
+use crate::notename::NoteName;
+use crate::protect;
+use crate::render::{NoteRenderer, Plain};
+use crate::sql::{query, query_as, query_scalar};
+use crate::users::{self, Access};
 use errors::NotebookError;
 
-use sqlx::{self, PgPool};
+use chrono::{DateTime, Local, Utc};
+use futures_util::{future, Stream, TryStreamExt};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use sqlx::{self, PgConnection, PgPool};
+use std::{collections::HashSet, fmt};
 use tracing::{event, Level};
 
-/// This is a `struct` that containing information about notes.
-///
-/// This `struct` is returned by `functions` from [`command` module][`crate::commands`]:
-/// * [`add`]
-/// * [`upd`]
-/// * [`upd_notename`]
-/// * [`display`]
-/// ### Example
-/// ```rust,no run
-/// async fn struct_example(pool: &PgPool) -> Result<(), NotebookError> {
-///     // `add()` returns struct `Note` that we can use later as we wish
-///     let row = add("early_sleep", "I'll go to bed early today", pool).await?;
-///
-///     assert_eq!("early_sleep", row.note_name);
-///
-///     Ok(())
-/// }
-pub struct Note {
-    pub id: i32,
-    pub note: Option<String>,
-    pub note_name: String,
-}
-
-impl Note {
-    /// Return field `note` as `&str`.
-    ///
-    /// If note is `Some()`, returns content of note as `&str`; else returns empty `&str`("")
-    pub async fn note_str(&mut self) -> String {
-        if let Some(some_note) = &self.note {
-            some_note.to_owned()
-        } else {
-            "".to_owned()
-        }
-    }
-}
+pub use crate::note::{Note, NoteSummary, PREVIEW_LEN};
 
 /// Displays the requested note.
 /// ### Returns
 /// * Errors
 ///     * [`NotebookError::Sqlx`] error from [`sqlx::Error`]
 pub async fn display(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
-    let mut row = select_one(notename, pool).await?;
-    let row_note = row.note_str().await;
+    let row = select_one(notename, pool).await?;
+
+    event!(Level::INFO, "Requested note:\n{}", Plain.render_note(&row));
+
+    Ok(())
+}
+
+/// Displays all total notes in notebook ordered by ID.
+///
+/// Notes are read by one query, so they are displayed as they were at one moment even if
+/// notebook is changed at the same time. All notes are displayed as one numbered summary
+/// rendered by [`Plain`] renderer, use [`select_all`] to render them in other way.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn display_all(pool: &PgPool) -> Result<(), NotebookError> {
+    let notes = select_all(pool).await?;
 
     event!(
         Level::INFO,
-        "Requested note:\nID: {}\nName: {}\nData:\n{}",
-        row.id,
-        row.note_name,
-        row_note
+        "All notes in notebook:\n{}",
+        Plain.render_notes(&notes)
     );
 
     Ok(())
 }
 
-/// Displays all total notes in notebook.
+/// Returns short information about all total notes in notebook.
+///
+/// Content of notes isn't transferred from database, only its first line.
 /// ### Returns
+/// * Ok
+///     * `Vec` of [`NoteSummary`] ordered by ID
 /// * Errors
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
-pub async fn display_all(pool: &PgPool) -> Result<(), NotebookError> {
-    let rows = sqlx::query!(
-        "
-SELECT * 
-FROM notebook
-        "
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn list_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk\nbread\n", pool).await?;
+///
+///     let notes = list(pool).await?;
+///
+///     assert_eq!("milk", notes[0].preview);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list(pool: &PgPool) -> Result<Vec<NoteSummary>, NotebookError> {
+    list_previews(PREVIEW_LEN, pool).await
+}
+
+/// Returns short information about all total notes in notebook, like [`list`],
+/// with previews cut to `preview_len` characters.
+///
+/// Previews are cut by the database, so only `preview_len` characters of every note
+/// are transferred even if notes are huge. Protected notes (read about them in
+/// [`protect` module][crate::protect]) have preview `(protected)`.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`NoteSummary`] ordered by ID
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn list_previews_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk and bread\n", pool).await?;
+///
+///     let notes = list_previews(4, pool).await?;
+///
+///     assert_eq!("milk", notes[0].preview);
+///     assert!(notes[0].truncated);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_previews(
+    preview_len: i32,
+    pool: &PgPool,
+) -> Result<Vec<NoteSummary>, NotebookError> {
+    let rows = query_as!(
+        {
+            id: i32,
+            note_name: String,
+            preview: String,
+            truncated: bool,
+            lines: i64,
+            updated_at: DateTime<Utc>,
+            packed: Option<Vec<u8>>,
+        },
+        r#"
+SELECT
+    id,
+    note_name,
+    preview AS "preview!",
+    char_length(rtrim(COALESCE(content, ''), E'\n')) > char_length(preview) AS "truncated!",
+    COALESCE(array_length(string_to_array(content, E'\n'), 1), 0)::INT8 AS "lines!",
+    updated_at,
+    CASE WHEN compressed AND passphrase_hash IS NULL THEN note_bytes END AS packed
+FROM notebook,
+    LATERAL (SELECT CASE WHEN passphrase_hash IS NULL THEN note END AS content) AS opened,
+    LATERAL substring(
+        split_part(
+            CASE
+                WHEN passphrase_hash IS NOT NULL THEN '(protected)'
+                ELSE COALESCE(note, '(binary, ' || octet_length(note_bytes) || ' bytes)', '')
+            END,
+            E'\n',
+            1
+        )
+        FROM 1 FOR $1
+    ) AS preview
+WHERE can_access_note(id, owner, $2, false)
+ORDER BY id
+        "#,
+        preview_len,
+        users::acting_user()
     )
     .fetch_all(pool)
     .await?;
 
-    event!(Level::INFO, "All notes in notebook:");
-    rows.iter().for_each(|row| {
-        let row_note = if let Some(n) = &row.note { n } else { "" };
+    rows.into_iter()
+        .map(|row| {
+            let updated_at = Some(row.updated_at.with_timezone(&Local));
+            Ok(
+                match compression::content(&row.note_name, None, row.packed)? {
+                    Some(note) => {
+                        NoteSummary::of(row.id, row.note_name, &note, preview_len, updated_at)
+                    }
+                    None => NoteSummary {
+                        id: row.id,
+                        note_name: row.note_name,
+                        preview: row.preview,
+                        truncated: row.truncated,
+                        lines: row.lines,
+                        updated_at,
+                    },
+                },
+            )
+        })
+        .collect()
+}
 
-        event!(
-            Level::INFO,
-            "\nID: {}:\nName: {}\nData:\n{}",
-            row.id,
-            row.note_name,
-            row_note
-        );
-    });
+/// Displays short information about all total notes in notebook.
+///
+/// Unlike [`display_all`], displays only first line of every note. Use [`list`]
+/// to render notes in other way.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn display_list(pool: &PgPool) -> Result<(), NotebookError> {
+    let notes = list(pool).await?;
+
+    event!(
+        Level::INFO,
+        "All notes in notebook:\n{}",
+        Plain.render_summaries(&notes)
+    );
 
     Ok(())
 }
@@ -97,40 +200,57 @@ FROM notebook
 /// * Ok
 ///     * [Note] that was added into notebook
 /// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `notename` is not valid
+///       (read about valid notenames in [`notename`][crate::notename] module)
+///     * [`NotebookError::NoteTooLarge`] error if `note` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
 ///     * [`NotebookError::AlreadyTaken`] error if a note with the same name already exists
 ///     * [`NotebookError::Sqlx`] error from [`sqlx::Error`]
-/// if any other [`sqlx::Error`] occurs
+///       if any other [`sqlx::Error`] occurs
 /// ### Example
-/// ```rust,no run
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
 /// async fn add_example(pool: &PgPool) -> Result<(), NotebookError> {
 ///     add("add_note", "Added a some note so you don't forget", pool).await?;
 ///
 ///     let row = select_one("add_note", pool).await?;
 ///
-///     assert_eq!("add", row.note_name);
+///     assert_eq!("add_note", row.note_name);
 ///
 ///     Ok(())
 /// }
 /// ```
 pub async fn add(notename: &str, note: &str, pool: &PgPool) -> Result<Note, NotebookError> {
-    match sqlx::query!(
+    NoteName::new(notename)?;
+    config::check_size(note.len())?;
+
+    let mut tx = pool.begin().await?;
+
+    match query_as!(
+        { id: i32, note_name: String, note: Option<String> },
         "
-INSERT INTO notebook (note_name, note)
-VALUES ( $1, $2 )
+INSERT INTO notebook (note_name, note, owner)
+VALUES ( $1, $2, $3 )
 RETURNING id, note_name, note
         ",
         notename,
-        note
+        note,
+        users::acting_user()
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
     {
         Ok(row) => {
+            compression::pack(row.id, row.note.as_deref(), &mut tx).await?;
+            tx.commit().await?;
+
             event!(
                 Level::INFO,
                 "Insert note with name `{}` with data `{}` into notebook",
                 notename,
-                note
+                logged(note)
             );
             Ok(Note {
                 id: row.id,
@@ -139,30 +259,153 @@ RETURNING id, note_name, note
             })
         }
         Err(err) => {
-            if let Some(db_err) = err.as_database_error() {
-                if let Some(code) = db_err.code() {
-                    if code == "23505" {
-                        return Err(NotebookError::AlreadyTaken {
-                            notename: notename.to_owned(),
-                        });
-                    }
-                }
+            if errors::is_unique_violation(&err) {
+                return Err(NotebookError::AlreadyTaken {
+                    notename: notename.to_owned(),
+                });
             }
             Err(err.into())
         }
     }
 }
 
+/// Adds and returns a new binary note that contains any bytes, like keys or images.
+///
+/// Content of binary note is read only by [`get_binary`]: functions that work with text
+/// (like [`select_one`] and [`search`][crate::search::search]) see it as note without content,
+/// and text can't be added to it. Binary note becomes text note when its content is replaced
+/// with [`upd`] or cleared with [`clear`]. History of notes keeps only text content.
+/// ### Returns
+/// * Ok
+///     * [Note] that was added into notebook, without content
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `notename` is not valid
+///     * [`NotebookError::NoteTooLarge`] error if `data` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
+///     * [`NotebookError::AlreadyTaken`] error if a note with the same name already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn add_binary_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add_binary("key", vec![0x00, 0x9f, 0xff], pool).await?;
+///
+///     assert_eq!(vec![0x00, 0x9f, 0xff], get_binary("key", pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn add_binary(
+    notename: &str,
+    data: Vec<u8>,
+    pool: &PgPool,
+) -> Result<Note, NotebookError> {
+    NoteName::new(notename)?;
+    config::check_size(data.len())?;
+
+    let row = query_as!(
+        Note { id: i32, note_name: String, note: Option<String> },
+        "
+INSERT INTO notebook (note_name, note_bytes, owner)
+VALUES ( $1, $2, $3 )
+RETURNING id, note_name, note
+        ",
+        notename,
+        data,
+        users::acting_user()
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| {
+        if errors::is_unique_violation(&err) {
+            NotebookError::AlreadyTaken {
+                notename: notename.to_owned(),
+            }
+        } else {
+            err.into()
+        }
+    })?;
+
+    event!(
+        Level::INFO,
+        "Insert binary note with name `{}` ({} bytes) into notebook",
+        notename,
+        data.len()
+    );
+
+    Ok(row)
+}
+
+/// Returns content of note as bytes.
+///
+/// Content of binary note (read about them in [`add_binary`]) is returned as it is,
+/// and content of text note is returned as UTF-8.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn get_binary(notename: &str, pool: &PgPool) -> Result<Vec<u8>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let row = query_as!(
+        { data: Vec<u8>, compressed: bool },
+        r#"
+SELECT COALESCE(note_bytes, convert_to(COALESCE(note, ''), 'UTF8')) AS "data!", compressed
+FROM notebook
+WHERE note_name = $1
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if row.compressed {
+        let note = compression::content(notename, None, Some(row.data))?;
+        return Ok(note.unwrap_or_default().into_bytes());
+    }
+
+    Ok(row.data)
+}
+
+/// Returns [`NotebookError::BinaryNote`] if text was added to binary note `notename`,
+/// or `err` as it is.
+pub(crate) fn text_error(notename: &str, err: sqlx::Error) -> NotebookError {
+    if errors::is_check_violation(&err, errors::TEXT_OR_BINARY) {
+        NotebookError::BinaryNote {
+            notename: notename.to_owned(),
+        }
+    } else {
+        err.into()
+    }
+}
+
 /// Deletes the requested note.
 /// ### Returns
+/// * Ok
+///     * [Note] that was deleted
 /// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
 /// ### Example
-/// ```rust,no run
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
 /// async fn delete_example(pool: &PgPool) -> Result<(), NotebookError> {
 ///     add("bad_cat", "Buy new slippers. The old ones were ruined by the cat", pool).await?;
 ///
-///     del(&row.note_name, pool).await?;
+///     del("bad_cat", pool).await?;
 ///
 ///     // Should return error because note `bad_cat` is not exist
 ///     select_one("bad_cat", pool).await?;
@@ -170,41 +413,49 @@ RETURNING id, note_name, note
 ///     Ok(())
 /// }
 /// ```
-pub async fn del(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
-    match sqlx::query!(
+pub async fn del(notename: &str, pool: &PgPool) -> Result<Note, NotebookError> {
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let row = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
         "
 DELETE FROM notebook
 WHERE note_name = $1
-RETURNING id, note_name, note
+RETURNING id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
         ",
         notename
     )
     .fetch_one(pool)
-    .await
-    {
-        Ok(row) => {
-            let row_note = if let Some(n) = &row.note { n } else { "" };
+    .await?;
 
-            event!(
-                Level::INFO,
-                "Deleteing note:\nID: {}\nName: {}\nData:\n{}",
-                row.id,
-                notename,
-                row_note
-            );
+    event!(
+        Level::INFO,
+        "Deleting note `{}` (ID: {})",
+        row.note_name,
+        row.id
+    );
 
-            Ok(())
-        }
-        Err(err) => Err(NotebookError::Sqlx(err)),
-    }
+    row.into_note()
 }
 
 /// Deletes all total notes in notebook.
+///
+/// Notes are deleted by one query, so notes added at the same time are either deleted
+/// or not deleted at all. Deleted notes are logged as one numbered summary. Protected notes
+/// (read about them in [`protect` module][crate::protect]) and notes the acting user can't
+/// change (read about them in [`users` module][crate::users]) are kept.
 /// ### Returns
+/// * Ok
+///     * Names of deleted notes ordered by ID
 /// * Errors
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
 /// ### Example
-/// ```rust,no run
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
 /// async fn delete_all_example(pool: &PgPool) -> Result<(), NotebookError> {
 ///     // Adding new notes
 ///     add(
@@ -221,7 +472,8 @@ RETURNING id, note_name, note
 ///     .await?;
 ///     add("empty", "", pool).await?;
 ///
-///     del_all(pool).await?;
+///     let deleted = del_all(pool).await?;
+///     assert_eq!(vec!["bad_cat", "cool_cat", "empty"], deleted);
 ///
 ///     // Should display empty list
 ///     display_all(pool).await?;
@@ -229,66 +481,257 @@ RETURNING id, note_name, note
 ///     Ok(())
 /// }
 /// ```
-pub async fn del_all(pool: &PgPool) -> Result<(), NotebookError> {
-    match sqlx::query!(
+pub async fn del_all(pool: &PgPool) -> Result<Vec<String>, NotebookError> {
+    let rows = query_as!(
+        { id: i32, note_name: String },
+        r#"
+WITH deleted AS (
+    DELETE FROM notebook
+    WHERE passphrase_hash IS NULL AND can_access_note(id, owner, $1, true)
+    RETURNING id, note_name
+)
+SELECT id AS "id!", note_name AS "note_name!"
+FROM deleted
+ORDER BY id
+        "#,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = format!("Deleted {} notes:", rows.len());
+    for (number, row) in rows.iter().enumerate() {
+        summary += &format!("\n{}. ID: {}; Name: {}", number + 1, row.id, row.note_name);
+    }
+    event!(Level::INFO, "{summary}");
+
+    Ok(rows.into_iter().map(|row| row.note_name).collect())
+}
+
+/// Pattern that notenames are matched against.
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// Glob pattern where `*` matches any characters and `?` matches one character,
+    /// for example `tmp_*`. Pattern must match the whole notename.
+    Glob(String),
+    /// Regular expression, for example `^tmp_\d+$`. Pattern can match any part of notename.
+    Regex(String),
+}
+
+impl NamePattern {
+    fn to_regex(&self) -> Result<Regex, NotebookError> {
+        let (pattern, re) = match self {
+            NamePattern::Glob(glob) => {
+                let mut re = String::from("^");
+                for c in glob.chars() {
+                    match c {
+                        '*' => re += ".*",
+                        '?' => re.push('.'),
+                        c => re += &regex::escape(&c.to_string()),
+                    }
+                }
+                re.push('$');
+
+                (glob, re)
+            }
+            NamePattern::Regex(re) => (re, re.to_owned()),
+        };
+
+        Regex::new(&re).map_err(|err| NotebookError::InvalidPattern {
+            pattern: pattern.to_owned(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Returns `true` if `notename` matches pattern.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::InvalidPattern`] error if pattern is not valid
+    /// ### Example
+    /// ```
+    /// # use lnotebook::commands::NamePattern;
+    /// let pattern = NamePattern::Glob("tmp_*".to_owned());
+    ///
+    /// assert!(pattern.is_match("tmp_ideas").unwrap());
+    /// assert!(!pattern.is_match("ideas_tmp_").unwrap());
+    /// ```
+    pub fn is_match(&self, notename: &str) -> Result<bool, NotebookError> {
+        Ok(self.to_regex()?.is_match(notename))
+    }
+}
+
+/// Returns names of all notes that match `pattern`, ordered by name.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::InvalidPattern`] error if `pattern` is not valid
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_matching(
+    pattern: &NamePattern,
+    pool: &PgPool,
+) -> Result<Vec<String>, NotebookError> {
+    let re = pattern.to_regex()?;
+
+    let rows = query_as!(
+        { note_name: String },
         "
-DELETE FROM notebook
-RETURNING id, note_name, note
+SELECT note_name
+FROM notebook
+WHERE can_access_note(id, owner, $1, false)
+ORDER BY note_name
+        ",
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| row.note_name)
+        .filter(|notename| re.is_match(notename))
+        .collect())
+}
+
+/// Deletes all notes that match `pattern`.
+/// ### Returns
+/// * Ok
+///     * Names of deleted notes
+/// * Errors
+///     * [`NotebookError::InvalidPattern`] error if `pattern` is not valid
+///     * Errors of [`del_many`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn del_matching_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("tmp_1", "", pool).await?;
+///     add("tmp_2", "", pool).await?;
+///     add("ideas", "", pool).await?;
+///
+///     let deleted = del_matching(&NamePattern::Glob("tmp_*".to_owned()), pool).await?;
+///
+///     assert_eq!(vec!["tmp_1", "tmp_2"], deleted);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn del_matching(
+    pattern: &NamePattern,
+    pool: &PgPool,
+) -> Result<Vec<String>, NotebookError> {
+    let notenames = select_matching(pattern, pool).await?;
+
+    del_many(&notenames, pool).await
+}
+
+/// Deletes notes `notenames`.
+///
+/// Notes are deleted by one query, so either all of them are deleted or none of them.
+/// Notenames of notes that don't exist are skipped.
+/// ### Returns
+/// * Ok
+///     * Names of deleted notes, ordered by name
+/// * Errors
+///     * [`NotebookError::Locked`] error if any of notes is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change any of notes (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if any of notes is protected and its passphrase
+///       isn't given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn del_many_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("tmp_1", "", pool).await?;
+///     add("tmp_2", "", pool).await?;
+///
+///     let deleted = del_many(&["tmp_2".to_owned(), "tmp_3".to_owned()], pool).await?;
+///
+///     assert_eq!(vec!["tmp_2"], deleted);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn del_many(notenames: &[String], pool: &PgPool) -> Result<Vec<String>, NotebookError> {
+    for notename in notenames {
+        lock::check_unlocked(notename, pool).await?;
+        users::check_access(notename, Access::Write, pool).await?;
+        protect::check_passphrase(notename, pool).await?;
+    }
+
+    let del_rows = query_as!(
+        { note_name: String },
         "
+DELETE FROM notebook
+WHERE note_name = ANY($1)
+RETURNING note_name
+        ",
+        notenames
     )
     .fetch_all(pool)
-    .await
-    {
-        Ok(del_rows) => {
-            del_rows.iter().for_each(|row| {
-                let row_note = if let Some(n) = &row.note { n } else { "" };
-
-                event!(
-                    Level::INFO,
-                    "Deleting ID: {}; Name: {}; Data:\n{}",
-                    row.id,
-                    row.note_name,
-                    row_note
-                )
-            });
+    .await?;
 
-            Ok(())
-        }
-        Err(err) => Err(NotebookError::Sqlx(err)),
+    let mut deleted: Vec<String> = del_rows.into_iter().map(|row| row.note_name).collect();
+    deleted.sort();
+
+    for notename in &deleted {
+        event!(Level::INFO, "Deleting note `{}`", notename);
     }
+
+    Ok(deleted)
 }
 
 /// Clears the content of requested note.
 /// ### Returns
 /// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
 /// ### Example
-/// ```rust,no run
-/// async fn delete_example(pool: &PgPool) -> Result<(), NotebookError> {
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn clear_example(pool: &PgPool) -> Result<(), NotebookError> {
 ///     add("clear_note", "meow meow meow meow", pool).await?;
 ///
 ///     clear("clear_note", pool).await?;
 ///     let row = select_one("clear_note", pool).await?;
 ///
-///     assert_eq!("", row.note_str().await);
+///     assert_eq!("", row.body());
 ///
 ///     Ok(())
 /// }
 /// ```
 pub async fn clear(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
-    match sqlx::query!(
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    match query_as!(
+        { note_name: String },
         "
 UPDATE notebook
-SET note = ''
+SET note = '', note_bytes = NULL
 WHERE note_name = $1
 RETURNING note_name
         ",
         notename
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
     {
         Ok(_) => {
+            tx.commit().await?;
+
             event!(Level::INFO, "Content of `{}` was cleared", notename);
 
             Ok(())
@@ -302,36 +745,63 @@ RETURNING note_name
 /// * Ok
 ///     * [Note] that was updated
 /// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::NoteTooLarge`] error if `new_note` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
 /// ### Example
-/// ```rust,no run
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
 /// async fn upd_example(pool: &PgPool) -> Result<(), NotebookError> {
 ///    add("wrong_note", "Thos is erong nlte", pool).await?;
 ///
 ///    // Returns updated note
-///    let mut upd_row = upd("wrong_note", "This is NOT wrong note", pool).await?;
+///    let upd_row = upd("wrong_note", "This is NOT wrong note", pool).await?;
 ///
-///    assert_eq!("This is NOT wrong note", upd_row.note_str().await);
+///    assert_eq!("This is NOT wrong note", upd_row.body());
 ///
 ///    Ok(())
 /// }
 /// ```
 pub async fn upd(notename: &str, new_note: &str, pool: &PgPool) -> Result<Note, NotebookError> {
-    match sqlx::query!(
+    config::check_size(new_note.len())?;
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    match query_as!(
+        { id: i32, note_name: String, note: Option<String> },
         "
 UPDATE notebook
-SET note = $1
+SET note = $1, note_bytes = NULL
 WHERE note_name = $2
 RETURNING id, note_name, note
         ",
         new_note,
         notename,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
     {
         Ok(upd_row) => {
-            event!(Level::INFO, "Update `{}` data to:\n{}", notename, new_note,);
+            compression::pack(upd_row.id, upd_row.note.as_deref(), &mut tx).await?;
+            tx.commit().await?;
+
+            event!(
+                Level::INFO,
+                "Update `{}` data to:\n{}",
+                notename,
+                logged(new_note)
+            );
 
             Ok(Note {
                 id: upd_row.id,
@@ -343,49 +813,361 @@ RETURNING id, note_name, note
     }
 }
 
-/// Updates notename and returns note that name was updated.
+/// Returns SHA-256 hash of content of note (in hex), the same as
+/// [`Revision::body_hash`][crate::history::Revision] of its last revision.
+///
+/// Pass it to [`upd_if_unchanged`] to update note only if nobody changed it since it was read.
 /// ### Returns
-/// * Ok
-///     * [Note] that name was updated
 /// * Errors
-///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
-/// ### Example
-/// ```rust,no run
-/// async fn upd_notename_example(pool: &PgPool) -> Result<(), NotebookError> {
-///    add("wrlng_nptenAme", "", pool).await?;
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn content_hash(notename: &str, pool: &PgPool) -> Result<String, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let row = query_as!(
+        { hash: String, packed: Option<Vec<u8>> },
+        r#"
+SELECT
+    encode(sha256(convert_to(COALESCE(note, ''), 'UTF8')), 'hex') AS "hash!",
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $1
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    match compression::content(notename, None, row.packed)? {
+        Some(note) => Ok(hash_content(&note)),
+        None => Ok(row.hash),
+    }
+}
+
+/// Returns SHA-256 hash of `note` (in hex), the same as [`content_hash`] returns for note
+/// with such content.
 ///
-///    // Returns updated notename
-///    let upd_row = upd_notename("wrlng_nptenAme", "not_wrong_name", pool).await?;
+/// Use it to get hash of content that was read earlier, so [`upd_if_unchanged`] finds changes
+/// made since it was read.
+/// ### Example
+/// ```
+/// # use lnotebook::commands::hash_content;
+/// assert_eq!(
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+///     hash_content("")
+/// );
+/// ```
+pub fn hash_content(note: &str) -> String {
+    format!("{:x}", Sha256::digest(note.as_bytes()))
+}
+
+/// Updates content of note like [`upd`], but only if its content still has hash `expected_hash`
+/// returned by [`content_hash`] or [`hash_content`], so changes made by other clients since note
+/// was read are not overwritten.
 ///
-///    assert_eq!("not_wrong_name", upd_row.note_name);
+/// Hash is checked in the same query that updates note.
+/// ### Returns
+/// * Ok
+///     * [Note] that was updated
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Conflict`] error if content of note was changed
+///     * [`NotebookError::NoteTooLarge`] error if `new_note` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn upd_if_unchanged_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk", pool).await?;
+///     let hash = content_hash("shopping", pool).await?;
 ///
-///    Ok(())
+///     // Other client changes note
+///     upd("shopping", "milk\nbread", pool).await?;
+///
+///     let res = upd_if_unchanged("shopping", &hash, "milk\neggs", pool).await;
+///
+///     assert!(matches!(res, Err(NotebookError::Conflict { .. })));
+///
+///     Ok(())
 /// }
 /// ```
-pub async fn upd_notename(
+pub async fn upd_if_unchanged(
     notename: &str,
-    new_notename: &str,
+    expected_hash: &str,
+    new_note: &str,
     pool: &PgPool,
 ) -> Result<Note, NotebookError> {
-    match sqlx::query!(
+    config::check_size(new_note.len())?;
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    let updated = query_as!(
+        Note { id: i32, note_name: String, note: Option<String> },
         "
 UPDATE notebook
-SET note_name = $1
+SET note = $1, note_bytes = NULL
 WHERE note_name = $2
+    AND encode(sha256(convert_to(COALESCE(note, ''), 'UTF8')), 'hex') = $3
 RETURNING id, note_name, note
         ",
-        new_notename,
+        new_note,
+        notename,
+        expected_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    match updated {
+        Some(note) => {
+            compression::pack(note.id, note.note.as_deref(), &mut tx).await?;
+            tx.commit().await?;
+
+            event!(
+                Level::INFO,
+                "Update `{}` data to:\n{}",
+                notename,
+                logged(new_note)
+            );
+
+            Ok(note)
+        }
+        None if exists(notename, pool).await? => Err(NotebookError::Conflict {
+            notename: notename.to_owned(),
+        }),
+        None => Err(NotebookError::Sqlx(sqlx::Error::RowNotFound)),
+    }
+}
+
+/// Adds text to the end of note and returns updated note.
+/// ### Returns
+/// * Ok
+///     * [Note] that was updated
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::NoteTooLarge`] error if note with `text` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
+///     * [`NotebookError::BinaryNote`] error if note is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn append_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk\n", pool).await?;
+///
+///     let upd_row = append("shopping", "bread\n", pool).await?;
+///
+///     assert_eq!("milk\nbread\n", upd_row.body());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn append(notename: &str, text: &str, pool: &PgPool) -> Result<Note, NotebookError> {
+    config::check_size(text.len())?;
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    let upd_row = query_as!(
+        { id: i32, note_name: String, note: Option<String>, size: i32 },
+        r#"
+UPDATE notebook
+SET note = COALESCE(note, '') || $1
+WHERE note_name = $2
+RETURNING id, note_name, note, octet_length(note) AS "size!"
+        "#,
+        text,
         notename
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| text_error(notename, err))?;
+
+    // Size of the whole note is known only after update, so larger note is rolled back
+    config::check_size(upd_row.size as usize)?;
+    compression::pack(upd_row.id, upd_row.note.as_deref(), &mut tx).await?;
+    tx.commit().await?;
+
+    event!(Level::INFO, "Append to `{}`:\n{}", notename, logged(text));
+
+    Ok(Note {
+        id: upd_row.id,
+        note_name: upd_row.note_name,
+        note: upd_row.note,
+    })
+}
+
+/// Adds text to the beginning of note and returns updated note.
+/// ### Returns
+/// * Ok
+///     * [Note] that was updated
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::NoteTooLarge`] error if note with `text` is larger than
+///       [`max_note_size`][crate::config::max_note_size]
+///     * [`NotebookError::BinaryNote`] error if note is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn prepend_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("todo", "feed the cat\n", pool).await?;
+///
+///     let upd_row = prepend("todo", "buy cat food\n", pool).await?;
+///
+///     assert_eq!("buy cat food\nfeed the cat\n", upd_row.body());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn prepend(notename: &str, text: &str, pool: &PgPool) -> Result<Note, NotebookError> {
+    config::check_size(text.len())?;
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    let upd_row = query_as!(
+        { id: i32, note_name: String, note: Option<String>, size: i32 },
+        r#"
+UPDATE notebook
+SET note = $1 || COALESCE(note, '')
+WHERE note_name = $2
+RETURNING id, note_name, note, octet_length(note) AS "size!"
+        "#,
+        text,
+        notename
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| text_error(notename, err))?;
+
+    // Size of the whole note is known only after update, so larger note is rolled back
+    config::check_size(upd_row.size as usize)?;
+    compression::pack(upd_row.id, upd_row.note.as_deref(), &mut tx).await?;
+    tx.commit().await?;
+
+    event!(Level::INFO, "Prepend to `{}`:\n{}", notename, logged(text));
+
+    Ok(Note {
+        id: upd_row.id,
+        note_name: upd_row.note_name,
+        note: upd_row.note,
+    })
+}
+
+/// Inserts text before line `line_no` of note and returns updated note.
+///
+/// Lines are numbered from 1. If `line_no` is greater than the number of lines in note,
+/// text will be added as the last line.
+/// ### Returns
+/// * Ok
+///     * [Note] that was updated
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::InvalidLineNumber`] error if `line_no` is 0
+///     * [`NotebookError::BinaryNote`] error if note is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn insert_at_line_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("steps", "first\nthird", pool).await?;
+///
+///     let upd_row = insert_at_line("steps", 2, "second", pool).await?;
+///
+///     assert_eq!("first\nsecond\nthird", upd_row.body());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn insert_at_line(
+    notename: &str,
+    line_no: u32,
+    text: &str,
+    pool: &PgPool,
+) -> Result<Note, NotebookError> {
+    lock::check_unlocked(notename, pool).await?;
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let line = match i32::try_from(line_no) {
+        Ok(line) if line > 0 => line,
+        _ => return Err(NotebookError::InvalidLineNumber { line_no }),
+    };
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(notename, &mut tx).await?;
+
+    match query_as!(
+        { id: i32, note_name: String, note: Option<String> },
+        "
+UPDATE notebook
+SET note = array_to_string(
+    (string_to_array(COALESCE(note, ''), E'\\n'))[:$1::INT4 - 1]
+    || string_to_array($2, E'\\n')
+    || (string_to_array(COALESCE(note, ''), E'\\n'))[$1::INT4:],
+    E'\\n'
+)
+WHERE note_name = $3
+RETURNING id, note_name, note
+        ",
+        line,
+        text,
+        notename
+    )
+    .fetch_one(&mut *tx)
     .await
     {
         Ok(upd_row) => {
+            compression::pack(upd_row.id, upd_row.note.as_deref(), &mut tx).await?;
+            tx.commit().await?;
+
             event!(
                 Level::INFO,
-                "Update notename\nFrom: {}\nTo: {}",
+                "Insert into `{}` at line {}:\n{}",
                 notename,
-                new_notename
+                line_no,
+                logged(text)
             );
 
             Ok(Note {
@@ -394,20 +1176,907 @@ RETURNING id, note_name, note
                 note: upd_row.note,
             })
         }
-        Err(err) => Err(NotebookError::Sqlx(err)),
+        Err(err) => Err(text_error(notename, err)),
+    }
+}
+
+/// What to do when note is renamed to name of other note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OnConflict {
+    /// Fail with [`NotebookError::AlreadyTaken`]
+    #[default]
+    Error,
+    /// Append content of renamed note to the other note and delete renamed note
+    Merge,
+    /// Delete the other note
+    Overwrite,
+}
+
+/// Returns content of note `target` with content of merged note appended on a new line.
+fn merge_content(target: Option<&str>, merged: Option<&str>) -> String {
+    let (target, merged) = (target.unwrap_or(""), merged.unwrap_or(""));
+
+    if target.is_empty() || merged.is_empty() || target.ends_with('\n') {
+        format!("{target}{merged}")
+    } else {
+        format!("{target}\n{merged}")
+    }
+}
+
+pub(crate) async fn rename_in(
+    notename: &str,
+    new_notename: &str,
+    on_conflict: OnConflict,
+    conn: &mut PgConnection,
+) -> Result<Note, NotebookError> {
+    NoteName::new(new_notename)?;
+
+    let old = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $1
+FOR UPDATE
+        ",
+        notename
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .into_note()?;
+
+    if notename == new_notename {
+        return Ok(old);
+    }
+
+    lock::check_unlocked(notename, &mut *conn).await?;
+    users::check_access(notename, Access::Write, &mut *conn).await?;
+    protect::check_passphrase(notename, &mut *conn).await?;
+    if on_conflict != OnConflict::Error {
+        lock::check_unlocked(new_notename, &mut *conn).await?;
+        users::check_access(new_notename, Access::Write, &mut *conn).await?;
+        protect::check_passphrase(new_notename, &mut *conn).await?;
+    }
+
+    let target = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $1
+FOR UPDATE
+        ",
+        new_notename
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .map(StoredNote::into_note)
+    .transpose()?;
+
+    match (target, on_conflict) {
+        (None, _) => {}
+        (Some(_), OnConflict::Error) => {
+            return Err(NotebookError::AlreadyTaken {
+                notename: new_notename.to_owned(),
+            })
+        }
+        (Some(target), OnConflict::Overwrite) => {
+            query!("DELETE FROM notebook WHERE id = $1", target.id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        (Some(target), OnConflict::Merge) => {
+            // Content of binary note would be lost, because only text is merged
+            let binary = query_scalar!(
+                String,
+                "
+SELECT note_name
+FROM notebook
+WHERE id IN ($1, $2) AND note_bytes IS NOT NULL AND NOT compressed
+                ",
+                target.id,
+                old.id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            if let Some(notename) = binary {
+                return Err(NotebookError::BinaryNote { notename });
+            }
+
+            let note = merge_content(target.note.as_deref(), old.note.as_deref());
+
+            // Attachments with names that merged note already has are deleted with renamed note
+            query!(
+                "
+UPDATE attachments
+SET note_id = $1
+WHERE note_id = $2
+    AND file_name NOT IN (SELECT file_name FROM attachments WHERE note_id = $1)
+                ",
+                target.id,
+                old.id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            compression::unpack(new_notename, &mut *conn).await?;
+            let merged = query_as!(
+                Note { id: i32, note_name: String, note: Option<String> },
+                "
+UPDATE notebook
+SET note = $1,
+    due_at = COALESCE(notebook.due_at, old.due_at),
+    metadata = old.metadata || notebook.metadata,
+    passphrase_hash = COALESCE(notebook.passphrase_hash, old.passphrase_hash)
+FROM notebook AS old
+WHERE notebook.id = $2 AND old.id = $3
+RETURNING notebook.id, notebook.note_name, notebook.note
+                ",
+                note,
+                target.id,
+                old.id
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+
+            query!("DELETE FROM notebook WHERE id = $1", old.id)
+                .execute(&mut *conn)
+                .await?;
+            compression::pack(merged.id, merged.note.as_deref(), &mut *conn).await?;
+
+            event!(Level::INFO, "Merge `{}` into `{}`", notename, new_notename);
+
+            return Ok(merged);
+        }
+    }
+
+    let renamed = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+UPDATE notebook
+SET note_name = $1
+WHERE id = $2
+RETURNING id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+        ",
+        new_notename,
+        old.id
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .into_note()?;
+
+    event!(
+        Level::INFO,
+        "Update notename\nFrom: {}\nTo: {}",
+        notename,
+        new_notename
+    );
+
+    Ok(renamed)
+}
+
+/// Renames note, resolving conflict with other note that already has `new_notename`
+/// as `on_conflict` says.
+///
+/// With [`OnConflict::Merge`] content of renamed note is appended on a new line to content
+/// of the other note, which keeps its name, and renamed note is deleted. The other note also
+/// gets files attached to renamed note (except ones with the same names as its own), its
+/// deadline and metadata if it doesn't have them, and its protection if it's not protected.
+/// Everything is done in the same transaction.
+/// ### Returns
+/// * Ok
+///     * [Note] that has `new_notename` now
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `new_notename` is not valid
+///     * [`NotebookError::AlreadyTaken`] error if `on_conflict` is [`OnConflict::Error`]
+///       and `new_notename` is already taken
+///     * [`NotebookError::BinaryNote`] error if `on_conflict` is [`OnConflict::Merge`]
+///       and one of notes is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn rename_example(pool: &PgPool) -> Result<(), NotebookError> {
+///    add("shopping", "milk", pool).await?;
+///    add("groceries", "bread", pool).await?;
+///
+///    let merged = rename("shopping", "groceries", OnConflict::Merge, pool).await?;
+///
+///    assert_eq!("bread\nmilk", merged.body());
+///    assert!(!exists("shopping", pool).await?);
+///
+///    Ok(())
+/// }
+/// ```
+pub async fn rename(
+    notename: &str,
+    new_notename: &str,
+    on_conflict: OnConflict,
+    pool: &PgPool,
+) -> Result<Note, NotebookError> {
+    let mut tx = pool.begin().await?;
+
+    let note = rename_in(notename, new_notename, on_conflict, &mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(note)
+}
+
+/// Updates notename and returns note that name was updated.
+///
+/// It's the same as [`rename`] with [`OnConflict::Error`].
+/// ### Returns
+/// * Ok
+///     * [Note] that name was updated
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `new_notename` is not valid
+///     * [`NotebookError::AlreadyTaken`] error if `new_notename` is already taken
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn upd_notename_example(pool: &PgPool) -> Result<(), NotebookError> {
+///    add("wrlng_nptenAme", "", pool).await?;
+///
+///    // Returns updated notename
+///    let upd_row = upd_notename("wrlng_nptenAme", "not_wrong_name", pool).await?;
+///
+///    assert_eq!("not_wrong_name", upd_row.note_name);
+///
+///    Ok(())
+/// }
+/// ```
+pub async fn upd_notename(
+    notename: &str,
+    new_notename: &str,
+    pool: &PgPool,
+) -> Result<Note, NotebookError> {
+    rename(notename, new_notename, OnConflict::Error, pool).await
+}
+
+/// Renames note like [`rename`] and rewrites wiki-style links `[[notename]]` in all notes to
+/// `[[new_notename]]`, so links to the renamed note don't break.
+///
+/// Rename and rewriting of links are done in the same transaction.
+/// ### Returns
+/// * Ok
+///     * [Note] that has `new_notename` now and the number of notes whose links were rewritten
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `new_notename` is not valid
+///     * [`NotebookError::AlreadyTaken`] error if `on_conflict` is [`OnConflict::Error`]
+///       and `new_notename` is already taken
+///     * [`NotebookError::BinaryNote`] error if `on_conflict` is [`OnConflict::Merge`]
+///       and one of notes is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn rename_rewrite_links(
+    notename: &str,
+    new_notename: &str,
+    on_conflict: OnConflict,
+    pool: &PgPool,
+) -> Result<(Note, u64), NotebookError> {
+    let mut tx = pool.begin().await?;
+
+    // Renamed note is locked first, so links can't be rewritten if it doesn't exist
+    let note = rename_in(notename, new_notename, on_conflict, &mut tx).await?;
+
+    let rewritten = rewrite_links_in(notename, new_notename, &mut tx).await?;
+
+    tx.commit().await?;
+
+    event!(
+        Level::INFO,
+        "Rewrote links to `{}` in {} notes",
+        notename,
+        rewritten
+    );
+
+    Ok((note, rewritten))
+}
+
+/// Rewrites links `[[notename]]` in all notes to `[[new_notename]]` and returns the number
+/// of notes whose links were rewritten.
+pub(crate) async fn rewrite_links_in(
+    notename: &str,
+    new_notename: &str,
+    conn: &mut PgConnection,
+) -> Result<u64, NotebookError> {
+    let link = format!("[[{notename}]]");
+    let unpacked = compression::unpack_matching(|note| note.contains(&link), conn).await?;
+    let rewritten = query!(
+        "
+UPDATE notebook
+SET note = replace(note, '[[' || $1 || ']]', '[[' || $2 || ']]')
+WHERE strpos(note, '[[' || $1 || ']]') > 0
+        ",
+        notename,
+        new_notename
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    compression::pack_many(&unpacked, conn).await?;
+
+    Ok(rewritten)
+}
+
+/// Updates notename and rewrites wiki-style links `[[notename]]` in all notes to
+/// `[[new_notename]]`, so links to the renamed note don't break.
+///
+/// It's the same as [`rename_rewrite_links`] with [`OnConflict::Error`].
+/// ### Returns
+/// * Ok
+///     * [Note] that name was updated and the number of notes whose links were rewritten
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `new_notename` is not valid
+///     * [`NotebookError::AlreadyTaken`] error if `new_notename` is already taken
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn upd_notename_rewrite_links_example(pool: &PgPool) -> Result<(), NotebookError> {
+///    add("recipes", "", pool).await?;
+///    add("dinner", "Look at [[recipes]]", pool).await?;
+///
+///    let (_, rewritten) = upd_notename_rewrite_links("recipes", "cookbook", pool).await?;
+///    let row = select_one("dinner", pool).await?;
+///
+///    assert_eq!(1, rewritten);
+///    assert_eq!("Look at [[cookbook]]", row.body());
+///
+///    Ok(())
+/// }
+/// ```
+pub async fn upd_notename_rewrite_links(
+    notename: &str,
+    new_notename: &str,
+    pool: &PgPool,
+) -> Result<(Note, u64), NotebookError> {
+    rename_rewrite_links(notename, new_notename, OnConflict::Error, pool).await
+}
+
+/// Renames all notes whose names match regular expression `pattern`.
+///
+/// New notename is made by replacing the first match of `pattern` with `replacement`,
+/// which can refer to groups of `pattern` as `$1`, `$2` or `$name`
+/// (read more in [`regex::Regex::replace`]). All notes are renamed in the same transaction,
+/// so if any note can't be renamed, no notes are renamed. Notes the acting user can't change
+/// (read about them in [`users` module][crate::users]) are not matched.
+/// ### Returns
+/// * Ok
+///     * `Vec` of old and new notenames of renamed notes
+/// * Errors
+///     * [`NotebookError::InvalidPattern`] error if `pattern` is not a valid regular expression
+///     * [`NotebookError::InvalidNoteName`] error if new notename is not valid
+///     * [`NotebookError::AlreadyTaken`] error if new notename is already taken
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn upd_notename_bulk_example(pool: &PgPool) -> Result<(), NotebookError> {
+///    add("tmp_shopping", "", pool).await?;
+///    add("tmp_ideas", "", pool).await?;
+///
+///    let renamed = upd_notename_bulk("^tmp_(.*)", "draft_$1", pool).await?;
+///
+///    assert_eq!(2, renamed.len());
+///    select_one("draft_ideas", pool).await?;
+///
+///    Ok(())
+/// }
+/// ```
+pub async fn upd_notename_bulk(
+    pattern: &str,
+    replacement: &str,
+    pool: &PgPool,
+) -> Result<Vec<(String, String)>, NotebookError> {
+    let re = Regex::new(pattern).map_err(|err| NotebookError::InvalidPattern {
+        pattern: pattern.to_owned(),
+        reason: err.to_string(),
+    })?;
+
+    let mut tx = pool.begin().await?;
+
+    let rows = query_as!(
+        { id: i32, note_name: String },
+        "
+SELECT id, note_name
+FROM notebook
+WHERE can_access_note(id, owner, $1, true)
+ORDER BY note_name
+FOR UPDATE
+        ",
+        users::acting_user()
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut renamed = Vec::new();
+    let mut new_notenames = HashSet::new();
+    for row in rows {
+        if !re.is_match(&row.note_name) {
+            continue;
+        }
+
+        let new_notename = re.replace(&row.note_name, replacement).into_owned();
+        if new_notename == row.note_name {
+            continue;
+        }
+        NoteName::new(&new_notename)?;
+        if !new_notenames.insert(new_notename.to_owned()) {
+            return Err(NotebookError::AlreadyTaken {
+                notename: new_notename,
+            });
+        }
+
+        renamed.push((row.id, row.note_name, new_notename));
+    }
+
+    // Notes are first moved to temporary names, so notes can take names
+    // of each other (for example `a` -> `b` and `b` -> `c`). Temporary names contain `[`,
+    // so they can't be taken by any note
+    for (id, _, _) in &renamed {
+        query!(
+            "
+UPDATE notebook
+SET note_name = '[rename]' || id
+WHERE id = $1
+            ",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    for (id, _, new_notename) in &renamed {
+        query!(
+            "
+UPDATE notebook
+SET note_name = $1
+WHERE id = $2
+            ",
+            new_notename,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| {
+            if errors::is_unique_violation(&err) {
+                NotebookError::AlreadyTaken {
+                    notename: new_notename.to_owned(),
+                }
+            } else {
+                err.into()
+            }
+        })?;
+    }
+    // Temporary names were never seen by `sync`, so they must not be deleted in other notebooks
+    query!(
+        "
+DELETE FROM note_tombstones
+WHERE note_name LIKE '[rename]%'
+        "
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let renamed: Vec<(String, String)> = renamed
+        .into_iter()
+        .map(|(_, notename, new_notename)| (notename, new_notename))
+        .collect();
+    for (notename, new_notename) in &renamed {
+        event!(
+            Level::INFO,
+            "Update notename\nFrom: {}\nTo: {}",
+            notename,
+            new_notename
+        );
+    }
+
+    Ok(renamed)
+}
+
+/// This is a `struct` that containing changed line of note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChange {
+    /// Number of line, lines are numbered from 1
+    pub line_no: u32,
+    pub old: String,
+    pub new: String,
+}
+
+/// This is a `struct` that containing changes of one note made by [`replace_all`].
+///
+/// It implements [`fmt::Display`], which renders changes as diff of lines:
+/// ```text
+/// bookmarks:2
+/// - docs: http://old.example.com/docs
+/// + docs: https://example.com/docs
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteReplacement {
+    pub note_name: String,
+    /// Changed lines ordered by number
+    pub changes: Vec<LineChange>,
+}
+
+impl fmt::Display for NoteReplacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{}:{}\n- {}\n+ {}",
+                self.note_name, change.line_no, change.old, change.new
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces all matches of `re` in every line of `text` with `replacement`.
+fn replace_lines(re: &Regex, replacement: &str, text: &str) -> (String, Vec<LineChange>) {
+    let mut res = String::with_capacity(text.len());
+    let mut changes = Vec::new();
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let (line, end) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+
+        let new_line = re.replace_all(line, replacement);
+        if new_line != line {
+            changes.push(LineChange {
+                line_no: i as u32 + 1,
+                old: line.to_owned(),
+                new: new_line.clone().into_owned(),
+            });
+        }
+
+        res += &new_line;
+        res += end;
+    }
+
+    (res, changes)
+}
+
+/// Replaces all matches of regular expression `pattern` in content of all notes with `replacement`.
+///
+/// Like `sed`, `pattern` is matched in every line separately, so `^` and `$` match start and end
+/// of line. `replacement` can refer to groups of `pattern` as `$1`, `$2` or `$name`
+/// (read more in [`regex::Regex::replace`]). All notes are changed in the same transaction.
+/// If `dry_run` is `true`, notes are not changed, only changes that would be made are returned.
+/// Protected notes (read about them in [`protect` module][crate::protect]) and notes the acting
+/// user can't change (read about them in [`users` module][crate::users]) are skipped.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`NoteReplacement`] of changed notes ordered by notename
+/// * Errors
+///     * [`NotebookError::InvalidPattern`] error if `pattern` is not a valid regular expression
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn replace_all_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("bookmarks", "news: http://old.example.com/news", pool).await?;
+///
+///     let changed = replace_all(r"http://old\.example\.com", "https://example.com", false, pool).await?;
+///
+///     assert_eq!(1, changed.len());
+///     assert_eq!("news: https://example.com/news", changed[0].changes[0].new);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn replace_all(
+    pattern: &str,
+    replacement: &str,
+    dry_run: bool,
+    pool: &PgPool,
+) -> Result<Vec<NoteReplacement>, NotebookError> {
+    let re = Regex::new(pattern).map_err(|err| NotebookError::InvalidPattern {
+        pattern: pattern.to_owned(),
+        reason: err.to_string(),
+    })?;
+
+    let mut tx = pool.begin().await?;
+
+    let rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE (note IS NOT NULL OR compressed)
+    AND passphrase_hash IS NULL
+    AND can_access_note(id, owner, $1, true)
+ORDER BY note_name
+FOR UPDATE
+        ",
+        users::acting_user()
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut replaced = Vec::new();
+    for row in rows {
+        let compressed = row.packed.is_some();
+        let row = row.into_note()?;
+        let (new_note, changes) = replace_lines(&re, replacement, row.body());
+        if changes.is_empty() {
+            continue;
+        }
+
+        if !dry_run {
+            if compressed {
+                compression::unpack_with(row.id, row.body(), &mut tx).await?;
+            }
+            query!(
+                "
+UPDATE notebook
+SET note = $1
+WHERE id = $2
+                ",
+                new_note,
+                row.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            compression::pack(row.id, Some(&new_note), &mut tx).await?;
+        }
+
+        replaced.push(NoteReplacement {
+            note_name: row.note_name,
+            changes,
+        });
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+        return Ok(replaced);
+    }
+
+    tx.commit().await?;
+
+    for note in &replaced {
+        event!(
+            Level::INFO,
+            "Replace `{}` in {} lines of `{}`",
+            pattern,
+            note.changes.len(),
+            note.note_name
+        );
+    }
+
+    Ok(replaced)
+}
+
+/// Returns lines from `start_line` to `end_line` (both inclusive) of the requested note.
+///
+/// Lines are numbered from 1. Lines after the end of note are ignored, so `u32::MAX`
+/// can be used as `end_line` to get everything from `start_line` to the end of note.
+/// Lines are selected by database, so the whole note isn't loaded (unless it's compressed).
+/// ### Returns
+/// * Ok
+///     * Selected lines joined with `\n`
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::InvalidLineNumber`] error if `start_line` is 0 or `end_line` is less than `start_line`
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn select_range_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("steps", "first\nsecond\nthird\nfourth", pool).await?;
+///
+///     assert_eq!("second\nthird", select_range("steps", 2, 3, pool).await?);
+///     assert_eq!("fourth", select_range("steps", 4, u32::MAX, pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn select_range(
+    notename: &str,
+    start_line: u32,
+    end_line: u32,
+    pool: &PgPool,
+) -> Result<String, NotebookError> {
+    let start = match i32::try_from(start_line) {
+        Ok(line) if line > 0 => line,
+        _ => {
+            return Err(NotebookError::InvalidLineNumber {
+                line_no: start_line,
+            })
+        }
+    };
+    if end_line < start_line {
+        return Err(NotebookError::InvalidLineNumber { line_no: end_line });
+    }
+    let end = i32::try_from(end_line).unwrap_or(i32::MAX);
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let row = query_as!(
+        { lines: String, packed: Option<Vec<u8>> },
+        r#"
+SELECT
+    array_to_string(
+        (string_to_array(COALESCE(note, ''), E'\n'))[$1::INT4:$2::INT4],
+        E'\n'
+    ) AS "lines!",
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $3
+        "#,
+        start,
+        end,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    match compression::content(notename, None, row.packed)? {
+        Some(note) => Ok(note
+            .split('\n')
+            .skip(start as usize - 1)
+            .take((end - start) as usize + 1)
+            .collect::<Vec<_>>()
+            .join("\n")),
+        None => Ok(row.lines),
     }
 }
 
+/// Checks if note with `notename` exists.
+/// ### Returns
+/// * Ok
+///     * `true` if note exists
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn exists_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk", pool).await?;
+///
+///     assert!(exists("shopping", pool).await?);
+///     assert!(!exists("travel", pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn exists(notename: &str, pool: &PgPool) -> Result<bool, NotebookError> {
+    let exists = query_scalar!(
+        bool,
+        r#"
+SELECT EXISTS (SELECT 1 FROM notebook WHERE note_name = $1) AS "exists!"
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Returns number of notes in notebook.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn count_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     del_all(pool).await?;
+///     add("shopping", "milk", pool).await?;
+///
+///     assert_eq!(1, count(pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn count(pool: &PgPool) -> Result<i64, NotebookError> {
+    let count = query_scalar!(
+        i64,
+        r#"
+SELECT COUNT(*) AS "count!"
+FROM notebook
+WHERE can_access_note(id, owner, $1, false)
+        "#,
+        users::acting_user()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Returns random note, every note can be returned with the same probability.
+/// Protected notes (read about them in [`protect` module][crate::protect]) and notes hidden
+/// from the acting user (read about them in [`users` module][crate::users]) are never returned.
+/// ### Returns
+/// * Ok
+///     * [Note]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error with [`sqlx::Error::RowNotFound`]
+///       if notebook is empty or all notes are protected or hidden
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn random_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let note = random(pool).await?;
+///
+///     println!("Review note `{}`", note.note_name);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn random(pool: &PgPool) -> Result<Note, NotebookError> {
+    let note = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE passphrase_hash IS NULL AND can_access_note(id, owner, $1, false)
+ORDER BY random()
+LIMIT 1
+        ",
+        users::acting_user()
+    )
+    .fetch_one(pool)
+    .await?
+    .into_note()?;
+
+    event!(Level::INFO, "Select random note `{}`", note.note_name);
+
+    Ok(note)
+}
+
 /// Returns the requested note.
 /// ### Returns
 /// * Ok
 ///     * [Note]
 /// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
 ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
 pub async fn select_one(notename: &str, pool: &PgPool) -> Result<Note, NotebookError> {
-    let row = sqlx::query!(
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let row = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
         "
-SELECT *
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
 FROM notebook
 WHERE note_name = $1
         ",
@@ -416,9 +2085,126 @@ WHERE note_name = $1
     .fetch_one(pool)
     .await?;
 
-    Ok(Note {
-        id: row.id,
-        note: row.note,
-        note_name: row.note_name,
-    })
+    row.into_note()
+}
+
+/// Returns all notes whose names start with `prefix`, ordered by name.
+///
+/// Useful when notes are named by convention, like `project_x_todo` and `project_x_ideas`.
+/// Protected notes (read about them in [`protect` module][crate::protect]) are returned only if
+/// their passphrases are given.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [Note], empty if no notes start with `prefix`
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn select_by_prefix_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("meeting_monday", "Plan the week", pool).await?;
+///     add("meeting_friday", "Review the week", pool).await?;
+///
+///     let notes = select_by_prefix("meeting_", pool).await?;
+///
+///     assert_eq!("meeting_friday", notes[0].note_name);
+///     assert_eq!("meeting_monday", notes[1].note_name);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn select_by_prefix(prefix: &str, pool: &PgPool) -> Result<Vec<Note>, NotebookError> {
+    let rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE starts_with(note_name, $1) AND can_access_note(id, owner, $2, false)
+ORDER BY note_name
+        ",
+        prefix,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut notes = Vec::with_capacity(rows.len());
+    for row in rows {
+        if protect::is_opened(&row.note_name, pool).await? {
+            notes.push(row.into_note()?);
+        }
+    }
+
+    Ok(notes)
+}
+
+/// Returns all total notes in notebook, except protected ones (read about them
+/// in [`protect` module][crate::protect]) and notes hidden from the acting user (read about
+/// them in [`users` module][crate::users]).
+/// ### Returns
+/// * Ok
+///     * `Vec` of [Note]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_all(pool: &PgPool) -> Result<Vec<Note>, NotebookError> {
+    let rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE passphrase_hash IS NULL AND can_access_note(id, owner, $1, false)
+ORDER BY id
+        ",
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(StoredNote::into_note).collect()
+}
+
+/// Returns stream of all total notes in notebook ordered by ID.
+///
+/// Unlike [`select_all`], notes are read from database while stream is consumed,
+/// so all notes are never loaded into memory at the same time. Use it to process
+/// large notebooks note by note. Like [`select_all`], it skips protected notes and notes
+/// hidden from the user acting when the stream is created.
+/// ### Returns
+/// * Stream items
+///     * [Note]
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::NotebookError;
+/// # use futures_util::TryStreamExt;
+/// # use sqlx::PgPool;
+/// async fn stream_all_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let mut notes = stream_all(pool);
+///
+///     let mut bytes = 0;
+///     while let Some(note) = notes.try_next().await? {
+///         bytes += note.note.map_or(0, |note| note.len());
+///     }
+///     println!("All notes take {bytes} bytes");
+///
+///     Ok(())
+/// }
+/// ```
+pub fn stream_all(pool: &PgPool) -> impl Stream<Item = Result<Note, NotebookError>> + '_ {
+    query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE passphrase_hash IS NULL AND can_access_note(id, owner, $1, false)
+ORDER BY id
+        ",
+        users::acting_user()
+    )
+    .fetch(pool)
+    .map_err(NotebookError::from)
+    .and_then(|row| future::ready(row.into_note()))
 }
@@ -13,30 +13,54 @@
 //! call [`NoteCommand::new`] and [`NoteCommand::execute_command`].
 //! For example, this is what the code from [`notebook_example`](https://github.com/Nnenty/lnotebook/tree/master/notebook_example)
 //! that meets the requirements looks like:
-//! ```rust,no run
+//! ```rust,no_run
+//! # use lnotebook::{connect, get_db_url, NoteCommand, Verbosity};
+//! # use tracing::{event, Level};
+//! # use tracing_subscriber::{fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
 //! // --snip--
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
-//!    tracing_subscriber::registry()
-//!         .with(fmt::layer())
-//!         .with(EnvFilter::new("debug"))
-//!         .init();
+//!     // Converting CLI command variable to NoteCommand option
+//!     let a = NoteCommand::new().await.unwrap_or_else(|err| err.exit());
+//!
+//!     // With `--script` and `--quiet` only results of command are printed, so logging is disabled
+//!     let level = match a.verbosity() {
+//!         Verbosity::Quiet => None,
+//!         Verbosity::Normal => Some("info"),
+//!         Verbosity::Verbose => Some("debug"),
+//!     };
+//!     if let Some(level) = level {
+//!         tracing_subscriber::registry()
+//!             .with(fmt::layer())
+//!             .with(EnvFilter::new(level))
+//!             .init();
+//!     }
 //!
 //!     // Get database URL from enivroment variable
 //!     let db_url = get_db_url().await?;
 //!
-//!     // Connecting to database
-//!     let db = PgPool::connect(&db_url).await?;
+//!     // Connecting to database, retrying if it's temporarily unavailable
+//!     let db = match connect(&db_url, a.connect_options()).await {
+//!         Ok(db) => db,
+//!         // Changes can be queued while database is unreachable
+//!         Err(err) => {
+//!             a.execute_offline(err).await?;
+//!             return Ok(());
+//!         }
+//!     };
 //!
 //!     event!(Level::DEBUG, "Connect to db");
 //!
-//!     // Converting CLI command variable to NoteCommand option
-//!     let a = NoteCommand::new().await?;
 //!     // Execute the selected command
-//!     a.execute_command(&db).await?;
+//!     let response = a.execute_command(&db).await?;
 //!
 //!     event!(Level::DEBUG, "Command executed");
 //!
+//!     // For example `exists` exits with code 1 if note doesn't exist
+//!     if response.exit_code() != 0 {
+//!         std::process::exit(response.exit_code());
+//!     }
+//!
 //!     Ok(())
 //! }
 //! ```
@@ -45,14 +69,234 @@
 //! cargo run -- `your-command`
 //! ```
 //! ##### List of all commands you can call from CLI:
-//! * `add-note <notename>` - will prompt to enter new note that will be added to the notebook under `notename`.
+//! * `add-note <notename> [-m <text> | --file <path>]` - will prompt to enter new note that will be added
+//!   to the notebook under `notename`. Content can be given with `--message` instead, or read from file
+//!   with `--file` (`--file -` reads standard input until its end), for example in scripts and cron jobs.
+//!   With `--auto-name` instead of `notename` note is named after its first line or heading,
+//!   with suffix like `_2` if the name is taken (read more in
+//!   [`NoteName::from_body`](crate::notename::NoteName::from_body)).
+//! * `add-binary <notename> --file <path>` - adds binary note with content of file as it is
+//!   (`--file -` reads standard input), for keys, images and other files that are not text.
+//!   Read about binary notes in [`add_binary`](crate::commands::add_binary).
 //! * `del-note <notename>` - deletes note with `notename` if it exist.
-//! * `del-all` - deletes all total notes from the notebook.
+//! * `del-all [--force]` - deletes all total notes from the notebook. Asks to type name
+//!   of the notebook (database) to confirm, unless `--force` is used.
+//! * `del-matching <pattern> [--regex] [--yes]` - deletes all notes whose names match glob `pattern`
+//!   (`*` matches any characters, `?` matches one character) or regular expression with `--regex`.
+//!   Asks for confirmation, unless `--yes` is used.
 //! * `clear-note <notename>` - clears content of `notename`
-//! * `upd-note <notename>` - will prompt to enter a note that will be added instead old note in `notename`.
-//! * `upd-notename <new notename>` - updates old notename to new `notename` of requested note.
+//! * `upd-note <notename> [-m <text> | --file <path>]` - will prompt to enter a note that will be added
+//!   instead old note in `notename`. Content can be given with `--message` or `--file` like in `add-note`.
+//! * `upd-notename <notename> <new notename>` - updates old notename to new `notename` of requested note.
+//!   With `--rewrite-links` also rewrites `[[notename]]` links in other notes to the new notename.
+//!   If the new notename is taken, fails, or with `--merge` appends content of the note
+//!   to the note with the new notename and deletes the note.
+//! * `rename-matching <pattern> <replacement>` - renames all notes whose names match regular
+//!   expression `pattern`, replacing the match with `replacement` (`$1` refers to the first group).
+//!   All notes are renamed at once, or none if any of them can't be renamed.
+//! * `sed <pattern> <replacement> [--dry-run]` - replaces all matches of regular expression `pattern`
+//!   in every line of all notes with `replacement` (`$1` refers to the first group) and displays
+//!   changed lines. With `--dry-run` only displays lines that would be changed.
+//! * `append-note <notename>` - will prompt to enter text that will be added to the end of `notename`.
+//! * `journal [--name-format <format>]` - will prompt to enter entry that will be added with
+//!   current time to the note of today (created if missing), read more in [`journal` module](crate::journal).
+//! * `prepend-note <notename>` - will prompt to enter text that will be added to the beginning of `notename`.
+//! * `insert-note <notename> <line>` - will prompt to enter text that will be inserted into `notename`
+//!   before `line` (lines are numbered from 1).
 //! * `display-note <notename>` - displays `notename`, `note` and note-`id` of requested note.
-//! * If you did not specify which command to execute, then all total notes will be displayed.
+//!   With `--lines <start>:<end>` displays only content of lines from `start` to `end`
+//!   (numbered from 1); `--lines <start>:` displays lines from `start` to the end of note.
+//!   If `notename` ends with `*`, like `meeting_*`, displays all notes whose names start with
+//!   the text before `*`.
+//! * `cat <notename>` - prints only content of note as it is, without logging and other messages
+//!   (always like with `--script`), so it can be piped into other programs. Exits with code `1`
+//!   if note doesn't exist.
+//! * `get-binary <notename>` - prints content of binary note as raw bytes, like `cat` does
+//!   for text notes.
+//! * `add-from-clipboard <notename>` - adds note with text copied into clipboard
+//!   (only with `clipboard` feature).
+//! * `copy-to-clipboard <notename>` - copies content of note into clipboard, exits with code `1`
+//!   if note doesn't exist (only with `clipboard` feature).
+//! * `qr <notename> [--png <path>]` - prints content of note as QR code, or saves it into PNG
+//!   image `path` with `--png` (only with `qr` feature). Exits with code `1` if note doesn't exist.
+//! * `compact [--batch-size <n>] [--compress-threshold <bytes>]` - rewrites all notes applying
+//!   the current policies: compresses large notes and decompresses small ones, and renames notes
+//!   to normalized names if `LNOTEBOOK_NORMALIZE_NAMES` is `true`. Then compacts history of notes,
+//!   compressing large contents of revisions. Read about policies in
+//!   [`compact` module](crate::compact) and about history in [`history` module](crate::history).
+//! * `dedup [--merge] [--yes]` - displays groups of notes with identical content as
+//!   `oldest: duplicate, ...`. With `--merge` keeps the oldest note of every group and deletes
+//!   the others, asking for confirmation unless `--yes` is used. Read more in
+//!   [`dedup` module](crate::dedup).
+//! * `set-due <notename> <date>` - sets deadline of note. Read about formats of date
+//!   in [`due` module](crate::due).
+//! * `clear-due <notename>` - removes deadline of note.
+//! * `due` - displays notes that have deadline sorted by deadline, overdue notes first.
+//! * `remind-daemon [--interval <seconds>] [--command <command>]` - checks deadlines every
+//!   `interval` seconds and sends reminder about every note whose deadline has passed, with
+//!   desktop notification or `command`, until it's stopped with Ctrl+C.
+//!   Read more in [`remind` module](crate::remind).
+//! * `add-template <template name>` - will prompt to enter a template of notes.
+//!   Read about templates in [`templates` module](crate::templates).
+//! * `del-template <template name>` - deletes template.
+//! * `display-templates` - displays all templates.
+//! * `new-from-template <template name> <notename>` - adds a new note `notename` created from template.
+//! * `template-from <notename> [--name <template name>] [--vars <key=value,...>]` - adds a template
+//!   made from `notename`, replacing every value in it with placeholder `{{key}}`. Without `--vars`
+//!   will prompt to enter `key=value` pairs. Template is named as the note unless `--name` is used.
+//! * `search <query> [--all-profiles]` - displays notes whose name or content contains `query`.
+//!   With `--all-profiles` also searches notebooks of all profiles, read about profiles
+//!   in [`config` module](crate::config).
+//! * `grep <pattern> [--ignore-case] [--names-only]` - displays lines of notes that match regular
+//!   expression `pattern` as `notename:line: text`. With `--names-only` displays only names of notes.
+//! * `history <notename>` - displays revisions of note: ID, time, hash and size of content.
+//!   Read about history in [`history` module](crate::history).
+//! * `display-revision <revision ID>` - displays content of note in revision.
+//! * `diff <notename> <other notename> [--context <lines>]` - displays unified diff of two notes,
+//!   and `diff <notename> --version <revision ID>` - of note in revision and its current content.
+//!   Read more in [`diff` module](crate::diff).
+//! * `stats [--usage]` - displays statistics about notes in the notebook as a table.
+//!   With `--usage` displays usage statistics of commands, read about them in [`usage` module](crate::usage).
+//! * `count-words <notename>` - displays number of words, lines, characters and bytes in `notename`.
+//! * `summary <notename> [--max-len <chars>]` - displays the first paragraph of `notename`
+//!   (cut to `max-len` characters), its number of words and estimated reading time.
+//! * `lint <notename> [--max-line-len <chars>]` - displays trailing whitespace and too long lines
+//!   in `notename`, and exits with code `1` if anything is found. With `--spellcheck [--dictionary
+//!   <file>]` also displays words that are not in dictionary (only with `spellcheck` feature).
+//!   Read more in [`lint` module](crate::lint).
+//! * `exists <notename>` - checks if `notename` exists: exits with code `0` if it does and `1`
+//!   if it doesn't (read about exit code in
+//!   [`CommandResponse::exit_code`](crate::notebook::CommandResponse::exit_code)).
+//! * `count` - displays number of notes in the notebook.
+//! * `random-note` - displays random note, for example to review old notes.
+//! * `links <notename>` - displays names of notes that `notename` links to as `[[other_note]]`,
+//!   read about links in [`links` module](crate::links).
+//! * `backlinks <notename>` - displays names of notes that link to `notename`.
+//! * `export-graph [--graph-format <dot|json>]` - displays graph of links between all notes,
+//!   DOT can be rendered with Graphviz.
+//! * `check-links [--http]` - displays links to notes that don't exist and, with `--http` (only
+//!   with `linkcheck` feature), URLs that can't be opened. Exits with code `1` if any link is
+//!   broken. Read more in [`linkcheck` module](crate::linkcheck).
+//! * `attach <notename> <file>` - attaches `file` to note, read about attachments
+//!   in [`attachments` module](crate::attachments).
+//! * `attachments <notename>` - displays name, size and time of files attached to note.
+//! * `save-attachment <notename> <file> <dest>` - saves attached `file` into `dest`
+//!   (file or directory).
+//! * `highlight <notename> <lines>` - highlights lines `start:end` of note (numbered from 1),
+//!   `start:` highlights lines to the end of note and `line` only one line. Excerpt is kept
+//!   even if note is changed later, read more in [`highlights` module](crate::highlights).
+//! * `highlights [notename]` - displays highlighted excerpts of note, or of all notes.
+//! * `set-meta <notename> <key> <value>` - sets metadata `key` of note to `value` (only with
+//!   `metadata` feature). `value` that is valid JSON (like `3` or `true`) is stored as it is,
+//!   anything else as string. Read more in [`metadata` module](crate::metadata).
+//! * `get-meta <notename> [key]` - displays value of metadata `key` of note as JSON (`null` if note
+//!   doesn't have it), or all metadata without `key` (only with `metadata` feature).
+//! * `find-by-meta <key> <value>` - displays notes whose metadata `key` is `value`
+//!   (only with `metadata` feature).
+//! * `protect-note <notename>` - will prompt to enter passphrase twice and protect note with it
+//!   (only with `crypto` feature): `display-note` and `upd-note` will ask for the passphrase before
+//!   showing or changing the note. Read more in [`protect` module](crate::protect).
+//! * `unprotect-note <notename>` - will prompt to enter passphrase of note and remove its protection
+//!   (only with `crypto` feature).
+//! * `export-markdown <dir> [--template <file>]` - exports all notes into Markdown files in `dir`
+//!   (only with `export` feature). Read about templates in [`export` module](crate::export).
+//! * `export-html <dir>` - exports all notes into static site in `dir`: HTML page for every note
+//!   with rendered Markdown and `index.html` with search box (only with `export` feature).
+//! * `export <path> [--encrypt]` - exports all notes into archive `path` (only with `export` feature).
+//!   With `--encrypt` (requires `crypto` feature) will prompt to enter passphrase to encrypt archive.
+//! * `import <path> [--fail-fast]` - imports notes from archive `path` created by `export`, will prompt
+//!   to enter passphrase if archive is encrypted (only with `export` feature). Notes that can't be
+//!   imported are reported; with `--fail-fast` no notes are imported if any of them can't be imported.
+//!   With `--from joplin|enex|standardnotes` imports notes exported by Joplin, Evernote or Standard
+//!   Notes instead (read more in [`import` module][crate::import]).
+//! * `export-archive <path> [--password]` - exports all notes with attachments into single archive
+//!   `path` compressed with zstd, `--password` encrypts it (only with `export` feature, encryption
+//!   also requires `crypto` feature).
+//! * `import-archive <path> [--fail-fast]` - imports notes with attachments from archive `path`
+//!   created by `export-archive`, will prompt to enter password if archive is encrypted (only with
+//!   `export` feature).
+//! * `dump <path>` - writes all notes, their history, attachments, templates, users, shares and
+//!   highlights into SQL file `path` without `pg_dump`. Read more in [`dump` module](crate::dump).
+//! * `restore <path> [--force]` - replaces all data of the notebook with dump `path`. Asks to type
+//!   name of the notebook (database) to confirm, unless `--force` is used.
+//! * `export-csv <path>` - exports all notes into CSV file `path` with columns `name` and `note`,
+//!   for spreadsheets and other tools (only with `export` feature).
+//! * `import-csv <path> [--fail-fast]` - imports notes from CSV file `path` with columns `name`
+//!   and `note`, like `import` (only with `export` feature).
+//! * `lock-note <notename>` - locks note, so other users of the notebook can't change or delete it
+//!   until it's unlocked. Read more in [`lock` module](crate::lock).
+//! * `unlock-note <notename> [--force]` - unlocks note locked by you, or by anyone with `--force`.
+//! * `audit [--since <date>]` - displays mutating commands recorded in the audit log: when, by whom
+//!   and with which note they were executed. Read more in [`audit` module](crate::audit).
+//! * `add-user <name> [--admin]` - creates user of notebook shared by several people, admin with
+//!   `--admin`. Read more in [`users` module](crate::users).
+//! * `del-user <name>` - deletes user, its notes become shared.
+//! * `list-users` - displays all users.
+//! * `chown <notename> [user]` - makes user owner of note, or makes note shared if user
+//!   is not given.
+//! * `share-note <notename> <user> [--read-only]` - lets user read and change your note,
+//!   or only read it with `--read-only`.
+//! * `unshare-note <notename> <user>` - stops sharing note with user.
+//! * `shared-with-me` - displays notes that other users shared with user selected
+//!   with `--as-user`.
+//! * `split-note <notename> [--by <heading|delimiter>] [--delimiter <text>] [--keep]` - splits note
+//!   into one note per Markdown heading, or per part separated by lines with delimiter (`---` by
+//!   default), and deletes it unless `--keep` is used. Read more in [`split` module](crate::split).
+//! * `tui` - opens terminal user interface to browse and edit notes (only with `tui` feature),
+//!   read more in [`tui` module](crate::tui).
+//! * `pick [--action <display|edit|delete>]` - shows notes filtered by fuzzy search as you type
+//!   and runs action with the picked note, like `display-note`, `upd-note` or `del-note`
+//!   (only with `tui` feature). Read more in [`pick` module](crate::pick).
+//! * `watch` - prints changes of notes made by anyone as they happen, until it's stopped with Ctrl+C
+//!   (only with `watch` feature). Read more in [`watch` module](crate::watch).
+//! * `serve [--addr <address>]` - starts HTTP server with notebook (only with `server` feature),
+//!   read more in [`server` module](crate::server).
+//! * `serve-grpc [--addr <address>]` - starts gRPC service with notebook (only with `grpc` feature),
+//!   read more in [`grpc` module](crate::grpc).
+//!   Both server modes migrate database on startup, read more in [`migrate` module](crate::migrate).
+//! * `replay-queue` - applies changes of `add-note` and `upd-note` queued while the database
+//!   was unreachable, read more in [`offline` module](crate::offline).
+//! * `sync --remote <url> [--conflict <newer|local|remote|keep-both>] [--dry-run]` - synchronizes
+//!   notes with notebook in database `url` in both directions: copies added and changed notes
+//!   and deletes deleted ones. Notes changed in both notebooks are resolved by `--conflict`,
+//!   `newer` by default. Read more in [`sync` module](crate::sync).
+//! * `bench [--notes <n>] [--runs <n>] [--yes]` - adds `n` synthetic notes, measures throughput
+//!   and latency of adding, listing, searching and exporting notes and deletes the notes.
+//!   Asks for confirmation, unless `--yes` is used. Read more in [`bench` module](crate::bench).
+//! * `gen-man <dir>` - writes man pages of all commands into `dir` (only with `man` feature).
+//! * `help [command]` - displays help of the notebook or command with examples.
+//! * `--format <plain|table|json|markdown>` can be used with any command that displays notes,
+//!   read about formats in [`render` module](crate::render).
+//! * `--script` can be used with any command: the notebook doesn't print prompts and other
+//!   messages, doesn't ask for confirmations (like `--force` and `--yes`) and prints only results
+//!   of command without logging, so output can be used by other programs. Content of notes
+//!   and passphrases are read from input as usual.
+//! * `--no-color` can be used with any command: output is not colored. Colors are also disabled
+//!   if output doesn't go to terminal or `NO_COLOR` enivroment variable is set, read more in
+//!   [`format` module](crate::format).
+//! * `-q/--quiet` can be used with any command: like `--script`, the notebook prints only results
+//!   of command without other messages and logging, but still asks for confirmations.
+//! * `-v/--verbose` can be used with any command: SQL queries are logged with their timing and
+//!   time taken by command is printed into stderr. Read more in
+//!   [`NoteCommand::verbosity`](crate::NoteCommand::verbosity).
+//! * `--timing` can be used with any command (only with `metrics` feature): after command its
+//!   duration, number and duration of its SQL queries and number of rows they returned and changed
+//!   are printed into stderr. Read more in [`metrics` module](crate::metrics).
+//! * `--as-user <name>` can be used with any command: the notebook acts as user `name`, so notes
+//!   it adds are owned by the user and notes of other users are not listed, found or changed.
+//!   Only admins can use `add-user`, `del-user` and `chown` with it. Read more in
+//!   [`users` module](crate::users).
+//! * `--no-echo` can be used with any command: content of notes (entered and current one in
+//!   `upd-note`) is not printed back, only number of its lines and bytes. It can also be enabled
+//!   with `LNOTEBOOK_NO_ECHO=true`. Content is hidden from logs too. Passphrases are never printed.
+//! * `--end-marker <marker>` can be used with any command that prompts to enter a note: entered note
+//!   is finished by `marker` instead of `#endnote#` (it can also be set with `LNOTEBOOK_END_MARKER`).
+//!   Note is always finished by the end of input (Ctrl+D on a new line), and with empty marker
+//!   only by it.
+//! * If you did not specify which command to execute, then name and first line of all total notes
+//!   will be displayed, cut to 60 characters (`--preview-len <n>` changes it). Previews of notes
+//!   that have more content end with `…`. Use `--full` (or set `LNOTEBOOK_LEGACY_DISPLAY=true`,
+//!   read more in [`config` module](crate::config)) to display whole content of all notes.
 //!
 //! #### Examples
 //! Code under deletes 'unnecessary_note' if it exists:
@@ -62,7 +306,7 @@
 //!
 //! Сommands such as `add-note` and `upd-note`
 //! will prompt you to enter a new note. To finish write note you
-//! should write `#endnote#` at the end, as written in the tooltip.
+//! should write `#endnote#` at the end, as written in the tooltip, or press Ctrl+D on a new line.
 //! For example the code below will update the 'passwords' content to
 //! 'login: krutoy_4el\npassword: 123' if note exists:
 //! ```bash
@@ -70,7 +314,7 @@
 //!
 //! # output
 //! Enter note you want to add instead old note in `passwords`
-//! (At the end of the note, enter `#endnote#` to finish writing the note):
+//! (At the end of the note, enter `#endnote#` or press Ctrl+D on a new line to finish writing the note):
 //!
 //! # input
 //! login: krutoy_4el
@@ -86,7 +330,7 @@
 //! If you did not specify which command to execute, then all total notes will be displayed.
 //! You also can use `display-note` to display it, but for a variety we will do it like in the code below:
 //! ```bash
-//! cargo run
+//! cargo run -- --full
 //!
 //! # output
 //! All notes in notebook:
@@ -98,182 +342,2886 @@
 //! ```
 //! If there were more notes here, they would all be displayed, but since we only have one note, we only got that one.
 
-use crate::commands::{
-    add, clear, del, del_all, display, display_all, select_one, upd, upd_notename,
-};
+use crate::bench;
+use crate::commands::{self, NamePattern, OnConflict, PREVIEW_LEN};
+use crate::compact::DEFAULT_BATCH_SIZE;
+use crate::config::{self, Config};
+use crate::diff::{DiffWith, DEFAULT_CONTEXT};
 use crate::errors::NotebookError;
+use crate::format;
+#[cfg(feature = "git")]
+use crate::git::GitRepo;
+#[cfg(feature = "export")]
+use crate::import::ImportFormat;
+use crate::journal;
+use crate::links::GraphFormat;
+use crate::lint::DEFAULT_MAX_LINE_LEN;
+use crate::notebook::{CommandRequest, CommandResponse, Notebook};
+use crate::notename::NoteName;
+use crate::offline::{self, Operation, QueuedChange};
+#[cfg(feature = "tui")]
+use crate::pick::PickAction;
+use crate::pool::ConnectOptions;
+use crate::prompt::Prompt;
+use crate::protect;
+use crate::remind::{self, Notifier};
+use crate::render::RenderFormat;
+use crate::split::{SplitBy, DEFAULT_DELIMITER};
+use crate::stats::SUMMARY_LEN;
+use crate::usage::{self, UsageStats};
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
 use sqlx::{self, PgPool};
-use std::{io, process};
-use structopt::StructOpt;
-use tracing::{event, Level};
+use std::{
+    collections::HashSet,
+    io::Write,
+    time::{Duration, Instant},
+};
+use tracing::{event, level_filters::LevelFilter, Level};
 
-#[derive(StructOpt)]
+mod help;
+
+#[derive(Subcommand)]
 enum Command {
+    /// Add a new note; content is entered after the command
+    #[command(after_help = help::ADD_NOTE)]
     AddNote {
+        /// Name of the note
+        #[arg(required_unless_present = "auto_name")]
+        notename: Option<String>,
+
+        /// Name the note after its first line or heading, adding suffix like `_2` if the name is taken
+        #[arg(long, conflicts_with = "notename")]
+        auto_name: bool,
+
+        #[command(flatten)]
+        content: NoteContent,
+    },
+
+    /// Add a new binary note with content of file as it is, like keys or images
+    #[command(after_help = help::ADD_BINARY)]
+    AddBinary {
+        /// Name of the note
         notename: String,
+
+        /// File with content of the note, `-` reads standard input until its end
+        #[arg(long)]
+        file: std::path::PathBuf,
     },
 
+    /// Delete note
+    #[command(after_help = help::DEL_NOTE)]
     DelNote {
+        /// Name of the note
         notename: String,
     },
 
-    DelAll,
+    /// Delete all notes
+    #[command(after_help = help::DEL_ALL)]
+    DelAll {
+        /// Don't ask to type name of the notebook to confirm
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Delete all notes whose names match pattern
+    #[command(after_help = help::DEL_MATCHING)]
+    DelMatching {
+        /// Glob pattern like `tmp_*`, or regular expression with `--regex`
+        pattern: String,
+
+        /// Treat pattern as regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Clear content of note
+    #[command(after_help = help::CLEAR_NOTE)]
     ClearNote {
+        /// Name of the note
         notename: String,
     },
 
+    /// Rename note
+    #[command(after_help = help::UPD_NOTENAME)]
     UpdNotename {
+        /// Name of the note
         notename: String,
+        /// New name of the note
         new_notename: String,
+
+        /// Rewrite `[[notename]]` links in other notes to the new notename
+        #[arg(long)]
+        rewrite_links: bool,
+
+        /// If note with the new notename exists, append content of the note to it
+        /// instead of failing
+        #[arg(long)]
+        merge: bool,
+    },
+
+    /// Rename all notes whose names match regular expression
+    #[command(after_help = help::RENAME_MATCHING)]
+    RenameMatching {
+        /// Regular expression matching notenames
+        pattern: String,
+        /// Replacement of the match, can refer to groups as `$1`
+        replacement: String,
+    },
+
+    /// Replace text in content of all notes
+    #[command(after_help = help::SED)]
+    Sed {
+        /// Regular expression matched in every line of notes
+        pattern: String,
+        /// Replacement of the matches, can refer to groups as `$1`
+        replacement: String,
+
+        /// Display changes without changing notes
+        #[arg(long)]
+        dry_run: bool,
     },
 
+    /// Replace content of note; new content is entered after the command
+    #[command(after_help = help::UPD_NOTE)]
     UpdNote {
+        /// Name of the note
+        notename: String,
+
+        #[command(flatten)]
+        content: NoteContent,
+    },
+
+    /// Add text to the end of note
+    #[command(after_help = help::APPEND_NOTE)]
+    AppendNote {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Add entry into journal note of today, creating it if missing
+    #[command(after_help = help::JOURNAL)]
+    Journal {
+        /// Format of name of journal note, for example `%Y-%m-%d`
+        #[arg(long)]
+        name_format: Option<String>,
+    },
+
+    /// Add text to the beginning of note
+    #[command(after_help = help::PREPEND_NOTE)]
+    PrependNote {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Insert text into note before the line
+    #[command(after_help = help::INSERT_NOTE)]
+    InsertNote {
+        /// Name of the note
         notename: String,
+        /// Line to insert text before, lines are numbered from 1
+        line: u32,
     },
 
+    /// Display note
+    #[command(after_help = help::DISPLAY_NOTE)]
     DisplayNote {
+        /// Name of the note, `prefix*` displays all notes whose names start with `prefix`
         notename: String,
+
+        /// Display only lines `start:end` (numbered from 1); `start:` displays lines to the end
+        #[arg(long, value_parser = parse_lines)]
+        lines: Option<(u32, u32)>,
     },
-}
 
-/// Contains the command as `enum` from CLI to run it later.
-///
-/// This `struct` was created to conveniently store and execute commands on a notebook from CLI commands.
-/// More about commands for which this structure was created [here][crate::commands::execute_commands].
-#[derive(StructOpt)]
-pub struct NoteCommand {
-    #[structopt(subcommand)]
-    cmd: Option<Command>,
-}
-impl NoteCommand {
-    /// Convert a command from CLI to `enum` and saves it in [struct `NoteCommand`][NoteCommand].
-    ///
-    /// Command stores in [`NoteCommand`] as `Option<Command>` and will be:
-    /// * `Some(Command)` if you selected any existing command
-    /// * `None` if you **didn't selected**/**selected a non-existent command**
-    ///
-    /// Read about CLI commands [here][crate::commands::execute_commands].
-    pub async fn new() -> Result<NoteCommand, structopt::clap::Error> {
-        Ok(NoteCommand::from_args_safe()?)
-    }
-    /// Execute specifed command.
-    ///
-    /// [List of all CLI commands.](https://docs.rs/lnotebook/latest/lnotebook/commands/execute_commands/index.html#list-of-all-commands-you-can-call-from-CLI).
-    ///
-    /// Read about CLI commands [here][crate::commands::execute_commands].
-    pub async fn execute_command(&self, pool: &PgPool) -> Result<(), NotebookError> {
-        match self.cmd.as_ref() {
-            Some(Command::AddNote { notename }) => {
-                println!("Enter note you want to add into `{}`", notename);
-                println!("(At the end of the note, enter `#endnote#` to finish writing the note):");
+    /// Print only content of note, for piping it into other programs; exits with code 1 if note
+    /// doesn't exist
+    #[command(after_help = help::CAT)]
+    Cat {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Print content of binary note as raw bytes; exits with code 1 if note doesn't exist
+    #[command(after_help = help::GET_BINARY)]
+    GetBinary {
+        /// Name of the note
+        notename: String,
+    },
 
-                let mut note = String::new();
-                loop {
-                    let mut note_part = String::new();
+    /// Add note with text copied into clipboard
+    #[cfg(feature = "clipboard")]
+    #[command(after_help = help::ADD_FROM_CLIPBOARD)]
+    AddFromClipboard {
+        /// Name of the note
+        notename: String,
+    },
 
-                    io::stdin().read_line(&mut note_part).unwrap_or_else(|e| {
-                        event!(Level::DEBUG, "Problem to read line: {e}");
+    /// Copy content of note into clipboard; exits with code 1 if note doesn't exist
+    #[cfg(feature = "clipboard")]
+    #[command(after_help = help::COPY_TO_CLIPBOARD)]
+    CopyToClipboard {
+        /// Name of the note
+        notename: String,
+    },
 
-                        process::exit(1);
-                    });
+    /// Print content of note as QR code; exits with code 1 if note doesn't exist
+    #[cfg(feature = "qr")]
+    #[command(after_help = help::QR)]
+    Qr {
+        /// Name of the note
+        notename: String,
 
-                    if note_part.contains("#endnote#") {
-                        delete_end(&mut note_part, "#endnote#").await;
-                        note = note + note_part.as_str();
+        /// Save QR code into PNG image instead of printing it
+        #[arg(long, value_name = "PATH")]
+        png: Option<std::path::PathBuf>,
+    },
 
-                        break;
-                    } else {
-                        note = note + note_part.as_str();
-                    }
-                }
-                println!("Note to add into `{notename}`:\n{note}");
+    /// Search notes by name and content
+    #[command(after_help = help::SEARCH)]
+    Search {
+        /// Text to search, case-insensitive
+        query: String,
 
-                add(&notename, &note, pool).await?;
-            }
+        /// Also search notebooks of all profiles
+        #[arg(long)]
+        all_profiles: bool,
+    },
 
-            Some(Command::DelNote { notename }) => {
-                del(&notename, pool).await?;
-            }
+    /// Display lines of notes that match regular expression
+    #[command(after_help = help::GREP)]
+    Grep {
+        /// Regular expression
+        pattern: String,
 
-            Some(Command::DelAll) => {
-                del_all(pool).await?;
-            }
+        /// Ignore case of letters
+        #[arg(long, short)]
+        ignore_case: bool,
 
-            Some(Command::ClearNote { notename }) => {
-                clear(notename, pool).await?;
-            }
+        /// Display only names of notes that have matching lines
+        #[arg(long, short = 'l')]
+        names_only: bool,
+    },
 
-            Some(Command::UpdNotename {
-                notename,
-                new_notename,
-            }) => {
-                upd_notename(&notename, &new_notename, pool).await?;
-            }
+    /// Display revisions of note
+    #[command(after_help = help::HISTORY)]
+    History {
+        /// Name of the note
+        notename: String,
+    },
 
-            Some(Command::UpdNote { notename }) => {
-                println!(
-                    "Current content of `{}`:\n{}",
-                    notename,
-                    select_one(notename, pool).await?.note_str().await
-                );
+    /// Display content of note in revision
+    #[command(after_help = help::DISPLAY_REVISION)]
+    DisplayRevision {
+        /// Revision ID from `history`
+        revision: i32,
+    },
 
-                println!(
-                    "Enter note you want to add instead old note in `{}`",
-                    notename
-                );
-                println!("(At the end of the note, enter `#endnote#` to finish writing the note):");
+    /// Display differences between two notes, or between revision of note and its current content
+    #[command(after_help = help::DIFF)]
+    Diff {
+        /// Name of the note
+        notename: String,
 
-                let mut note = String::new();
-                loop {
-                    let mut note_part = String::new();
+        /// Name of the note that the first note is compared with
+        #[arg(required_unless_present = "version", conflicts_with = "version")]
+        other: Option<String>,
 
-                    io::stdin().read_line(&mut note_part).unwrap_or_else(|e| {
-                        event!(Level::DEBUG, "Problem to read line: {e}");
+        /// Revision ID from `history`, the note in this revision is compared with its current content
+        #[arg(long)]
+        version: Option<i32>,
 
-                        process::exit(1);
-                    });
+        /// Number of unchanged lines displayed around changed lines
+        #[arg(short = 'U', long, default_value_t = DEFAULT_CONTEXT)]
+        context: usize,
+    },
 
-                    if note_part.contains("#endnote#") {
-                        delete_end(&mut note_part, "#endnote#").await;
-                        note = note + note_part.as_str();
+    /// Display statistics about notes
+    #[command(after_help = help::STATS)]
+    Stats {
+        /// Display usage statistics of commands instead (read about them in `usage` module)
+        #[arg(long)]
+        usage: bool,
+    },
 
-                        break;
-                    } else {
-                        note = note + note_part.as_str();
-                    }
-                }
-                println!("Note to add into `{notename}` instead old note:\n{note}");
+    /// Display number of words, lines, characters and bytes in note
+    #[command(after_help = help::COUNT_WORDS)]
+    CountWords {
+        /// Name of the note
+        notename: String,
+    },
 
-                upd(&notename, &note, pool).await?;
-            }
+    /// Display the first paragraph, number of words and reading time of note
+    #[command(after_help = help::SUMMARY)]
+    Summary {
+        /// Name of the note
+        notename: String,
 
-            Some(Command::DisplayNote { notename }) => {
-                display(notename, pool).await?;
-            }
+        /// Max length of the paragraph in characters
+        #[arg(long, default_value_t = SUMMARY_LEN)]
+        max_len: usize,
+    },
 
-            None => {
-                display_all(pool).await?;
-            }
-        }
-        Ok(())
-    }
-}
-async fn delete_end(source: &mut String, end: &str) -> String {
-    let _: Vec<_> = source
-        .to_owned()
-        .char_indices()
-        .map(|(i, _)| {
-            // length of end
-            let len = i + end.len();
+    /// Display trailing whitespace, too long lines and other problems in note
+    #[command(after_help = help::LINT)]
+    Lint {
+        /// Name of the note
+        notename: String,
 
-            if source.contains(end) {
-                if &source[i..len] == end {
-                    // delete end from source and extra information behind it
-                    source.drain(i..);
-                }
-            }
-        })
-        .collect();
+        /// Max length of lines in characters
+        #[arg(long, default_value_t = DEFAULT_MAX_LINE_LEN)]
+        max_line_len: usize,
+
+        /// Also display words that are not in dictionary
+        #[cfg(feature = "spellcheck")]
+        #[arg(long)]
+        spellcheck: bool,
+
+        /// File with words of dictionary, one per line [default: `LNOTEBOOK_DICTIONARY` or
+        /// /usr/share/dict/words]
+        #[cfg(feature = "spellcheck")]
+        #[arg(long, requires = "spellcheck")]
+        dictionary: Option<std::path::PathBuf>,
+    },
+
+    /// Check if note exists: exits with code 0 if it does and 1 if it doesn't
+    #[command(after_help = help::EXISTS)]
+    Exists {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Display number of notes
+    #[command(after_help = help::COUNT)]
+    Count,
+
+    /// Display random note
+    #[command(after_help = help::RANDOM_NOTE)]
+    RandomNote,
+
+    /// Display notes that note links to as `[[notename]]`
+    #[command(after_help = help::LINKS)]
+    Links {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Display notes that link to note as `[[notename]]`
+    #[command(after_help = help::BACKLINKS)]
+    Backlinks {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Display graph of links between notes
+    #[command(after_help = help::EXPORT_GRAPH)]
+    ExportGraph {
+        /// Format of graph
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        graph_format: GraphFormat,
+    },
+
+    /// Display links to notes that don't exist and dead URLs
+    #[command(after_help = help::CHECK_LINKS)]
+    CheckLinks {
+        /// Also request URLs in notes and display ones that can't be opened
+        #[cfg(feature = "linkcheck")]
+        #[arg(long)]
+        http: bool,
+    },
+
+    /// Attach file to note
+    #[command(after_help = help::ATTACH)]
+    Attach {
+        /// Name of the note
+        notename: String,
+        /// File to attach
+        file: std::path::PathBuf,
+    },
+
+    /// Display files attached to note
+    #[command(after_help = help::ATTACHMENTS)]
+    Attachments {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Save file attached to note
+    #[command(after_help = help::SAVE_ATTACHMENT)]
+    SaveAttachment {
+        /// Name of the note
+        notename: String,
+        /// Name of the attached file
+        file: String,
+        /// File or directory to save into
+        dest: std::path::PathBuf,
+    },
+
+    /// Highlight lines of note to review them later
+    #[command(after_help = help::HIGHLIGHT)]
+    Highlight {
+        /// Name of the note
+        notename: String,
+        /// Lines `start:end` (numbered from 1); `start:` highlights lines to the end, `line` one line
+        #[arg(value_parser = parse_lines)]
+        lines: (u32, u32),
+    },
+
+    /// Display highlighted excerpts of notes
+    #[command(after_help = help::HIGHLIGHTS)]
+    Highlights {
+        /// Name of the note, highlights of all notes are displayed if it's not given
+        notename: Option<String>,
+    },
+
+    /// Rewrite content of all notes applying the current policies
+    #[command(after_help = help::COMPACT)]
+    Compact {
+        /// Number of notes rewritten in one transaction
+        #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+        batch_size: i64,
+
+        /// Compress contents of notes and revisions from this size in bytes, 0 to not compress;
+        /// `LNOTEBOOK_COMPRESS_THRESHOLD` or 4096 by default
+        #[arg(long)]
+        compress_threshold: Option<usize>,
+    },
+
+    /// Display notes with identical content and merge them
+    #[command(after_help = help::DEDUP)]
+    Dedup {
+        /// Keep the oldest note of every group and delete the others
+        #[arg(long)]
+        merge: bool,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Set deadline of note
+    #[command(after_help = help::SET_DUE)]
+    SetDue {
+        /// Name of the note
+        notename: String,
+        /// Deadline, like `2024-07-20 18:30` or `2024-07-20`
+        due_at: String,
+    },
+
+    /// Remove deadline of note
+    #[command(after_help = help::CLEAR_DUE)]
+    ClearDue {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Display notes that have deadline, overdue notes first
+    #[command(after_help = help::DUE)]
+    Due,
+
+    /// Send reminders when deadlines of notes pass, until it's stopped
+    #[command(after_help = help::REMIND_DAEMON)]
+    RemindDaemon {
+        /// How often deadlines are checked, in seconds
+        #[arg(long, default_value_t = remind::DEFAULT_INTERVAL.as_secs())]
+        interval: u64,
+        /// Shell command run for every reminder instead of desktop notification
+        #[arg(long)]
+        command: Option<String>,
+    },
+
+    /// Add a template of notes; template is entered after the command
+    #[command(after_help = help::ADD_TEMPLATE)]
+    AddTemplate {
+        /// Name of the template
+        template_name: String,
+    },
+
+    /// Delete template
+    #[command(after_help = help::DEL_TEMPLATE)]
+    DelTemplate {
+        /// Name of the template
+        template_name: String,
+    },
+
+    /// Display all templates
+    #[command(after_help = help::DISPLAY_TEMPLATES)]
+    DisplayTemplates,
+
+    /// Add a new note created from template
+    #[command(after_help = help::NEW_FROM_TEMPLATE)]
+    NewFromTemplate {
+        /// Name of the template
+        template_name: String,
+        /// Name of the new note
+        notename: String,
+    },
+
+    /// Add template made from existing note, replacing values in it with placeholders
+    #[command(after_help = help::TEMPLATE_FROM)]
+    TemplateFrom {
+        /// Name of the note
+        notename: String,
+
+        /// Name of the template, the same as name of the note by default
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Values to replace with placeholders, like `name=standup,date=2024-07-19`.
+        /// If not used, placeholders are asked interactively
+        #[arg(long, value_delimiter = ',', value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+    },
+
+    /// Set metadata of note
+    #[cfg(feature = "metadata")]
+    #[command(after_help = help::SET_META)]
+    SetMeta {
+        /// Name of the note
+        notename: String,
+        /// Key of metadata
+        key: String,
+        /// Value, JSON or any other text stored as string
+        value: String,
+    },
+
+    /// Display metadata of note as JSON
+    #[cfg(feature = "metadata")]
+    #[command(after_help = help::GET_META)]
+    GetMeta {
+        /// Name of the note
+        notename: String,
+        /// Key of metadata, all metadata is displayed without it
+        key: Option<String>,
+    },
+
+    /// Display notes whose metadata has value
+    #[cfg(feature = "metadata")]
+    #[command(after_help = help::FIND_BY_META)]
+    FindByMeta {
+        /// Key of metadata
+        key: String,
+        /// Value, JSON or any other text as string
+        value: String,
+    },
+
+    /// Protect note with passphrase; passphrase is entered after the command
+    #[cfg(feature = "crypto")]
+    #[command(after_help = help::PROTECT_NOTE)]
+    ProtectNote {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Remove protection of note; passphrase is entered after the command
+    #[cfg(feature = "crypto")]
+    #[command(after_help = help::UNPROTECT_NOTE)]
+    UnprotectNote {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Export all notes into Markdown files
+    #[cfg(feature = "export")]
+    #[command(after_help = help::EXPORT_MARKDOWN)]
+    ExportMarkdown {
+        /// Directory to export notes into
+        dir: std::path::PathBuf,
+
+        /// File with template used to render every note
+        #[arg(long)]
+        template: Option<std::path::PathBuf>,
+    },
+
+    /// Export all notes into static HTML site
+    #[cfg(feature = "export")]
+    #[command(after_help = help::EXPORT_HTML)]
+    ExportHtml {
+        /// Directory to export site into
+        dir: std::path::PathBuf,
+    },
+
+    /// Export all notes into archive
+    #[cfg(feature = "export")]
+    #[command(after_help = help::EXPORT)]
+    Export {
+        /// File to export notes into
+        path: std::path::PathBuf,
+
+        /// Encrypt archive with passphrase
+        #[cfg(feature = "crypto")]
+        #[arg(long)]
+        encrypt: bool,
+    },
+
+    /// Import notes from archive
+    #[cfg(feature = "export")]
+    #[command(after_help = help::IMPORT)]
+    Import {
+        /// Archive to import notes from
+        path: std::path::PathBuf,
+
+        /// Import notes exported by other note app instead of archive
+        #[arg(long, value_name = "APP")]
+        from: Option<ImportFormat>,
+
+        /// Import all notes in one transaction and stop on the first error
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Export all notes with attachments into compressed archive
+    #[cfg(feature = "export")]
+    #[command(after_help = help::EXPORT_ARCHIVE)]
+    ExportArchive {
+        /// File to export notes into
+        path: std::path::PathBuf,
+
+        /// Encrypt archive with password
+        #[cfg(feature = "crypto")]
+        #[arg(long)]
+        password: bool,
+    },
+
+    /// Import notes with attachments from archive created by `export-archive`
+    #[cfg(feature = "export")]
+    #[command(after_help = help::IMPORT_ARCHIVE)]
+    ImportArchive {
+        /// Archive to import notes from
+        path: std::path::PathBuf,
+
+        /// Import all notes in one transaction and stop on the first error
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Write all data of the notebook into SQL file
+    #[command(after_help = help::DUMP)]
+    Dump {
+        /// File to write dump into
+        path: std::path::PathBuf,
+    },
+
+    /// Replace all data of the notebook with SQL dump
+    #[command(after_help = help::RESTORE)]
+    Restore {
+        /// File with dump created by `dump`
+        path: std::path::PathBuf,
+
+        /// Don't ask to type name of the notebook to confirm
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Export all notes into CSV file
+    #[cfg(feature = "export")]
+    #[command(after_help = help::EXPORT_CSV)]
+    ExportCsv {
+        /// File to export notes into
+        path: std::path::PathBuf,
+    },
+
+    /// Import notes from CSV file
+    #[cfg(feature = "export")]
+    #[command(after_help = help::IMPORT_CSV)]
+    ImportCsv {
+        /// CSV file with `name` and `note` columns
+        path: std::path::PathBuf,
+
+        /// Import all notes in one transaction and stop on the first error
+        #[arg(long)]
+        fail_fast: bool,
+    },
+
+    /// Lock note, so other users can't change it
+    #[command(after_help = help::LOCK_NOTE)]
+    LockNote {
+        /// Name of the note
+        notename: String,
+    },
+
+    /// Unlock note locked by you
+    #[command(after_help = help::UNLOCK_NOTE)]
+    UnlockNote {
+        /// Name of the note
+        notename: String,
+
+        /// Unlock note locked by other user
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Display commands that changed the notebook
+    #[command(after_help = help::AUDIT)]
+    Audit {
+        /// Display only commands executed since date, like `2024-07-20` or `2024-07-20 18:30`
+        #[arg(long)]
+        since: Option<String>,
+    },
+
+    /// Create user of shared notebook
+    #[command(after_help = help::ADD_USER)]
+    AddUser {
+        /// Name of the user
+        name: String,
+
+        /// Let the user see and change notes of all users and manage users
+        #[arg(long)]
+        admin: bool,
+    },
+
+    /// Delete user, its notes become shared
+    #[command(after_help = help::DEL_USER)]
+    DelUser {
+        /// Name of the user
+        name: String,
+    },
+
+    /// Display users of shared notebook
+    #[command(after_help = help::LIST_USERS)]
+    ListUsers,
+
+    /// Change owner of note
+    #[command(after_help = help::CHOWN)]
+    Chown {
+        /// Name of the note
+        notename: String,
+
+        /// New owner of the note, the note becomes shared if it's not given
+        owner: Option<String>,
+    },
+
+    /// Share note with other user
+    #[command(after_help = help::SHARE_NOTE)]
+    ShareNote {
+        /// Name of the note
+        notename: String,
+
+        /// Name of the user
+        user: String,
+
+        /// Let the user only read the note
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Stop sharing note with other user
+    #[command(after_help = help::UNSHARE_NOTE)]
+    UnshareNote {
+        /// Name of the note
+        notename: String,
+
+        /// Name of the user
+        user: String,
+    },
+
+    /// Display notes that other users shared with you
+    #[command(after_help = help::SHARED_WITH_ME)]
+    SharedWithMe,
+
+    /// Split note into several notes by headings or delimiter
+    #[command(after_help = help::SPLIT_NOTE)]
+    SplitNote {
+        /// Name of the note
+        notename: String,
+
+        /// How note is divided into sections
+        #[arg(long, value_enum, default_value_t)]
+        by: SplitBy,
+
+        /// Line that separates sections with `--by delimiter`
+        #[arg(long, default_value = DEFAULT_DELIMITER)]
+        delimiter: String,
+
+        /// Don't delete the original note
+        #[arg(long)]
+        keep: bool,
+    },
+
+    /// Open terminal user interface
+    #[cfg(feature = "tui")]
+    #[command(after_help = help::TUI)]
+    Tui,
+
+    /// Pick note with fuzzy search and run action with it
+    #[cfg(feature = "tui")]
+    #[command(after_help = help::PICK)]
+    Pick {
+        /// Action run with the picked note, can be changed in picker with `Tab`
+        #[arg(long, value_enum, default_value_t = PickAction::Display)]
+        action: PickAction,
+    },
+
+    /// Print changes of notes as they happen
+    #[cfg(feature = "watch")]
+    #[command(after_help = help::WATCH)]
+    Watch,
+
+    /// Start HTTP server with notebook
+    #[cfg(feature = "server")]
+    #[command(after_help = help::SERVE)]
+    Serve {
+        /// Address the server will listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+
+    /// Start gRPC service with notebook
+    #[cfg(feature = "grpc")]
+    #[command(after_help = help::SERVE_GRPC)]
+    ServeGrpc {
+        /// Address the gRPC service will listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: String,
+    },
+
+    /// Apply changes queued while the database was unreachable
+    #[command(after_help = help::REPLAY_QUEUE)]
+    ReplayQueue,
+
+    /// Synchronize notes with other notebook
+    #[command(after_help = help::SYNC)]
+    Sync {
+        /// URL of database of other notebook to synchronize notes with in both directions
+        #[arg(long)]
+        remote: String,
+
+        /// How notes changed in both notebooks since the last sync are synchronized
+        #[arg(long, value_enum, default_value_t)]
+        conflict: crate::sync::ConflictPolicy,
+
+        /// Display what would be changed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Measure performance of the notebook on the connected database
+    #[command(after_help = help::BENCH)]
+    Bench {
+        /// Number of synthetic notes
+        #[arg(long, default_value_t = 1000)]
+        notes: usize,
+
+        /// Number of runs of every operation except adding
+        #[arg(long, default_value_t = 20)]
+        runs: usize,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Generate man pages of all commands
+    #[cfg(feature = "man")]
+    #[command(after_help = help::GEN_MAN)]
+    GenMan {
+        /// Directory to write man pages into
+        dir: std::path::PathBuf,
+    },
+}
+
+/// How much the notebook prints, read more in [`NoteCommand::verbosity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only results of command and prompts, without logging
+    Quiet,
+    /// Results, messages and log of changes
+    #[default]
+    Normal,
+    /// Also SQL queries with their timing and how long command took
+    Verbose,
+}
+
+/// Content of note given in command instead of entering it after the command.
+#[derive(Args, Default)]
+struct NoteContent {
+    /// Content of the note
+    #[arg(short, long, conflicts_with = "file")]
+    message: Option<String>,
+
+    /// File with content of the note, `-` reads standard input until its end
+    #[arg(long)]
+    file: Option<std::path::PathBuf>,
+}
+
+impl NoteContent {
+    /// Returns content given with `--message` or `--file`, `None` if it should be entered.
+    fn read(&self) -> Result<Option<String>, NotebookError> {
+        if let Some(message) = &self.message {
+            return Ok(Some(message.to_owned()));
+        }
+
+        match self.file.as_deref() {
+            Some(path) if path == std::path::Path::new("-") => {
+                Ok(Some(std::io::read_to_string(std::io::stdin())?))
+            }
+            Some(path) => Ok(Some(std::fs::read_to_string(path)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Parses range of lines `start:end`, `start:` or `line` given to `display-note --lines`
+/// and `highlight`.
+fn parse_lines(lines: &str) -> Result<(u32, u32), String> {
+    let (start, end) = match lines.split_once(':') {
+        Some((start, end)) => (start, Some(end)),
+        None => (lines, None),
+    };
+
+    let start = start
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid start line `{start}`"))?;
+    let end = match end.map(str::trim) {
+        None => start,
+        Some("") => u32::MAX,
+        Some(end) => end
+            .parse()
+            .map_err(|_| format!("invalid end line `{end}`"))?,
+    };
+
+    Ok((start, end))
+}
+
+/// Parses placeholder `key=value` given to `template-from --vars`.
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    let (key, value) = var
+        .split_once('=')
+        .ok_or_else(|| format!("`{var}` should look like `key=value`"))?;
+
+    let key = key.trim();
+    if key.is_empty() || key.contains(['{', '}']) {
+        return Err(format!("invalid placeholder name `{key}`"));
+    }
+    if value.is_empty() {
+        return Err(format!("value of placeholder `{key}` is empty"));
+    }
+
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+/// Contains the command as `enum` from CLI to run it later.
+///
+/// This `struct` was created to conveniently store and execute commands on a notebook from CLI commands.
+/// More about commands for which this structure was created [here][crate::commands::execute_commands].
+#[derive(Parser)]
+#[command(
+    name = "notebook",
+    version,
+    about = "Notebook that stores notes in a database",
+    long_about = None
+)]
+pub struct NoteCommand {
+    /// Display whole content of all notes when no command is selected
+    #[arg(long)]
+    full: bool,
+
+    /// Number of characters of the first line of every note displayed when no command
+    /// is selected
+    #[arg(long, default_value_t = PREVIEW_LEN, value_parser = clap::value_parser!(i32).range(1..))]
+    preview_len: i32,
+
+    /// Don't ask anything and print only results, for use in scripts
+    #[arg(long, global = true)]
+    script: bool,
+
+    /// Format of displayed notes
+    #[arg(long, global = true, value_enum, default_value_t = RenderFormat::Plain)]
+    format: RenderFormat,
+
+    /// Don't print back content of entered notes, only their size
+    #[arg(long, global = true)]
+    no_echo: bool,
+
+    /// Marker that finishes entered note, empty to finish only with end of input (Ctrl+D)
+    #[arg(long, global = true)]
+    end_marker: Option<String>,
+
+    /// Act as user of shared notebook: see and change only notes of the user and shared notes
+    #[arg(long, global = true)]
+    as_user: Option<String>,
+
+    /// Don't color output, also disabled by `NO_COLOR` enivroment variable
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Print only results and prompts, without other messages and logging
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log SQL queries with their timing and print how long command took
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Print duration of command and its SQL queries into stderr
+    #[cfg(feature = "metrics")]
+    #[arg(long, global = true)]
+    timing: bool,
+
+    #[command(subcommand)]
+    cmd: Option<Command>,
+}
+impl NoteCommand {
+    /// Convert a command from CLI to `enum` and saves it in [struct `NoteCommand`][NoteCommand].
+    ///
+    /// Command stores in [`NoteCommand`] as `Option<Command>` and will be:
+    /// * `Some(Command)` if you selected any existing command
+    /// * `None` if you **didn't selected**/**selected a non-existent command**
+    ///
+    /// Read about CLI commands [here][crate::commands::execute_commands].
+    pub async fn new() -> Result<NoteCommand, clap::Error> {
+        let mut command = NoteCommand::try_parse()?;
+
+        // Output of `cat` is piped into other programs, so nothing else can be printed
+        if let Some(Command::Cat { .. } | Command::GetBinary { .. }) = command.cmd {
+            command.script = true;
+        }
+
+        if Config::from_env().normalize_names {
+            command.normalize_names();
+        }
+
+        Ok(command)
+    }
+    /// Normalizes names of notes that command creates.
+    ///
+    /// Names that are not valid after normalization are left as they are, so command
+    /// returns [`NotebookError::InvalidNoteName`] about the name that was entered.
+    fn normalize_names(&mut self) {
+        let notename = match self.cmd.as_mut() {
+            Some(Command::AddNote {
+                notename: Some(notename),
+                ..
+            })
+            | Some(Command::UpdNotename {
+                new_notename: notename,
+                ..
+            })
+            | Some(Command::NewFromTemplate { notename, .. }) => notename,
+            #[cfg(feature = "clipboard")]
+            Some(Command::AddFromClipboard { notename }) => notename,
+            _ => return,
+        };
+
+        if let Ok(normalized) = NoteName::normalized(notename) {
+            *notename = normalized.into_string();
+        }
+    }
+    /// Returns `true` if `--script` was used.
+    ///
+    /// In script mode the notebook doesn't print prompts and other messages, accepts
+    /// all confirmations (like `--force` and `--yes`) and prints results without logging,
+    /// so output doesn't depend on time or terminal. Logging is set up by your program,
+    /// so it should be disabled when this returns `true`.
+    pub fn is_script(&self) -> bool {
+        self.script
+    }
+    /// Returns how much the notebook prints, selected with `--quiet` and `--verbose`.
+    ///
+    /// It's [`Verbosity::Quiet`] with `--script` too. Logging is set up by your program,
+    /// so its level should depend on this, like in the example.
+    /// ### Example
+    /// ```rust,no_run
+    /// # use lnotebook::{NoteCommand, Verbosity};
+    /// # async fn verbosity_example() {
+    /// let command = NoteCommand::new().await.unwrap_or_else(|err| err.exit());
+    ///
+    /// let level = match command.verbosity() {
+    ///     Verbosity::Quiet => None,
+    ///     Verbosity::Normal => Some("info"),
+    ///     Verbosity::Verbose => Some("debug"),
+    /// };
+    /// # }
+    /// ```
+    pub fn verbosity(&self) -> Verbosity {
+        if self.script || self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+    /// Prints message that is not a result of command, unless `--script` or `--quiet` is used.
+    fn info(&self, message: std::fmt::Arguments) {
+        if self.verbosity() != Verbosity::Quiet {
+            println!("{message}");
+        }
+    }
+    /// Reads content of note finished with `end_marker` or end of input.
+    fn read_note(&self, prompt: &Prompt, end_marker: &str) -> Result<String, NotebookError> {
+        if end_marker.is_empty() {
+            self.info(format_args!(
+                "(Press Ctrl+D on a new line to finish writing the note):"
+            ));
+        } else {
+            self.info(format_args!(
+                "(At the end of the note, enter `{end_marker}` or press Ctrl+D on a new line to finish writing the note):"
+            ));
+        }
+
+        prompt.read_note(end_marker)
+    }
+    fn end_marker(&self, config: &Config) -> String {
+        self.end_marker
+            .clone()
+            .or(config.end_marker.clone())
+            .unwrap_or_else(|| config::DEFAULT_END_MARKER.to_owned())
+    }
+    /// Asks for passphrase of `notename` if it's protected and checks it.
+    ///
+    /// Returns the passphrase, `None` if note is not protected.
+    async fn unlock(
+        &self,
+        notename: &str,
+        prompt: &Prompt,
+        pool: &PgPool,
+    ) -> Result<Option<String>, NotebookError> {
+        // Notes that are not in the database (like notes kept by other `NoteStore`)
+        // can't be protected, so error is returned by the command itself
+        match protect::is_protected(notename, pool).await {
+            Ok(false) | Err(NotebookError::Sqlx(sqlx::Error::RowNotFound)) => return Ok(None),
+            Ok(true) => {}
+            Err(err) => return Err(err),
+        }
+
+        #[cfg(feature = "crypto")]
+        {
+            let passphrase = prompt.passphrase(&format!("Enter passphrase of `{notename}`: "))?;
+            protect::unlock(notename, &passphrase, pool).await?;
+
+            Ok(Some(passphrase))
+        }
+
+        #[cfg(not(feature = "crypto"))]
+        {
+            let _ = prompt;
+            Err(NotebookError::NoteProtected {
+                notename: notename.to_owned(),
+            })
+        }
+    }
+    /// Returns change that command makes if it can be queued while the database is unreachable.
+    fn offline_change(&self) -> Option<(Operation, &str, &NoteContent)> {
+        match self.cmd.as_ref() {
+            // Name of auto-named note is known only after it's added
+            Some(Command::AddNote {
+                notename: Some(notename),
+                content,
+                ..
+            }) => Some((Operation::Add, notename, content)),
+            Some(Command::UpdNote { notename, content }) => {
+                Some((Operation::Upd, notename, content))
+            }
+            _ => None,
+        }
+    }
+    /// Returns options of connection to the database for this command.
+    ///
+    /// If command can be queued while the database is unreachable (read about it in
+    /// [`offline` module][crate::offline]), the notebook doesn't wait long for the database.
+    ///
+    /// SQL queries are logged with level that depends on [`NoteCommand::verbosity`]:
+    /// `INFO` with `--verbose`, `DEBUG` by default and not logged with `--quiet`
+    /// (`TRACE` with `--timing`, so they are recorded by
+    /// [`MetricsLayer`][crate::metrics::MetricsLayer]).
+    pub fn connect_options(&self) -> ConnectOptions {
+        let log_statements = match self.verbosity() {
+            #[cfg(feature = "metrics")]
+            Verbosity::Quiet if self.timing => LevelFilter::TRACE,
+            Verbosity::Quiet => LevelFilter::OFF,
+            Verbosity::Normal => LevelFilter::DEBUG,
+            Verbosity::Verbose => LevelFilter::INFO,
+        };
+
+        if Config::from_env().offline_queue && self.offline_change().is_some() {
+            ConnectOptions {
+                acquire_timeout: Duration::from_secs(3),
+                retries: 0,
+                log_statements,
+                ..ConnectOptions::default()
+            }
+        } else {
+            ConnectOptions {
+                log_statements,
+                ..ConnectOptions::default()
+            }
+        }
+    }
+    /// Executes command without the database after connection to it failed with `err`.
+    ///
+    /// If queue of changes is enabled and the database is unreachable, `add-note` and `upd-note`
+    /// read content of note as usual and queue the change, read more in
+    /// [`offline` module][crate::offline].
+    /// ### Returns
+    /// * Ok
+    ///     * [`CommandResponse::None`] if change was queued
+    /// * Errors
+    ///     * `err` if command can't be queued
+    ///     * [`NotebookError::Io`] error if queue can't be written
+    /// ### Example
+    /// ```rust,no_run
+    /// # use lnotebook::{connect, get_db_url, NoteCommand, NotebookError};
+    /// async fn execute_offline_example() -> Result<(), NotebookError> {
+    ///     let command = NoteCommand::new().await.unwrap_or_else(|err| err.exit());
+    ///
+    ///     match connect(&get_db_url().await?, command.connect_options()).await {
+    ///         Ok(db) => command.execute_command(&db).await?,
+    ///         Err(err) => command.execute_offline(err).await?,
+    ///     };
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute_offline(
+        &self,
+        err: NotebookError,
+    ) -> Result<CommandResponse, NotebookError> {
+        let config = Config::from_env();
+        let Some(path) = offline::queue_path(&config)
+            .filter(|_| config.offline_queue && offline::is_unreachable(&err))
+        else {
+            return Err(err);
+        };
+        let Some((operation, notename, content)) = self.offline_change() else {
+            return Err(err);
+        };
+
+        config::set_hide_content(self.no_echo || config.no_echo);
+        config::set_max_note_size(config.max_note_size);
+        self.info(format_args!(
+            "Database is unreachable ({err}), the change will be queued"
+        ));
+
+        let note = match content.read()? {
+            Some(note) => note,
+            None => {
+                self.info(format_args!("Enter note for `{notename}`"));
+
+                let prompt = Prompt::new().script(self.script);
+                self.read_note(&prompt, &self.end_marker(&config))?
+            }
+        };
+
+        config::check_size(note.len())?;
+        let mut change = QueuedChange::new(operation, notename, &note);
+        if let Some(bases) = offline::bases_path(&config) {
+            if let Some(hash) = offline::remembered_base(&bases, notename)? {
+                change = change.based_on(hash);
+            }
+        }
+        offline::push(&path, &change)?;
+        self.info(format_args!(
+            "Change of `{notename}` is queued, run `replay-queue` to apply it when the database is reachable"
+        ));
+
+        Ok(CommandResponse::None)
+    }
+    /// Execute specifed command.
+    ///
+    /// [List of all CLI commands.](https://docs.rs/lnotebook/latest/lnotebook/commands/execute_commands/index.html#list-of-all-commands-you-can-call-from-CLI).
+    ///
+    /// Read about CLI commands [here][crate::commands::execute_commands].
+    /// ### Returns
+    /// * Ok
+    ///     * [`CommandResponse`] with data that command printed, so it can also be used by
+    ///       your program. It's [`CommandResponse::None`] if command was cancelled
+    ///       or doesn't return any data (like `tui` or `serve`)
+    /// * Errors
+    ///     * Any [`NotebookError`] from executed command or reading input
+    /// ### Example
+    /// ```rust,no_run
+    /// # use lnotebook::NoteCommand;
+    /// # use lnotebook::notebook::CommandResponse;
+    /// # use lnotebook::NotebookError;
+    /// # use sqlx::PgPool;
+    /// async fn execute_command_example(pool: &PgPool) -> Result<(), NotebookError> {
+    ///     let command = NoteCommand::new().await.unwrap_or_else(|err| err.exit());
+    ///
+    ///     if let CommandResponse::Note(note) = command.execute_command(pool).await? {
+    ///         println!("Command returned note `{}`", note.note_name);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute_command(&self, pool: &PgPool) -> Result<CommandResponse, NotebookError> {
+        let notebook = Notebook::new(pool.clone());
+        #[cfg(feature = "webhooks")]
+        let notebook = notebook.with_webhooks(Config::from_env().webhooks);
+        #[cfg(feature = "git")]
+        let notebook = match Config::from_env().git_dir {
+            Some(dir) => notebook.with_git(GitRepo::new(dir)),
+            None => notebook,
+        };
+        let notebook = match &self.as_user {
+            Some(user) => notebook.as_user(user),
+            None => notebook,
+        };
+
+        self.execute_in(&notebook).await
+    }
+
+    /// Executes command like [`NoteCommand::execute_command`] in `notebook`, which can keep notes
+    /// in its own [`NoteStore`][crate::store::NoteStore] (read more [here][crate::store]).
+    /// ### Returns
+    /// * Ok
+    ///     * [`CommandResponse`] with data that command printed
+    /// * Errors
+    ///     * Any [`NotebookError`] from executed command or reading input
+    pub async fn execute_in(&self, notebook: &Notebook) -> Result<CommandResponse, NotebookError> {
+        let pool = notebook.pool();
+        let config = Config::from_env();
+        let no_echo = self.no_echo || config.no_echo;
+        config::set_hide_content(no_echo);
+        config::set_max_note_size(config.max_note_size);
+        config::set_compress_threshold(config.compress_threshold);
+        let end_marker = self.end_marker(&config);
+        let prompt = Prompt::new().script(self.script);
+        // Passphrases of protected notes, read about them in `protect` module
+        let mut passphrases = Vec::new();
+
+        let request = match self.cmd.as_ref() {
+            Some(Command::AddNote {
+                notename: Some(notename),
+                content,
+                ..
+            }) => {
+                let note = match content.read()? {
+                    Some(note) => note,
+                    None => {
+                        self.info(format_args!(
+                            "Enter note you want to add into `{}`",
+                            notename
+                        ));
+                        self.read_note(&prompt, &end_marker)?
+                    }
+                };
+                self.info(format_args!(
+                    "Note to add into `{notename}`:\n{}",
+                    echo(&note, no_echo)
+                ));
+
+                CommandRequest::AddNote {
+                    notename: notename.to_owned(),
+                    note,
+                }
+            }
+
+            Some(Command::AddNote {
+                notename: None,
+                content,
+                ..
+            }) => {
+                let note = match content.read()? {
+                    Some(note) => note,
+                    None => {
+                        self.info(format_args!(
+                            "Enter note you want to add, it will be named after its first line"
+                        ));
+                        self.read_note(&prompt, &end_marker)?
+                    }
+                };
+                self.info(format_args!("Note to add:\n{}", echo(&note, no_echo)));
+
+                CommandRequest::AddAutoNamed {
+                    note,
+                    normalize: config.normalize_names,
+                }
+            }
+
+            Some(Command::AddBinary { notename, file }) => {
+                let data = if file == std::path::Path::new("-") {
+                    let mut data = Vec::new();
+                    std::io::Read::read_to_end(&mut std::io::stdin(), &mut data)?;
+                    data
+                } else {
+                    std::fs::read(file)?
+                };
+                self.info(format_args!(
+                    "Binary note to add into `{notename}`: {} bytes",
+                    data.len()
+                ));
+
+                CommandRequest::AddBinary {
+                    notename: notename.to_owned(),
+                    data,
+                }
+            }
+
+            Some(Command::DelNote { notename }) => CommandRequest::DelNote {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::DelAll { force }) => {
+                if !force {
+                    let name = notebook.name().await?;
+
+                    let message = format!(
+                        "This will delete all notes from notebook `{name}` and can't be undone."
+                    );
+                    if !prompt.confirm_typed(&message, &name)? {
+                        self.info(format_args!("Nothing was deleted"));
+                        return Ok(CommandResponse::None);
+                    }
+                }
+
+                CommandRequest::DelAll
+            }
+
+            Some(Command::DelMatching {
+                pattern,
+                regex,
+                yes,
+            }) => {
+                let pattern = if *regex {
+                    NamePattern::Regex(pattern.to_owned())
+                } else {
+                    NamePattern::Glob(pattern.to_owned())
+                };
+
+                let notenames = match notebook
+                    .execute(CommandRequest::SelectMatching { pattern })
+                    .await?
+                {
+                    CommandResponse::Notenames(notenames) => notenames,
+                    _ => Vec::new(),
+                };
+                if notenames.is_empty() {
+                    self.info(format_args!("No notes match the pattern"));
+                    return Ok(CommandResponse::None);
+                }
+
+                self.info(format_args!("Notes to delete:"));
+                for notename in &notenames {
+                    self.info(format_args!("  {notename}"));
+                }
+
+                if !yes && !prompt.confirm(&format!("Delete {} notes?", notenames.len()))? {
+                    return Ok(CommandResponse::None);
+                }
+
+                CommandRequest::DelNames { notenames }
+            }
+
+            Some(Command::ClearNote { notename }) => CommandRequest::ClearNote {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::UpdNotename {
+                notename,
+                new_notename,
+                rewrite_links,
+                merge,
+            }) => CommandRequest::UpdNotename {
+                notename: notename.to_owned(),
+                new_notename: new_notename.to_owned(),
+                rewrite_links: *rewrite_links,
+                on_conflict: if *merge {
+                    OnConflict::Merge
+                } else {
+                    OnConflict::Error
+                },
+            },
+
+            Some(Command::RenameMatching {
+                pattern,
+                replacement,
+            }) => CommandRequest::RenameMatching {
+                pattern: pattern.to_owned(),
+                replacement: replacement.to_owned(),
+            },
+
+            Some(Command::Sed {
+                pattern,
+                replacement,
+                dry_run,
+            }) => CommandRequest::ReplaceAll {
+                pattern: pattern.to_owned(),
+                replacement: replacement.to_owned(),
+                dry_run: *dry_run,
+            },
+
+            Some(Command::UpdNote { notename, content }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                let note = match content.read()? {
+                    Some(note) => note,
+                    None => {
+                        if let CommandResponse::Note(note) = protect::with_passphrases(
+                            passphrases.clone(),
+                            notebook.execute(CommandRequest::DisplayNote {
+                                notename: notename.to_owned(),
+                                lines: None,
+                            }),
+                        )
+                        .await?
+                        {
+                            self.info(format_args!(
+                                "Current content of `{}`:\n{}",
+                                notename,
+                                echo(note.body(), no_echo)
+                            ));
+                        }
+
+                        self.info(format_args!(
+                            "Enter note you want to add instead old note in `{}`",
+                            notename
+                        ));
+                        self.read_note(&prompt, &end_marker)?
+                    }
+                };
+                self.info(format_args!(
+                    "Note to add into `{notename}` instead old note:\n{}",
+                    echo(&note, no_echo)
+                ));
+
+                CommandRequest::UpdNote {
+                    notename: notename.to_owned(),
+                    note,
+                }
+            }
+
+            Some(Command::AppendNote { notename }) => {
+                self.info(format_args!(
+                    "Enter text you want to add to the end of `{}`",
+                    notename
+                ));
+
+                CommandRequest::AppendNote {
+                    notename: notename.to_owned(),
+                    text: self.read_note(&prompt, &end_marker)?,
+                }
+            }
+
+            Some(Command::Journal { name_format }) => {
+                let name_format = name_format
+                    .clone()
+                    .or(config.journal_format.clone())
+                    .unwrap_or_else(|| journal::DEFAULT_NAME_FORMAT.to_owned());
+
+                self.info(format_args!(
+                    "Enter entry you want to add into journal `{}`",
+                    journal::journal_name(&name_format, Local::now())?
+                ));
+
+                CommandRequest::Journal {
+                    text: self.read_note(&prompt, &end_marker)?,
+                    name_format,
+                }
+            }
+
+            Some(Command::PrependNote { notename }) => {
+                self.info(format_args!(
+                    "Enter text you want to add to the beginning of `{}`",
+                    notename
+                ));
+
+                CommandRequest::PrependNote {
+                    notename: notename.to_owned(),
+                    text: self.read_note(&prompt, &end_marker)?,
+                }
+            }
+
+            Some(Command::InsertNote { notename, line }) => {
+                self.info(format_args!(
+                    "Enter text you want to insert into `{}` before line {}",
+                    notename, line
+                ));
+
+                CommandRequest::InsertNote {
+                    notename: notename.to_owned(),
+                    line: *line,
+                    text: self.read_note(&prompt, &end_marker)?,
+                }
+            }
+
+            Some(Command::DisplayNote {
+                notename,
+                lines: None,
+            }) if notename.ends_with('*') => {
+                let prefix = notename.trim_end_matches('*');
+
+                // Protected notes are displayed only if their passphrases are given
+                let pattern = NamePattern::Regex(format!("^{}", regex::escape(prefix)));
+                for notename in commands::select_matching(&pattern, pool).await? {
+                    passphrases.extend(self.unlock(&notename, &prompt, pool).await?);
+                }
+
+                CommandRequest::DisplayPrefix {
+                    prefix: prefix.to_owned(),
+                }
+            }
+
+            Some(Command::DisplayNote { notename, lines }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                CommandRequest::DisplayNote {
+                    notename: notename.to_owned(),
+                    lines: *lines,
+                }
+            }
+
+            Some(Command::Search {
+                query,
+                all_profiles,
+            }) => CommandRequest::Search {
+                query: query.to_owned(),
+                all_profiles: *all_profiles,
+            },
+
+            Some(Command::Grep {
+                pattern,
+                ignore_case,
+                names_only,
+            }) => CommandRequest::Grep {
+                pattern: pattern.to_owned(),
+                ignore_case: *ignore_case,
+                names_only: *names_only,
+            },
+
+            Some(Command::History { notename }) => CommandRequest::History {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::DisplayRevision { revision }) => CommandRequest::DisplayRevision {
+                revision: *revision,
+            },
+
+            Some(Command::Diff {
+                notename,
+                other,
+                version,
+                context,
+            }) => CommandRequest::Diff {
+                notename: notename.to_owned(),
+                with: match (other, version) {
+                    (_, Some(revision)) => DiffWith::Revision(*revision),
+                    (Some(other), None) => DiffWith::Note(other.to_owned()),
+                    // clap requires one of them
+                    (None, None) => unreachable!(),
+                },
+                context: *context,
+            },
+
+            Some(Command::Stats { usage: true }) => {
+                match usage::usage_path(&config) {
+                    Some(path) => {
+                        let usage = UsageStats::load(&path)?;
+
+                        if !config.usage {
+                            self.info(format_args!(
+                                "Usage statistics are not recorded, set `LNOTEBOOK_USAGE=true` to record them"
+                            ));
+                        }
+                        println!("{usage}");
+                    }
+                    None => self.info(format_args!(
+                        "Config directory is unknown, set `LNOTEBOOK_CONFIG_DIR`"
+                    )),
+                }
+                return Ok(CommandResponse::None);
+            }
+
+            Some(Command::Stats { usage: false }) => CommandRequest::Stats,
+
+            Some(Command::CountWords { notename }) => CommandRequest::CountWords {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::Summary { notename, max_len }) => CommandRequest::Summary {
+                notename: notename.to_owned(),
+                max_len: *max_len,
+            },
+
+            Some(Command::Lint {
+                notename,
+                max_line_len,
+                #[cfg(feature = "spellcheck")]
+                spellcheck,
+                #[cfg(feature = "spellcheck")]
+                dictionary,
+            }) => CommandRequest::Lint {
+                notename: notename.to_owned(),
+                max_line_len: *max_line_len,
+                #[cfg(feature = "spellcheck")]
+                dictionary: spellcheck.then(|| {
+                    dictionary
+                        .clone()
+                        .or(config.dictionary.clone())
+                        .unwrap_or_else(|| crate::lint::DEFAULT_DICTIONARY.into())
+                }),
+                #[cfg(not(feature = "spellcheck"))]
+                dictionary: None,
+            },
+
+            Some(Command::Cat { notename }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                CommandRequest::Cat {
+                    notename: notename.to_owned(),
+                }
+            }
+
+            #[cfg(feature = "clipboard")]
+            Some(Command::AddFromClipboard { notename }) => {
+                let note = crate::clipboard::paste()?;
+                self.info(format_args!(
+                    "Note to add into `{notename}` from clipboard:\n{}",
+                    echo(&note, no_echo)
+                ));
+
+                CommandRequest::AddNote {
+                    notename: notename.to_owned(),
+                    note,
+                }
+            }
+
+            #[cfg(feature = "clipboard")]
+            Some(Command::CopyToClipboard { notename }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                let response = protect::with_passphrases(
+                    passphrases,
+                    notebook.execute(CommandRequest::Cat {
+                        notename: notename.to_owned(),
+                    }),
+                )
+                .await?;
+
+                match &response {
+                    CommandResponse::Content(Some(content)) => {
+                        crate::clipboard::copy(content)?;
+                        self.info(format_args!("Note `{notename}` is copied into clipboard"));
+                    }
+                    _ => self.info(format_args!("Note `{notename}` doesn't exist")),
+                }
+                return Ok(response);
+            }
+
+            #[cfg(feature = "qr")]
+            Some(Command::Qr { notename, png }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                let response = protect::with_passphrases(
+                    passphrases,
+                    notebook.execute(CommandRequest::Cat {
+                        notename: notename.to_owned(),
+                    }),
+                )
+                .await?;
+
+                match (&response, png) {
+                    (CommandResponse::Content(Some(content)), Some(path)) => {
+                        crate::qr::write_png(content, path)?;
+                        self.info(format_args!(
+                            "QR code of `{notename}` is saved into `{}`",
+                            path.display()
+                        ));
+                    }
+                    (CommandResponse::Content(Some(content)), None) => {
+                        println!("{}", crate::qr::render_terminal(content)?);
+                    }
+                    _ => self.info(format_args!("Note `{notename}` doesn't exist")),
+                }
+                return Ok(response);
+            }
+
+            Some(Command::GetBinary { notename }) => {
+                passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                CommandRequest::GetBinary {
+                    notename: notename.to_owned(),
+                }
+            }
+
+            Some(Command::Exists { notename }) => CommandRequest::Exists {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::Count) => CommandRequest::Count,
+
+            Some(Command::RandomNote) => CommandRequest::RandomNote,
+
+            Some(Command::Links { notename }) => CommandRequest::Links {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::Backlinks { notename }) => CommandRequest::Backlinks {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::ExportGraph { graph_format }) => CommandRequest::ExportGraph {
+                format: *graph_format,
+            },
+
+            Some(Command::CheckLinks {
+                #[cfg(feature = "linkcheck")]
+                http,
+            }) => CommandRequest::CheckLinks {
+                #[cfg(feature = "linkcheck")]
+                http: *http,
+                #[cfg(not(feature = "linkcheck"))]
+                http: false,
+            },
+
+            Some(Command::Attach { notename, file }) => CommandRequest::Attach {
+                notename: notename.to_owned(),
+                path: file.to_owned(),
+            },
+
+            Some(Command::Attachments { notename }) => CommandRequest::Attachments {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::SaveAttachment {
+                notename,
+                file,
+                dest,
+            }) => CommandRequest::SaveAttachment {
+                notename: notename.to_owned(),
+                file_name: file.to_owned(),
+                dest: dest.to_owned(),
+            },
+
+            Some(Command::Highlight {
+                notename,
+                lines: (start_line, end_line),
+            }) => CommandRequest::Highlight {
+                notename: notename.to_owned(),
+                start_line: *start_line,
+                end_line: *end_line,
+            },
+
+            Some(Command::Highlights { notename }) => CommandRequest::Highlights {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::Compact {
+                batch_size,
+                compress_threshold,
+            }) => CommandRequest::Compact {
+                batch_size: *batch_size,
+                compress_threshold: match compress_threshold {
+                    Some(0) => None,
+                    Some(threshold) => Some(*threshold),
+                    None => config.compress_threshold,
+                },
+                normalize_names: config.normalize_names,
+            },
+
+            Some(Command::Dedup { merge, yes }) => {
+                if *merge {
+                    let groups = match notebook
+                        .execute(CommandRequest::Dedup { merge: false })
+                        .await?
+                    {
+                        CommandResponse::Duplicates(groups) => groups,
+                        _ => Vec::new(),
+                    };
+                    if groups.is_empty() {
+                        self.info(format_args!("No notes with identical content"));
+                        return Ok(CommandResponse::None);
+                    }
+
+                    self.info(format_args!("Notes to merge into the first ones:"));
+                    for group in &groups {
+                        self.info(format_args!("  {group}"));
+                    }
+
+                    let count: usize = groups.iter().map(|group| group.duplicates.len()).sum();
+                    if !yes && !prompt.confirm(&format!("Delete {count} duplicated notes?"))? {
+                        return Ok(CommandResponse::None);
+                    }
+                }
+
+                CommandRequest::Dedup { merge: *merge }
+            }
+
+            Some(Command::SetDue { notename, due_at }) => CommandRequest::SetDue {
+                notename: notename.to_owned(),
+                due_at: due_at.to_owned(),
+            },
+
+            Some(Command::ClearDue { notename }) => CommandRequest::ClearDue {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::Due) => CommandRequest::Due,
+
+            Some(Command::AddTemplate { template_name }) => {
+                self.info(format_args!(
+                    "Enter template you want to add as `{}`",
+                    template_name
+                ));
+                self.info(format_args!("(Placeholders `{{{{date}}}}`, `{{{{time}}}}` and `{{{{name}}}}` will be filled when note is created)"));
+
+                CommandRequest::AddTemplate {
+                    template_name: template_name.to_owned(),
+                    template: self.read_note(&prompt, &end_marker)?,
+                }
+            }
+
+            Some(Command::DelTemplate { template_name }) => CommandRequest::DelTemplate {
+                template_name: template_name.to_owned(),
+            },
+
+            Some(Command::DisplayTemplates) => CommandRequest::DisplayTemplates,
+
+            Some(Command::NewFromTemplate {
+                template_name,
+                notename,
+            }) => CommandRequest::NewFromTemplate {
+                template_name: template_name.to_owned(),
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::TemplateFrom {
+                notename,
+                name,
+                vars,
+            }) => {
+                let mut vars = vars.to_owned();
+
+                if vars.is_empty() && !self.script {
+                    passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+
+                    if let CommandResponse::Note(note) = protect::with_passphrases(
+                        passphrases.clone(),
+                        notebook.execute(CommandRequest::DisplayNote {
+                            notename: notename.to_owned(),
+                            lines: None,
+                        }),
+                    )
+                    .await?
+                    {
+                        println!("Content of `{}`:\n{}", notename, note.body());
+                    }
+
+                    println!("Enter values that should be replaced with placeholders");
+                    println!("(one `key=value` on a line, for example `date=2024-07-19`; empty line to finish):");
+
+                    loop {
+                        let line = prompt.read_line("")?;
+                        if line.trim().is_empty() {
+                            break;
+                        }
+
+                        match parse_var(&line) {
+                            Ok(var) => vars.push(var),
+                            Err(err) => println!("{err}"),
+                        }
+                    }
+                }
+
+                CommandRequest::TemplateFrom {
+                    notename: notename.to_owned(),
+                    template_name: name.as_deref().unwrap_or(notename).to_owned(),
+                    vars,
+                }
+            }
+
+            #[cfg(feature = "metadata")]
+            Some(Command::SetMeta {
+                notename,
+                key,
+                value,
+            }) => CommandRequest::SetMeta {
+                notename: notename.to_owned(),
+                key: key.to_owned(),
+                value: crate::metadata::parse_value(value),
+            },
+
+            #[cfg(feature = "metadata")]
+            Some(Command::GetMeta { notename, key }) => CommandRequest::GetMeta {
+                notename: notename.to_owned(),
+                key: key.to_owned(),
+            },
+
+            #[cfg(feature = "metadata")]
+            Some(Command::FindByMeta { key, value }) => CommandRequest::FindByMeta {
+                key: key.to_owned(),
+                value: crate::metadata::parse_value(value),
+            },
+
+            #[cfg(feature = "crypto")]
+            Some(Command::ProtectNote { notename }) => {
+                let passphrase =
+                    prompt.passphrase(&format!("Enter passphrase for `{notename}`: "))?;
+                if passphrase != prompt.passphrase("Repeat passphrase: ")? {
+                    return Err(NotebookError::Crypto {
+                        reason: "passphrases don't match".to_owned(),
+                    });
+                }
+
+                CommandRequest::Protect {
+                    notename: notename.to_owned(),
+                    passphrase,
+                }
+            }
+
+            #[cfg(feature = "crypto")]
+            Some(Command::UnprotectNote { notename }) => CommandRequest::Unprotect {
+                notename: notename.to_owned(),
+                passphrase: prompt.passphrase(&format!("Enter passphrase of `{notename}`: "))?,
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::ExportMarkdown { dir, template }) => CommandRequest::ExportMarkdown {
+                dir: dir.to_owned(),
+                template: template.to_owned(),
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::ExportHtml { dir }) => CommandRequest::ExportHtml {
+                dir: dir.to_owned(),
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::Export {
+                path,
+                #[cfg(feature = "crypto")]
+                encrypt,
+            }) => {
+                #[cfg(feature = "crypto")]
+                let passphrase = if *encrypt {
+                    let passphrase = prompt.passphrase("Enter passphrase to encrypt archive: ")?;
+                    if passphrase != prompt.passphrase("Repeat passphrase: ")? {
+                        return Err(NotebookError::Crypto {
+                            reason: "passphrases don't match".to_owned(),
+                        });
+                    }
+
+                    Some(passphrase)
+                } else {
+                    None
+                };
+                #[cfg(not(feature = "crypto"))]
+                let passphrase: Option<String> = None;
+
+                CommandRequest::Export {
+                    path: path.to_owned(),
+                    passphrase,
+                }
+            }
+
+            #[cfg(feature = "export")]
+            Some(Command::Import {
+                path,
+                from: Some(format),
+                fail_fast,
+            }) => CommandRequest::ImportFrom {
+                format: *format,
+                path: path.to_owned(),
+                fail_fast: *fail_fast,
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::Import {
+                path,
+                from: None,
+                fail_fast,
+            }) => {
+                let passphrase = if crate::export::is_archive_encrypted(path)? {
+                    Some(prompt.passphrase("Enter passphrase to decrypt archive: ")?)
+                } else {
+                    None
+                };
+
+                CommandRequest::Import {
+                    path: path.to_owned(),
+                    passphrase,
+                    fail_fast: *fail_fast,
+                }
+            }
+
+            #[cfg(feature = "export")]
+            Some(Command::ExportArchive {
+                path,
+                #[cfg(feature = "crypto")]
+                password,
+            }) => {
+                #[cfg(feature = "crypto")]
+                let passphrase = if *password {
+                    let passphrase = prompt.passphrase("Enter password to encrypt archive: ")?;
+                    if passphrase != prompt.passphrase("Repeat password: ")? {
+                        return Err(NotebookError::Crypto {
+                            reason: "passwords don't match".to_owned(),
+                        });
+                    }
+
+                    Some(passphrase)
+                } else {
+                    None
+                };
+                #[cfg(not(feature = "crypto"))]
+                let passphrase: Option<String> = None;
+
+                CommandRequest::ExportArchive {
+                    path: path.to_owned(),
+                    passphrase,
+                }
+            }
+
+            #[cfg(feature = "export")]
+            Some(Command::ImportArchive { path, fail_fast }) => {
+                let passphrase = if crate::export::is_archive_encrypted(path)? {
+                    Some(prompt.passphrase("Enter password to decrypt archive: ")?)
+                } else {
+                    None
+                };
+
+                CommandRequest::ImportArchive {
+                    path: path.to_owned(),
+                    passphrase,
+                    fail_fast: *fail_fast,
+                }
+            }
+
+            Some(Command::Dump { path }) => CommandRequest::Dump {
+                path: path.to_owned(),
+            },
+
+            Some(Command::Restore { path, force }) => {
+                if !force {
+                    let name = notebook.name().await?;
+
+                    let message = format!(
+                        "This will replace all notes of notebook `{name}` with `{}` and can't be undone.",
+                        path.display()
+                    );
+                    if !prompt.confirm_typed(&message, &name)? {
+                        self.info(format_args!("Nothing was restored"));
+                        return Ok(CommandResponse::None);
+                    }
+                }
+
+                CommandRequest::Restore {
+                    path: path.to_owned(),
+                }
+            }
+
+            Some(Command::Sync {
+                remote,
+                conflict,
+                dry_run,
+            }) => CommandRequest::Sync {
+                remote_url: remote.to_owned(),
+                policy: *conflict,
+                dry_run: *dry_run,
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::ExportCsv { path }) => CommandRequest::ExportCsv {
+                path: path.to_owned(),
+            },
+
+            #[cfg(feature = "export")]
+            Some(Command::ImportCsv { path, fail_fast }) => CommandRequest::ImportCsv {
+                path: path.to_owned(),
+                fail_fast: *fail_fast,
+            },
+
+            Some(Command::LockNote { notename }) => CommandRequest::LockNote {
+                notename: notename.to_owned(),
+            },
+
+            Some(Command::UnlockNote { notename, force }) => CommandRequest::UnlockNote {
+                notename: notename.to_owned(),
+                force: *force,
+            },
+
+            Some(Command::Audit { since }) => CommandRequest::Audit {
+                since: since.to_owned(),
+            },
+
+            Some(Command::AddUser { name, admin }) => CommandRequest::AddUser {
+                name: name.to_owned(),
+                admin: *admin,
+            },
+
+            Some(Command::DelUser { name }) => CommandRequest::DelUser {
+                name: name.to_owned(),
+            },
+
+            Some(Command::ListUsers) => CommandRequest::Users,
+
+            Some(Command::Chown { notename, owner }) => CommandRequest::Chown {
+                notename: notename.to_owned(),
+                owner: owner.to_owned(),
+            },
+
+            Some(Command::ShareNote {
+                notename,
+                user,
+                read_only,
+            }) => CommandRequest::ShareNote {
+                notename: notename.to_owned(),
+                user: user.to_owned(),
+                read_only: *read_only,
+            },
+
+            Some(Command::UnshareNote { notename, user }) => CommandRequest::UnshareNote {
+                notename: notename.to_owned(),
+                user: user.to_owned(),
+            },
+
+            Some(Command::SharedWithMe) => {
+                let Some(user) = &self.as_user else {
+                    self.info(format_args!("Select user with `--as-user <name>`"));
+                    return Ok(CommandResponse::None);
+                };
+
+                CommandRequest::SharedWith {
+                    user: user.to_owned(),
+                }
+            }
+
+            Some(Command::SplitNote {
+                notename,
+                by,
+                delimiter,
+                keep,
+            }) => CommandRequest::SplitNote {
+                notename: notename.to_owned(),
+                by: *by,
+                delimiter: delimiter.to_owned(),
+                keep: *keep,
+            },
+
+            #[cfg(feature = "tui")]
+            Some(Command::Tui) => {
+                crate::tui::run(notebook).await?;
+                return Ok(CommandResponse::None);
+            }
+
+            #[cfg(feature = "tui")]
+            Some(Command::Pick { action }) => {
+                let Some((notename, action)) = crate::pick::pick(*action, notebook).await? else {
+                    return Ok(CommandResponse::None);
+                };
+
+                let cmd = match action {
+                    PickAction::Display => Command::DisplayNote {
+                        notename,
+                        lines: None,
+                    },
+                    PickAction::Edit => Command::UpdNote {
+                        notename,
+                        content: NoteContent::default(),
+                    },
+                    PickAction::Delete => {
+                        if !prompt.confirm(&format!("Delete note `{notename}`?"))? {
+                            return Ok(CommandResponse::None);
+                        }
+                        Command::DelNote { notename }
+                    }
+                };
+
+                let picked = NoteCommand {
+                    full: self.full,
+                    preview_len: self.preview_len,
+                    script: self.script,
+                    format: self.format,
+                    no_echo: self.no_echo,
+                    end_marker: self.end_marker.clone(),
+                    as_user: self.as_user.clone(),
+                    no_color: self.no_color,
+                    quiet: self.quiet,
+                    verbose: self.verbose,
+                    #[cfg(feature = "metrics")]
+                    timing: self.timing,
+                    cmd: Some(cmd),
+                };
+                return Box::pin(picked.execute_command(pool)).await;
+            }
+
+            Some(Command::RemindDaemon { interval, command }) => {
+                if self.as_user.is_some() {
+                    self.info(format_args!(
+                        "Reminders are sent about notes of all users, run `remind-daemon` without `--as-user`"
+                    ));
+                    return Ok(CommandResponse::None);
+                }
+
+                let notifier = Notifier::new(command.clone().or(config.remind_command.clone()));
+                self.info(format_args!(
+                    "Sending reminders about deadlines of notes, press Ctrl+C to stop"
+                ));
+
+                remind::remind_daemon(&notifier, Duration::from_secs(*interval), pool, |note| {
+                    println!("{note}")
+                })
+                .await;
+                return Ok(CommandResponse::None);
+            }
+
+            #[cfg(feature = "watch")]
+            Some(Command::Watch) => {
+                use futures_util::TryStreamExt;
+
+                if self.as_user.is_some() {
+                    self.info(format_args!(
+                        "Changes of notes of all users are watched, run `watch` without `--as-user`"
+                    ));
+                    return Ok(CommandResponse::None);
+                }
+
+                let mut changes = crate::watch::watch(pool).await?;
+                self.info(format_args!(
+                    "Watching changes of notes, press Ctrl+C to stop"
+                ));
+
+                while let Some(change) = changes.try_next().await? {
+                    println!("{change}");
+                }
+                return Ok(CommandResponse::None);
+            }
+
+            #[cfg(feature = "server")]
+            Some(Command::Serve { addr }) => {
+                crate::migrate::migrate(pool).await?;
+                let options = crate::server::ServerOptions::from_config(&config);
+                crate::server::serve_with(addr, notebook.clone(), options).await?;
+                return Ok(CommandResponse::None);
+            }
+
+            #[cfg(feature = "grpc")]
+            Some(Command::ServeGrpc { addr }) => {
+                crate::migrate::migrate(pool).await?;
+                crate::grpc::serve_notebook(addr, notebook.clone()).await?;
+                return Ok(CommandResponse::None);
+            }
+
+            Some(Command::ReplayQueue) => {
+                let Some(path) = offline::queue_path(&config) else {
+                    self.info(format_args!(
+                        "Config directory is unknown, set `LNOTEBOOK_CONFIG_DIR`"
+                    ));
+                    return Ok(CommandResponse::None);
+                };
+
+                if offline::load(&path)?.is_empty() {
+                    println!("Nothing to replay");
+                } else {
+                    println!("{}", offline::replay(&path, notebook).await?);
+                }
+                return Ok(CommandResponse::None);
+            }
+
+            Some(Command::Bench { notes, runs, yes }) => {
+                if !yes
+                    && !prompt.confirm(&format!(
+                        "Add {notes} synthetic notes to the notebook for benchmark?"
+                    ))?
+                {
+                    return Ok(CommandResponse::None);
+                }
+
+                println!("{}", bench::bench(*notes, *runs, pool).await?);
+                return Ok(CommandResponse::None);
+            }
+
+            #[cfg(feature = "man")]
+            Some(Command::GenMan { dir }) => {
+                let count = gen_man(dir)?;
+
+                println!("Generated {count} man pages in `{}`", dir.display());
+                return Ok(CommandResponse::None);
+            }
+
+            None => CommandRequest::List {
+                full: self.full || config.legacy_display,
+                preview_len: self.preview_len,
+            },
+        };
+
+        let mut opened = HashSet::new();
+        let (response, elapsed) = loop {
+            let start = Instant::now();
+            let response =
+                protect::with_passphrases(passphrases.clone(), notebook.execute(request.clone()))
+                    .await;
+            let elapsed = start.elapsed();
+
+            // Passphrase of protected note is asked when command finds it, and the command
+            // is run again; note is checked before command changes anything. `protect-note`
+            // returns the same error for notes that are already protected
+            match &response {
+                Err(NotebookError::NoteProtected { notename })
+                    if request.name() != "protect-note" && opened.insert(notename.clone()) =>
+                {
+                    passphrases.extend(self.unlock(notename, &prompt, pool).await?);
+                }
+                _ => break (response, elapsed),
+            }
+        };
+        usage::record_usage(&config, request.name(), elapsed);
+
+        // Timing goes to stderr, so it doesn't mix with results
+        if self.verbosity() == Verbosity::Verbose {
+            eprintln!("Command `{}` took {:?}", request.name(), elapsed);
+        }
+
+        #[cfg(feature = "metrics")]
+        if self.timing {
+            eprintln!("{}", crate::metrics::metrics());
+        }
+
+        let response = response?;
+        print_response(
+            &request,
+            &response,
+            self.format,
+            self.verbosity() == Verbosity::Quiet,
+            format::colors_enabled(self.no_color),
+        );
+
+        if let (CommandResponse::Note(note), Some(path)) = (&response, offline::bases_path(&config))
+        {
+            let deleted = matches!(request, CommandRequest::DelNote { .. });
+            if config.offline_queue && !deleted {
+                // Base of updates queued later, read more in `offline` module
+                let hash = commands::hash_content(note.body());
+                if let Err(err) = offline::remember_base(&path, &note.note_name, &hash) {
+                    event!(
+                        Level::WARN,
+                        "Can't remember hash of `{}`: {}",
+                        note.note_name,
+                        err
+                    );
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+/// Returns `text` entered by user, or only its size if `no_echo` is `true`.
+fn echo(text: &str, no_echo: bool) -> std::borrow::Cow<'_, str> {
+    if no_echo {
+        config::hidden(text).into()
+    } else {
+        text.into()
+    }
+}
+
+/// Prints line of result into log, or just into stdout in script mode.
+fn print_line(script: bool, line: std::fmt::Arguments) {
+    if script {
+        println!("{line}");
+    } else {
+        event!(Level::INFO, "{line}");
+    }
+}
+
+/// Prints data returned by command.
+fn print_response(
+    request: &CommandRequest,
+    response: &CommandResponse,
+    format: RenderFormat,
+    script: bool,
+    colors: bool,
+) {
+    let renderer = format.renderer();
+
+    match response {
+        CommandResponse::Note(note) => {
+            if let CommandRequest::Journal { .. } = request {
+                print_line(
+                    script,
+                    format_args!("Entry is added into journal `{}`", note.note_name),
+                );
+            }
+            if let CommandRequest::AddAutoNamed { .. } = request {
+                print_line(
+                    script,
+                    format_args!("Note is added as `{}`", note.note_name),
+                );
+            }
+            if let CommandRequest::DisplayNote { .. } | CommandRequest::RandomNote = request {
+                if format == RenderFormat::Plain {
+                    print_line(
+                        script,
+                        format_args!("Requested note:\n{}", renderer.render_note(note)),
+                    );
+                } else {
+                    print_line(script, format_args!("{}", renderer.render_note(note)));
+                }
+            }
+        }
+
+        CommandResponse::Notes(notes) => match (request, format) {
+            (CommandRequest::DisplayPrefix { prefix }, RenderFormat::Plain) => {
+                if notes.is_empty() {
+                    println!("No notes start with `{prefix}`");
+                } else {
+                    let notes: Vec<_> = notes
+                        .iter()
+                        .map(|note| renderer.render_note(note))
+                        .collect();
+                    println!("{}", notes.join("\n\n"));
+                }
+            }
+            (CommandRequest::SplitNote { notename, .. }, RenderFormat::Plain) => {
+                if notes.is_empty() {
+                    println!("Nothing to split in `{notename}`");
+                } else {
+                    println!("Split `{notename}` into:");
+                    for note in notes {
+                        println!("{}", note.note_name);
+                    }
+                }
+            }
+            // Listings are printed as they are, without a log line for every note
+            _ => println!("{}", renderer.render_notes(notes)),
+        },
+
+        CommandResponse::Summaries(notes) => match (request, format) {
+            (CommandRequest::Search { .. }, RenderFormat::Plain) => {
+                for note in notes {
+                    println!("{}: {}", note.note_name, note.preview);
+                }
+                println!("Found {} notes", notes.len());
+            }
+            (_, RenderFormat::Plain) => println!("{}", format::summaries_table(notes, colors)),
+            _ => println!("{}", renderer.render_summaries(notes)),
+        },
+
+        CommandResponse::Text(text) => match request {
+            CommandRequest::Diff { .. } if text.is_empty() => println!("No differences"),
+            _ => println!("{text}"),
+        },
+
+        CommandResponse::Content(Some(content)) => print!("{content}"),
+        CommandResponse::Content(None) => {}
+
+        CommandResponse::Bytes(Some(data)) => {
+            let mut stdout = std::io::stdout();
+            if let Err(err) = stdout.write_all(data).and_then(|()| stdout.flush()) {
+                event!(Level::ERROR, "Can't print content of note: {}", err);
+            }
+        }
+        CommandResponse::Bytes(None) => {}
+
+        CommandResponse::Renamed {
+            rewritten_links, ..
+        } => {
+            if let CommandRequest::UpdNotename {
+                rewrite_links: true,
+                ..
+            } = request
+            {
+                println!("Links were rewritten in {rewritten_links} notes");
+            }
+        }
+
+        CommandResponse::RenamedMany(renamed) => {
+            for (notename, new_notename) in renamed {
+                println!("`{notename}` -> `{new_notename}`");
+            }
+            println!("Renamed {} notes", renamed.len());
+        }
+
+        CommandResponse::Replaced(replaced) => {
+            for note in replaced {
+                println!("{note}");
+            }
+
+            let lines: usize = replaced.iter().map(|note| note.changes.len()).sum();
+            if let CommandRequest::ReplaceAll { dry_run: true, .. } = request {
+                println!(
+                    "{lines} lines in {} notes would be changed (dry run)",
+                    replaced.len()
+                );
+            } else {
+                println!("Changed {lines} lines in {} notes", replaced.len());
+            }
+        }
+
+        CommandResponse::Deleted(deleted) => println!("Deleted {} notes", deleted.len()),
+
+        CommandResponse::ProfilesSearch(res) => {
+            for hit in &res.hits {
+                println!(
+                    "[{}] {}: {}",
+                    hit.profile, hit.note.note_name, hit.note.preview
+                );
+            }
+            for (profile, err) in &res.failed {
+                eprintln!("Can't search profile `{profile}`: {err}");
+            }
+            println!("Found {} notes", res.hits.len());
+        }
+
+        CommandResponse::Grep(matches) => {
+            if let CommandRequest::Grep {
+                names_only: true, ..
+            } = request
+            {
+                let mut notenames: Vec<&str> =
+                    matches.iter().map(|m| m.note_name.as_str()).collect();
+                notenames.dedup();
+
+                for notename in notenames {
+                    println!("{notename}");
+                }
+            } else {
+                for m in matches {
+                    println!("{m}");
+                }
+            }
+        }
+
+        CommandResponse::History(revisions) => {
+            for revision in revisions {
+                println!(
+                    "{:>6}  {}  {}  {} bytes",
+                    revision.id,
+                    revision.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    &revision.body_hash[..12],
+                    revision.size
+                );
+            }
+        }
+
+        CommandResponse::Stats(stats) => println!("{stats}"),
+
+        CommandResponse::NoteStats(stats) => println!("{stats}"),
+
+        CommandResponse::Preview(preview) => println!("{preview}"),
+
+        CommandResponse::Exists(exists) => {
+            if let (CommandRequest::Exists { notename }, false) = (request, script) {
+                if *exists {
+                    println!("Note `{notename}` exists");
+                } else {
+                    println!("Note `{notename}` doesn't exist");
+                }
+            }
+        }
+
+        CommandResponse::Count(count) => println!("{count}"),
+
+        CommandResponse::Links(links) => {
+            for link in links {
+                println!("{link}");
+            }
+        }
+
+        CommandResponse::Graph(graph) => {
+            if let CommandRequest::ExportGraph { format } = request {
+                println!("{}", graph.render(*format));
+            }
+        }
+
+        CommandResponse::Findings(findings) => {
+            if let CommandRequest::Lint { notename, .. } = request {
+                for finding in findings {
+                    println!("{notename}:{finding}");
+                }
+                if findings.is_empty() {
+                    println!("No problems in `{notename}`");
+                }
+            }
+        }
+
+        CommandResponse::BrokenLinks(broken) => {
+            for link in broken {
+                println!("{link}");
+            }
+            if broken.is_empty() {
+                println!("No broken links");
+            }
+        }
+
+        CommandResponse::Notenames(notenames) => {
+            for notename in notenames {
+                println!("{notename}");
+            }
+        }
+
+        CommandResponse::Attachment(attachment) => println!("Attached {attachment}"),
+
+        CommandResponse::Attachments(attachments) => {
+            for attachment in attachments {
+                println!("{attachment}");
+            }
+        }
+
+        CommandResponse::Highlight(highlight) => println!("Highlighted {highlight}"),
+
+        CommandResponse::Highlights(highlights) => {
+            for (i, highlight) in highlights.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("{highlight}");
+            }
+            if highlights.is_empty() {
+                println!("No highlights");
+            }
+        }
+
+        CommandResponse::Saved(path) => println!("Saved into `{}`", path.display()),
+
+        CommandResponse::Compacted {
+            notes,
+            history,
+            history_stats,
+        } => {
+            println!(
+                "Done: {} notes rewritten, {} renamed, {} can't be renamed",
+                notes.rewritten, notes.renamed, notes.skipped
+            );
+            println!(
+                "History: {} contents stored as deltas, {} compressed, {} removed",
+                history.deltas, history.compressed, history.removed
+            );
+            println!("History: {history_stats}");
+        }
+
+        CommandResponse::Duplicates(groups) => {
+            let merged = matches!(request, CommandRequest::Dedup { merge: true });
+
+            if groups.is_empty() {
+                println!("No notes with identical content");
+            }
+            for group in groups {
+                if merged {
+                    println!("Merged into {group}");
+                } else {
+                    println!("{group}");
+                }
+            }
+        }
+
+        CommandResponse::Due(notes) => {
+            if notes.is_empty() {
+                println!("No notes with deadline");
+            }
+            for note in notes {
+                println!("{note}");
+            }
+        }
+
+        CommandResponse::Template(template) => {
+            println!("{}:\n{}", template.template_name, template.template)
+        }
+
+        CommandResponse::Templates(templates) => {
+            for template in templates {
+                println!("{}:\n{}", template.template_name, template.template);
+            }
+        }
+
+        CommandResponse::Batch(report) => println!("{report}"),
+
+        CommandResponse::Restored(notes) => println!("Restored {notes} notes"),
+
+        CommandResponse::Synced(report) => {
+            for (action, notenames) in [
+                ("Pulled", &report.pulled),
+                ("Pushed", &report.pushed),
+                ("Deleted locally", &report.deleted_local),
+                ("Deleted remotely", &report.deleted_remote),
+                ("Conflict", &report.conflicts),
+                ("Locked", &report.locked),
+            ] {
+                for notename in notenames {
+                    println!("{action}: {notename}");
+                }
+            }
+            println!("{report}");
+        }
+
+        CommandResponse::Audit(entries) => {
+            if entries.is_empty() {
+                println!("No recorded commands");
+            }
+            for entry in entries {
+                println!("{entry}");
+            }
+        }
+
+        CommandResponse::User(user) => {
+            if let CommandRequest::DelUser { .. } = request {
+                print_line(script, format_args!("User `{}` is deleted", user.name));
+            } else {
+                print_line(script, format_args!("User `{}` is added", user.name));
+            }
+        }
+
+        CommandResponse::Users(users) => {
+            if users.is_empty() {
+                println!("No users");
+            }
+            for user in users {
+                println!("{user}");
+            }
+        }
+
+        CommandResponse::SharedNotes(notes) => {
+            if notes.is_empty() {
+                println!("No notes are shared with you");
+            }
+            for note in notes {
+                println!("{note}");
+            }
+        }
+
+        CommandResponse::None | CommandResponse::Exported(_) => {}
+    }
+}
+
+/// Writes man pages of the CLI into `dir`: `notebook.1` for the notebook itself and
+/// `notebook-<command>.1` for every command.
+/// ### Returns
+/// * Ok
+///     * Number of written man pages
+/// * Errors
+///     * [`NotebookError::Io`] error if man pages can't be written
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::execute_commands::gen_man;
+/// # use lnotebook::NotebookError;
+/// fn gen_man_example() -> Result<(), NotebookError> {
+///     gen_man(std::path::Path::new("target/man"))?;
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "man")]
+pub fn gen_man(dir: &std::path::Path) -> Result<usize, NotebookError> {
+    use clap::CommandFactory;
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut cmd = NoteCommand::command();
+    cmd.build();
+
+    let mut pages = vec![cmd.clone()];
+    pages.extend(
+        cmd.get_subcommands()
+            .filter(|sub| sub.get_name() != "help")
+            .map(|sub| {
+                sub.clone()
+                    .name(format!("{}-{}", cmd.get_name(), sub.get_name()))
+            }),
+    );
+
+    for page in &pages {
+        let mut buf = Vec::new();
+        clap_mangen::Man::new(page.clone()).render(&mut buf)?;
+
+        std::fs::write(dir.join(format!("{}.1", page.get_name())), buf)?;
+    }
 
-    source.to_owned()
+    Ok(pages.len())
 }
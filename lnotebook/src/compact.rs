@@ -0,0 +1,250 @@
+//! Rewriting of all notes with the current policies.
+//!
+//! Policies are rules about how notes are stored. New policies usually apply only to notes
+//! written after they were added, so [`compact`] rewrites every note in the notebook to apply
+//! [`CompactPolicies`] retroactively:
+//! * compression - content of notes larger than the threshold is compressed, and content
+//!   of smaller notes is decompressed (read more in [`compression` module][crate::compression])
+//! * name normalization - notes are renamed to their normalized names, and links to them
+//!   are rewritten (read more in [`notename` module][crate::notename])
+//!
+//! Compressing and decompressing don't change content of notes, so they don't add revisions.
+//! Archives are encrypted when they're exported (read more in [`export` module][crate::export])
+//! and notes are never stored encrypted, so there is no encryption for [`compact`] to apply.
+//!
+//! History of notes is compacted separately by [`compact_history`][crate::history::compact_history].
+//!
+//! Notes are rewritten in batches, every batch in its own transaction, so a large notebook
+//! isn't locked for the whole time and progress isn't lost if compaction is interrupted.
+
+use crate::commands::{self, OnConflict};
+use crate::compression::{self, StoredNote};
+use crate::errors::NotebookError;
+use crate::notename::NoteName;
+use crate::sql::{query_as, query_scalar};
+
+use sqlx::{self, PgPool};
+use tracing::{event, Level};
+
+/// Number of notes rewritten in one transaction by default.
+pub const DEFAULT_BATCH_SIZE: i64 = 100;
+
+/// This is a `struct` that containing policies applied by [`compact`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactPolicies {
+    /// Size in bytes from which content of notes is compressed, `None` to decompress all notes
+    pub compress_threshold: Option<usize>,
+    /// Rename notes to names made by [`NoteName::normalized`]
+    pub normalize_names: bool,
+}
+
+/// This is a `struct` that containing progress of [`compact`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactProgress {
+    /// Number of notes that were checked
+    pub scanned: i64,
+    /// Number of notes whose content was compressed or decompressed
+    pub rewritten: i64,
+    /// Number of notes renamed to normalized names
+    pub renamed: i64,
+    /// Number of notes that can't be renamed, because the normalized name is taken
+    /// or note is locked or owned by other user
+    pub skipped: i64,
+    /// Number of notes in notebook when compaction started
+    pub total: i64,
+}
+
+/// Rewrites all notes applying `policies`, read about them [here][crate::compact].
+///
+/// Notes are rewritten in transactions of `batch_size` notes; `progress` is called
+/// after every batch.
+/// ### Returns
+/// * Ok
+///     * [`CompactProgress`] after all notes were checked
+/// * Errors
+///     * [`NotebookError::Compression`] error if note is compressed, but the notebook is built
+///       without `compression` feature
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::compact::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn compact_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let policies = CompactPolicies {
+///         compress_threshold: Some(4096),
+///         normalize_names: true,
+///     };
+///     let res = compact(policies, DEFAULT_BATCH_SIZE, |p| println!("{}/{}", p.scanned, p.total), pool).await?;
+///
+///     println!("Rewritten {} notes, renamed {}", res.rewritten, res.renamed);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn compact(
+    policies: CompactPolicies,
+    batch_size: i64,
+    mut progress: impl FnMut(CompactProgress),
+    pool: &PgPool,
+) -> Result<CompactProgress, NotebookError> {
+    let batch_size = batch_size.max(1);
+    let mut res = CompactProgress {
+        total: query_scalar!(i64, r#"SELECT COUNT(*) AS "count!" FROM notebook"#)
+            .fetch_one(pool)
+            .await?,
+        ..Default::default()
+    };
+    let mut last_id = 0;
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        let rows = query_as!(
+            StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+            "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE id > $1
+ORDER BY id
+LIMIT $2
+FOR UPDATE
+            ",
+            last_id,
+            batch_size
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let mut notenames = Vec::with_capacity(rows.len());
+        for row in rows {
+            res.scanned += 1;
+
+            let compressed = row.packed.is_some();
+            let row = row.into_note()?;
+            notenames.push(row.note_name);
+            let Some(note) = row.note.as_deref() else {
+                continue;
+            };
+
+            let threshold = policies.compress_threshold;
+            if compressed && threshold.is_some_and(|threshold| note.len() >= threshold) {
+                continue;
+            }
+            if compressed {
+                compression::unpack_with(row.id, note, &mut tx).await?;
+            }
+            let packed = compression::pack_with(row.id, Some(note), threshold, &mut tx).await?;
+            if packed != compressed {
+                res.rewritten += 1;
+            }
+        }
+
+        // Notes are renamed after contents of the whole batch are rewritten, because renaming
+        // rewrites links in other notes
+        for notename in notenames.iter().filter(|_| policies.normalize_names) {
+            let Ok(normalized) = NoteName::normalized(notename) else {
+                continue;
+            };
+            if normalized.as_str() == notename {
+                continue;
+            }
+
+            match commands::rename_in(notename, &normalized, OnConflict::Error, &mut tx).await {
+                Ok(_) => {
+                    commands::rewrite_links_in(notename, &normalized, &mut tx).await?;
+                    res.renamed += 1;
+                }
+                Err(
+                    err @ (NotebookError::AlreadyTaken { .. }
+                    | NotebookError::Locked { .. }
+                    | NotebookError::NotOwner { .. }
+                    | NotebookError::ReadOnly { .. }),
+                ) => {
+                    event!(
+                        Level::WARN,
+                        "Can't rename `{}` to `{}`: {}",
+                        notename,
+                        normalized,
+                        err
+                    );
+                    res.skipped += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        tx.commit().await?;
+
+        progress(res);
+    }
+
+    event!(
+        Level::INFO,
+        "Compacted notebook: {} notes checked, {} rewritten, {} renamed, {} skipped",
+        res.scanned,
+        res.rewritten,
+        res.renamed,
+        res.skipped
+    );
+
+    Ok(res)
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+    use crate::history;
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn policies_are_applied(pool: PgPool) {
+        let large = "line\r\n".repeat(300);
+        commands::add("Old Log", &large, &pool).await.unwrap();
+        commands::add("index", "See [[Old Log]]", &pool)
+            .await
+            .unwrap();
+        commands::add("old_note", "taken", &pool).await.unwrap();
+        commands::add("Old Note", "kept", &pool).await.unwrap();
+
+        let policies = CompactPolicies {
+            compress_threshold: Some(1024),
+            normalize_names: true,
+        };
+        let res = compact(policies, 2, |_| {}, &pool).await.unwrap();
+        assert_eq!(
+            (4, 1, 1, 1),
+            (res.scanned, res.rewritten, res.renamed, res.skipped)
+        );
+
+        // Content is kept as it was, line breaks too
+        let note = commands::select_one("old_log", &pool).await.unwrap();
+        assert_eq!(large, note.body());
+        assert_eq!(1, history::history("old_log", &pool).await.unwrap().len());
+        let index = commands::select_one("index", &pool).await.unwrap();
+        assert_eq!("See [[old_log]]", index.body());
+        assert_eq!(
+            "kept",
+            commands::select_one("Old Note", &pool)
+                .await
+                .unwrap()
+                .body()
+        );
+
+        let res = compact(CompactPolicies::default(), 2, |_| {}, &pool)
+            .await
+            .unwrap();
+        assert_eq!((1, 0), (res.rewritten, res.renamed));
+        let compressed: bool =
+            sqlx::query_scalar("SELECT compressed FROM notebook WHERE note_name = 'old_log'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(!compressed);
+    }
+}
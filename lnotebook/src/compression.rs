@@ -0,0 +1,364 @@
+//! Compression of large notes.
+//!
+//! Content of notes larger than [`compress_threshold`][crate::config::compress_threshold]
+//! is compressed with zstd when it's written (only with `compression` feature), so notes like
+//! large pasted logs take much less space. Compressed content is kept in the same row with
+//! `compressed` flag and decompressed when note is read, so functions that return notes
+//! (like [`select_one`][crate::commands::select_one]) return their content as usual.
+//! Contents of revisions are compressed separately by
+//! [`compact_history`][crate::history::compact_history].
+//!
+//! The database can't read compressed content, so compressed note is decompressed in the same
+//! transaction before the database changes it (like [`append`][crate::commands::append] does)
+//! and compressed again after that, and commands that look into content of all notes (like
+//! [`search`][crate::search::search] or [`stats`][crate::stats::stats]) decompress compressed
+//! notes themselves. Compressing and decompressing don't add revisions and don't change time
+//! of the last change of note.
+//!
+//! Notebook built without `compression` feature doesn't compress notes and returns
+//! [`NotebookError::Compression`] when it reads note compressed by other build.
+
+use crate::commands::Note;
+use crate::config;
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as};
+
+use sqlx::{self, PgConnection};
+
+/// Size of content in bytes from which content is compressed if other threshold is not set.
+pub const DEFAULT_COMPRESS_THRESHOLD: usize = 4096;
+
+/// Level of zstd compression, the default level of zstd.
+#[cfg(feature = "compression")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Returns `text` compressed with zstd.
+#[cfg(feature = "compression")]
+pub(crate) fn compress(text: &str) -> Result<Vec<u8>, NotebookError> {
+    Ok(zstd::encode_all(text.as_bytes(), COMPRESSION_LEVEL)?)
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compress(_text: &str) -> Result<Vec<u8>, NotebookError> {
+    Err(NotebookError::Compression {
+        reason: "LNotebook is built without `compression` feature".to_owned(),
+    })
+}
+
+/// Returns text compressed by [`compress`], `what` names it in errors.
+#[cfg(feature = "compression")]
+pub(crate) fn decompress(what: &str, data: &[u8]) -> Result<String, NotebookError> {
+    zstd::decode_all(data)
+        .ok()
+        .and_then(|data| String::from_utf8(data).ok())
+        .ok_or_else(|| NotebookError::Compression {
+            reason: format!("{what} is damaged"),
+        })
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn decompress(what: &str, _data: &[u8]) -> Result<String, NotebookError> {
+    Err(NotebookError::Compression {
+        reason: format!(
+            "{what} is compressed, but LNotebook is built without `compression` feature"
+        ),
+    })
+}
+
+/// Returns content of note `notename`: `note`, or `packed` decompressed if note is compressed.
+///
+/// Queries select `packed` as `CASE WHEN compressed THEN note_bytes END`.
+pub(crate) fn content(
+    notename: &str,
+    note: Option<String>,
+    packed: Option<Vec<u8>>,
+) -> Result<Option<String>, NotebookError> {
+    match packed {
+        Some(data) => decompress(&format!("Content of note `{notename}`"), &data).map(Some),
+        None => Ok(note),
+    }
+}
+
+/// Row of note as it's stored, compressed content is in `packed` (read about it in [`content`]).
+pub(crate) struct StoredNote {
+    pub(crate) id: i32,
+    pub(crate) note_name: String,
+    pub(crate) note: Option<String>,
+    pub(crate) packed: Option<Vec<u8>>,
+}
+
+impl StoredNote {
+    /// Returns note with decompressed content.
+    pub(crate) fn into_note(self) -> Result<Note, NotebookError> {
+        Ok(Note {
+            note: content(&self.note_name, self.note, self.packed)?,
+            id: self.id,
+            note_name: self.note_name,
+        })
+    }
+}
+
+/// Stores `note`, decompressed content of compressed note `id`, as text.
+pub(crate) async fn unpack_with(
+    id: i32,
+    note: &str,
+    conn: &mut PgConnection,
+) -> Result<(), NotebookError> {
+    query!(
+        "
+UPDATE notebook
+SET note = $1, note_bytes = NULL, compressed = false
+WHERE id = $2 AND compressed
+        ",
+        note,
+        id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Decompresses note `notename` in the database if it's compressed, so the database can change
+/// its content.
+///
+/// It's called in transaction that changes note, before the change, and [`pack`] is called
+/// after it.
+pub(crate) async fn unpack(notename: &str, conn: &mut PgConnection) -> Result<(), NotebookError> {
+    let packed = query_as!(
+        { id: i32, note_bytes: Option<Vec<u8>> },
+        "
+SELECT id, note_bytes
+FROM notebook
+WHERE note_name = $1 AND compressed
+FOR UPDATE
+        ",
+        notename
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(row) = packed {
+        let note = content(notename, None, row.note_bytes)?.unwrap_or_default();
+        unpack_with(row.id, &note, conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Decompresses in the database all compressed notes whose content matches `filter`,
+/// like [`unpack`] does, and returns their IDs to [`pack_many`] them later.
+pub(crate) async fn unpack_matching(
+    filter: impl Fn(&str) -> bool,
+    conn: &mut PgConnection,
+) -> Result<Vec<i32>, NotebookError> {
+    let rows = query_as!(
+        { id: i32, note_name: String, note_bytes: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note_bytes
+FROM notebook
+WHERE compressed
+ORDER BY id
+FOR UPDATE
+        "
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut unpacked = Vec::new();
+    for row in rows {
+        let note = content(&row.note_name, None, row.note_bytes)?.unwrap_or_default();
+        if filter(&note) {
+            unpack_with(row.id, &note, &mut *conn).await?;
+            unpacked.push(row.id);
+        }
+    }
+
+    Ok(unpacked)
+}
+
+/// Compresses `note`, content of note `id` that was just written, if it's larger than
+/// [`compress_threshold`][config::compress_threshold].
+///
+/// Content that zstd can't make smaller (like encrypted text) is kept as it is.
+pub(crate) async fn pack(
+    id: i32,
+    note: Option<&str>,
+    conn: &mut PgConnection,
+) -> Result<(), NotebookError> {
+    pack_with(id, note, config::compress_threshold(), conn).await?;
+
+    Ok(())
+}
+
+/// Compresses `note`, content of note `id`, like [`pack`] does, but with `threshold`.
+/// Returns `true` if note was compressed.
+pub(crate) async fn pack_with(
+    id: i32,
+    note: Option<&str>,
+    threshold: Option<usize>,
+    conn: &mut PgConnection,
+) -> Result<bool, NotebookError> {
+    let threshold = threshold.filter(|_| cfg!(feature = "compression"));
+    let (Some(note), Some(threshold)) = (note, threshold) else {
+        return Ok(false);
+    };
+    if note.len() < threshold {
+        return Ok(false);
+    }
+
+    let data = compress(note)?;
+    if data.len() >= note.len() {
+        return Ok(false);
+    }
+
+    let packed = query!(
+        "
+UPDATE notebook
+SET note = NULL, note_bytes = $1, compressed = true
+WHERE id = $2 AND note IS NOT NULL
+        ",
+        data,
+        id
+    )
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    Ok(packed > 0)
+}
+
+/// Compresses content of notes `ids` like [`pack`] does.
+pub(crate) async fn pack_many(ids: &[i32], conn: &mut PgConnection) -> Result<(), NotebookError> {
+    if ids.is_empty() || config::compress_threshold().is_none() {
+        return Ok(());
+    }
+
+    let rows = query_as!(
+        { id: i32, note: Option<String> },
+        "
+SELECT id, note
+FROM notebook
+WHERE id = ANY($1)
+ORDER BY id
+        ",
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for row in rows {
+        pack(row.id, row.note.as_deref(), &mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+    use crate::commands::{self, hash_content};
+    use crate::{history, links, search};
+    use sqlx::PgPool;
+
+    /// Returns note larger than [`DEFAULT_COMPRESS_THRESHOLD`] that links to `link`.
+    fn large_note(link: &str) -> String {
+        let mut note = format!("See [[{link}]]\n");
+        for i in 0..1000 {
+            note.push_str(&format!("line {i}\n"));
+        }
+        note
+    }
+
+    async fn is_compressed(notename: &str, pool: &PgPool) -> bool {
+        sqlx::query_scalar("SELECT compressed AND note IS NULL FROM notebook WHERE note_name = $1")
+            .bind(notename)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn large_note_is_compressed(pool: PgPool) {
+        let note = large_note("shopping");
+        commands::add("log", &note, &pool).await.unwrap();
+        commands::add("shopping", "milk", &pool).await.unwrap();
+
+        assert!(is_compressed("log", &pool).await);
+        assert!(!is_compressed("shopping", &pool).await);
+        assert_eq!(
+            note,
+            commands::select_one("log", &pool).await.unwrap().body()
+        );
+        assert_eq!(
+            "line 999",
+            commands::select_range("log", 1001, 1001, &pool)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            hash_content(&note),
+            commands::content_hash("log", &pool).await.unwrap()
+        );
+
+        let found = search::search("LINE 500", &pool).await.unwrap();
+        assert_eq!(
+            vec!["log"],
+            found.iter().map(|n| &n.note_name).collect::<Vec<_>>()
+        );
+        assert!(found[0].preview.starts_with("See [[shopping]]"));
+        assert_eq!(
+            1,
+            search::grep("^line 500$", false, &pool)
+                .await
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            vec!["log"],
+            links::backlinks("shopping", &pool).await.unwrap()
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn compressed_note_is_changed(pool: PgPool) {
+        let note = large_note("shopping");
+        commands::add("log", &note, &pool).await.unwrap();
+        commands::add("shopping", "milk", &pool).await.unwrap();
+
+        commands::append("log", "line 1000", &pool).await.unwrap();
+        let note = format!("{note}line 1000");
+        assert!(is_compressed("log", &pool).await);
+        assert_eq!(
+            note,
+            commands::select_one("log", &pool).await.unwrap().body()
+        );
+
+        let hash = commands::content_hash("log", &pool).await.unwrap();
+        commands::upd_if_unchanged("log", &hash, &format!("{note}\n"), &pool)
+            .await
+            .unwrap();
+        assert!(commands::upd_if_unchanged("log", &hash, "other", &pool)
+            .await
+            .is_err());
+
+        commands::upd_notename_rewrite_links("shopping", "groceries", &pool)
+            .await
+            .unwrap();
+        let note = commands::select_one("log", &pool).await.unwrap();
+        assert!(note.body().starts_with("See [[groceries]]\n"));
+        assert!(is_compressed("log", &pool).await);
+
+        // Compressing and decompressing don't add revisions
+        assert_eq!(4, history::history("log", &pool).await.unwrap().len());
+
+        commands::upd("log", "short", &pool).await.unwrap();
+        assert!(!is_compressed("log", &pool).await);
+        assert_eq!(
+            "short",
+            commands::select_one("log", &pool).await.unwrap().body()
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Encryption of data with passphrase.
+//!
+//! Data is encrypted with AES-256-GCM using key derived from passphrase with Argon2id.
+//! Encrypted data looks like:
+//! ```text
+//! | "LNBENC1\0" (8 bytes) | salt (16 bytes) | nonce (12 bytes) | ciphertext |
+//! ```
+//!
+//! Passphrases of protected notes are stored as Argon2id hashes made by [`hash_passphrase`]
+//! (read more in [`protect` module][crate::protect]).
+
+use crate::errors::NotebookError;
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+/// Bytes every encrypted data starts with.
+pub const MAGIC: &[u8; 8] = b"LNBENC1\0";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, NotebookError> {
+    let mut key = Key::<Aes256Gcm>::default();
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| NotebookError::Crypto {
+            reason: err.to_string(),
+        })?;
+
+    Ok(key)
+}
+
+/// Returns `true` if `data` was encrypted by [`encrypt`].
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts `data` with `passphrase`.
+/// ### Returns
+/// * Ok
+///     * Encrypted data
+/// * Errors
+///     * [`NotebookError::Crypto`] error if data can't be encrypted
+/// ### Example
+/// ```
+/// # use lnotebook::crypto::{decrypt, encrypt};
+/// let encrypted = encrypt(b"login: krutoy_4el", "secret").unwrap();
+///
+/// assert_eq!(b"login: krutoy_4el".to_vec(), decrypt(&encrypted, "secret").unwrap());
+/// assert!(decrypt(&encrypted, "wrong").is_err());
+/// ```
+pub fn encrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt)?;
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|err| NotebookError::Crypto {
+            reason: err.to_string(),
+        })?;
+
+    let mut encrypted = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    encrypted.extend_from_slice(MAGIC);
+    encrypted.extend_from_slice(&salt);
+    encrypted.extend_from_slice(&nonce);
+    encrypted.extend_from_slice(&ciphertext);
+
+    Ok(encrypted)
+}
+
+/// Decrypts `data` that was encrypted by [`encrypt`].
+/// ### Returns
+/// * Ok
+///     * Decrypted data
+/// * Errors
+///     * [`NotebookError::WrongPassphrase`] error if passphrase is wrong or data is damaged
+///     * [`NotebookError::Crypto`] error if data wasn't encrypted by [`encrypt`]
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    if !is_encrypted(data) || data.len() < MAGIC.len() + SALT_LEN + NONCE_LEN {
+        return Err(NotebookError::Crypto {
+            reason: "data is not encrypted by LNotebook".to_owned(),
+        });
+    }
+
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = derive_key(passphrase, salt)?;
+
+    Aes256Gcm::new(&key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| NotebookError::WrongPassphrase)
+}
+
+/// Hashes `passphrase` with Argon2id and random salt.
+/// ### Returns
+/// * Ok
+///     * Hash in PHC string format, like `$argon2id$v=19$...`
+/// * Errors
+///     * [`NotebookError::Crypto`] error if passphrase can't be hashed
+/// ### Example
+/// ```
+/// # use lnotebook::crypto::{hash_passphrase, verify_passphrase};
+/// let hash = hash_passphrase("secret").unwrap();
+///
+/// assert!(verify_passphrase("secret", &hash).unwrap());
+/// assert!(!verify_passphrase("wrong", &hash).unwrap());
+/// ```
+pub fn hash_passphrase(passphrase: &str) -> Result<String, NotebookError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| NotebookError::Crypto {
+            reason: err.to_string(),
+        })
+}
+
+/// Checks `passphrase` against `hash` made by [`hash_passphrase`].
+/// ### Returns
+/// * Ok
+///     * `true` if passphrase is right
+/// * Errors
+///     * [`NotebookError::Crypto`] error if `hash` is damaged
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> Result<bool, NotebookError> {
+    let hash = PasswordHash::new(hash).map_err(|err| NotebookError::Crypto {
+        reason: err.to_string(),
+    })?;
+
+    Ok(Argon2::default()
+        .verify_password(passphrase.as_bytes(), &hash)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypts_what_was_encrypted() {
+        let data = "login: krutoy_4el\nпароль: ✓".as_bytes();
+        let encrypted = encrypt(data, "secret").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(data, decrypt(&encrypted, "secret").unwrap());
+    }
+
+    #[test]
+    fn same_data_is_encrypted_differently() {
+        let first = encrypt(b"note", "secret").unwrap();
+        let second = encrypt(b"note", "secret").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(b"note".to_vec(), decrypt(&second, "secret").unwrap());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_error() {
+        let encrypted = encrypt(b"note", "secret").unwrap();
+
+        assert!(matches!(
+            decrypt(&encrypted, "Secret"),
+            Err(NotebookError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            decrypt(&encrypted, ""),
+            Err(NotebookError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn damaged_data_is_error() {
+        let mut encrypted = encrypt(b"note", "secret").unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+
+        assert!(matches!(
+            decrypt(&encrypted, "secret"),
+            Err(NotebookError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn passphrase_is_verified_by_hash() {
+        let hash = hash_passphrase("secret").unwrap();
+
+        assert_ne!(hash, hash_passphrase("secret").unwrap());
+        assert!(verify_passphrase("secret", &hash).unwrap());
+        assert!(!verify_passphrase("Secret", &hash).unwrap());
+        assert!(!verify_passphrase("", &hash).unwrap());
+        assert!(matches!(
+            verify_passphrase("secret", "not a hash"),
+            Err(NotebookError::Crypto { .. })
+        ));
+    }
+
+    #[test]
+    fn not_encrypted_data_is_error() {
+        assert!(!is_encrypted(b"note"));
+        assert!(matches!(
+            decrypt(b"note", "secret"),
+            Err(NotebookError::Crypto { .. })
+        ));
+        assert!(matches!(
+            decrypt(MAGIC, "secret"),
+            Err(NotebookError::Crypto { .. })
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//! Finding and merging notes with identical content.
+//!
+//! [`dedup`] groups notes by SHA-256 hash of their content, computed by the database, so notes
+//! aren't loaded to compare them (except compressed notes, read about them in
+//! [`compression` module][crate::compression]). Empty notes are never treated as duplicates.
+//!
+//! When duplicates are merged, the oldest note of every group (the one added first) is kept
+//! with its name, and the others are deleted:
+//! * files attached to deleted notes are moved to the kept note, unless it already has
+//!   a file with the same name
+//! * links `[[deleted note]]` in all notes are rewritten to `[[kept note]]`
+//!   (read about links in [`links` module][crate::links])
+//!
+//! All notes are merged in the same transaction. Note that was changed since it was found
+//! is not deleted.
+//!
+//! Notes that the acting user can't read (read about users in [`users` module][crate::users])
+//! are not compared, and notes that it can't change are not merged.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::add;
+//! # use lnotebook::dedup::dedup;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn dedup_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("shopping", "milk", pool).await?;
+//!     add("shopping (imported)", "milk", pool).await?;
+//!
+//!     let merged = dedup(true, pool).await?;
+//!
+//!     assert_eq!("shopping", merged[0].kept);
+//!     assert_eq!(vec!["shopping (imported)".to_owned()], merged[0].duplicates);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{self, hash_content};
+use crate::compression;
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as, query_scalar};
+use crate::users::{self, Access};
+
+use sqlx::{self, PgPool};
+use std::collections::HashMap;
+use std::fmt;
+use tracing::{event, Level};
+
+/// This is a `struct` that containing group of notes with identical content.
+///
+/// It implements [`fmt::Display`], which renders it as `kept: duplicate, duplicate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// SHA-256 hash of content of notes
+    pub hash: String,
+    /// Name of the oldest note, which is kept when duplicates are merged
+    pub kept: String,
+    /// Names of other notes from the oldest to the newest
+    pub duplicates: Vec<String>,
+}
+
+impl fmt::Display for DuplicateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kept, self.duplicates.join(", "))
+    }
+}
+
+/// Returns hash of content of note `notename` selected as `hash`, or of `packed` content
+/// if note is compressed, which the database can't hash.
+fn content_hash(
+    notename: &str,
+    hash: Option<String>,
+    packed: Option<Vec<u8>>,
+) -> Result<String, NotebookError> {
+    Ok(match compression::content(notename, None, packed)? {
+        Some(note) => hash_content(&note),
+        None => hash.unwrap_or_default(),
+    })
+}
+
+/// Notes with the same hash of content.
+struct DuplicateRow {
+    hash: String,
+    ids: Vec<i32>,
+    names: Vec<String>,
+}
+
+/// Finds groups of notes with identical content and, if `merge` is `true`, merges every group
+/// into its oldest note.
+///
+/// Read about merging [here][crate::dedup]. Protected notes (read about them
+/// in [`protect` module][crate::protect]) are skipped.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`DuplicateGroup`] ordered by the oldest notes. If `merge` is `true`,
+///       it contains only notes that were deleted
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn dedup(merge: bool, pool: &PgPool) -> Result<Vec<DuplicateGroup>, NotebookError> {
+    let mut tx = pool.begin().await?;
+
+    let notes = query_as!(
+        { id: i32, note_name: String, hash: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT
+    id,
+    note_name,
+    encode(sha256(convert_to(note, 'UTF8')), 'hex') AS hash,
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE (note <> '' OR compressed) AND passphrase_hash IS NULL
+ORDER BY id
+        "
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Notes are grouped in order of their IDs, so groups are ordered by the oldest notes
+    let mut rows: Vec<DuplicateRow> = Vec::new();
+    let mut by_hash = HashMap::new();
+    for note in notes {
+        let hash = content_hash(&note.note_name, note.hash, note.packed)?;
+        let i = *by_hash.entry(hash.clone()).or_insert_with(|| {
+            rows.push(DuplicateRow {
+                hash,
+                ids: Vec::new(),
+                names: Vec::new(),
+            });
+            rows.len() - 1
+        });
+        rows[i].ids.push(note.id);
+        rows[i].names.push(note.note_name);
+    }
+    rows.retain(|row| row.ids.len() > 1);
+
+    let access = if merge { Access::Write } else { Access::Read };
+
+    let mut groups = Vec::new();
+    for row in rows {
+        let mut ids = Vec::new();
+        let mut names = Vec::new();
+        for (id, name) in row.ids.into_iter().zip(row.names) {
+            if users::has_access(&name, access, &mut *tx).await? {
+                ids.push(id);
+                names.push(name);
+            }
+        }
+        if ids.len() < 2 {
+            continue;
+        }
+
+        let mut names = names.into_iter();
+        let kept = names.next().unwrap_or_default();
+
+        if !merge {
+            groups.push(DuplicateGroup {
+                hash: row.hash,
+                kept,
+                duplicates: names.collect(),
+            });
+            continue;
+        }
+
+        let kept_id = ids[0];
+        let locked = query_as!(
+            { id: i32, note_name: String, hash: Option<String>, packed: Option<Vec<u8>> },
+            "
+SELECT
+    id,
+    note_name,
+    encode(sha256(convert_to(note, 'UTF8')), 'hex') AS hash,
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE id = ANY($1)
+ORDER BY id
+FOR UPDATE
+            ",
+            &ids[1..]
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut duplicate_ids = Vec::new();
+        for note in locked {
+            if content_hash(&note.note_name, note.hash, note.packed)? == row.hash {
+                duplicate_ids.push(note.id);
+            }
+        }
+
+        query!(
+            "
+UPDATE attachments
+SET note_id = $1
+WHERE note_id = ANY($2)
+    AND NOT EXISTS (
+        SELECT 1 FROM attachments AS other
+        WHERE other.note_id = $1 AND other.file_name = attachments.file_name
+    )
+    AND id = (
+        SELECT MIN(other.id) FROM attachments AS other
+        WHERE other.note_id = ANY($2) AND other.file_name = attachments.file_name
+    )
+            ",
+            kept_id,
+            &duplicate_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let duplicates = query_scalar!(
+            String,
+            "
+DELETE FROM notebook
+WHERE id = ANY($1)
+RETURNING note_name
+            ",
+            &duplicate_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !duplicates.is_empty() {
+            groups.push(DuplicateGroup {
+                hash: row.hash,
+                kept,
+                duplicates,
+            });
+        }
+    }
+
+    // Links are rewritten after all duplicates are deleted, so rewriting doesn't change
+    // content of notes that are not checked yet
+    for group in groups.iter().filter(|_| merge) {
+        for duplicate in &group.duplicates {
+            commands::rewrite_links_in(duplicate, &group.kept, &mut tx).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    if merge {
+        for group in &groups {
+            event!(
+                Level::INFO,
+                "Merge {} notes into `{}`",
+                group.duplicates.len(),
+                group.kept
+            );
+        }
+    }
+
+    Ok(groups)
+}
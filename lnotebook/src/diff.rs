@@ -0,0 +1,196 @@
+//! Differences between notes and revisions of notes.
+//!
+//! [`diff_notes`] compares content of two notes, and [`diff_revision`] compares content of note in
+//! revision (read about revisions in [`history` module][crate::history]) with its current content.
+//! Differences are returned as unified diff of lines, like `diff -u` and `git diff` print them,
+//! so they can be read during merges of notes or applied with `patch`:
+//! ```text
+//! --- shopping@12
+//! +++ shopping
+//! @@ -1,2 +1,3 @@
+//!  milk
+//! -bread
+//! +rye bread
+//! +butter
+//! ```
+//! Line break at the end of content is not compared, so diff is empty if contents are the same
+//! or differ only by it.
+//!
+//! CLI displays diffs with `diff <notename> <other notename>` and `diff <notename> --version <ID>`.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::diff::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn diff_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("shopping", "milk\nbread", pool).await?;
+//!     add("shopping_old", "milk", pool).await?;
+//!
+//!     let diff = diff_notes("shopping_old", "shopping", DEFAULT_CONTEXT, pool).await?;
+//!
+//!     assert!(diff.contains("+bread"));
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands;
+use crate::errors::NotebookError;
+use crate::history;
+use crate::protect;
+use crate::users::{self, Access};
+
+use similar::TextDiff;
+use sqlx::PgPool;
+use std::borrow::Cow;
+
+/// Number of unchanged lines displayed around changed lines if other number is not given.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// What note is compared with, read more [here][crate::diff].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffWith {
+    /// Other note with the name
+    Note(String),
+    /// The same note in revision with the ID
+    Revision(i32),
+}
+
+/// Returns unified diff of `old` and `new` with `context` unchanged lines around changes.
+///
+/// `old_name` and `new_name` are written in the header of diff. Diff is empty if `old` and `new`
+/// are the same (read more [here][crate::diff]), and it doesn't end with line break.
+/// ### Example
+/// ```
+/// # use lnotebook::diff::unified_diff;
+/// let diff = unified_diff("milk\nbread", "milk\nbutter", "old", "new", 3);
+///
+/// assert_eq!("--- old\n+++ new\n@@ -1,2 +1,2 @@\n milk\n-bread\n+butter", diff);
+/// assert!(unified_diff("milk", "milk\n", "old", "new", 3).is_empty());
+/// ```
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    // Without line break at the end, the last line would differ from the same line followed by
+    // other lines
+    let old = with_line_break(old);
+    let new = with_line_break(new);
+
+    let diff = TextDiff::from_lines(old.as_ref(), new.as_ref())
+        .unified_diff()
+        .context_radius(context)
+        .header(old_name, new_name)
+        .to_string();
+
+    diff.trim_end_matches('\n').to_owned()
+}
+
+fn with_line_break(text: &str) -> Cow<'_, str> {
+    if text.is_empty() || text.ends_with('\n') {
+        text.into()
+    } else {
+        format!("{text}\n").into()
+    }
+}
+
+/// Returns number of the same lines at the beginning and at the end of `old` and `new`.
+///
+/// Lines at the end are counted only after lines at the beginning, so they don't overlap
+/// and lines between them are the changed ones.
+pub(crate) fn common_lines<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    (prefix, suffix)
+}
+
+/// Returns unified diff of content of note `old` and content of note `new`,
+/// read more [here][crate::diff].
+/// ### Returns
+/// * Ok
+///     * Diff with `context` unchanged lines around changes, empty if contents are the same
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if any of the notes is owned by other user and isn't
+///       shared with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if any of the notes doesn't exist
+pub async fn diff_notes(
+    old: &str,
+    new: &str,
+    context: usize,
+    pool: &PgPool,
+) -> Result<String, NotebookError> {
+    let old_note = commands::select_one(old, pool).await?;
+    let new_note = commands::select_one(new, pool).await?;
+
+    Ok(unified_diff(
+        old_note.body(),
+        new_note.body(),
+        old,
+        new,
+        context,
+    ))
+}
+
+/// Returns unified diff of content of note `notename` in revision `revision_id`
+/// and its current content, read more [here][crate::diff].
+///
+/// Revision is named `<notename>@<revision ID>` in the header of diff.
+/// ### Returns
+/// * Ok
+///     * Diff with `context` unchanged lines around changes, empty if contents are the same
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist or doesn't have such revision
+pub async fn diff_revision(
+    notename: &str,
+    revision_id: i32,
+    context: usize,
+    pool: &PgPool,
+) -> Result<String, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let old = history::select_note_revision(notename, revision_id, pool).await?;
+    let new = commands::select_one(notename, pool).await?;
+
+    Ok(unified_diff(
+        &old,
+        new.body(),
+        &format!("{notename}@{revision_id}"),
+        notename,
+        context,
+    ))
+}
+
+/// Returns unified diff of note `notename` and `with`, read more [here][crate::diff].
+/// ### Returns
+/// * Errors
+///     * Errors of [`diff_notes`] or [`diff_revision`]
+pub async fn diff(
+    notename: &str,
+    with: &DiffWith,
+    context: usize,
+    pool: &PgPool,
+) -> Result<String, NotebookError> {
+    match with {
+        DiffWith::Note(other) => diff_notes(notename, other, context, pool).await,
+        DiffWith::Revision(revision_id) => {
+            diff_revision(notename, *revision_id, context, pool).await
+        }
+    }
+}
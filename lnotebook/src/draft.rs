@@ -0,0 +1,155 @@
+//! Builder of new notes.
+//!
+//! [`NoteDraft`] collects everything about a new note (content, deadline, metadata) and adds
+//! the note with all of it in one query, so note is never seen half-filled. Unlike
+//! [`add`][crate::commands::add], new properties of notes are added to [`NoteDraft`] as new
+//! methods, and code that doesn't use them doesn't change.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::draft::NoteDraft;
+//! # use lnotebook::due::parse_due;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn draft_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let note = NoteDraft::new("report")
+//!         .body("Quarterly numbers")
+//!         .due(parse_due("2024-07-20 18:30")?)
+//!         .insert(pool)
+//!         .await?;
+//!
+//!     assert_eq!("report", note.note_name);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::Note;
+use crate::compression;
+use crate::config::{self, logged};
+use crate::errors::{self, NotebookError};
+use crate::notename::NoteName;
+use crate::sql::query_as;
+use crate::users;
+
+use chrono::{DateTime, Local};
+use sqlx::{self, PgPool};
+use tracing::{event, Level};
+
+/// Builder of a new note.
+///
+/// Read more [here][crate::draft].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteDraft {
+    note_name: String,
+    body: String,
+    due_at: Option<DateTime<Local>>,
+    #[cfg(feature = "metadata")]
+    metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl NoteDraft {
+    /// Creates draft of empty note `notename`.
+    pub fn new(notename: &str) -> NoteDraft {
+        NoteDraft {
+            note_name: notename.to_owned(),
+            body: String::new(),
+            due_at: None,
+            #[cfg(feature = "metadata")]
+            metadata: serde_json::Map::new(),
+        }
+    }
+
+    /// Sets content of note.
+    pub fn body(mut self, body: &str) -> NoteDraft {
+        self.body = body.to_owned();
+        self
+    }
+
+    /// Sets deadline of note, read about deadlines in [`due` module][crate::due].
+    pub fn due(mut self, due_at: DateTime<Local>) -> NoteDraft {
+        self.due_at = Some(due_at);
+        self
+    }
+
+    /// Sets metadata `key` of note to `value` (only with `metadata` feature),
+    /// read about metadata in [`metadata` module][crate::metadata].
+    /// ### Example
+    /// ```rust,no_run
+    /// # use lnotebook::NoteDraft;
+    /// # use lnotebook::NotebookError;
+    /// # use sqlx::PgPool;
+    /// async fn meta_example(pool: &PgPool) -> Result<(), NotebookError> {
+    ///     let note = NoteDraft::new("invoice")
+    ///         .meta("amount", 120)
+    ///         .meta("paid", false)
+    ///         .insert(pool)
+    ///         .await?;
+    ///
+    ///     assert_eq!(Some(false), note.meta::<bool>("paid", pool).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "metadata")]
+    pub fn meta(mut self, key: &str, value: impl Into<serde_json::Value>) -> NoteDraft {
+        self.metadata.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Adds note to the notebook.
+    /// ### Returns
+    /// * Ok
+    ///     * [Note] that was added
+    /// * Errors
+    ///     * [`NotebookError::InvalidNoteName`] error if notename is not valid
+    ///     * [`NotebookError::NoteTooLarge`] error if body is larger than
+    ///       [`max_note_size`][crate::config::max_note_size]
+    ///     * [`NotebookError::AlreadyTaken`] error if notename is already taken
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+    pub async fn insert(self, pool: &PgPool) -> Result<Note, NotebookError> {
+        NoteName::new(&self.note_name)?;
+        config::check_size(self.body.len())?;
+
+        #[cfg(feature = "metadata")]
+        let metadata = Some(serde_json::Value::Object(self.metadata).to_string());
+        #[cfg(not(feature = "metadata"))]
+        let metadata: Option<String> = None;
+
+        let mut tx = pool.begin().await?;
+        let res = query_as!(
+            Note { id: i32, note_name: String, note: Option<String> },
+            "
+INSERT INTO notebook (note_name, note, due_at, metadata, owner)
+VALUES ($1, $2, $3, COALESCE($4::TEXT::JSONB, '{}'), $5)
+RETURNING id, note_name, note
+            ",
+            self.note_name,
+            self.body,
+            self.due_at,
+            metadata,
+            users::acting_user()
+        )
+        .fetch_one(&mut *tx)
+        .await;
+
+        match res {
+            Ok(note) => {
+                compression::pack(note.id, note.note.as_deref(), &mut tx).await?;
+                tx.commit().await?;
+
+                event!(
+                    Level::INFO,
+                    "Insert note with name `{}` with data `{}` into notebook",
+                    self.note_name,
+                    logged(&self.body)
+                );
+
+                Ok(note)
+            }
+            Err(err) if errors::is_unique_violation(&err) => Err(NotebookError::AlreadyTaken {
+                notename: self.note_name,
+            }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
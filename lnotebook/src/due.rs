@@ -0,0 +1,207 @@
+//! Due dates of notes.
+//!
+//! Every note can have a deadline, so notes can be used as simple TODOs.
+//! Deadline is set with [`set_due`] and notes that have deadline are listed by [`due`]
+//! sorted by deadline, so overdue notes go first.
+//!
+//! Deadline can be written as:
+//! * `2024-07-20 18:30` or `2024-07-20T18:30` - local date and time
+//! * `2024-07-20` - end of the local day (`23:59:59`)
+//! * `2024-07-20T18:30:00+03:00` - date and time in [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) format
+
+use crate::errors::NotebookError;
+use crate::protect;
+use crate::sql::query_as;
+use crate::users::{self, Access};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use sqlx::{self, PgPool};
+use std::fmt;
+use tracing::{event, Level};
+
+/// This is a `struct` that containing note that has a deadline.
+pub struct DueNote {
+    pub id: i32,
+    pub note_name: String,
+    pub due_at: DateTime<Local>,
+}
+
+impl DueNote {
+    /// Returns `true` if deadline of note has already passed.
+    pub fn is_overdue(&self) -> bool {
+        self.due_at < Local::now()
+    }
+}
+
+impl fmt::Display for DueNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.is_overdue() {
+            "overdue"
+        } else {
+            "upcoming"
+        };
+
+        write!(
+            f,
+            "{:<8}  {}  {}",
+            status,
+            self.due_at.format("%Y-%m-%d %H:%M"),
+            self.note_name
+        )
+    }
+}
+
+/// Parses deadline written in one of [supported formats][crate::due].
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::InvalidDate`] error if deadline can't be parsed
+/// ### Example
+/// ```
+/// # use lnotebook::due::parse_due;
+/// use chrono::{Local, TimeZone};
+///
+/// let due_at = parse_due("2024-07-20 18:30").unwrap();
+///
+/// assert_eq!(Local.with_ymd_and_hms(2024, 7, 20, 18, 30, 0).unwrap(), due_at);
+/// assert!(parse_due("tomorrow").is_err());
+/// ```
+pub fn parse_due(date: &str) -> Result<DateTime<Local>, NotebookError> {
+    let date = date.trim();
+    let invalid = || NotebookError::InvalidDate {
+        date: date.to_owned(),
+    };
+
+    if let Ok(due_at) = DateTime::parse_from_rfc3339(date) {
+        return Ok(due_at.with_timezone(&Local));
+    }
+
+    let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(date, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|day| day.and_hms_opt(23, 59, 59))
+        })
+        .ok_or_else(invalid)?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(invalid)
+}
+
+/// Sets deadline of the requested note.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::due::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn due_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     set_due("buy_milk", parse_due("2024-07-20 18:30")?, pool).await?;
+///
+///     for note in due(pool).await? {
+///         println!("{note}");
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn set_due(
+    notename: &str,
+    due_at: DateTime<Local>,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    query_as!(
+        { id: i32 },
+        "
+UPDATE notebook
+SET due_at = $1, reminded_at = NULL
+WHERE note_name = $2
+RETURNING id
+        ",
+        due_at,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Set deadline of note `{}` to {}",
+        notename,
+        due_at.format("%Y-%m-%d %H:%M")
+    );
+
+    Ok(())
+}
+
+/// Removes deadline of the requested note.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn clear_due(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    query_as!(
+        { id: i32 },
+        "
+UPDATE notebook
+SET due_at = NULL, reminded_at = NULL
+WHERE note_name = $1
+RETURNING id
+        ",
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(Level::INFO, "Remove deadline of note `{}`", notename);
+
+    Ok(())
+}
+
+/// Returns all notes that have deadline, sorted by deadline.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`DueNote`]; overdue notes go first
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn due(pool: &PgPool) -> Result<Vec<DueNote>, NotebookError> {
+    let rows = query_as!(
+        { id: i32, note_name: String, due_at: DateTime<Utc> },
+        r#"
+SELECT id, note_name, due_at AS "due_at!"
+FROM notebook
+WHERE due_at IS NOT NULL AND can_access_note(id, owner, $1, false)
+ORDER BY due_at, note_name
+        "#,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DueNote {
+            id: row.id,
+            note_name: row.note_name,
+            due_at: row.due_at.with_timezone(&Local),
+        })
+        .collect())
+}
@@ -0,0 +1,285 @@
+//! SQL dumps of the notebook.
+//!
+//! [`dump`] writes all notes, their history, attachments, templates, users, shares and highlights
+//! of notes, and the audit log (read about it in [`audit` module][crate::audit]) into SQL file
+//! that [`restore`] can apply to any database, replacing everything in it. Dump is made with
+//! queries of the notebook itself, so it doesn't need `pg_dump` and works wherever the notebook
+//! runs.
+//!
+//! Dumps contain notes of all users, so only admins can make and restore them when notebook
+//! acts as user (read more in [`users` module][crate::users]).
+//!
+//! Dump contains only data: schema is created by migrations (read about them in
+//! [`migrate` module][crate::migrate]), which [`restore`] applies before data. Dump can also be
+//! applied with `psql --single-transaction -f <path>` to database that is already migrated.
+//! Every row is stored as JSON and inserted only into columns it has, so dumps don't depend
+//! on order of columns, and columns added by newer versions of the notebook get their default
+//! values.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::dump::{dump, restore};
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn dump_example(pool: &PgPool, backup_pool: &PgPool) -> Result<(), NotebookError> {
+//!     dump("notebook.sql", pool).await?;
+//!
+//!     let notes = restore("notebook.sql", backup_pool).await?;
+//!     println!("Restored {notes} notes");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+use crate::migrate;
+use crate::sql::{query_as, query_scalar};
+use crate::users;
+
+use futures_util::TryStreamExt;
+use sqlx::{self, Executor, PgPool};
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+use tracing::{event, Level};
+
+/// First line of every dump, [`restore`] refuses files without it.
+pub const DUMP_HEADER: &str = "-- LNotebook SQL dump";
+
+/// Removes everything from the notebook before data of dump is inserted.
+/// Locks of notes are not dumped, so restored notes are not locked. State of sync is not dumped
+/// either, so the next sync after restore treats all different notes as conflicts.
+const CLEAR: &str = "TRUNCATE notebook, templates, note_bodies, note_revisions, attachments, \
+note_locks, note_tombstones, sync_state, users, note_shares, highlights, audit_log RESTART IDENTITY;";
+
+/// Continues IDs of restored rows after the largest restored ones.
+const RESET_SEQUENCES: &str = "\
+SELECT setval(pg_get_serial_sequence('notebook', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM notebook;
+SELECT setval(pg_get_serial_sequence('note_revisions', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM note_revisions;
+SELECT setval(pg_get_serial_sequence('attachments', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM attachments;
+SELECT setval(pg_get_serial_sequence('templates', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM templates;
+SELECT setval(pg_get_serial_sequence('highlights', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM highlights;
+SELECT setval(pg_get_serial_sequence('audit_log', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM audit_log;";
+
+/// Writes all data of the notebook into SQL file `path`.
+///
+/// Read about dumps [here][crate::dump].
+/// ### Returns
+/// * Ok
+///     * Number of dumped notes
+/// * Errors
+///     * [`NotebookError::NotAdmin`] error if the acting user is not an admin
+///     * [`NotebookError::Io`] error if file can't be written
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn dump(path: impl AsRef<Path>, pool: &PgPool) -> Result<usize, NotebookError> {
+    users::check_admin(pool).await?;
+
+    let path = path.as_ref();
+    let mut file = BufWriter::new(fs::File::create(path)?);
+
+    writeln!(file, "{DUMP_HEADER}")?;
+    writeln!(
+        file,
+        "-- Restore with `restore` command, or with `psql --single-transaction -f` into migrated database\n"
+    )?;
+    writeln!(file, "{CLEAR}")?;
+
+    // Users are inserted before notes they own. Bodies are inserted before notes, so triggers
+    // of history find them already stored, and every delta is inserted after body it refers to.
+    // Revisions made by triggers while notes are inserted are replaced with the dumped ones.
+    let mut statements = query_as!(
+        { part: i32, statement: String },
+        r#"
+WITH RECURSIVE bodies AS (
+    SELECT hash, 0 AS depth
+    FROM note_bodies
+    WHERE base_hash IS NULL
+    UNION ALL
+    SELECT note_bodies.hash, bodies.depth + 1
+    FROM note_bodies
+    JOIN bodies ON note_bodies.base_hash = bodies.hash
+),
+dumped AS (
+    SELECT 0 AS part, 0 AS depth, 0 AS id, t.name AS hash, 'users' AS tbl, row_to_json(t) AS data
+    FROM users t
+    UNION ALL
+    SELECT 1, bodies.depth, 0, t.hash, 'note_bodies', row_to_json(t)
+    FROM note_bodies t
+    JOIN bodies USING (hash)
+    UNION ALL
+    SELECT 2, 0, t.id, '', 'notebook', row_to_json(t)
+    FROM notebook t
+    UNION ALL
+    SELECT 4, 0, t.id, '', 'note_revisions', row_to_json(t)
+    FROM note_revisions t
+    UNION ALL
+    SELECT 5, 0, t.id, '', 'attachments', row_to_json(t)
+    FROM attachments t
+    UNION ALL
+    SELECT 6, 0, t.id, '', 'templates', row_to_json(t)
+    FROM templates t
+    UNION ALL
+    SELECT 7, 0, t.note_id, t.user_name, 'note_shares', row_to_json(t)
+    FROM note_shares t
+    UNION ALL
+    SELECT 8, 0, t.id, '', 'highlights', row_to_json(t)
+    FROM highlights t
+    UNION ALL
+    SELECT 9, 0, t.id, '', 'audit_log', row_to_json(t)
+    FROM audit_log t
+)
+SELECT part AS "part!", statement AS "statement!"
+FROM (
+    SELECT part, depth, id, hash,
+        format(
+            'INSERT INTO %1$I (%2$s) SELECT %2$s FROM json_populate_record(NULL::%1$I, %3$L);',
+            tbl,
+            (SELECT string_agg(quote_ident(key), ', ') FROM json_object_keys(data) AS key),
+            data
+        ) AS statement
+    FROM dumped
+    UNION ALL
+    SELECT 3, 0, 0, '', 'DELETE FROM note_revisions;'
+) AS statements
+ORDER BY part, depth, id, hash
+        "#
+    )
+    .fetch(pool);
+
+    let mut notes = 0;
+    while let Some(row) = statements.try_next().await? {
+        if row.part == 2 {
+            notes += 1;
+        }
+
+        writeln!(file, "{}", row.statement)?;
+    }
+
+    writeln!(file, "{RESET_SEQUENCES}")?;
+    file.flush()?;
+
+    event!(
+        Level::INFO,
+        "Dumped {} notes into {}",
+        notes,
+        path.display()
+    );
+
+    Ok(notes)
+}
+
+/// Replaces all data of the notebook with dump from file `path` created by [`dump`].
+///
+/// Database is migrated first, then dump is applied in one transaction,
+/// so if it fails, the notebook is left as it was.
+/// ### Returns
+/// * Ok
+///     * Number of restored notes
+/// * Errors
+///     * [`NotebookError::NotAdmin`] error if the acting user is not an admin
+///     * [`NotebookError::InvalidArchive`] error if file is not a dump of the notebook
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+///       if database can't be migrated or dump can't be applied
+pub async fn restore(path: impl AsRef<Path>, pool: &PgPool) -> Result<usize, NotebookError> {
+    users::check_admin(pool).await?;
+
+    let path = path.as_ref();
+    let sql = fs::read_to_string(path)?;
+
+    if sql.lines().next() != Some(DUMP_HEADER) {
+        return Err(NotebookError::InvalidArchive {
+            reason: format!("`{}` is not a dump of the notebook", path.display()),
+        });
+    }
+
+    migrate::migrate(pool).await?;
+
+    let mut transaction = pool.begin().await?;
+
+    // Query without arguments is sent as simple query, so it can contain many statements
+    transaction.execute(sql.as_str()).await?;
+
+    let notes = query_scalar!(i64, r#"SELECT COUNT(*) AS "count!" FROM notebook"#)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    event!(
+        Level::INFO,
+        "Restored {} notes from {}",
+        notes,
+        path.display()
+    );
+
+    Ok(notes as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands;
+
+    fn dump_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("lnotebook-{}-{name}.sql", std::process::id()))
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn dump_is_restored(pool: PgPool) {
+        let path = dump_path("restored");
+        commands::add("shopping", "milk\nbread", &pool)
+            .await
+            .unwrap();
+        commands::upd("shopping", "milk", &pool).await.unwrap();
+
+        assert_eq!(1, dump(&path, &pool).await.unwrap());
+        commands::del("shopping", &pool).await.unwrap();
+        assert_eq!(1, restore(&path, &pool).await.unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let note = commands::select_one("shopping", &pool).await.unwrap();
+        assert_eq!("milk", note.body());
+        let revisions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM note_revisions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(2, revisions);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn columns_missing_in_dump_get_defaults(pool: PgPool) {
+        // Dump made before `metadata` of notes and `compressed` of revision contents were added
+        let path = dump_path("old");
+        let hash = commands::hash_content("old note");
+        let sql = format!(
+            "{DUMP_HEADER}\n{CLEAR}\n\
+INSERT INTO note_bodies (hash, base_hash, body, size) SELECT hash, base_hash, body, size \
+FROM json_populate_record(NULL::note_bodies, '{{\"hash\":\"{hash}\",\"base_hash\":null,\"body\":\"old note\",\"size\":8}}');\n\
+INSERT INTO notebook (id, note_name, note) SELECT id, note_name, note \
+FROM json_populate_record(NULL::notebook, '{{\"id\":1,\"note_name\":\"old\",\"note\":\"old note\"}}');\n\
+{RESET_SEQUENCES}\n"
+        );
+        fs::write(&path, sql).unwrap();
+
+        assert_eq!(1, restore(&path, &pool).await.unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let note = commands::select_one("old", &pool).await.unwrap();
+        assert_eq!("old note", note.body());
+        let (metadata, compressed): (String, bool) = sqlx::query_as(
+            "
+SELECT notebook.metadata::TEXT, note_bodies.compressed
+FROM notebook, note_bodies
+            ",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!("{}", metadata);
+        assert!(!compressed);
+    }
+}
@@ -0,0 +1,1493 @@
+//! Exporting notes from the notebook to files.
+//!
+//! Notes can be exported into Markdown files with [`export_markdown`] or into a single archive
+//! with [`export_archive`]. Archive can be imported back with [`import_archive`]
+//! and optionally encrypted with passphrase. [`export_tar`] also exports attachments and binary
+//! notes into single compressed archive, that is imported back with [`import_tar`].
+//! For spreadsheets and other tools that don't read JSON, notes can be exported into CSV file
+//! with [`export_csv`] and imported back with [`import_csv`].
+//! [`export_html`] exports notes into static site that can be published as read-only copy
+//! of the notebook. Protected notes (read about them in [`protect` module][crate::protect])
+//! are not exported, use [`dump`][crate::dump] to back them up.
+//!
+//! ### Templates
+//! How every note is rendered is controlled by [`Template`]. Template is a text
+//! with placeholders `{{ variable }}` that are replaced with information about note:
+//! * `{{ id }}` - ID of note
+//! * `{{ name }}` - notename
+//! * `{{ body }}` - content of note
+//! * `{{ lines }}` - number of lines in content of note
+//! * `{{ bytes }}` - size of content of note in bytes
+//!
+//! For example, this template adds YAML front matter to every exported note:
+//! ```text
+//! ---
+//! id: {{ id }}
+//! title: {{ name }}
+//! ---
+//! {{ body }}
+//! ```
+
+use crate::batch::{add_many, BatchReport};
+use crate::commands::{select_all, Note};
+use crate::compression;
+use crate::config;
+use crate::errors::NotebookError;
+use crate::notename::NoteName;
+use crate::sql::{query, query_as, query_scalar};
+use crate::users;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Read,
+    path::Path,
+};
+use tracing::{event, Level};
+
+/// Template used by default when exporting notes to Markdown.
+pub const DEFAULT_MARKDOWN_TEMPLATE: &str = "# {{ name }}\n\n{{ body }}\n";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Var(Var),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Var {
+    Id,
+    Name,
+    Body,
+    Lines,
+    Bytes,
+}
+
+/// Parsed template that controls how notes are rendered in exports.
+///
+/// Read about template syntax [here][crate::export].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses template from text.
+    /// ### Returns
+    /// * Ok
+    ///     * Parsed [`Template`]
+    /// * Errors
+    ///     * [`NotebookError::InvalidTemplate`] error if placeholder is not closed or
+    ///       uses unknown variable
+    /// ### Example
+    /// ```
+    /// # use lnotebook::export::Template;
+    /// # use lnotebook::commands::Note;
+    /// let template = Template::parse("## {{ name }}\n{{ body }}").unwrap();
+    /// let note = Note {
+    ///     id: 1,
+    ///     note_name: "cat".to_owned(),
+    ///     note: Some("meow".to_owned()),
+    /// };
+    ///
+    /// assert_eq!("## cat\nmeow", template.render(&note));
+    /// ```
+    pub fn parse(source: &str) -> Result<Template, NotebookError> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+
+            let Some(len) = rest[start..].find("}}") else {
+                return Err(NotebookError::InvalidTemplate {
+                    reason: "placeholder `{{` is not closed with `}}`".to_owned(),
+                });
+            };
+
+            let var = match rest[start + 2..start + len].trim() {
+                "id" => Var::Id,
+                "name" => Var::Name,
+                "body" => Var::Body,
+                "lines" => Var::Lines,
+                "bytes" => Var::Bytes,
+                unknown => {
+                    return Err(NotebookError::InvalidTemplate {
+                        reason: format!("unknown variable `{unknown}`"),
+                    })
+                }
+            };
+            parts.push(Part::Var(var));
+
+            rest = &rest[start + len + 2..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Reads template from file and parses it.
+    /// ### Returns
+    /// * Ok
+    ///     * Parsed [`Template`]
+    /// * Errors
+    ///     * [`NotebookError::Io`] error if file can't be read
+    ///     * [`NotebookError::InvalidTemplate`] error if template is invalid
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Template, NotebookError> {
+        Template::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Renders note using this template.
+    pub fn render(&self, note: &Note) -> String {
+        let body = note.body();
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.to_owned(),
+                Part::Var(Var::Id) => note.id.to_string(),
+                Part::Var(Var::Name) => note.note_name.to_owned(),
+                Part::Var(Var::Body) => body.to_owned(),
+                Part::Var(Var::Lines) => body.lines().count().to_string(),
+                Part::Var(Var::Bytes) => body.len().to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Returns notename that can be safely used as file name.
+pub(crate) fn file_stem(notename: &str) -> String {
+    notename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Exports all notes to Markdown files (one `<notename>.md` per note) in directory `dir`.
+///
+/// If `template` is `None`, notes are rendered with [`DEFAULT_MARKDOWN_TEMPLATE`].
+/// Directory is created if it doesn't exist.
+/// ### Returns
+/// * Ok
+///     * Number of exported notes
+/// * Errors
+///     * [`NotebookError::Io`] error if files can't be written
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::export::{export_markdown, Template};
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn export_markdown_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let template = Template::parse("---\ntitle: {{ name }}\n---\n{{ body }}\n")?;
+///
+///     export_markdown("exported_notes", Some(&template), pool).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_markdown(
+    dir: impl AsRef<Path>,
+    template: Option<&Template>,
+    pool: &PgPool,
+) -> Result<usize, NotebookError> {
+    let dir = dir.as_ref();
+    let default_template;
+    let template = match template {
+        Some(template) => template,
+        None => {
+            default_template = Template::parse(DEFAULT_MARKDOWN_TEMPLATE)?;
+            &default_template
+        }
+    };
+
+    fs::create_dir_all(dir)?;
+
+    let notes = select_all(pool).await?;
+    for note in notes.iter() {
+        let path = dir.join(format!("{}.md", file_stem(&note.note_name)));
+
+        fs::write(&path, template.render(note))?;
+
+        event!(
+            Level::DEBUG,
+            "Export `{}` into {}",
+            note.note_name,
+            path.display()
+        );
+    }
+
+    event!(
+        Level::INFO,
+        "Exported {} notes into {}",
+        notes.len(),
+        dir.display()
+    );
+
+    Ok(notes.len())
+}
+
+/// Style of pages created by [`export_html`].
+const HTML_STYLE: &str = "\
+body { max-width: 48rem; margin: 2rem auto; padding: 0 1rem; font-family: sans-serif; line-height: 1.5; color: #222; }
+a { color: #0550ae; }
+a.missing { color: #b42318; text-decoration: line-through; }
+pre { background: #f4f4f4; padding: 0.75rem; overflow-x: auto; }
+code { background: #f4f4f4; padding: 0 0.2rem; }
+pre code { padding: 0; }
+blockquote { margin-left: 0; padding-left: 1rem; border-left: 3px solid #ccc; color: #555; }
+nav { margin-bottom: 1rem; }
+#search { width: 100%; padding: 0.5rem; font-size: 1rem; box-sizing: border-box; }
+#notes { list-style: none; padding: 0; }
+#notes li { padding: 0.5rem 0; border-bottom: 1px solid #eee; }
+.preview { display: block; color: #666; font-size: 0.9rem; }
+";
+
+/// Script of the search box on index page created by [`export_html`].
+const HTML_SEARCH_SCRIPT: &str = "\
+const search = document.getElementById(\"search\");
+search.addEventListener(\"input\", () => {
+  const words = search.value.toLowerCase().split(/\\s+/).filter(Boolean);
+  let shown = 0;
+  for (const note of document.querySelectorAll(\"#notes li\")) {
+    const found = words.every((word) => note.dataset.text.includes(word));
+    note.hidden = !found;
+    shown += found ? 1 : 0;
+  }
+  document.getElementById(\"nothing\").hidden = shown > 0;
+});
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns file name escaped to be used in `href`.
+fn escape_href(file_name: &str) -> String {
+    file_name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Renders inline Markdown of one line: code, bold, italic, links and `[[notename]]` links.
+fn inline_html(text: &str, link: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        // Closing part is looked for after the opening one, and span must be not empty
+        let span = |open: &str, close: &str| {
+            let inner = rest.strip_prefix(open)?;
+            let end = inner.find(close).filter(|&end| end > 0)?;
+            Some((&inner[..end], open.len() + end + close.len()))
+        };
+
+        if let Some((code, len)) = span("`", "`") {
+            html += &format!("<code>{}</code>", escape_html(code));
+            rest = &rest[len..];
+        } else if let Some((notename, len)) = span("[[", "]]") {
+            html += &match link(notename) {
+                Some(href) => format!("<a href=\"{href}\">{}</a>", escape_html(notename)),
+                None => format!(
+                    "<a class=\"missing\" title=\"Note doesn't exist\">{}</a>",
+                    escape_html(notename)
+                ),
+            };
+            rest = &rest[len..];
+        } else if let Some((bold, len)) = span("**", "**") {
+            html += &format!("<strong>{}</strong>", inline_html(bold, link));
+            rest = &rest[len..];
+        } else if let Some((italic, len)) = span("*", "*").filter(|(italic, _)| {
+            !italic.starts_with(char::is_whitespace) && !italic.ends_with(char::is_whitespace)
+        }) {
+            html += &format!("<em>{}</em>", inline_html(italic, link));
+            rest = &rest[len..];
+        } else if let Some((title, title_len)) = span("[", "](") {
+            match rest[title_len..].find(')') {
+                // Scripts are never run from exported pages
+                Some(end)
+                    if !rest[title_len..title_len + end]
+                        .trim_start()
+                        .to_lowercase()
+                        .starts_with("javascript:") =>
+                {
+                    html += &format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape_html(rest[title_len..title_len + end].trim()),
+                        inline_html(title, link)
+                    );
+                    rest = &rest[title_len + end + 1..];
+                }
+                _ => {
+                    html += "[";
+                    rest = &rest[1..];
+                }
+            }
+        } else {
+            html += &escape_html(&c.to_string());
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    html
+}
+
+/// Returns list tag and content of list item if `line` is item of list.
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    let line = line.trim_start();
+
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some(("ul", item));
+        }
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match line[digits..].strip_prefix(". ") {
+        Some(item) if digits > 0 => Some(("ol", item)),
+        _ => None,
+    }
+}
+
+/// Renders Markdown `text` into HTML.
+///
+/// Headers, paragraphs, lists (with `[ ]` and `[x]` tasks), quotes, code blocks, horizontal
+/// lines, code, bold and italic text and links are rendered. Line breaks inside paragraphs
+/// are kept, because notes are often written without Markdown in mind. All other text
+/// is escaped, so note can't add own tags into page.
+///
+/// `link` returns `href` of note linked as `[[notename]]` or `None` if note doesn't exist,
+/// then link is rendered as missing.
+/// ### Example
+/// ```
+/// # use lnotebook::export::markdown_to_html;
+/// let html = markdown_to_html("# Dinner\nLook at [[recipes]] <b>now</b>", |notename| {
+///     Some(format!("{notename}.html"))
+/// });
+///
+/// assert_eq!(
+///     "<h1>Dinner</h1>\n<p>Look at <a href=\"recipes.html\">recipes</a> &lt;b&gt;now&lt;/b&gt;</p>\n",
+///     html
+/// );
+/// ```
+pub fn markdown_to_html(text: &str, link: impl Fn(&str) -> Option<String>) -> String {
+    fn close(html: &mut String, paragraph: &mut Vec<String>, list: &mut Option<&str>) {
+        if !paragraph.is_empty() {
+            *html += &format!("<p>{}</p>\n", paragraph.join("<br>\n"));
+            paragraph.clear();
+        }
+        if let Some(tag) = list.take() {
+            *html += &format!("</{tag}>\n");
+        }
+    }
+
+    let link: &dyn Fn(&str) -> Option<String> = &link;
+    let mut html = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            close(&mut html, &mut paragraph, &mut list);
+
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+
+            let class = match lang.trim() {
+                "" => String::new(),
+                lang => format!(" class=\"language-{}\"", escape_html(lang)),
+            };
+            html += &format!(
+                "<pre><code{class}>{}\n</code></pre>\n",
+                escape_html(&code.join("\n"))
+            );
+        } else if trimmed.is_empty() {
+            close(&mut html, &mut paragraph, &mut list);
+        } else if let Some((level, header)) = (1..=6).rev().find_map(|level| {
+            trimmed
+                .strip_prefix(&format!("{} ", "#".repeat(level)))
+                .map(|header| (level, header))
+        }) {
+            close(&mut html, &mut paragraph, &mut list);
+            html += &format!(
+                "<h{level}>{}</h{level}>\n",
+                inline_html(header.trim(), link)
+            );
+        } else if ["---", "***", "___"].contains(&trimmed) {
+            close(&mut html, &mut paragraph, &mut list);
+            html += "<hr>\n";
+        } else if let Some(first) = trimmed.strip_prefix('>') {
+            close(&mut html, &mut paragraph, &mut list);
+
+            let mut quote = vec![first.trim_start()];
+            while let Some(line) = lines.next_if(|line| line.trim().starts_with('>')) {
+                quote.push(line.trim()[1..].trim_start());
+            }
+            html += &format!(
+                "<blockquote>\n{}</blockquote>\n",
+                markdown_to_html(&quote.join("\n"), link)
+            );
+        } else if let Some((tag, item)) = list_item(line) {
+            if list != Some(tag) {
+                close(&mut html, &mut paragraph, &mut list);
+                html += &format!("<{tag}>\n");
+                list = Some(tag);
+            }
+
+            let (checkbox, item) = match item.get(..4) {
+                Some("[ ] ") => ("<input type=\"checkbox\" disabled> ", &item[4..]),
+                Some("[x] " | "[X] ") => {
+                    ("<input type=\"checkbox\" checked disabled> ", &item[4..])
+                }
+                _ => ("", item),
+            };
+            html += &format!("<li>{checkbox}{}</li>\n", inline_html(item, link));
+        } else {
+            if list.is_some() {
+                close(&mut html, &mut paragraph, &mut list);
+            }
+            paragraph.push(inline_html(trimmed, link));
+        }
+    }
+    close(&mut html, &mut paragraph, &mut list);
+
+    html
+}
+
+/// Returns HTML page with `title` and `body` styled like all pages of [`export_html`].
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{}</title>\n<style>\n{HTML_STYLE}</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape_html(title)
+    )
+}
+
+/// Exports all notes into static site in directory `dir`: one HTML page per note and
+/// `index.html` with list of all notes and search box.
+///
+/// Content of notes is rendered with [`markdown_to_html`], and `[[notename]]` links lead
+/// to pages of linked notes. Search box filters notes by words in their names and content
+/// in browser, so site can be published on any static hosting.
+/// Directory is created if it doesn't exist.
+/// ### Returns
+/// * Ok
+///     * Number of exported notes
+/// * Errors
+///     * [`NotebookError::Io`] error if files can't be written
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::export::export_html;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn export_html_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     let exported = export_html("site", pool).await?;
+///
+///     println!("Exported {exported} notes, open site/index.html");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_html(dir: impl AsRef<Path>, pool: &PgPool) -> Result<usize, NotebookError> {
+    let dir = dir.as_ref();
+    let mut notes = select_all(pool).await?;
+    notes.sort_by(|a, b| a.note_name.cmp(&b.note_name));
+
+    // Names of files must not repeat (like for `a/b` and `a_b`) and be `index.html`
+    let mut taken: HashSet<String> = HashSet::from(["index.html".to_owned()]);
+    let pages: HashMap<&str, String> = notes
+        .iter()
+        .map(|note| {
+            let mut file_name = format!("{}.html", file_stem(&note.note_name));
+            if !taken.insert(file_name.to_lowercase()) {
+                file_name = format!("{}-{}.html", file_stem(&note.note_name), note.id);
+                taken.insert(file_name.to_lowercase());
+            }
+
+            (note.note_name.as_str(), file_name)
+        })
+        .collect();
+    let link = |notename: &str| pages.get(notename).map(|file_name| escape_href(file_name));
+
+    fs::create_dir_all(dir)?;
+
+    let mut index = String::new();
+    for note in notes.iter() {
+        let file_name = &pages[note.note_name.as_str()];
+        let body = match &note.note {
+            Some(note) => markdown_to_html(note, link),
+            None => "<p><em>Binary note</em></p>\n".to_owned(),
+        };
+        let path = dir.join(file_name);
+
+        fs::write(
+            &path,
+            html_page(
+                &note.note_name,
+                &format!(
+                    "<nav><a href=\"index.html\">All notes</a></nav>\n<h1>{}</h1>\n{body}",
+                    escape_html(&note.note_name)
+                ),
+            ),
+        )?;
+
+        event!(
+            Level::DEBUG,
+            "Export `{}` into {}",
+            note.note_name,
+            path.display()
+        );
+
+        let text = note.body();
+        let preview = text.lines().find(|line| !line.trim().is_empty());
+        index += &format!(
+            "<li data-text=\"{}\"><a href=\"{}\">{}</a><span class=\"preview\">{}</span></li>\n",
+            escape_html(&format!("{}\n{text}", note.note_name).to_lowercase()),
+            escape_href(file_name),
+            escape_html(&note.note_name),
+            escape_html(preview.unwrap_or_default().trim())
+        );
+    }
+
+    fs::write(
+        dir.join("index.html"),
+        html_page(
+            "Notebook",
+            &format!(
+                "<h1>Notebook</h1>\n\
+<input id=\"search\" type=\"search\" placeholder=\"Search notes\" autofocus>\n\
+<ul id=\"notes\">\n{index}</ul>\n<p id=\"nothing\"{}>Nothing found</p>\n\
+<script>\n{HTML_SEARCH_SCRIPT}</script>\n",
+                if notes.is_empty() { "" } else { " hidden" }
+            ),
+        ),
+    )?;
+
+    event!(
+        Level::INFO,
+        "Exported {} notes into {}",
+        notes.len(),
+        dir.display()
+    );
+
+    Ok(notes.len())
+}
+
+/// Bytes encrypted archives start with, the same as `crypto::MAGIC`.
+const ARCHIVE_MAGIC: &[u8; 8] = b"LNBENC1\0";
+
+/// Version of archives created by [`export_archive`].
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    version: u32,
+    notes: Vec<ArchivedNote>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedNote {
+    note_name: String,
+    note: Option<String>,
+}
+
+#[cfg(feature = "crypto")]
+fn encrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    crate::crypto::encrypt(data, passphrase)
+}
+
+#[cfg(feature = "crypto")]
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    crate::crypto::decrypt(data, passphrase)
+}
+
+#[cfg(not(feature = "crypto"))]
+fn encrypt(_data: &[u8], _passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    Err(NotebookError::Crypto {
+        reason: "LNotebook is built without `crypto` feature".to_owned(),
+    })
+}
+
+#[cfg(not(feature = "crypto"))]
+fn decrypt(_data: &[u8], _passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+    Err(NotebookError::Crypto {
+        reason: "LNotebook is built without `crypto` feature".to_owned(),
+    })
+}
+
+/// Returns `true` if archive in file `path` is encrypted.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read
+pub fn is_archive_encrypted(path: impl AsRef<Path>) -> Result<bool, NotebookError> {
+    let mut magic = [0u8; 8];
+    let read = fs::File::open(path)?.read(&mut magic)?;
+
+    Ok(read == magic.len() && &magic == ARCHIVE_MAGIC)
+}
+
+/// Exports all notes into archive file `path` that can be imported by [`import_archive`].
+///
+/// If `passphrase` is `Some`, archive is encrypted with it (requires `crypto` feature),
+/// so it can be stored on untrusted storage. Read about encryption in `crypto` module.
+/// ### Returns
+/// * Ok
+///     * Number of exported notes
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be written
+///     * [`NotebookError::Crypto`] error if archive can't be encrypted
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::export::{export_archive, import_archive};
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn archive_example(pool: &PgPool, backup_pool: &PgPool) -> Result<(), NotebookError> {
+///     export_archive("notes.lnb", Some("secret"), pool).await?;
+///
+///     import_archive("notes.lnb", Some("secret"), false, backup_pool).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_archive(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+    pool: &PgPool,
+) -> Result<usize, NotebookError> {
+    let path = path.as_ref();
+    let notes = select_all(pool).await?;
+
+    let archive = Archive {
+        version: ARCHIVE_VERSION,
+        notes: notes
+            .into_iter()
+            .map(|note| ArchivedNote {
+                note_name: note.note_name,
+                note: note.note,
+            })
+            .collect(),
+    };
+    let mut data =
+        serde_json::to_vec_pretty(&archive).map_err(|err| NotebookError::InvalidArchive {
+            reason: err.to_string(),
+        })?;
+
+    if let Some(passphrase) = passphrase {
+        data = encrypt(&data, passphrase)?;
+    }
+
+    fs::write(path, data)?;
+
+    event!(
+        Level::INFO,
+        "Exported {} notes into {}{}",
+        archive.notes.len(),
+        path.display(),
+        if passphrase.is_some() {
+            " (encrypted)"
+        } else {
+            ""
+        }
+    );
+
+    Ok(archive.notes.len())
+}
+
+/// Imports all notes from archive file `path` created by [`export_archive`].
+///
+/// If archive is encrypted, `passphrase` is used to decrypt it (requires `crypto` feature).
+/// Notes that already exist with the same content are skipped and notes that can't be imported
+/// are reported in [`BatchReport`]. With `fail_fast` notes are imported in one transaction,
+/// so if any note can't be imported, no notes are imported. Read more [here][crate::batch].
+/// ### Returns
+/// * Ok
+///     * [`BatchReport`] about every note in archive
+/// * Errors
+///     * [`NotebookError::PassphraseRequired`] error if archive is encrypted, but `passphrase` is `None`
+///     * [`NotebookError::WrongPassphrase`] error if `passphrase` is wrong
+///     * [`NotebookError::InvalidArchive`] error if archive can't be read
+///     * [`NotebookError::AlreadyTaken`] error if `fail_fast` is `true` and note with the same name,
+///       but other content already exists
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn import_archive(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+    fail_fast: bool,
+    pool: &PgPool,
+) -> Result<BatchReport, NotebookError> {
+    let path = path.as_ref();
+    let mut data = fs::read(path)?;
+
+    if data.starts_with(ARCHIVE_MAGIC) {
+        let Some(passphrase) = passphrase else {
+            return Err(NotebookError::PassphraseRequired);
+        };
+        data = decrypt(&data, passphrase)?;
+    }
+
+    let archive: Archive =
+        serde_json::from_slice(&data).map_err(|err| NotebookError::InvalidArchive {
+            reason: err.to_string(),
+        })?;
+    if archive.version > ARCHIVE_VERSION {
+        return Err(NotebookError::InvalidArchive {
+            reason: format!("unsupported archive version {}", archive.version),
+        });
+    }
+
+    let report = add_many(
+        archive
+            .notes
+            .iter()
+            .map(|note| (note.note_name.as_str(), note.note.as_deref())),
+        fail_fast,
+        pool,
+    )
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Imported notes from {}: {}",
+        path.display(),
+        report
+    );
+
+    Ok(report)
+}
+
+/// Version of archives created by [`export_tar`].
+const TAR_VERSION: u32 = 1;
+
+/// Note read from archive created by [`export_tar`].
+#[derive(Default)]
+struct TarNote {
+    note_name: Option<String>,
+    note: Option<String>,
+    note_bytes: Option<Vec<u8>>,
+    attachments: Vec<(String, Vec<u8>)>,
+}
+
+fn invalid_tar(err: impl std::fmt::Display) -> NotebookError {
+    NotebookError::InvalidArchive {
+        reason: err.to_string(),
+    }
+}
+
+/// Exports all notes with their attachments into archive file `path` that can be imported
+/// by [`import_tar`].
+///
+/// Archive is tar file compressed with zstd, where every note is in directory `notes/<id>`:
+/// * `name` - notename
+/// * `note.txt` - content of note, or `note.bin` for binary note
+///   (read about them in [`add_binary`][crate::commands::add_binary])
+/// * `attachments/<file_name>` - files attached to note
+///
+/// If `passphrase` is `Some`, archive is encrypted with it (requires `crypto` feature),
+/// so single file can be safely stored in cloud drives. Without passphrase archive can be
+/// unpacked with `tar --zstd -xf`.
+/// ### Returns
+/// * Ok
+///     * Number of exported notes
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be written
+///     * [`NotebookError::Crypto`] error if archive can't be encrypted
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::export::{export_tar, import_tar};
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn tar_example(pool: &PgPool, backup_pool: &PgPool) -> Result<(), NotebookError> {
+///     export_tar("notes.tar.zst.lnb", Some("secret"), pool).await?;
+///
+///     import_tar("notes.tar.zst.lnb", Some("secret"), false, backup_pool).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_tar(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+    pool: &PgPool,
+) -> Result<usize, NotebookError> {
+    let path = path.as_ref();
+
+    let notes = query_as!(
+        {
+            id: i32,
+            note_name: String,
+            note: Option<String>,
+            note_bytes: Option<Vec<u8>>,
+            compressed: bool,
+        },
+        "
+SELECT id, note_name, note, note_bytes, compressed
+FROM notebook
+WHERE passphrase_hash IS NULL AND can_access_note(id, owner, $1, false)
+ORDER BY id
+        ",
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+    let attachments = query_as!(
+        { note_id: i32, file_name: String, data: Vec<u8> },
+        "
+SELECT note_id, file_name, data
+FROM attachments
+JOIN notebook ON notebook.id = attachments.note_id
+WHERE passphrase_hash IS NULL AND can_access_note(notebook.id, owner, $1, false)
+ORDER BY note_id, file_name
+        ",
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mtime = chrono::Local::now().timestamp().max(0) as u64;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 3)?);
+    let mut append = |path: &str, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+
+        builder.append_data(&mut header, path, data)
+    };
+
+    append("VERSION", TAR_VERSION.to_string().as_bytes())?;
+    let exported = notes.len();
+    for mut note in notes {
+        let dir = format!("notes/{}", note.id);
+        if note.compressed {
+            note.note = compression::content(&note.note_name, None, note.note_bytes.take())?;
+        }
+
+        append(&format!("{dir}/name"), note.note_name.as_bytes())?;
+        match (&note.note, &note.note_bytes) {
+            (Some(text), _) => append(&format!("{dir}/note.txt"), text.as_bytes())?,
+            (None, Some(bytes)) => append(&format!("{dir}/note.bin"), bytes)?,
+            (None, None) => {}
+        }
+    }
+    for attachment in attachments.iter() {
+        append(
+            &format!(
+                "notes/{}/attachments/{}",
+                attachment.note_id, attachment.file_name
+            ),
+            &attachment.data,
+        )?;
+    }
+
+    let mut data = builder.into_inner()?.finish()?;
+    if let Some(passphrase) = passphrase {
+        data = encrypt(&data, passphrase)?;
+    }
+
+    fs::write(path, data)?;
+
+    event!(
+        Level::INFO,
+        "Exported {} notes with {} attachments into {}{}",
+        exported,
+        attachments.len(),
+        path.display(),
+        if passphrase.is_some() {
+            " (encrypted)"
+        } else {
+            ""
+        }
+    );
+
+    Ok(exported)
+}
+
+/// Reads notes from tar archive compressed with zstd, in order of their IDs.
+fn read_tar(data: &[u8]) -> Result<Vec<TarNote>, NotebookError> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(data).map_err(invalid_tar)?);
+    let mut version = None;
+    let mut notes: std::collections::BTreeMap<i64, TarNote> = Default::default();
+
+    for entry in archive.entries().map_err(invalid_tar)? {
+        let mut entry = entry.map_err(invalid_tar)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .map_err(invalid_tar)?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(invalid_tar)?;
+
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts == ["VERSION"] {
+            version = Some(String::from_utf8_lossy(&data).trim().to_owned());
+            continue;
+        }
+
+        let ["notes", id, file @ ..] = parts.as_slice() else {
+            return Err(invalid_tar(format!("unexpected file `{path}`")));
+        };
+        let id: i64 = id
+            .parse()
+            .map_err(|_| invalid_tar(format!("unexpected directory `notes/{id}`")))?;
+        let note = notes.entry(id).or_default();
+        let text = |data: Vec<u8>| {
+            String::from_utf8(data).map_err(|_| invalid_tar(format!("`{path}` is not UTF-8")))
+        };
+
+        match file {
+            ["name"] => note.note_name = Some(text(data)?),
+            ["note.txt"] => note.note = Some(text(data)?),
+            ["note.bin"] => note.note_bytes = Some(data),
+            ["attachments", file_name] if !file_name.is_empty() => {
+                note.attachments.push((file_name.to_string(), data))
+            }
+            _ => return Err(invalid_tar(format!("unexpected file `{path}`"))),
+        }
+    }
+
+    match version.as_deref().map(str::parse::<u32>) {
+        Some(Ok(version)) if version <= TAR_VERSION => {}
+        Some(_) => {
+            return Err(invalid_tar(format!(
+                "unsupported archive version {}",
+                version.unwrap_or_default()
+            )))
+        }
+        None => return Err(invalid_tar("archive has no `VERSION` file")),
+    }
+
+    notes
+        .into_iter()
+        .map(|(id, note)| match note.note_name {
+            Some(_) => Ok(note),
+            None => Err(invalid_tar(format!("`notes/{id}` has no `name` file"))),
+        })
+        .collect()
+}
+
+/// Adds note from archive with its attachments. Returns `false` if note already exists
+/// with the same content, then only attachments that note doesn't have are added.
+async fn import_tar_note(note: &TarNote, conn: &mut PgConnection) -> Result<bool, NotebookError> {
+    let notename = note.note_name.as_deref().unwrap_or_default();
+    NoteName::new(notename)?;
+    config::check_size(match (&note.note, &note.note_bytes) {
+        (Some(text), _) => text.len(),
+        (None, Some(bytes)) => bytes.len(),
+        (None, None) => 0,
+    })?;
+
+    let added = query_scalar!(
+        i32,
+        "
+INSERT INTO notebook (note_name, note, note_bytes, owner)
+VALUES ( $1, $2, $3, $4 )
+ON CONFLICT (note_name) DO NOTHING
+RETURNING id
+        ",
+        notename,
+        note.note,
+        note.note_bytes,
+        users::acting_user()
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let id = match added {
+        Some(id) => {
+            compression::pack(id, note.note.as_deref(), &mut *conn).await?;
+            id
+        }
+        None => {
+            let existing = query_as!(
+                { id: i32, note: Option<String>, note_bytes: Option<Vec<u8>>, compressed: bool },
+                "
+SELECT id, note, note_bytes, compressed
+FROM notebook
+WHERE note_name = $1
+                ",
+                notename
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            let (text, bytes) = match existing.compressed {
+                true => (
+                    compression::content(notename, None, existing.note_bytes)?,
+                    None,
+                ),
+                false => (existing.note, existing.note_bytes),
+            };
+
+            if text != note.note || bytes != note.note_bytes {
+                return Err(NotebookError::AlreadyTaken {
+                    notename: notename.to_owned(),
+                });
+            }
+
+            existing.id
+        }
+    };
+
+    for (file_name, data) in note.attachments.iter() {
+        query!(
+            "
+INSERT INTO attachments (note_id, file_name, data)
+VALUES ( $1, $2, $3 )
+ON CONFLICT (note_id, file_name) DO NOTHING
+            ",
+            id,
+            file_name,
+            data
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(added.is_some())
+}
+
+/// Imports all notes with their attachments from archive file `path` created by [`export_tar`].
+///
+/// If archive is encrypted, `passphrase` is used to decrypt it (requires `crypto` feature).
+/// Notes are imported like in [`import_archive`]: notes that already exist with the same content
+/// are skipped (but get attachments from archive they don't have), and with `fail_fast`
+/// no notes are imported if any of them can't be imported.
+/// ### Returns
+/// * Ok
+///     * [`BatchReport`] about every note in archive
+/// * Errors
+///     * [`NotebookError::PassphraseRequired`] error if archive is encrypted, but `passphrase` is `None`
+///     * [`NotebookError::WrongPassphrase`] error if `passphrase` is wrong
+///     * [`NotebookError::InvalidArchive`] error if archive can't be read
+///     * [`NotebookError::AlreadyTaken`] error if `fail_fast` is `true` and note with the same name,
+///       but other content already exists
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn import_tar(
+    path: impl AsRef<Path>,
+    passphrase: Option<&str>,
+    fail_fast: bool,
+    pool: &PgPool,
+) -> Result<BatchReport, NotebookError> {
+    let path = path.as_ref();
+    let mut data = fs::read(path)?;
+
+    if data.starts_with(ARCHIVE_MAGIC) {
+        let Some(passphrase) = passphrase else {
+            return Err(NotebookError::PassphraseRequired);
+        };
+        data = decrypt(&data, passphrase)?;
+    }
+
+    let notes = read_tar(&data)?;
+    let mut report = BatchReport::default();
+
+    if fail_fast {
+        let mut tx = pool.begin().await?;
+        for note in notes.iter() {
+            let notename = note.note_name.to_owned().unwrap_or_default();
+            match import_tar_note(note, &mut tx).await? {
+                true => report.succeeded.push(notename),
+                false => report.skipped.push(notename),
+            }
+        }
+        tx.commit().await?;
+    } else {
+        let mut conn = pool.acquire().await?;
+        for note in notes.iter() {
+            let notename = note.note_name.to_owned().unwrap_or_default();
+            match import_tar_note(note, &mut conn).await {
+                Ok(true) => report.succeeded.push(notename),
+                Ok(false) => report.skipped.push(notename),
+                Err(err) => report.failed.push((notename, err)),
+            }
+        }
+    }
+
+    event!(
+        Level::INFO,
+        "Imported notes from {}: {}",
+        path.display(),
+        report
+    );
+
+    Ok(report)
+}
+
+/// Returns `field` quoted for CSV if it contains separators, quotes or line breaks.
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Parses CSV `text` into records of fields (RFC 4180). Quoted fields can contain
+/// separators, line breaks and quotes doubled as `""`.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, NotebookError> {
+    let invalid = |line: usize, reason: &str| NotebookError::InvalidArchive {
+        reason: format!("line {line} of CSV: {reason}"),
+    };
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text
+        .strip_prefix('\u{feff}')
+        .unwrap_or(text)
+        .chars()
+        .peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => {
+                    quoted = false;
+                    if !matches!(chars.peek(), None | Some(',' | '\r' | '\n')) {
+                        return Err(invalid(line, "text after closing quote"));
+                    }
+                }
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '"' => return Err(invalid(line, "quote inside unquoted field")),
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                line += 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(invalid(line, "quoted field is not closed"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Exports all notes into CSV file `path` with columns `name` and `note`,
+/// that can be opened by spreadsheets and imported by [`import_csv`].
+///
+/// Fields with commas, quotes or line breaks (like multi-line notes) are quoted
+/// and quotes in them are doubled, as spreadsheets expect.
+/// ### Returns
+/// * Ok
+///     * Number of exported notes
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be written
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::export::{export_csv, import_csv};
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn csv_example(pool: &PgPool, backup_pool: &PgPool) -> Result<(), NotebookError> {
+///     export_csv("notes.csv", pool).await?;
+///
+///     import_csv("notes.csv", false, backup_pool).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_csv(path: impl AsRef<Path>, pool: &PgPool) -> Result<usize, NotebookError> {
+    let path = path.as_ref();
+    let notes = select_all(pool).await?;
+
+    let mut csv = String::from("name,note\r\n");
+    for note in notes.iter() {
+        csv += &format!(
+            "{},{}\r\n",
+            csv_field(&note.note_name),
+            csv_field(note.body())
+        );
+    }
+
+    fs::write(path, csv)?;
+
+    event!(
+        Level::INFO,
+        "Exported {} notes into {}",
+        notes.len(),
+        path.display()
+    );
+
+    Ok(notes.len())
+}
+
+/// Imports notes from CSV file `path`, like one created by [`export_csv`].
+///
+/// The first line of file must be header with columns `name` and `note` (in any order
+/// and case); other columns are ignored, so file can be edited in spreadsheets.
+/// Notes are imported like in [`import_archive`]: notes that already exist with the same content
+/// are skipped, and with `fail_fast` no notes are imported if any of them can't be imported.
+/// ### Returns
+/// * Ok
+///     * [`BatchReport`] about every note in file
+/// * Errors
+///     * [`NotebookError::InvalidArchive`] error if file is not valid CSV or has no
+///       `name` or `note` columns
+///     * [`NotebookError::AlreadyTaken`] error if `fail_fast` is `true` and note with the same name,
+///       but other content already exists
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn import_csv(
+    path: impl AsRef<Path>,
+    fail_fast: bool,
+    pool: &PgPool,
+) -> Result<BatchReport, NotebookError> {
+    let path = path.as_ref();
+    let mut records = parse_csv(&fs::read_to_string(path)?)?.into_iter();
+
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| NotebookError::InvalidArchive {
+                reason: format!("CSV has no `{name}` column"),
+            })
+    };
+    let (name_column, note_column) = (column("name")?, column("note")?);
+
+    let notes: Vec<(String, String)> = records
+        .filter(|record| record.iter().any(|field| !field.is_empty()))
+        .map(|mut record| {
+            record.resize(
+                record.len().max(name_column.max(note_column) + 1),
+                String::new(),
+            );
+
+            (
+                std::mem::take(&mut record[name_column]),
+                std::mem::take(&mut record[note_column]),
+            )
+        })
+        .collect();
+
+    let report = add_many(
+        notes
+            .iter()
+            .map(|(notename, note)| (notename.as_str(), Some(note.as_str()))),
+        fail_fast,
+        pool,
+    )
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Imported notes from {}: {}",
+        path.display(),
+        report
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "crypto")]
+    use crate::{attachments, commands};
+
+    #[cfg(feature = "crypto")]
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("lnotebook-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!("shopping", csv_field("shopping"));
+        assert_eq!("", csv_field(""));
+        assert_eq!("\"milk, bread\"", csv_field("milk, bread"));
+        assert_eq!("\"say \"\"hi\"\"\"", csv_field("say \"hi\""));
+        assert_eq!("\"first\nsecond\"", csv_field("first\nsecond"));
+        assert_eq!("\"first\r\nsecond\"", csv_field("first\r\nsecond"));
+    }
+
+    #[test]
+    fn quoted_fields_are_parsed_back() {
+        let fields = [
+            "plain",
+            "",
+            "milk, bread",
+            "say \"hi\"",
+            "first\r\nsecond\n",
+            "\"",
+        ];
+        let csv: String = fields
+            .iter()
+            .map(|field| format!("{},{}\r\n", csv_field("name"), csv_field(field)))
+            .collect();
+
+        let records = parse_csv(&csv).unwrap();
+
+        assert_eq!(fields.len(), records.len());
+        for (record, field) in records.iter().zip(fields) {
+            assert_eq!(&vec!["name".to_owned(), field.to_owned()], record);
+        }
+    }
+
+    #[test]
+    fn csv_of_spreadsheets_is_parsed() {
+        // BOM, `\n` line breaks, missing line break at the end and empty last field
+        let records = parse_csv("\u{feff}name,note\nshopping,\"milk\"\nempty,").unwrap();
+
+        assert_eq!(
+            vec![
+                vec!["name", "note"],
+                vec!["shopping", "milk"],
+                vec!["empty", ""],
+            ],
+            records
+        );
+    }
+
+    #[test]
+    fn malformed_csv_is_error() {
+        for csv in [
+            "name,note\r\nshopping,\"milk",
+            "name,note\r\nshopping,\"milk\"bread",
+            "name,note\r\nshopping,mi\"lk",
+        ] {
+            assert!(
+                matches!(parse_csv(csv), Err(NotebookError::InvalidArchive { .. })),
+                "{csv:?}"
+            );
+        }
+
+        let Err(NotebookError::InvalidArchive { reason }) = parse_csv("name,note\n\"a\nb\nc")
+        else {
+            panic!("unclosed quote is not an error");
+        };
+        assert_eq!("line 4 of CSV: quoted field is not closed", reason);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn encrypted_tar_is_imported_back(pool: PgPool) {
+        let path = temp_path("round-trip.tar.zst.lnb");
+        let dir = temp_path("attachments");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("receipt.txt");
+        fs::write(&file, "2 bottles").unwrap();
+
+        commands::add("shopping", "milk\nbread", &pool)
+            .await
+            .unwrap();
+        commands::add_binary("key", vec![0x00, 0x9f, 0xff], &pool)
+            .await
+            .unwrap();
+        attachments::attach("shopping", &file, &pool).await.unwrap();
+
+        assert_eq!(2, export_tar(&path, Some("secret"), &pool).await.unwrap());
+        assert!(is_archive_encrypted(&path).unwrap());
+        commands::del("shopping", &pool).await.unwrap();
+        commands::del("key", &pool).await.unwrap();
+
+        let report = import_tar(&path, Some("secret"), false, &pool)
+            .await
+            .unwrap();
+        assert!(report.is_success());
+        assert_eq!(2, report.succeeded.len());
+        fs::remove_file(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let note = commands::select_one("shopping", &pool).await.unwrap();
+        assert_eq!("milk\nbread", note.body());
+        assert_eq!(
+            vec![0x00, 0x9f, 0xff],
+            commands::get_binary("key", &pool).await.unwrap()
+        );
+        assert_eq!(
+            b"2 bottles".to_vec(),
+            attachments::select_attachment("shopping", "receipt.txt", &pool)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "crypto")]
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn encrypted_tar_needs_right_passphrase(pool: PgPool) {
+        let path = temp_path("wrong-passphrase.tar.zst.lnb");
+        commands::add("shopping", "milk", &pool).await.unwrap();
+        export_tar(&path, Some("secret"), &pool).await.unwrap();
+        commands::del("shopping", &pool).await.unwrap();
+
+        assert!(matches!(
+            import_tar(&path, None, false, &pool).await,
+            Err(NotebookError::PassphraseRequired)
+        ));
+        assert!(matches!(
+            import_tar(&path, Some("wrong"), false, &pool).await,
+            Err(NotebookError::WrongPassphrase)
+        ));
+        fs::remove_file(&path).unwrap();
+
+        // Nothing is imported from archive that can't be decrypted
+        assert!(commands::select_one("shopping", &pool).await.is_err());
+    }
+}
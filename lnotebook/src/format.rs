@@ -0,0 +1,130 @@
+//! Formatting of output for terminal: aligned tables and colors.
+//!
+//! [`table`] aligns cells of every column, and [`Style`] colors text with ANSI escape codes.
+//! Colors are used only if [`colors_enabled`] returns `true`: output goes to terminal,
+//! `NO_COLOR` enivroment variable is not set (read about it on <https://no-color.org>)
+//! and colors are not disabled with `--no-color` in CLI.
+//!
+//! Listing of notes (like `list` command of CLI and [`display_list`][crate::commands::display_list])
+//! is rendered by [`summaries_table`].
+//! ### Example
+//! ```
+//! # use lnotebook::format::*;
+//! let table = table(
+//!     &["NAME", "LINES"],
+//!     &[
+//!         vec!["shopping".to_owned(), "3".to_owned()],
+//!         vec!["todo".to_owned(), "12".to_owned()],
+//!     ],
+//!     &[Style::Name, Style::Plain],
+//!     false,
+//! );
+//!
+//! assert_eq!("NAME      LINES\nshopping  3\ntodo      12", table);
+//! ```
+
+use crate::commands::NoteSummary;
+use crate::render::{preview, updated};
+
+use std::io::IsTerminal;
+
+/// Style of text in terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Text as it is
+    Plain,
+    /// Header of table
+    Header,
+    /// Name of note
+    Name,
+    /// Less important text, like dates
+    Dim,
+}
+
+impl Style {
+    /// Returns `text` colored with the style, or `text` as it is if `colors` is `false`.
+    pub fn paint(&self, text: &str, colors: bool) -> String {
+        let code = match self {
+            _ if !colors => return text.to_owned(),
+            Style::Plain => return text.to_owned(),
+            Style::Header => "1",
+            Style::Name => "1;36",
+            Style::Dim => "2",
+        };
+
+        format!("\x1b[{code}m{text}\x1b[0m")
+    }
+}
+
+/// Returns `true` if output can be colored.
+///
+/// Read when it's `true` [here][crate::format].
+pub fn colors_enabled(no_color: bool) -> bool {
+    !no_color
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::io::stdout().is_terminal()
+}
+
+/// Returns table with `header` and `rows`, where cells of every column are aligned.
+///
+/// Cells of column are colored with style from `styles` with the same index, and header
+/// is colored with [`Style::Header`] (if `colors` is `true`). Spaces at the end of rows
+/// are removed.
+pub fn table(header: &[&str], rows: &[Vec<String>], styles: &[Style], colors: bool) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // Cells are padded before coloring, because escape codes take no place in terminal
+    let line = |cells: &mut dyn Iterator<Item = (&str, Style)>| {
+        let mut line = String::new();
+        let mut spaces = 0;
+
+        for ((cell, style), width) in cells.zip(&widths) {
+            if !cell.is_empty() {
+                line += &" ".repeat(spaces);
+                line += &style.paint(cell, colors);
+                spaces = 0;
+            }
+            spaces += width - cell.chars().count() + 2;
+        }
+
+        line.trim_end().to_owned()
+    };
+
+    let mut lines = vec![line(&mut header.iter().map(|cell| (*cell, Style::Header)))];
+    lines.extend(rows.iter().map(|row| {
+        line(
+            &mut row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| (cell.as_str(), *styles.get(i).unwrap_or(&Style::Plain))),
+        )
+    }));
+
+    lines.join("\n")
+}
+
+/// Returns table of notes with their names, previews and time of the last change.
+///
+/// Previews of notes that have more content end with `…`. If `notes` is empty, returns message that notebook is empty.
+pub fn summaries_table(notes: &[NoteSummary], colors: bool) -> String {
+    if notes.is_empty() {
+        return "Notebook is empty".to_owned();
+    }
+
+    let rows: Vec<_> = notes
+        .iter()
+        .map(|note| vec![note.note_name.to_owned(), preview(note), updated(note)])
+        .collect();
+
+    table(
+        &["NAME", "PREVIEW", "UPDATED"],
+        &rows,
+        &[Style::Name, Style::Plain, Style::Dim],
+        colors,
+    )
+}
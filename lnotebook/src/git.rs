@@ -0,0 +1,188 @@
+//! Committing notes into git repository (only with `git` feature).
+//!
+//! Directory of repository is set in `LNOTEBOOK_GIT_DIR` enivroment variable
+//! (read more in [`config` module][crate::config]). After note is changed by
+//! [`Notebook`][crate::notebook::Notebook] (so by CLI too), notes are exported into the
+//! repository as Markdown files (one `<notename>.md` per note, rendered like
+//! [`export_markdown`][crate::export::export_markdown] does) and changed files are committed
+//! with message `<command> <notename>`. Files of deleted notes are removed from the repository.
+//!
+//! This gives notes free history that can be read with `git log`, and the repository can be
+//! synchronized with remote by `git push` and `git pull` as any other repository.
+//! Repository is created if it doesn't exist. Commits are made by user from git configuration,
+//! or by `lnotebook` if it's not configured.
+//!
+//! Repository that can't be committed is logged and doesn't make the command fail.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::git::GitRepo;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn git_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let repo = GitRepo::new("notes_repo");
+//!
+//!     if let Some(commit) = repo.commit_notes("snapshot", pool).await? {
+//!         println!("Notes are committed in {commit}");
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::select_all;
+use crate::errors::NotebookError;
+use crate::export::{file_stem, Template, DEFAULT_MARKDOWN_TEMPLATE};
+
+use git2::{IndexAddOption, Repository, Signature};
+use sqlx::PgPool;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{event, Level};
+
+/// Name and email of commits if user is not set in git configuration.
+const DEFAULT_AUTHOR: (&str, &str) = ("lnotebook", "lnotebook@localhost");
+
+fn git_error(err: impl std::fmt::Display) -> NotebookError {
+    NotebookError::Git {
+        reason: err.to_string(),
+    }
+}
+
+/// Git repository that notes are committed into.
+///
+/// Read more [here][crate::git].
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    dir: PathBuf,
+}
+
+impl GitRepo {
+    /// Creates repository in directory `dir`.
+    ///
+    /// Nothing is created until notes are committed.
+    pub fn new(dir: impl Into<PathBuf>) -> GitRepo {
+        GitRepo { dir: dir.into() }
+    }
+
+    /// Returns directory of repository.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Opens repository, or creates it if it doesn't exist.
+    fn open(&self) -> Result<Repository, NotebookError> {
+        match Repository::open(&self.dir) {
+            Ok(repo) => Ok(repo),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                event!(
+                    Level::INFO,
+                    "Create git repository in {}",
+                    self.dir.display()
+                );
+
+                Repository::init(&self.dir).map_err(git_error)
+            }
+            Err(err) => Err(git_error(err)),
+        }
+    }
+
+    /// Exports all notes into repository and commits them with `message`.
+    ///
+    /// Every `.md` file in repository that is not a file of existing note is treated as file
+    /// of deleted note and removed. Other files (like `.gitignore`) are left as they are.
+    /// ### Returns
+    /// * Ok
+    ///     * ID of new commit, or `None` if notes didn't change since the last commit
+    /// * Errors
+    ///     * [`NotebookError::Git`] error if repository can't be opened or committed
+    ///     * [`NotebookError::Io`] error if files can't be written
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+    pub async fn commit_notes(
+        &self,
+        message: &str,
+        pool: &PgPool,
+    ) -> Result<Option<String>, NotebookError> {
+        let notes = select_all(pool).await?;
+        let template = Template::parse(DEFAULT_MARKDOWN_TEMPLATE)?;
+
+        let repo = self.open()?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| git_error("repository is bare"))?
+            .to_path_buf();
+
+        let mut files = HashSet::new();
+        for note in notes.iter() {
+            let file_name = format!("{}.md", file_stem(&note.note_name));
+            let path = workdir.join(&file_name);
+            let content = template.render(note);
+
+            if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                fs::write(&path, content)?;
+            }
+            files.insert(file_name);
+        }
+
+        for entry in fs::read_dir(&workdir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+
+            if file_name.ends_with(".md")
+                && entry.file_type()?.is_file()
+                && !files.contains(&file_name)
+            {
+                fs::remove_file(entry.path())?;
+            }
+        }
+
+        let commit = commit(&repo, message).map_err(git_error)?;
+
+        match &commit {
+            Some(id) => event!(Level::DEBUG, "Committed notes in {}: {}", id, message),
+            None => event!(Level::DEBUG, "Notes didn't change, nothing to commit"),
+        }
+
+        Ok(commit)
+    }
+}
+
+/// Stages all files in repository and commits them, if they changed since `HEAD`.
+fn commit(repo: &Repository, message: &str) -> Result<Option<String>, git2::Error> {
+    let mut index = repo.index()?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+    index.update_all(["*"], None)?;
+    index.write()?;
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+        Err(err) => return Err(err),
+    };
+
+    if parent
+        .as_ref()
+        .is_some_and(|parent| parent.tree_id() == tree.id())
+    {
+        return Ok(None);
+    }
+
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now(DEFAULT_AUTHOR.0, DEFAULT_AUTHOR.1))?;
+    let parents: Vec<_> = parent.iter().collect();
+    let id = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+
+    Ok(Some(id.to_string()))
+}
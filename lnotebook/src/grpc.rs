@@ -0,0 +1,225 @@
+//! gRPC service of the notebook.
+//!
+//! Service is described in [`notebook.proto`](https://github.com/Nnenty/lnotebook/tree/master/lnotebook/proto/notebook.proto)
+//! and allows to get, add, update and delete notes, and also to stream all notes in notebook.
+//! Code generated from `notebook.proto` (including the client) is in [`proto`] module.
+//!
+//! Requests that change notes are executed by [`Notebook`][crate::notebook::Notebook] like
+//! commands of CLI, so they are recorded in audit log (read more in [`audit` module][crate::audit])
+//! and sent to webhooks and git repository of the notebook given to [`serve_notebook`].
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::{connect, get_db_url, ConnectOptions, NotebookError};
+//! async fn grpc_example() -> Result<(), NotebookError> {
+//!     let db = connect(&get_db_url().await?, ConnectOptions::default()).await?;
+//!
+//!     lnotebook::grpc::serve("127.0.0.1:50051", db).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::Note;
+use crate::compression::StoredNote;
+use crate::errors::NotebookError;
+use crate::notebook::{self, CommandRequest, CommandResponse};
+use crate::sql::query_as;
+
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::{event, Level};
+
+/// Code generated from `notebook.proto`.
+pub mod proto {
+    tonic::include_proto!("notebook");
+}
+
+use proto::notebook_server::{Notebook, NotebookServer};
+
+impl From<Note> for proto::Note {
+    fn from(note: Note) -> Self {
+        proto::Note {
+            id: note.id,
+            note: note.note.unwrap_or_default(),
+            note_name: note.note_name,
+        }
+    }
+}
+
+impl From<NotebookError> for Status {
+    fn from(err: NotebookError) -> Self {
+        match err {
+            NotebookError::AlreadyTaken { .. } => Status::already_exists(err.to_string()),
+            NotebookError::Conflict { .. } => Status::aborted(err.to_string()),
+            NotebookError::Locked { .. } | NotebookError::BinaryNote { .. } => {
+                Status::failed_precondition(err.to_string())
+            }
+            NotebookError::InvalidLineNumber { .. }
+            | NotebookError::InvalidTemplate { .. }
+            | NotebookError::InvalidDate { .. }
+            | NotebookError::InvalidDateFormat { .. }
+            | NotebookError::InvalidPattern { .. }
+            | NotebookError::InvalidNoteName { .. }
+            | NotebookError::NoteTooLarge { .. } => Status::invalid_argument(err.to_string()),
+            NotebookError::Sqlx(sqlx::Error::RowNotFound) => Status::not_found(err.to_string()),
+            _ => {
+                event!(Level::ERROR, "Request failed: {}", err);
+
+                Status::internal(err.to_string())
+            }
+        }
+    }
+}
+
+/// Implementation of the gRPC `Notebook` service that stores notes in database.
+pub struct NotebookService {
+    notebook: notebook::Notebook,
+}
+
+impl NotebookService {
+    /// Creates service that uses the given database.
+    pub fn new(pool: PgPool) -> NotebookService {
+        NotebookService::with_notebook(notebook::Notebook::new(pool))
+    }
+
+    /// Creates service that executes requests by `notebook`, with its webhooks and git repository.
+    pub fn with_notebook(notebook: notebook::Notebook) -> NotebookService {
+        NotebookService { notebook }
+    }
+
+    /// Executes request that returns note.
+    async fn note(&self, request: CommandRequest) -> Result<proto::Note, Status> {
+        match self.notebook.execute(request).await? {
+            CommandResponse::Note(note) => Ok(note.into()),
+            _ => unreachable!("request returns note"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Notebook for NotebookService {
+    async fn get_note(
+        &self,
+        request: Request<proto::GetNoteRequest>,
+    ) -> Result<Response<proto::Note>, Status> {
+        let req = request.into_inner();
+        let request = CommandRequest::DisplayNote {
+            notename: req.note_name,
+            lines: None,
+        };
+
+        Ok(Response::new(self.note(request).await?))
+    }
+
+    async fn add_note(
+        &self,
+        request: Request<proto::AddNoteRequest>,
+    ) -> Result<Response<proto::Note>, Status> {
+        let req = request.into_inner();
+        let request = CommandRequest::AddNote {
+            notename: req.note_name,
+            note: req.note,
+        };
+
+        Ok(Response::new(self.note(request).await?))
+    }
+
+    async fn upd_note(
+        &self,
+        request: Request<proto::UpdNoteRequest>,
+    ) -> Result<Response<proto::Note>, Status> {
+        let req = request.into_inner();
+        let request = CommandRequest::UpdNote {
+            notename: req.note_name,
+            note: req.note,
+        };
+
+        Ok(Response::new(self.note(request).await?))
+    }
+
+    async fn del_note(
+        &self,
+        request: Request<proto::DelNoteRequest>,
+    ) -> Result<Response<proto::DelNoteResponse>, Status> {
+        let req = request.into_inner();
+        self.notebook
+            .execute(CommandRequest::DelNote {
+                notename: req.note_name,
+            })
+            .await?;
+
+        Ok(Response::new(proto::DelNoteResponse {}))
+    }
+
+    type ListNotesStream = ReceiverStream<Result<proto::Note, Status>>;
+
+    async fn list_notes(
+        &self,
+        _request: Request<proto::ListNotesRequest>,
+    ) -> Result<Response<Self::ListNotesStream>, Status> {
+        let pool = self.notebook.pool().clone();
+        let (tx, rx) = mpsc::channel(32);
+
+        // Notes are sent as they are fetched, so all notebook isn't loaded into memory
+        tokio::spawn(async move {
+            let mut rows = query_as!(
+                StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+                "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE passphrase_hash IS NULL
+ORDER BY id
+                "
+            )
+            .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                let note = row
+                    .map_err(NotebookError::Sqlx)
+                    .and_then(StoredNote::into_note)
+                    .map(|row| proto::Note {
+                        id: row.id,
+                        note_name: row.note_name,
+                        note: row.note.unwrap_or_default(),
+                    })
+                    .map_err(Status::from);
+
+                if tx.send(note).await.is_err() {
+                    // Client closed the stream
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Starts gRPC service on the given address and serves notebook until error occurs.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::InvalidAddress`] error if `addr` is not a socket address
+///     * [`NotebookError::Transport`] error if server can't be started
+pub async fn serve(addr: &str, pool: PgPool) -> Result<(), NotebookError> {
+    serve_notebook(addr, notebook::Notebook::new(pool)).await
+}
+
+/// Starts gRPC service on the given address and serves `notebook` until error occurs, see [`serve`].
+pub async fn serve_notebook(addr: &str, notebook: notebook::Notebook) -> Result<(), NotebookError> {
+    let socket_addr = addr.parse().map_err(|_| NotebookError::InvalidAddress {
+        addr: addr.to_owned(),
+    })?;
+
+    event!(Level::INFO, "Serving gRPC notebook on {}", addr);
+
+    Server::builder()
+        .add_service(NotebookServer::new(NotebookService::with_notebook(
+            notebook,
+        )))
+        .serve(socket_addr)
+        .await?;
+
+    Ok(())
+}
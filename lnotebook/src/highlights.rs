@@ -0,0 +1,233 @@
+//! Highlighted lines of notes.
+//!
+//! [`highlight`] captures lines of note, like key sentences of a long note, as excerpt that can
+//! be reviewed later with [`highlights`]. Excerpt is copied when it's highlighted, so it stays
+//! the same if the note is changed later, and it's deleted with the note.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::highlights::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn highlights_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("essay", "Intro\nThe key idea.\nIts proof.\nOutro", pool).await?;
+//!
+//!     let highlight = highlight("essay", 2, 3, pool).await?;
+//!     assert_eq!("The key idea.\nIts proof.", highlight.excerpt);
+//!
+//!     for highlight in highlights(Some("essay"), pool).await? {
+//!         println!("{}", highlight);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::compression;
+use crate::errors::NotebookError;
+use crate::protect;
+use crate::sql::query_as;
+use crate::users::{self, Access};
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{self, PgPool};
+use std::fmt;
+use tracing::{event, Level};
+
+/// This is a `struct` that containing excerpt of note highlighted by [`highlight`].
+pub struct Highlight {
+    pub id: i32,
+    pub note_name: String,
+    /// Number of the first highlighted line, lines are numbered from 1
+    pub start_line: u32,
+    /// Number of the last highlighted line
+    pub end_line: u32,
+    /// Highlighted lines as they were when they were highlighted
+    pub excerpt: String,
+    pub created_at: DateTime<Local>,
+}
+
+impl fmt::Display for Highlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}  {}",
+            self.note_name,
+            self.start_line,
+            self.end_line,
+            self.created_at.format("%Y-%m-%d %H:%M")
+        )?;
+        for line in self.excerpt.lines() {
+            write!(f, "\n> {line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Highlights lines of note `notename` from `start_line` to `end_line`.
+///
+/// Lines are numbered from 1. Lines after the end of note are not highlighted, so `u32::MAX`
+/// can be used as `end_line` to highlight everything from `start_line` to the end of note.
+/// ### Returns
+/// * Ok
+///     * [`Highlight`] that was added
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::InvalidLineNumber`] error if `start_line` is 0 or after the end of note,
+///       or `end_line` is less than `start_line`
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn highlight(
+    notename: &str,
+    start_line: u32,
+    end_line: u32,
+    pool: &PgPool,
+) -> Result<Highlight, NotebookError> {
+    let start = match i32::try_from(start_line) {
+        Ok(line) if line > 0 => line,
+        _ => {
+            return Err(NotebookError::InvalidLineNumber {
+                line_no: start_line,
+            })
+        }
+    };
+    if end_line < start_line {
+        return Err(NotebookError::InvalidLineNumber { line_no: end_line });
+    }
+    let end = i32::try_from(end_line).unwrap_or(i32::MAX);
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let mut note = query_as!(
+        { id: i32, lines: i32, excerpt: String, packed: Option<Vec<u8>> },
+        r#"
+SELECT
+    id,
+    cardinality(string_to_array(COALESCE(note, ''), E'\n')) AS "lines!",
+    array_to_string(
+        (string_to_array(COALESCE(note, ''), E'\n'))[$1::INT4:$2::INT4],
+        E'\n'
+    ) AS "excerpt!",
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $3
+        "#,
+        start,
+        end,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // Compressed note can't be split by the database
+    if let Some(text) = compression::content(notename, None, note.packed.take())? {
+        let lines: Vec<_> = text.split('\n').collect();
+        note.lines = lines.len() as i32;
+        note.excerpt = lines
+            .iter()
+            .skip(start as usize - 1)
+            .take((end - start) as usize + 1)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    if start > note.lines {
+        return Err(NotebookError::InvalidLineNumber {
+            line_no: start_line,
+        });
+    }
+    let end = end.min(note.lines);
+
+    let row = query_as!(
+        { id: i32, created_at: DateTime<Utc> },
+        "
+INSERT INTO highlights (note_id, start_line, end_line, excerpt)
+VALUES ($1, $2, $3, $4)
+RETURNING id, created_at
+        ",
+        note.id,
+        start,
+        end,
+        note.excerpt
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Highlight lines {}-{} of note `{}`",
+        start,
+        end,
+        notename
+    );
+
+    Ok(Highlight {
+        id: row.id,
+        note_name: notename.to_owned(),
+        start_line: start as u32,
+        end_line: end as u32,
+        excerpt: note.excerpt,
+        created_at: row.created_at.with_timezone(&Local),
+    })
+}
+
+/// Returns highlights of note `notename`, or of all notes if it's `None`, ordered by notename
+/// and number of line. Highlights of all notes skip protected notes (read about them
+/// in [`protect` module][crate::protect]).
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`Highlight`]
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn highlights(
+    notename: Option<&str>,
+    pool: &PgPool,
+) -> Result<Vec<Highlight>, NotebookError> {
+    if let Some(notename) = notename {
+        users::check_access(notename, Access::Read, pool).await?;
+        protect::check_passphrase(notename, pool).await?;
+    }
+
+    let rows = query_as!(
+        {
+            id: i32,
+            note_name: String,
+            start_line: i32,
+            end_line: i32,
+            excerpt: String,
+            created_at: DateTime<Utc>,
+        },
+        "
+SELECT highlights.id, note_name, start_line, end_line, excerpt, highlights.created_at
+FROM highlights
+JOIN notebook ON notebook.id = highlights.note_id
+WHERE ($1::TEXT IS NULL AND passphrase_hash IS NULL AND can_access_note(notebook.id, owner, $2, false))
+    OR note_name = $1
+ORDER BY note_name, start_line, end_line, highlights.id
+        ",
+        notename,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Highlight {
+            id: row.id,
+            note_name: row.note_name,
+            start_line: row.start_line as u32,
+            end_line: row.end_line as u32,
+            excerpt: row.excerpt,
+            created_at: row.created_at.with_timezone(&Local),
+        })
+        .collect())
+}
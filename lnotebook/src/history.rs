@@ -0,0 +1,577 @@
+//! History of notes.
+//!
+//! Every time note is added or its content is changed, database saves a new revision of note.
+//! Content of revisions is stored once for every distinct content: it's addressed by its
+//! SHA-256 hash, so saving the same content again (or the same content in other notes)
+//! doesn't take any more space.
+//!
+//! To save space further, [`compact_history`] (also run by `compact` CLI command) stores
+//! content of revisions as deltas against the previous revision of the same note:
+//! only the changed lines are stored. Contents that are still larger than the threshold
+//! (like large pasted logs) are compressed with zstd and decompressed when revisions are read.
+//! [`history_stats`] shows how much space is saved. The current content of large notes is
+//! compressed when it's written (read more in [`compression` module][crate::compression]).
+
+use crate::compression::{compress, decompress};
+use crate::diff;
+use crate::errors::NotebookError;
+use crate::protect;
+use crate::sql::{query, query_as};
+use crate::users::{self, Access};
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{self, PgConnection, PgPool};
+use std::{collections::HashMap, fmt};
+use tracing::{event, Level};
+
+/// Maximum number of deltas that have to be applied to get content of the previous revision,
+/// so content of revision can be stored as delta against it.
+///
+/// Longer chains make reading old revisions slower, so after that content is stored as is.
+const MAX_DELTA_CHAIN: usize = 16;
+
+/// This is a `struct` that containing information about revision of note.
+pub struct Revision {
+    pub id: i32,
+    /// SHA-256 hash of content of note in this revision
+    pub body_hash: String,
+    /// Size of content of note in bytes
+    pub size: i64,
+    pub created_at: DateTime<Local>,
+}
+
+/// This is a `struct` that containing how much space history takes.
+pub struct HistoryStats {
+    /// Number of revisions of all notes
+    pub revisions: i64,
+    /// Number of stored distinct contents
+    pub bodies: i64,
+    /// Number of stored contents that are compressed
+    pub compressed: i64,
+    /// Size of content of all revisions in bytes, as if every revision was stored as is
+    pub logical_bytes: i64,
+    /// Size of stored content in bytes
+    pub stored_bytes: i64,
+}
+
+impl fmt::Display for HistoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let saved = if self.logical_bytes > 0 {
+            100.0 - self.stored_bytes as f64 * 100.0 / self.logical_bytes as f64
+        } else {
+            0.0
+        };
+
+        write!(
+            f,
+            "{} revisions, {} stored contents ({} compressed), {} of {} bytes stored ({:.1}% saved)",
+            self.revisions,
+            self.bodies,
+            self.compressed,
+            self.stored_bytes,
+            self.logical_bytes,
+            saved
+        )
+    }
+}
+
+/// This is a `struct` that containing result of [`compact_history`].
+#[derive(Debug, Default)]
+pub struct HistoryCompaction {
+    /// Number of contents that are now stored as deltas
+    pub deltas: u64,
+    /// Number of contents that are now compressed
+    pub compressed: u64,
+    /// Number of contents that were removed because no revision uses them
+    pub removed: u64,
+}
+
+/// Returns delta that turns `base` into `body`.
+///
+/// Delta stores number of same lines at the beginning and at the end of both contents,
+/// and lines between them from `body`: `<prefix>:<suffix>:<number of lines>:<lines>`.
+fn make_delta(base: &str, body: &str) -> String {
+    let base: Vec<&str> = base.split('\n').collect();
+    let body: Vec<&str> = body.split('\n').collect();
+
+    let (prefix, suffix) = diff::common_lines(&base, &body);
+    let middle = &body[prefix..body.len() - suffix];
+
+    format!(
+        "{}:{}:{}:{}",
+        prefix,
+        suffix,
+        middle.len(),
+        middle.join("\n")
+    )
+}
+
+/// Applies delta made by [`make_delta`] to `base`.
+fn apply_delta(base: &str, delta: &str) -> Option<String> {
+    let mut parts = delta.splitn(4, ':');
+    let prefix: usize = parts.next()?.parse().ok()?;
+    let suffix: usize = parts.next()?.parse().ok()?;
+    let len: usize = parts.next()?.parse().ok()?;
+    let middle = parts.next()?;
+
+    let base: Vec<&str> = base.split('\n').collect();
+    if prefix + suffix > base.len() {
+        return None;
+    }
+
+    let mut lines = base[..prefix].to_vec();
+    if len > 0 {
+        lines.extend(middle.split('\n'));
+    }
+    lines.extend(&base[base.len() - suffix..]);
+
+    Some(lines.join("\n"))
+}
+
+/// Returns content stored under `hash`, decompressing it and applying all deltas.
+async fn load_body(hash: &str, conn: &mut PgConnection) -> Result<String, NotebookError> {
+    let mut chain = Vec::new();
+    let mut next = Some(hash.to_owned());
+
+    while let Some(hash) = next {
+        let row = query_as!(
+            { base_hash: Option<String>, body: String, body_zstd: Option<Vec<u8>> },
+            "
+SELECT base_hash, body, body_zstd
+FROM note_bodies
+WHERE hash = $1
+            ",
+            hash
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        next = row.base_hash;
+        chain.push(match row.body_zstd {
+            Some(body) => decompress(&format!("Revision content `{hash}`"), &body)?,
+            None => row.body,
+        });
+    }
+
+    let mut body = chain.pop().unwrap_or_default();
+    while let Some(delta) = chain.pop() {
+        body = apply_delta(&body, &delta).ok_or_else(|| {
+            NotebookError::Sqlx(sqlx::Error::Decode(
+                format!("damaged delta of revision content `{hash}`").into(),
+            ))
+        })?;
+    }
+
+    Ok(body)
+}
+
+/// Returns all revisions of the requested note, from the oldest.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`Revision`]
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::history::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn history_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "milk", pool).await?;
+///     append("shopping", "\nbread", pool).await?;
+///
+///     let revisions = history("shopping", pool).await?;
+///
+///     assert_eq!(2, revisions.len());
+///     assert_eq!("milk", select_revision(revisions[0].id, pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn history(notename: &str, pool: &PgPool) -> Result<Vec<Revision>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let rows = query_as!(
+        { id: i32, body_hash: String, size: i64, created_at: DateTime<Utc> },
+        "
+SELECT note_revisions.id, body_hash, size, created_at
+FROM note_revisions
+JOIN notebook ON notebook.id = note_revisions.note_id
+JOIN note_bodies ON note_bodies.hash = note_revisions.body_hash
+WHERE notebook.note_name = $1
+ORDER BY note_revisions.id
+        ",
+        notename
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Revision {
+            id: row.id,
+            body_hash: row.body_hash,
+            size: row.size,
+            created_at: row.created_at.with_timezone(&Local),
+        })
+        .collect())
+}
+
+/// Returns content of note in the requested revision.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_revision(revision_id: i32, pool: &PgPool) -> Result<String, NotebookError> {
+    let mut conn = pool.acquire().await?;
+
+    let row = query_as!(
+        { body_hash: String, note_name: String },
+        "
+SELECT body_hash, note_name
+FROM note_revisions
+JOIN notebook ON notebook.id = note_revisions.note_id
+WHERE note_revisions.id = $1
+        ",
+        revision_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    users::check_access(&row.note_name, Access::Read, &mut *conn).await?;
+    protect::check_passphrase(&row.note_name, &mut *conn).await?;
+
+    load_body(&row.body_hash, &mut conn).await
+}
+
+/// Returns content of note `notename` in the requested revision.
+///
+/// Unlike [`select_revision`], revision must be a revision of `notename`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't have such revision
+pub async fn select_note_revision(
+    notename: &str,
+    revision_id: i32,
+    pool: &PgPool,
+) -> Result<String, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+    let mut conn = pool.acquire().await?;
+
+    let row = query_as!(
+        { body_hash: String },
+        "
+SELECT body_hash
+FROM note_revisions
+JOIN notebook ON notebook.id = note_revisions.note_id
+WHERE note_revisions.id = $1 AND notebook.note_name = $2
+        ",
+        revision_id,
+        notename
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    load_body(&row.body_hash, &mut conn).await
+}
+
+/// Returns how much space history takes.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn history_stats(pool: &PgPool) -> Result<HistoryStats, NotebookError> {
+    let row = query_as!(
+        { revisions: i64, bodies: i64, compressed: i64, logical_bytes: i64, stored_bytes: i64 },
+        r#"
+SELECT
+    (SELECT COUNT(*) FROM note_revisions) AS "revisions!",
+    (SELECT COUNT(*) FROM note_bodies) AS "bodies!",
+    (SELECT COUNT(*) FROM note_bodies WHERE compressed) AS "compressed!",
+    (
+        SELECT COALESCE(SUM(size), 0)
+        FROM note_revisions
+        JOIN note_bodies ON note_bodies.hash = note_revisions.body_hash
+    )::INT8 AS "logical_bytes!",
+    (
+        SELECT COALESCE(SUM(octet_length(body) + COALESCE(octet_length(body_zstd), 0)), 0)
+        FROM note_bodies
+    )::INT8 AS "stored_bytes!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(HistoryStats {
+        revisions: row.revisions,
+        bodies: row.bodies,
+        compressed: row.compressed,
+        logical_bytes: row.logical_bytes,
+        stored_bytes: row.stored_bytes,
+    })
+}
+
+/// Stores content of revisions as deltas against the previous revision of the same note,
+/// compresses contents that are still larger than `compress_threshold` bytes and removes
+/// contents that no revision uses.
+///
+/// Content is stored as delta or compressed only if it becomes smaller. Contents are not
+/// compressed if `compress_threshold` is `None` or the notebook is built without `compression`
+/// feature. Everything is done in one transaction.
+/// ### Returns
+/// * Ok
+///     * [`HistoryCompaction`]
+/// * Errors
+///     * [`NotebookError::Io`][NotebookError] error if content can't be compressed
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::compression::DEFAULT_COMPRESS_THRESHOLD;
+/// # use lnotebook::history::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn compact_history_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     compact_history(Some(DEFAULT_COMPRESS_THRESHOLD), pool).await?;
+///
+///     println!("{}", history_stats(pool).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn compact_history(
+    compress_threshold: Option<usize>,
+    pool: &PgPool,
+) -> Result<HistoryCompaction, NotebookError> {
+    let mut tx = pool.begin().await?;
+    let mut res = HistoryCompaction::default();
+
+    query!("LOCK TABLE note_bodies IN EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+
+    // Base of every content stored as delta
+    let mut bases: HashMap<String, Option<String>> = query_as!(
+        { hash: String, base_hash: Option<String> },
+        "
+SELECT hash, base_hash
+FROM note_bodies
+        "
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|row| (row.hash, row.base_hash))
+    .collect();
+
+    let revisions = query_as!(
+        { note_id: i32, body_hash: String },
+        "
+SELECT note_id, body_hash
+FROM note_revisions
+ORDER BY note_id, id
+        "
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for pair in revisions.windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+        if prev.note_id != cur.note_id || prev.body_hash == cur.body_hash {
+            continue;
+        }
+        if !matches!(bases.get(&cur.body_hash), Some(None)) {
+            continue;
+        }
+
+        // Content can't be based on itself and chain of deltas shouldn't be too long
+        let mut depth = 0;
+        let mut next = Some(&prev.body_hash);
+        while let Some(hash) = next {
+            if hash == &cur.body_hash || depth > MAX_DELTA_CHAIN {
+                break;
+            }
+            depth += 1;
+            next = bases.get(hash).and_then(Option::as_ref);
+        }
+        if next.is_some() {
+            continue;
+        }
+
+        let base = load_body(&prev.body_hash, &mut tx).await?;
+        let body = load_body(&cur.body_hash, &mut tx).await?;
+        let delta = make_delta(&base, &body);
+        if delta.len() >= body.len() {
+            continue;
+        }
+
+        query!(
+            "
+UPDATE note_bodies
+SET base_hash = $1, body = $2, compressed = false, body_zstd = NULL
+WHERE hash = $3
+            ",
+            prev.body_hash,
+            delta,
+            cur.body_hash
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        bases.insert(cur.body_hash.to_owned(), Some(prev.body_hash.to_owned()));
+        res.deltas += 1;
+    }
+
+    if let Some(threshold) = compress_threshold.filter(|_| cfg!(feature = "compression")) {
+        let bodies = query_as!(
+            { hash: String, body: String },
+            "
+SELECT hash, body
+FROM note_bodies
+WHERE NOT compressed AND octet_length(body) >= $1
+            ",
+            threshold as i64
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for row in bodies {
+            let compressed = compress(&row.body)?;
+            if compressed.len() >= row.body.len() {
+                continue;
+            }
+
+            query!(
+                "
+UPDATE note_bodies
+SET body = '', compressed = true, body_zstd = $1
+WHERE hash = $2
+                ",
+                compressed,
+                row.hash
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            res.compressed += 1;
+        }
+    }
+
+    // Contents of deleted notes, unless other contents are based on them.
+    // Removing content of delta can free its base, so it's repeated until nothing is removed
+    loop {
+        let removed = query!(
+            "
+DELETE FROM note_bodies
+WHERE NOT EXISTS (SELECT 1 FROM note_revisions WHERE body_hash = note_bodies.hash)
+    AND NOT EXISTS (SELECT 1 FROM note_bodies AS other WHERE other.base_hash = note_bodies.hash)
+            "
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if removed == 0 {
+            break;
+        }
+        res.removed += removed;
+    }
+
+    tx.commit().await?;
+
+    event!(
+        Level::INFO,
+        "Compacted history: {} contents stored as deltas, {} compressed, {} removed",
+        res.deltas,
+        res.compressed,
+        res.removed
+    );
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(base: &str, body: &str) {
+        let delta = make_delta(base, body);
+
+        assert_eq!(
+            Some(body),
+            apply_delta(base, &delta).as_deref(),
+            "delta `{delta}`"
+        );
+    }
+
+    #[test]
+    fn delta_of_empty_bodies() {
+        round_trip("", "");
+        round_trip("", "first\nsecond");
+        round_trip("first\nsecond", "");
+    }
+
+    #[test]
+    fn delta_keeps_trailing_line_breaks() {
+        round_trip("first\nsecond", "first\nsecond\n");
+        round_trip("first\nsecond\n", "first\nsecond");
+        round_trip("first\n", "first\n\n");
+        round_trip("\n", "");
+    }
+
+    #[test]
+    fn delta_with_only_prefix_changed() {
+        round_trip("old\nsame\nsame", "new\nnewer\nsame\nsame");
+        round_trip("old\nsame", "same");
+    }
+
+    #[test]
+    fn delta_with_only_suffix_changed() {
+        round_trip("same\nsame\nold", "same\nsame\nnew\nnewer");
+        round_trip("same\nold", "same");
+    }
+
+    #[test]
+    fn delta_of_lines_with_colons() {
+        round_trip("key: 1\n1:2:3:4", "key: 2\n1:2:3:4");
+        round_trip("a", "1:0:1:b\n::");
+    }
+
+    #[test]
+    fn delta_of_repeated_lines() {
+        round_trip("a\na", "a");
+        round_trip("a", "a\na\na");
+        round_trip("a\nb\na", "a\na");
+    }
+
+    #[test]
+    fn delta_stores_only_changed_lines() {
+        assert_eq!(
+            "1:1:1:new",
+            make_delta("first\nold\nlast", "first\nnew\nlast")
+        );
+        assert_eq!(
+            "2:0:0:",
+            make_delta("first\nsecond\nthird", "first\nsecond")
+        );
+    }
+
+    #[test]
+    fn damaged_delta_is_not_applied() {
+        assert_eq!(None, apply_delta("first", "2:1:0:"));
+        assert_eq!(None, apply_delta("first", "x:0:0:"));
+        assert_eq!(None, apply_delta("first", "0:0"));
+    }
+}
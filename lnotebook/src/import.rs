@@ -0,0 +1,591 @@
+//! Importing notes from other note apps (only with `export` feature).
+//!
+//! [`import_from`] reads notes from file exported by other app in one of [`ImportFormat`]s:
+//! * [`ImportFormat::Joplin`] - JEX archive created by `File > Export all > JEX` in Joplin.
+//!   Only notes are imported, notebooks, tags and resources (attachments) are skipped
+//! * [`ImportFormat::Enex`] - ENEX file created by `Export notes` in Evernote. Content of notes
+//!   is converted from HTML into plain text: paragraphs and line breaks are kept, lists
+//!   become `- ` items and checkboxes become `[ ]` or `[x]`. Attached files are skipped
+//! * [`ImportFormat::StandardNotes`] - decrypted backup created by `Download backup` in
+//!   Standard Notes. Encrypted backups can't be imported
+//!
+//! Title of note becomes its notename. Characters that can't be used in notenames (read about
+//! them in [`notename` module][crate::notename]) are replaced with `_`, notes without title are
+//! named `untitled`, and notes with the same title get ` (2)`, ` (3)` and so on after it.
+//! Notes are added like in [`add_many`], so notes that already exist with the same content
+//! are skipped.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::import::{import_from, ImportFormat};
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn import_from_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let report = import_from(ImportFormat::Enex, "evernote.enex", false, pool).await?;
+//!     println!("{report}");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::batch::{add_many, BatchReport};
+use crate::errors::NotebookError;
+use crate::notename::{FORBIDDEN_CHARS, MAX_LEN};
+
+use serde_json::Value;
+use sqlx::PgPool;
+use std::{collections::HashMap, fs, io::Read, path::Path};
+use tracing::{event, Level};
+
+/// Name of notes without title.
+const UNTITLED: &str = "untitled";
+
+/// Format of notes exported by other note app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ImportFormat {
+    /// JEX archive of Joplin
+    Joplin,
+    /// ENEX file of Evernote
+    Enex,
+    /// Decrypted JSON backup of Standard Notes
+    #[cfg_attr(
+        feature = "cli",
+        value(name = "standardnotes", alias = "standard-notes")
+    )]
+    StandardNotes,
+}
+
+fn invalid(format: ImportFormat, reason: impl std::fmt::Display) -> NotebookError {
+    let app = match format {
+        ImportFormat::Joplin => "Joplin",
+        ImportFormat::Enex => "Evernote",
+        ImportFormat::StandardNotes => "Standard Notes",
+    };
+
+    NotebookError::InvalidArchive {
+        reason: format!("not a valid export of {app}: {reason}"),
+    }
+}
+
+/// Returns notes of Joplin JEX archive as pairs of title and content.
+///
+/// Every note is a file in archive that starts with title, then content, and ends
+/// with properties like `type_: 1` separated from content by empty line.
+fn parse_joplin(data: &[u8]) -> Result<Vec<(String, String)>, NotebookError> {
+    let invalid = |err| invalid(ImportFormat::Joplin, err);
+
+    let mut notes = Vec::new();
+    let mut archive = tar::Archive::new(data);
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        let path = entry.path().map_err(invalid)?.into_owned();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+
+        let mut item = String::new();
+        entry.read_to_string(&mut item).map_err(invalid)?;
+        let item = item.replace("\r\n", "\n");
+
+        let (text, props) = match item.rfind("\n\n") {
+            Some(pos) => (&item[..pos], &item[pos + 2..]),
+            None => ("", item.as_str()),
+        };
+        let prop = |name: &str| {
+            props.lines().find_map(|line| {
+                let (key, value) = line.split_once(": ")?;
+                (key == name).then_some(value)
+            })
+        };
+
+        if prop("type_") != Some("1") {
+            continue;
+        }
+        if prop("encryption_applied") == Some("1") {
+            event!(
+                Level::WARN,
+                "Skip encrypted note {} of Joplin; decrypt it in Joplin before export",
+                path.display()
+            );
+            continue;
+        }
+
+        let (title, body) = match text.split_once("\n\n") {
+            Some((title, body)) => (title, body),
+            None => (text, ""),
+        };
+        notes.push((title.to_owned(), body.to_owned()));
+    }
+
+    Ok(notes)
+}
+
+/// Returns text of XML with entities like `&amp;` replaced with characters.
+fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&len| len <= 10)
+            .map(|len| &rest[1..len + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+
+        match (entity, c) {
+            (Some(entity), Some(c)) => {
+                unescaped.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+/// Returns what is inside of the first element `tag` in `xml`, and the rest of `xml` after it.
+fn element<'a>(xml: &'a str, tag: &str) -> Option<(&'a str, &'a str)> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+
+    let mut from = 0;
+    loop {
+        let start = from + xml[from..].find(&open)?;
+        let after = &xml[start + open.len()..];
+
+        // Skips elements whose names only start with `tag`
+        if after.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            let content = start + open.len() + after.find('>')? + 1;
+            let end = content + xml[content..].find(&close)?;
+
+            return Some((&xml[content..end], &xml[end + close.len()..]));
+        }
+        from = start + open.len();
+    }
+}
+
+/// Adds text between HTML tags to `text`, with whitespace collapsed like browsers do.
+fn push_html_text(text: &mut String, html: &str) {
+    let mut words = html.split_whitespace().peekable();
+    if words.peek().is_none() {
+        return;
+    }
+
+    let starts_line = text.is_empty() || text.ends_with('\n');
+    if html.starts_with(char::is_whitespace) && !starts_line && !text.ends_with(' ') {
+        text.push(' ');
+    }
+    text.push_str(&unescape_xml(&words.collect::<Vec<_>>().join(" ")));
+    if html.ends_with(char::is_whitespace) {
+        text.push(' ');
+    }
+}
+
+/// Returns plain text of ENML (HTML used by Evernote for content of notes).
+fn enml_to_text(enml: &str) -> String {
+    let mut text = String::new();
+    let mut rest = enml;
+
+    while let Some(start) = rest.find('<') {
+        push_html_text(&mut text, &rest[..start]);
+
+        let Some(len) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + len];
+        rest = &rest[start + len + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match (name.as_str(), closing) {
+            ("br", _) => text.push('\n'),
+            ("div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "tr" | "ul" | "ol", _)
+                if !text.is_empty() && !text.ends_with('\n') =>
+            {
+                text.push('\n');
+            }
+            ("li", false) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str("- ");
+            }
+            ("en-todo", false) => {
+                let checked = tag.contains("checked=\"true\"");
+                text.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            ("td" | "th", true) => text.push('\t'),
+            _ => {}
+        }
+    }
+    push_html_text(&mut text, rest);
+
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let mut text = String::new();
+    let mut empty_lines = 0;
+    for line in lines {
+        if line.is_empty() {
+            empty_lines += 1;
+            if empty_lines > 1 {
+                continue;
+            }
+        } else {
+            empty_lines = 0;
+        }
+
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text.trim().to_owned()
+}
+
+/// Returns notes of Evernote ENEX file as pairs of title and content.
+fn parse_enex(data: &[u8]) -> Result<Vec<(String, String)>, NotebookError> {
+    let xml = std::str::from_utf8(data).map_err(|err| invalid(ImportFormat::Enex, err))?;
+    if !xml.contains("<en-export") {
+        return Err(invalid(ImportFormat::Enex, "`<en-export>` is not found"));
+    }
+
+    let mut notes = Vec::new();
+    let mut rest = xml;
+    while let Some((note, after)) = element(rest, "note") {
+        let title = element(note, "title")
+            .map(|(title, _)| unescape_xml(title))
+            .unwrap_or_default();
+
+        let content = element(note, "content").map_or("", |(content, _)| content.trim());
+        let enml = match content
+            .strip_prefix("<![CDATA[")
+            .and_then(|content| content.strip_suffix("]]>"))
+        {
+            Some(enml) => enml.to_owned(),
+            None => unescape_xml(content),
+        };
+        let body = element(&enml, "en-note")
+            .map(|(enml, _)| enml_to_text(enml))
+            .unwrap_or_default();
+
+        notes.push((title, body));
+        rest = after;
+    }
+
+    Ok(notes)
+}
+
+/// Returns notes of Standard Notes backup as pairs of title and content.
+fn parse_standard_notes(data: &[u8]) -> Result<Vec<(String, String)>, NotebookError> {
+    let format = ImportFormat::StandardNotes;
+
+    let backup: Value = serde_json::from_slice(data).map_err(|err| invalid(format, err))?;
+    let items = backup
+        .get("items")
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid(format, "`items` is not found"))?;
+
+    let mut notes = Vec::new();
+    for item in items {
+        if item.get("content_type").and_then(Value::as_str) != Some("Note")
+            || item.get("deleted").and_then(Value::as_bool) == Some(true)
+        {
+            continue;
+        }
+
+        let content = match item.get("content") {
+            Some(Value::Object(content)) => content,
+            Some(Value::String(_)) => {
+                return Err(invalid(
+                    format,
+                    "backup is encrypted; download decrypted backup",
+                ))
+            }
+            _ => continue,
+        };
+        if content.get("trashed").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+
+        let field = |name| {
+            content
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned()
+        };
+        notes.push((field("title"), field("text")));
+    }
+
+    Ok(notes)
+}
+
+/// Returns valid notename made of `title`.
+fn notename_of(title: &str) -> String {
+    let notename: String = title
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_control() || FORBIDDEN_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_LEN - 8)
+        .collect();
+    let notename = notename.trim_end();
+
+    if notename.is_empty() {
+        UNTITLED.to_owned()
+    } else {
+        notename.to_owned()
+    }
+}
+
+/// Parses notes exported by other app in `format`.
+///
+/// Read how notes are parsed and named [here][crate::import].
+/// ### Returns
+/// * Ok
+///     * Pairs of notename and content of every note, in order they are in `data`
+/// * Errors
+///     * [`NotebookError::InvalidArchive`] error if `data` is not valid export of the app
+/// ### Example
+/// ```
+/// # use lnotebook::import::{parse, ImportFormat};
+/// let backup = r#"{"items": [{"content_type": "Note", "content": {"title": "cat", "text": "meow"}}]}"#;
+///
+/// let notes = parse(ImportFormat::StandardNotes, backup.as_bytes()).unwrap();
+///
+/// assert_eq!(vec![("cat".to_owned(), "meow".to_owned())], notes);
+/// ```
+pub fn parse(format: ImportFormat, data: &[u8]) -> Result<Vec<(String, String)>, NotebookError> {
+    let notes = match format {
+        ImportFormat::Joplin => parse_joplin(data)?,
+        ImportFormat::Enex => parse_enex(data)?,
+        ImportFormat::StandardNotes => parse_standard_notes(data)?,
+    };
+
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    Ok(notes
+        .into_iter()
+        .map(|(title, body)| {
+            let notename = notename_of(&title);
+            let count = taken.entry(notename.clone()).or_default();
+            *count += 1;
+
+            let notename = if *count == 1 {
+                notename
+            } else {
+                format!("{notename} ({count})")
+            };
+
+            (notename, body)
+        })
+        .collect())
+}
+
+/// Imports notes from file `path` exported by other app in `format`.
+///
+/// Read more about importing [here][crate::import] and about `fail_fast`
+/// [here][crate::batch].
+/// ### Returns
+/// * Ok
+///     * [`BatchReport`] about every note
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read
+///     * [`NotebookError::InvalidArchive`] error if file is not valid export of the app
+///     * Errors of [`add_many`] if `fail_fast` is `true`
+pub async fn import_from(
+    format: ImportFormat,
+    path: impl AsRef<Path>,
+    fail_fast: bool,
+    pool: &PgPool,
+) -> Result<BatchReport, NotebookError> {
+    let path = path.as_ref();
+    let notes = parse(format, &fs::read(path)?)?;
+
+    let report = add_many(
+        notes
+            .iter()
+            .map(|(notename, note)| (notename.as_str(), Some(note.as_str()))),
+        fail_fast,
+        pool,
+    )
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Imported notes from {}: {}",
+        path.display(),
+        report
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(notes: &[(&str, &str)]) -> Vec<(String, String)> {
+        notes
+            .iter()
+            .map(|&(notename, note)| (notename.to_owned(), note.to_owned()))
+            .collect()
+    }
+
+    fn is_invalid(res: Result<Vec<(String, String)>, NotebookError>) -> bool {
+        matches!(res, Err(NotebookError::InvalidArchive { .. }))
+    }
+
+    /// Returns JEX archive with `files` in it.
+    fn jex(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn joplin_notes_are_parsed() {
+        let data = jex(&[
+            (
+                "1a.md",
+                "Shopping\r\n\r\n- milk\r\n- bread\r\n\r\nid: 1a\r\ntype_: 1",
+            ),
+            ("2b.md", "Recipes\n\nid: 2b\ntype_: 2"),
+            (
+                "3c.md",
+                "Diary\n\nencrypted\n\nid: 3c\nencryption_applied: 1\ntype_: 1",
+            ),
+            ("resources/4d.png", "not a note"),
+            ("5e.md", "Empty\n\nid: 5e\ntype_: 1"),
+        ]);
+
+        assert_eq!(
+            notes(&[("Shopping", "- milk\n- bread"), ("Empty", "")]),
+            parse(ImportFormat::Joplin, &data).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_joplin_archive_is_error() {
+        assert!(is_invalid(parse(ImportFormat::Joplin, &[b'x'; 1024])));
+    }
+
+    #[test]
+    fn enex_notes_are_parsed() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export application="Evernote">
+  <note>
+    <title>Plans &amp; ideas</title>
+    <content><![CDATA[<en-note><div>First line<br/>second line</div><ul><li>one</li><li>two</li></ul><div><en-todo checked="true"/>done</div><div><en-todo/>not done</div></en-note>]]></content>
+    <note-attributes><title-like>skipped</title-like></note-attributes>
+  </note>
+  <note>
+    <title></title>
+    <content>&lt;en-note&gt;&lt;p&gt;Tom &amp;amp; Jerry&lt;/p&gt;&lt;/en-note&gt;</content>
+  </note>
+  <note>
+    <title>No content</title>
+  </note>
+</en-export>"#;
+
+        assert_eq!(
+            notes(&[
+                (
+                    "Plans & ideas",
+                    "First line\nsecond line\n- one\n- two\n[x] done\n[ ] not done"
+                ),
+                ("untitled", "Tom & Jerry"),
+                ("No content", ""),
+            ]),
+            parse(ImportFormat::Enex, data.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_enex_is_error() {
+        assert!(is_invalid(parse(
+            ImportFormat::Enex,
+            b"<notes><note><title>cat</title></note></notes>"
+        )));
+        assert!(is_invalid(parse(ImportFormat::Enex, b"<en-export>\xff")));
+    }
+
+    #[test]
+    fn standard_notes_are_parsed() {
+        let data = r#"{"items": [
+            {"content_type": "Note", "content": {"title": "[[cat]]", "text": "pets"}},
+            {"content_type": "Tag", "content": {"title": "skipped"}},
+            {"content_type": "Note", "deleted": true, "content": {"title": "deleted"}},
+            {"content_type": "Note", "content": {"title": "trashed", "trashed": true}},
+            {"content_type": "Note", "content": {"text": "first"}},
+            {"content_type": "Note", "content": {"title": "  ", "text": "second"}}
+        ]}"#;
+
+        assert_eq!(
+            notes(&[
+                ("__cat__", "pets"),
+                ("untitled", "first"),
+                ("untitled (2)", "second"),
+            ]),
+            parse(ImportFormat::StandardNotes, data.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_standard_notes_backup_is_error() {
+        let encrypted = r#"{"items": [{"content_type": "Note", "content": "004:..."}]}"#;
+
+        assert!(is_invalid(parse(
+            ImportFormat::StandardNotes,
+            encrypted.as_bytes()
+        )));
+        assert!(is_invalid(parse(
+            ImportFormat::StandardNotes,
+            b"{\"notes\": []}"
+        )));
+        assert!(is_invalid(parse(
+            ImportFormat::StandardNotes,
+            b"{\"items\": ["
+        )));
+    }
+}
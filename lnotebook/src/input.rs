@@ -0,0 +1,178 @@
+//! Reading of note content entered by user.
+//!
+//! Note is entered line by line and finished by terminator (`#endnote#` in CLI, read more
+//! in [`config` module][crate::config]) or by the end of input:
+//! * terminator can be entered at the end of the last line or on its own line;
+//!   text after it on the same line is ignored
+//! * line breaks are kept as they were entered, except that `\r\n` becomes `\n`,
+//!   so note finished by terminator on its own line ends with a line break
+//! * empty terminator means that note is finished only by the end of input
+//!
+//! [`NoteInput`] collects lines that come one by one (as [`Prompt`][crate::prompt::Prompt]
+//! receives them), and [`read_note_body`] reads note from any [`BufRead`].
+//! ### Example
+//! ```
+//! # use lnotebook::input::read_note_body;
+//! let input = "login: krutoy_4el\npassword: 123#endnote#\n";
+//!
+//! let note = read_note_body(input.as_bytes(), "#endnote#").unwrap();
+//!
+//! assert_eq!("login: krutoy_4el\npassword: 123", note);
+//! ```
+
+use crate::errors::NotebookError;
+
+use std::io::BufRead;
+
+/// Collects lines of note until terminator.
+///
+/// Read about rules of input [here][crate::input].
+#[derive(Debug, Clone)]
+pub struct NoteInput<'a> {
+    terminator: &'a str,
+    body: String,
+    finished: bool,
+}
+
+impl<'a> NoteInput<'a> {
+    /// Creates input of note finished by `terminator`.
+    pub fn new(terminator: &'a str) -> NoteInput<'a> {
+        NoteInput {
+            terminator,
+            body: String::new(),
+            finished: false,
+        }
+    }
+
+    /// Adds `line` (with its line break, if it has one) to note.
+    ///
+    /// Returns `true` if `line` contains terminator, so note is finished
+    /// and next lines are ignored.
+    pub fn push_line(&mut self, line: &str) -> bool {
+        if self.finished {
+            return true;
+        }
+
+        if !self.terminator.is_empty() {
+            if let Some(end) = line.find(self.terminator) {
+                self.body += &line[..end];
+                self.finished = true;
+
+                return true;
+            }
+        }
+
+        match line.strip_suffix("\r\n") {
+            Some(line) => {
+                self.body += line;
+                self.body.push('\n');
+            }
+            None => self.body += line,
+        }
+
+        false
+    }
+
+    /// Returns `true` if terminator was entered.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns entered note.
+    pub fn into_body(self) -> String {
+        self.body
+    }
+}
+
+/// Reads note from `reader` until `terminator` or the end of input.
+///
+/// Read about rules of input [here][crate::input].
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if input can't be read or is not valid UTF-8
+pub fn read_note_body(mut reader: impl BufRead, terminator: &str) -> Result<String, NotebookError> {
+    let mut input = NoteInput::new(terminator);
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || input.push_line(&line) {
+            return Ok(input.into_body());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const END: &str = "#endnote#";
+
+    fn read(input: &str, terminator: &str) -> String {
+        read_note_body(input.as_bytes(), terminator).unwrap()
+    }
+
+    #[test]
+    fn terminator_at_end_of_line() {
+        assert_eq!("first\nsecond", read("first\nsecond#endnote#\n", END));
+    }
+
+    #[test]
+    fn terminator_on_own_line_keeps_line_break() {
+        assert_eq!("first\nsecond\n", read("first\nsecond\n#endnote#\n", END));
+    }
+
+    #[test]
+    fn text_after_terminator_is_ignored() {
+        assert_eq!("note", read("note#endnote# ignored\nignored too\n", END));
+    }
+
+    #[test]
+    fn terminator_in_the_middle_of_multibyte_text() {
+        assert_eq!("заметка ✓", read("заметка ✓#endnote#ещё\n", END));
+        assert_eq!("日本語\n", read("日本語\n#endnote#", END));
+    }
+
+    #[test]
+    fn multibyte_terminator() {
+        assert_eq!("список\n", read("список\n§§\nдальше\n", "§§"));
+    }
+
+    #[test]
+    fn end_of_input_finishes_note() {
+        assert_eq!("a\nb\n", read("a\nb\n", END));
+        assert_eq!("a\nb", read("a\nb", END));
+        assert_eq!("", read("", END));
+    }
+
+    #[test]
+    fn empty_terminator_reads_until_end_of_input() {
+        assert_eq!("a #endnote#\nb\n", read("a #endnote#\nb\n", ""));
+    }
+
+    #[test]
+    fn crlf_becomes_lf() {
+        assert_eq!("a\nb\n", read("a\r\nb\r\n#endnote#\r\n", END));
+    }
+
+    #[test]
+    fn lines_after_finish_are_ignored() {
+        let mut input = NoteInput::new(END);
+
+        assert!(!input.push_line("a\n"));
+        assert!(input.push_line("b#endnote#\n"));
+        assert!(input.push_line("c\n"));
+        assert!(input.is_finished());
+        assert_eq!("a\nb", input.into_body());
+    }
+
+    #[test]
+    fn invalid_utf8_is_error() {
+        let input: &[u8] = b"\xff\xfe\n#endnote#\n";
+
+        assert!(matches!(
+            read_note_body(input, END),
+            Err(NotebookError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Daily journal.
+//!
+//! Journal is a note for every day named with the date, for example `2024-07-20`.
+//! [`journal`] adds entry to the note of today (creating it if missing), and every entry
+//! starts with header with the time it was added:
+//! ```text
+//! ## 09:15
+//! Planned the week
+//!
+//! ## 18:30
+//! Finished the report
+//! ```
+//! Format of names is [`DEFAULT_NAME_FORMAT`] by default and can be changed with
+//! `LNOTEBOOK_JOURNAL_FORMAT` (read more in [`config` module][crate::config])
+//! or `--name-format` of `journal` command. Format uses
+//! [`chrono` specifiers](https://docs.rs/chrono/latest/chrono/format/strftime/index.html).
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::journal::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn journal_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let note = journal("Finished the report", DEFAULT_NAME_FORMAT, pool).await?;
+//!
+//!     println!("Added entry into `{}`", note.note_name);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{self, Note};
+use crate::compression;
+use crate::config::logged;
+use crate::errors::NotebookError;
+use crate::notename::NoteName;
+use crate::sql::query_as;
+use crate::users;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local,
+};
+use sqlx::{self, PgPool};
+use tracing::{event, Level};
+
+/// Format of journal names used by default.
+pub const DEFAULT_NAME_FORMAT: &str = "%Y-%m-%d";
+
+/// Returns name of journal note for `date`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::InvalidDateFormat`] error if `format` is not a valid format
+///       or gives empty name
+///     * [`NotebookError::InvalidNoteName`] error if `format` gives name that is not valid
+/// ### Example
+/// ```
+/// # use lnotebook::journal::*;
+/// # use chrono::{Local, TimeZone};
+/// let date = Local.with_ymd_and_hms(2024, 7, 20, 18, 30, 0).unwrap();
+///
+/// assert_eq!("2024-07-20", journal_name(DEFAULT_NAME_FORMAT, date).unwrap());
+/// assert_eq!("journal/2024-07", journal_name("journal/%Y-%m", date).unwrap());
+/// assert!(journal_name("%Q", date).is_err());
+/// ```
+pub fn journal_name(format: &str, date: DateTime<Local>) -> Result<String, NotebookError> {
+    let invalid = || NotebookError::InvalidDateFormat {
+        format: format.to_owned(),
+    };
+
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(invalid());
+    }
+
+    let name = date.format(format).to_string();
+    if name.trim().is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(NoteName::new(&name)?.into_string())
+}
+
+/// Adds `text` as new entry into journal note of today with name in `format`.
+/// Note is created if it doesn't exist.
+/// ### Returns
+/// * Ok
+///     * [Note] of today with the new entry
+/// * Errors
+///     * [`NotebookError::InvalidDateFormat`] error if `format` is not a valid format
+///     * [`NotebookError::InvalidNoteName`] error if `format` gives name that is not valid
+///     * [`NotebookError::BinaryNote`] error if note of today is binary
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn journal(text: &str, format: &str, pool: &PgPool) -> Result<Note, NotebookError> {
+    let now = Local::now();
+    let notename = journal_name(format, now)?;
+
+    let mut entry = format!("## {}\n{}", now.format("%H:%M"), text);
+    if !entry.ends_with('\n') {
+        entry.push('\n');
+    }
+
+    let mut tx = pool.begin().await?;
+    compression::unpack(&notename, &mut tx).await?;
+    let note = query_as!(
+        Note { id: i32, note_name: String, note: Option<String> },
+        r#"
+INSERT INTO notebook (note_name, note, owner)
+VALUES ($1, $2, $3)
+ON CONFLICT (note_name) DO UPDATE
+SET note = CASE
+    WHEN COALESCE(notebook.note, '') = '' THEN EXCLUDED.note
+    WHEN right(notebook.note, 1) = E'\n' THEN notebook.note || E'\n' || EXCLUDED.note
+    ELSE notebook.note || E'\n\n' || EXCLUDED.note
+END
+RETURNING id, note_name, note
+        "#,
+        notename,
+        entry,
+        users::acting_user()
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| commands::text_error(&notename, err))?;
+    compression::pack(note.id, note.note.as_deref(), &mut tx).await?;
+    tx.commit().await?;
+
+    event!(
+        Level::INFO,
+        "Add journal entry into `{}`:\n{}",
+        note.note_name,
+        logged(text)
+    );
+
+    Ok(note)
+}
@@ -0,0 +1,359 @@
+//! Checking links of notes.
+//!
+//! [`check_links`] finds links in content of all notes that lead nowhere:
+//! * wiki-links `[[notename]]` to notes that don't exist (read about them in
+//!   [`links` module][crate::links])
+//! * if it's asked to check URLs (only with `linkcheck` feature) - `http://` and `https://` URLs
+//!   found by [`parse_urls`] that can't be opened: server can't be reached in [`TIMEOUT`] or
+//!   responds with status `4xx` or `5xx`.
+//!
+//! URLs are requested with `HEAD` (or with `GET` if server doesn't allow `HEAD`), redirects are
+//! not followed. Every URL is requested once even if several notes have it, and up to
+//! [`CONCURRENT_REQUESTS`] URLs are requested at the same time.
+//!
+//! CLI checks links with `check-links [--http]`. It exits with code `1` if any link is broken,
+//! so it can be run periodically to keep the notebook healthy.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::linkcheck::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn check_links_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("dinner", "Look at [[recipes]]", pool).await?;
+//!
+//!     let broken = check_links(false, pool).await?;
+//!
+//!     assert_eq!("dinner", broken[0].note_name);
+//!     assert_eq!("recipes", broken[0].target);
+//!     assert_eq!(LinkProblem::MissingNote, broken[0].problem);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{self, Note};
+use crate::errors::NotebookError;
+use crate::links;
+
+use std::{collections::HashSet, fmt, time::Duration};
+use tracing::{event, Level};
+
+/// How many URLs are requested at the same time.
+pub const CONCURRENT_REQUESTS: usize = 8;
+
+/// How long request to one URL can take.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why link is broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkProblem {
+    /// Linked note doesn't exist
+    MissingNote,
+    /// URL can't be opened, with the reason
+    DeadUrl(String),
+}
+
+/// This is a `struct` that containing link of note that leads nowhere.
+///
+/// It implements [`fmt::Display`]:
+/// ```text
+/// dinner: [[recipes]] (note doesn't exist)
+/// dinner: https://example.com/pancakes (response status is 404 Not Found)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// Name of note with the link
+    pub note_name: String,
+    /// Name of the linked note or URL
+    pub target: String,
+    pub problem: LinkProblem,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            LinkProblem::MissingNote => write!(
+                f,
+                "{}: [[{}]] (note doesn't exist)",
+                self.note_name, self.target
+            ),
+            LinkProblem::DeadUrl(reason) => {
+                write!(f, "{}: {} ({})", self.note_name, self.target, reason)
+            }
+        }
+    }
+}
+
+/// Returns `http://` and `https://` URLs in `text`, in order of their first occurrence.
+///
+/// URL ends with whitespace, quote, angle bracket or closing bracket that is not opened in it
+/// (like in Markdown link `[docs](https://example.com)`). Punctuation at the end of URL,
+/// like a period at the end of a sentence, is not a part of it.
+/// ### Example
+/// ```
+/// # use lnotebook::linkcheck::parse_urls;
+/// let urls = parse_urls(
+///     "Read [docs](https://example.com/docs) and https://en.wikipedia.org/wiki/Rust_(language).",
+/// );
+///
+/// assert_eq!(
+///     vec![
+///         "https://example.com/docs",
+///         "https://en.wikipedia.org/wiki/Rust_(language)"
+///     ],
+///     urls
+/// );
+/// ```
+pub fn parse_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = ["http://", "https://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        rest = &rest[start..];
+
+        let mut depth = 0;
+        let end = rest
+            .find(|c: char| match c {
+                '(' | '[' => {
+                    depth += 1;
+                    false
+                }
+                ')' | ']' if depth > 0 => {
+                    depth -= 1;
+                    false
+                }
+                ')' | ']' | '"' | '\'' | '<' | '>' | '`' => true,
+                c => c.is_whitespace(),
+            })
+            .unwrap_or(rest.len());
+        let url = rest[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+
+        // Only scheme is not a URL
+        if !url.ends_with("://") && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_owned());
+        }
+        rest = &rest[end..];
+    }
+
+    urls
+}
+
+/// Returns broken links of all notes, read more [here][crate::linkcheck].
+///
+/// URLs are checked only if `http` is `true`.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`BrokenLink`] ordered by notename and then by position of links in note:
+///       wiki-links first and URLs after them
+/// * Errors
+///     * [`NotebookError::LinkCheck`] error if `http` is `true`, but LNotebook is built
+///       without `linkcheck` feature
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn check_links(
+    http: bool,
+    pool: &sqlx::PgPool,
+) -> Result<Vec<BrokenLink>, NotebookError> {
+    let notes = commands::select_all(pool).await?;
+    let notenames: HashSet<&str> = notes.iter().map(|note| note.note_name.as_str()).collect();
+
+    let mut broken = Vec::new();
+    for note in &notes {
+        for target in links::parse_links(note.body()) {
+            if !notenames.contains(target.as_str()) {
+                broken.push(BrokenLink {
+                    note_name: note.note_name.to_owned(),
+                    target,
+                    problem: LinkProblem::MissingNote,
+                });
+            }
+        }
+    }
+
+    if http {
+        broken.extend(check_urls(&notes).await?);
+    }
+
+    // Sort is stable, so links of note stay in their order
+    broken.sort_by(|a, b| a.note_name.cmp(&b.note_name));
+
+    event!(
+        Level::INFO,
+        "Check links of {} notes, {} are broken",
+        notes.len(),
+        broken.len()
+    );
+
+    Ok(broken)
+}
+
+#[cfg(not(feature = "linkcheck"))]
+async fn check_urls(_notes: &[Note]) -> Result<Vec<BrokenLink>, NotebookError> {
+    Err(NotebookError::LinkCheck {
+        reason: "LNotebook is built without `linkcheck` feature".to_owned(),
+    })
+}
+
+/// Returns URLs of `notes` that can't be opened.
+#[cfg(feature = "linkcheck")]
+async fn check_urls(notes: &[Note]) -> Result<Vec<BrokenLink>, NotebookError> {
+    use futures_util::{stream, StreamExt};
+    use std::collections::HashMap;
+
+    let tls = http::tls_config()?;
+
+    let links: Vec<(&str, String)> = notes
+        .iter()
+        .flat_map(|note| {
+            parse_urls(note.body())
+                .into_iter()
+                .map(|url| (note.note_name.as_str(), url))
+        })
+        .collect();
+    // URLs are owned by requests, so the future of checking stays `Send`
+    let urls: HashSet<String> = links.iter().map(|(_, url)| url.to_owned()).collect();
+
+    event!(Level::INFO, "Check {} URLs", urls.len());
+
+    let results: HashMap<String, Result<(), String>> = stream::iter(urls)
+        .map(|url| {
+            let tls = tls.clone();
+            async move {
+                let res = http::check_url(&url, tls).await;
+                (url, res)
+            }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+
+    Ok(links
+        .iter()
+        .filter_map(|(notename, url)| match results.get(url.as_str()) {
+            Some(Err(reason)) => Some(BrokenLink {
+                note_name: notename.to_string(),
+                target: url.to_owned(),
+                problem: LinkProblem::DeadUrl(reason.to_owned()),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(feature = "linkcheck")]
+mod http {
+    use super::TIMEOUT;
+    use crate::errors::NotebookError;
+
+    use http_body_util::Empty;
+    use hyper::{body::Bytes, header, Method, Request, StatusCode, Uri};
+    use hyper_util::rt::TokioIo;
+    use std::sync::Arc;
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio::net::TcpStream;
+    use tokio_rustls::rustls::{self, pki_types::ServerName, ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    /// Returns configuration of TLS that trusts root certificates of Mozilla.
+    pub(super) fn tls_config() -> Result<Arc<ClientConfig>, NotebookError> {
+        let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(|err| NotebookError::LinkCheck {
+                    reason: err.to_string(),
+                })?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+
+        Ok(Arc::new(config))
+    }
+
+    /// Returns `Ok` if `url` can be opened, or the reason why it can't.
+    pub(super) async fn check_url(url: &str, tls: Arc<ClientConfig>) -> Result<(), String> {
+        let uri: Uri = url
+            .parse()
+            .map_err(|err: hyper::http::uri::InvalidUri| err.to_string())?;
+
+        let mut status = request(&uri, Method::HEAD, &tls).await?;
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            status = request(&uri, Method::GET, &tls).await?;
+        }
+
+        if status.is_client_error() || status.is_server_error() {
+            return Err(format!("response status is {status}"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns status of response to request with `method` to `uri`.
+    async fn request(
+        uri: &Uri,
+        method: Method,
+        tls: &Arc<ClientConfig>,
+    ) -> Result<StatusCode, String> {
+        let send = async {
+            let host = uri.host().ok_or("URL doesn't have host")?;
+            let https = uri.scheme_str() == Some("https");
+            let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+            // Host of IPv6 address is in brackets
+            let addr = host.trim_start_matches('[').trim_end_matches(']');
+            let stream = TcpStream::connect((addr, port))
+                .await
+                .map_err(|err| err.to_string())?;
+
+            if https {
+                let name = ServerName::try_from(addr.to_owned()).map_err(|err| err.to_string())?;
+                let stream = TlsConnector::from(tls.clone())
+                    .connect(name, stream)
+                    .await
+                    .map_err(|err| err.to_string())?;
+
+                send(stream, uri, method).await
+            } else {
+                send(stream, uri, method).await
+            }
+        };
+
+        match tokio::time::timeout(TIMEOUT, send).await {
+            Ok(res) => res,
+            Err(_) => Err("timed out".to_owned()),
+        }
+    }
+
+    async fn send<S>(stream: S, uri: &Uri, method: Method) -> Result<StatusCode, String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|err| err.to_string())?;
+        tokio::spawn(conn);
+
+        let authority = uri.authority().map_or("", |authority| authority.as_str());
+        let request = Request::builder()
+            .method(method)
+            .uri(uri.path_and_query().map_or("/", |path| path.as_str()))
+            .header(header::HOST, authority)
+            .header(
+                header::USER_AGENT,
+                concat!("lnotebook/", env!("CARGO_PKG_VERSION")),
+            )
+            .body(Empty::<Bytes>::new())
+            .map_err(|err| err.to_string())?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        Ok(response.status())
+    }
+}
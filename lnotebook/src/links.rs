@@ -0,0 +1,308 @@
+//! Wiki-style links between notes.
+//!
+//! Note can link to other note by its name in double square brackets: `[[other_note]]`.
+//! [`links`] returns notes that note links to, and [`backlinks`] returns notes that link to note.
+//! Links are kept when note is renamed with
+//! [`upd_notename_rewrite_links`][crate::commands::upd_notename_rewrite_links].
+//!
+//! Links of all notes form [`LinkGraph`] returned by [`graph`], that can be rendered for
+//! visualization in one of [`GraphFormat`]s.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::links::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn links_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("recipes", "Pancakes", pool).await?;
+//!     add("dinner", "Look at [[recipes]] and [[shopping]]", pool).await?;
+//!
+//!     let links = links("dinner", pool).await?;
+//!     assert_eq!("recipes", links[0].note_name);
+//!     assert!(links[0].exists);
+//!     assert!(!links[1].exists);
+//!
+//!     assert_eq!(vec!["dinner"], backlinks("recipes", pool).await?);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands;
+use crate::compression::StoredNote;
+use crate::errors::NotebookError;
+use crate::render::Json;
+use crate::sql::{query_as, query_scalar};
+use crate::users;
+
+use sqlx::{self, PgPool};
+use std::fmt;
+use tracing::{event, Level};
+
+/// This is a `struct` that containing link from note to other note.
+pub struct Link {
+    /// Name of the linked note
+    pub note_name: String,
+    /// `false` if linked note doesn't exist
+    pub exists: bool,
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exists {
+            write!(f, "{}", self.note_name)
+        } else {
+            write!(f, "{} (missing)", self.note_name)
+        }
+    }
+}
+
+/// Returns names of notes linked in `text` as `[[notename]]`, in order of their first link.
+///
+/// Links can't be empty or contain line breaks.
+/// ### Example
+/// ```
+/// # use lnotebook::links::parse_links;
+/// let links = parse_links("See [[b]], [[a]] and [[b]] again; [[]] is not a link");
+///
+/// assert_eq!(vec!["b", "a"], links);
+/// ```
+pub fn parse_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let link = &rest[..end];
+
+        if link.contains('\n') {
+            continue;
+        }
+        if !link.is_empty() && !links.iter().any(|l| l == link) {
+            links.push(link.to_owned());
+        }
+        rest = &rest[end + 2..];
+    }
+
+    links
+}
+
+/// Returns links from note `notename` to other notes.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`Link`] in order of their first link in note
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn links(notename: &str, pool: &PgPool) -> Result<Vec<Link>, NotebookError> {
+    let note = commands::select_one(notename, pool).await?;
+    let names = parse_links(note.body());
+
+    let existing = query_scalar!(
+        String,
+        "
+SELECT note_name
+FROM notebook
+WHERE note_name = ANY($1) AND can_access_note(id, owner, $2, false)
+        ",
+        &names,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(names
+        .into_iter()
+        .map(|note_name| Link {
+            exists: existing.contains(&note_name),
+            note_name,
+        })
+        .collect())
+}
+
+/// Returns names of notes that link to note `notename`, ordered by name.
+/// Links in protected notes (read about them in [`protect` module][crate::protect])
+/// are not looked for.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn backlinks(notename: &str, pool: &PgPool) -> Result<Vec<String>, NotebookError> {
+    let rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE (strpos(note, '[[' || $1 || ']]') > 0 OR compressed)
+    AND passphrase_hash IS NULL
+    AND can_access_note(id, owner, $2, false)
+ORDER BY note_name
+        ",
+        notename,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Links in compressed notes are looked for here, the database can't read them
+    let link = format!("[[{notename}]]");
+    let mut notenames = Vec::new();
+    for row in rows {
+        let note = row.into_note()?;
+        if note.body().contains(&link) {
+            notenames.push(note.note_name);
+        }
+    }
+
+    Ok(notenames)
+}
+
+/// Formats that [`LinkGraph`] can be rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum GraphFormat {
+    /// [DOT](https://graphviz.org/doc/info/lang.html) language of Graphviz
+    #[default]
+    Dot,
+    /// JSON object with `nodes` and `edges` arrays
+    Json,
+}
+
+/// This is a `struct` that containing links of all notes in notebook.
+pub struct LinkGraph {
+    /// Names of all notes ordered by ID
+    pub notes: Vec<String>,
+    /// Links from note with the given name, in order of notes and then of links in note
+    pub links: Vec<(String, Link)>,
+}
+
+impl LinkGraph {
+    /// Renders graph in `format`.
+    ///
+    /// Missing notes that are linked are rendered too: as dashed nodes in
+    /// [`GraphFormat::Dot`] and as nodes with `"exists": false` in [`GraphFormat::Json`].
+    /// ### Example
+    /// ```
+    /// # use lnotebook::links::*;
+    /// let graph = LinkGraph {
+    ///     notes: vec!["dinner".to_owned()],
+    ///     links: vec![(
+    ///         "dinner".to_owned(),
+    ///         Link {
+    ///             note_name: "recipes".to_owned(),
+    ///             exists: false,
+    ///         },
+    ///     )],
+    /// };
+    ///
+    /// assert_eq!(
+    ///     "digraph notebook {\n  \"dinner\";\n  \"recipes\" [style=dashed];\n  \"dinner\" -> \"recipes\";\n}",
+    ///     graph.render(GraphFormat::Dot)
+    /// );
+    /// ```
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.render_dot(),
+            GraphFormat::Json => self.render_json(),
+        }
+    }
+
+    /// Returns names of linked notes that don't exist, in order of their first link.
+    pub fn missing(&self) -> Vec<&str> {
+        let mut missing: Vec<&str> = Vec::new();
+
+        for (_, link) in &self.links {
+            if !link.exists && !missing.contains(&link.note_name.as_str()) {
+                missing.push(&link.note_name);
+            }
+        }
+
+        missing
+    }
+
+    fn render_dot(&self) -> String {
+        // Quoted IDs of DOT are escaped the same way as JSON strings
+        let id = |name: &str| Json::string(name);
+
+        let mut res = "digraph notebook {".to_owned();
+
+        for notename in &self.notes {
+            res += &format!("\n  {};", id(notename));
+        }
+        for notename in self.missing() {
+            res += &format!("\n  {} [style=dashed];", id(notename));
+        }
+        for (notename, link) in &self.links {
+            res += &format!("\n  {} -> {};", id(notename), id(&link.note_name));
+        }
+        res += "\n}";
+
+        res
+    }
+
+    fn render_json(&self) -> String {
+        let nodes =
+            self.notes
+                .iter()
+                .map(|notename| format!(r#"{{"name":{},"exists":true}}"#, Json::string(notename)))
+                .chain(self.missing().into_iter().map(|notename| {
+                    format!(r#"{{"name":{},"exists":false}}"#, Json::string(notename))
+                }))
+                .collect::<Vec<_>>();
+        let edges = self
+            .links
+            .iter()
+            .map(|(notename, link)| {
+                format!(
+                    r#"{{"from":{},"to":{}}}"#,
+                    Json::string(notename),
+                    Json::string(&link.note_name)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            r#"{{"nodes":[{}],"edges":[{}]}}"#,
+            nodes.join(","),
+            edges.join(",")
+        )
+    }
+}
+
+/// Returns links of all notes in notebook.
+/// ### Returns
+/// * Ok
+///     * [`LinkGraph`] with all notes and links between them
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn graph(pool: &PgPool) -> Result<LinkGraph, NotebookError> {
+    let notes = commands::select_all(pool).await?;
+    let notenames: Vec<String> = notes.iter().map(|note| note.note_name.to_owned()).collect();
+
+    let mut links = Vec::new();
+    for note in &notes {
+        for note_name in parse_links(note.body()) {
+            links.push((
+                note.note_name.to_owned(),
+                Link {
+                    exists: notenames.contains(&note_name),
+                    note_name,
+                },
+            ));
+        }
+    }
+
+    event!(
+        Level::INFO,
+        "Build graph of {} notes with {} links",
+        notenames.len(),
+        links.len()
+    );
+
+    Ok(LinkGraph {
+        notes: notenames,
+        links,
+    })
+}
@@ -0,0 +1,362 @@
+//! Checking hygiene of notes, like linters check code.
+//!
+//! [`Linter`] runs [`Checker`]s over content of note and returns their [`Finding`]s.
+//! Checkers that come with the notebook:
+//! * [`TrailingWhitespace`] - lines that end with spaces or tabs
+//! * [`LongLines`] - lines longer than [`DEFAULT_MAX_LINE_LEN`] characters or other limit.
+//!   Lines without spaces (like long URLs) are not reported, because they can't be wrapped
+//! * [`Spellcheck`] - words that are not in dictionary (only with `spellcheck` feature).
+//!   Dictionary is a file with one word per line, like [`DEFAULT_DICTIONARY`] that is installed
+//!   on most Unix systems. Code, URLs and wiki-links are not checked, and neither are words
+//!   in uppercase (like `HTTP`)
+//!
+//! Other checkers can be added by implementing [`Checker`].
+//!
+//! CLI lints notes with `lint <notename> [--max-line-len <chars>] [--spellcheck]
+//! [--dictionary <file>]`. It exits with code `1` if anything is found, so it can be used
+//! in scripts and CI.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::lint::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn lint_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("todo", "Buy milk  \nCall mom", pool).await?;
+//!
+//!     let linter = Linter::new().with(TrailingWhitespace).with(LongLines { max_len: 80 });
+//!     let findings = lint("todo", &linter, pool).await?;
+//!
+//!     assert_eq!(1, findings[0].line_no);
+//!     assert_eq!("trailing-whitespace", findings[0].checker);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands;
+use crate::errors::NotebookError;
+
+use sqlx::PgPool;
+use std::{fmt, path::Path};
+use tracing::{event, Level};
+
+/// Max length of line in characters that [`LongLines`] allows if other length is not given.
+pub const DEFAULT_MAX_LINE_LEN: usize = 100;
+
+/// Dictionary used by spellcheck if other dictionary is not given.
+pub const DEFAULT_DICTIONARY: &str = "/usr/share/dict/words";
+
+/// This is a `struct` that containing problem found in note by [`Checker`].
+///
+/// It implements [`fmt::Display`]:
+/// ```text
+/// 3: trailing whitespace (trailing-whitespace)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Name of checker that found the problem
+    pub checker: &'static str,
+    /// Number of line, lines are numbered from 1
+    pub line_no: u32,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.line_no, self.message, self.checker)
+    }
+}
+
+/// Finds problems in content of note.
+///
+/// Read about implementations [here][crate::lint].
+pub trait Checker: Send + Sync {
+    /// Returns problems found in `text`.
+    fn check(&self, text: &str) -> Vec<Finding>;
+}
+
+/// Reports lines that end with spaces or tabs, read more [here][crate::lint].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrailingWhitespace;
+
+impl Checker for TrailingWhitespace {
+    fn check(&self, text: &str) -> Vec<Finding> {
+        lines(text)
+            .filter(|(_, line)| line.ends_with([' ', '\t']))
+            .map(|(line_no, _)| Finding {
+                checker: "trailing-whitespace",
+                line_no,
+                message: "trailing whitespace".to_owned(),
+            })
+            .collect()
+    }
+}
+
+/// Reports lines longer than `max_len` characters, read more [here][crate::lint].
+#[derive(Debug, Clone, Copy)]
+pub struct LongLines {
+    pub max_len: usize,
+}
+
+impl Default for LongLines {
+    fn default() -> LongLines {
+        LongLines {
+            max_len: DEFAULT_MAX_LINE_LEN,
+        }
+    }
+}
+
+impl Checker for LongLines {
+    fn check(&self, text: &str) -> Vec<Finding> {
+        lines(text)
+            .filter_map(|(line_no, line)| {
+                let len = line.chars().count();
+                if len <= self.max_len || !line.trim().contains(char::is_whitespace) {
+                    return None;
+                }
+
+                Some(Finding {
+                    checker: "long-line",
+                    line_no,
+                    message: format!("line is {len} characters long, max is {}", self.max_len),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Reports words that are not in dictionary, read more [here][crate::lint].
+///
+/// Words are compared in lowercase.
+/// ### Example
+/// ```
+/// # use lnotebook::lint::*;
+/// let spellcheck = Spellcheck::new(["buy", "milk", "and"]);
+/// let findings = spellcheck.check("Buy milk and bred\n`cargo` and https://example.com");
+///
+/// assert_eq!(1, findings.len());
+/// assert_eq!("unknown word `bred`", findings[0].message);
+/// ```
+#[cfg(feature = "spellcheck")]
+#[derive(Debug, Clone, Default)]
+pub struct Spellcheck {
+    words: std::collections::HashSet<String>,
+}
+
+#[cfg(feature = "spellcheck")]
+impl Spellcheck {
+    /// Creates spellcheck with dictionary of `words`.
+    pub fn new<I, S>(words: I) -> Spellcheck
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Spellcheck {
+            words: words
+                .into_iter()
+                .map(|word| word.as_ref().trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Creates spellcheck with dictionary from file `path` with one word per line.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Dictionary`] error if file can't be read or has no words
+    pub fn from_file(path: &Path) -> Result<Spellcheck, NotebookError> {
+        let words = std::fs::read_to_string(path).map_err(|err| NotebookError::Dictionary {
+            reason: format!("{}: {err}", path.display()),
+        })?;
+
+        let spellcheck = Spellcheck::new(words.lines());
+        if spellcheck.words.is_empty() {
+            return Err(NotebookError::Dictionary {
+                reason: format!("{} has no words", path.display()),
+            });
+        }
+
+        Ok(spellcheck)
+    }
+}
+
+#[cfg(feature = "spellcheck")]
+impl Checker for Spellcheck {
+    fn check(&self, text: &str) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut in_code = false;
+
+        for (line_no, line) in lines(text) {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            if in_code {
+                continue;
+            }
+
+            let line = without_spans(&without_spans(line, "`", "`"), "[[", "]]");
+            let mut unknown: Vec<&str> = Vec::new();
+
+            for token in line
+                .split_whitespace()
+                .filter(|token| !token.contains("://"))
+            {
+                for word in token.split(|c: char| !c.is_alphabetic() && c != '\'') {
+                    let word = word.trim_matches('\'');
+                    let word = word.strip_suffix("'s").unwrap_or(word);
+
+                    if word.chars().count() < 2
+                        || word.chars().all(char::is_uppercase)
+                        || self.words.contains(&word.to_lowercase())
+                        || unknown.contains(&word)
+                    {
+                        continue;
+                    }
+                    unknown.push(word);
+                }
+            }
+
+            findings.extend(unknown.into_iter().map(|word| Finding {
+                checker: "spelling",
+                line_no,
+                message: format!("unknown word `{word}`"),
+            }));
+        }
+
+        findings
+    }
+}
+
+/// Returns `line` without parts between `open` and `close`, like inline code.
+#[cfg(feature = "spellcheck")]
+fn without_spans(line: &str, open: &str, close: &str) -> String {
+    let mut res = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find(open) {
+        let Some(len) = rest[start + open.len()..].find(close) else {
+            break;
+        };
+        res += &rest[..start];
+        res.push(' ');
+        rest = &rest[start + open.len() + len + close.len()..];
+    }
+    res += rest;
+
+    res
+}
+
+/// Returns lines of `text` with their numbers.
+fn lines(text: &str) -> impl Iterator<Item = (u32, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i as u32 + 1, line))
+}
+
+/// Runs [`Checker`]s over content of notes.
+///
+/// [`Linter::default`] runs [`TrailingWhitespace`] and [`LongLines`] with default length.
+pub struct Linter {
+    checkers: Vec<Box<dyn Checker>>,
+}
+
+impl Default for Linter {
+    fn default() -> Linter {
+        Linter::new()
+            .with(TrailingWhitespace)
+            .with(LongLines::default())
+    }
+}
+
+impl Linter {
+    /// Creates linter without checkers.
+    pub fn new() -> Linter {
+        Linter {
+            checkers: Vec::new(),
+        }
+    }
+
+    /// Creates linter that CLI uses: with [`TrailingWhitespace`], [`LongLines`] with `max_line_len`
+    /// and, if `dictionary` is given, [`Spellcheck`] with words from it.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Dictionary`] error if dictionary can't be read, or LNotebook
+    ///       is built without `spellcheck` feature
+    pub fn standard(
+        max_line_len: usize,
+        dictionary: Option<&Path>,
+    ) -> Result<Linter, NotebookError> {
+        let linter = Linter::new().with(TrailingWhitespace).with(LongLines {
+            max_len: max_line_len,
+        });
+
+        match dictionary {
+            #[cfg(feature = "spellcheck")]
+            Some(path) => Ok(linter.with(Spellcheck::from_file(path)?)),
+            #[cfg(not(feature = "spellcheck"))]
+            Some(_) => Err(NotebookError::Dictionary {
+                reason: "LNotebook is built without `spellcheck` feature".to_owned(),
+            }),
+            None => Ok(linter),
+        }
+    }
+
+    /// Adds `checker` to linter.
+    pub fn with(mut self, checker: impl Checker + 'static) -> Linter {
+        self.checkers.push(Box::new(checker));
+        self
+    }
+
+    /// Returns problems found in `text` by all checkers, ordered by line.
+    ///
+    /// Problems in the same line are in order of checkers.
+    /// ### Example
+    /// ```
+    /// # use lnotebook::lint::*;
+    /// let findings = Linter::default().lint("Buy milk\t\nCall mom ");
+    ///
+    /// assert_eq!(2, findings.len());
+    /// assert_eq!("2: trailing whitespace (trailing-whitespace)", findings[1].to_string());
+    /// ```
+    pub fn lint(&self, text: &str) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .checkers
+            .iter()
+            .flat_map(|checker| checker.check(text))
+            .collect();
+
+        // Sort is stable, so findings of the same line stay in order of checkers
+        findings.sort_by_key(|finding| finding.line_no);
+
+        findings
+    }
+}
+
+/// Returns problems found in content of note `notename` by `linter`, read more [here][crate::lint].
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`Finding`] ordered by line, empty if nothing is found
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn lint(
+    notename: &str,
+    linter: &Linter,
+    pool: &PgPool,
+) -> Result<Vec<Finding>, NotebookError> {
+    let note = commands::select_one(notename, pool).await?;
+    let findings = linter.lint(note.body());
+
+    event!(
+        Level::INFO,
+        "Lint `{}`, found {} problems",
+        notename,
+        findings.len()
+    );
+
+    Ok(findings)
+}
@@ -0,0 +1,161 @@
+//! Locks of notes for notebooks shared by several people.
+//!
+//! Note locked with [`lock_note`] can be changed only by user that locked it, others get
+//! [`NotebookError::Locked`] from [`upd`][crate::commands::upd], [`del`][crate::commands::del]
+//! and other functions that change content of one note, delete or rename it. Commands that
+//! change many notes at once (like `del-all` or `sed`) don't check locks. User is `LNOTEBOOK_USER` or user of operating system
+//! (read more in [`config` module][crate::config]), or database user if neither is known.
+//!
+//! Locks are stored in the database, so they are kept until [`unlock_note`] (unlike PostgreSQL
+//! advisory locks, which are released when connection is closed) and are seen by everyone
+//! who uses the same database. Locks only keep other users of the notebook from changing notes
+//! by mistake: they don't protect notes from queries made to the database directly.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::lock::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn lock_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     lock_note("report", pool).await?;
+//!
+//!     // Other users get `NotebookError::Locked` until the note is unlocked
+//!     upd("report", "Final numbers", pool).await?;
+//!
+//!     unlock_note("report", false, pool).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::config;
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as, query_scalar};
+
+use sqlx::{self, PgExecutor, PgPool};
+use tracing::{event, Level};
+
+/// Locks note for the current user. Lock that the user already has is kept.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn lock_note(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    let row = query_as!(
+        { locked_by: String, user: String },
+        r#"
+WITH note AS (
+    SELECT id FROM notebook WHERE note_name = $1
+), inserted AS (
+    INSERT INTO note_locks (note_id, locked_by)
+    SELECT id, COALESCE($2, session_user) FROM note
+    ON CONFLICT (note_id) DO NOTHING
+    RETURNING locked_by
+)
+SELECT COALESCE(
+    (SELECT locked_by FROM inserted),
+    (SELECT locked_by FROM note_locks WHERE note_id = note.id)
+) AS "locked_by!", COALESCE($2, session_user) AS "user!"
+FROM note
+        "#,
+        notename,
+        config::user()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if row.locked_by != row.user {
+        return Err(NotebookError::Locked {
+            notename: notename.to_owned(),
+            locked_by: row.locked_by,
+        });
+    }
+
+    event!(Level::INFO, "Lock `{}` for `{}`", notename, row.user);
+
+    Ok(())
+}
+
+/// Unlocks note locked by the current user, or by anyone if `force` is `true`.
+/// Note that is not locked is left as it is.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Locked`] error if note is locked by other user and `force` is `false`
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn unlock_note(notename: &str, force: bool, pool: &PgPool) -> Result<(), NotebookError> {
+    if !force {
+        check_unlocked(notename, pool).await?;
+    }
+
+    let id = query_scalar!(
+        i32,
+        "SELECT id FROM notebook WHERE note_name = $1",
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let unlocked = query!("DELETE FROM note_locks WHERE note_id = $1", id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    if unlocked > 0 {
+        event!(Level::INFO, "Unlock `{}`", notename);
+    }
+
+    Ok(())
+}
+
+/// Returns user that locked note, `None` if note is not locked.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn locked_by(notename: &str, pool: &PgPool) -> Result<Option<String>, NotebookError> {
+    let locked_by = query_scalar!(
+        Option<String>,
+        r#"
+SELECT note_locks.locked_by AS "locked_by?"
+FROM notebook
+LEFT JOIN note_locks ON note_locks.note_id = notebook.id
+WHERE note_name = $1
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(locked_by)
+}
+
+/// Returns [`NotebookError::Locked`] error if note is locked by other user.
+/// Note that doesn't exist is not locked.
+pub(crate) async fn check_unlocked<'c>(
+    notename: &str,
+    conn: impl PgExecutor<'c>,
+) -> Result<(), NotebookError> {
+    let row = query_as!(
+        { locked_by: String, user: String },
+        r#"
+SELECT note_locks.locked_by, COALESCE($2, session_user) AS "user!"
+FROM notebook
+JOIN note_locks ON note_locks.note_id = notebook.id
+WHERE note_name = $1
+        "#,
+        notename,
+        config::user()
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    match row {
+        Some(row) if row.locked_by != row.user => Err(NotebookError::Locked {
+            notename: notename.to_owned(),
+            locked_by: row.locked_by,
+        }),
+        _ => Ok(()),
+    }
+}
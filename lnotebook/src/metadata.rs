@@ -0,0 +1,236 @@
+//! Key/value metadata of notes (only with `metadata` feature).
+//!
+//! Every note has JSON object with metadata, where programs built on the notebook can keep
+//! any structured data about note: priority, source it was imported from, IDs in other systems.
+//! Values are any JSON values, and [`Note::meta`] and [`Note::set_meta`] convert them
+//! from and into Rust types with `serde`.
+//!
+//! CLI sets metadata with `set-meta <notename> <key> <value>`: `value` that is valid JSON
+//! (like `3`, `true` or `["a", "b"]`) is stored as it is, anything else is stored as string
+//! (read more in [`parse_value`]).
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::metadata::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn metadata_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let note = add("report", "", pool).await?;
+//!     note.set_meta("priority", &3, pool).await?;
+//!
+//!     assert_eq!(Some(3), note.meta::<i64>("priority", pool).await?);
+//!     assert_eq!(1, find_by_meta("priority", &3.into(), pool).await?.len());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::Note;
+use crate::compression::StoredNote;
+use crate::errors::NotebookError;
+use crate::protect;
+use crate::sql::{query_as, query_scalar};
+use crate::users::{self, Access};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{self, PgPool};
+use tracing::{event, Level};
+
+/// Returns `value` entered in CLI as JSON value.
+///
+/// Valid JSON is parsed, anything else is string, so quotes are needed only for strings
+/// that look like other values.
+/// ### Example
+/// ```
+/// # use lnotebook::metadata::parse_value;
+/// # use serde_json::json;
+/// assert_eq!(json!(3), parse_value("3"));
+/// assert_eq!(json!("alice"), parse_value("alice"));
+/// assert_eq!(json!("3"), parse_value("\"3\""));
+/// ```
+pub fn parse_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()))
+}
+
+/// Returns JSON object `{key: value}`.
+fn entry(key: &str, value: &Value) -> String {
+    Value::Object(Map::from_iter([(key.to_owned(), value.clone())])).to_string()
+}
+
+/// Sets metadata `key` of note to `value`, replacing the old value.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn set_meta(
+    notename: &str,
+    key: &str,
+    value: &Value,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Write, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    query_as!(
+        { id: i32 },
+        "
+UPDATE notebook
+SET metadata = metadata || $2::TEXT::JSONB
+WHERE note_name = $1
+RETURNING id
+        ",
+        notename,
+        entry(key, value)
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(Level::INFO, "Set metadata `{}` of `{}`", key, notename);
+
+    Ok(())
+}
+
+/// Returns value of metadata `key` of note, `None` if note has no such key.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn get_meta(
+    notename: &str,
+    key: &str,
+    pool: &PgPool,
+) -> Result<Option<Value>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let value = query_scalar!(
+        Option<String>,
+        "
+SELECT (metadata -> $2)::TEXT
+FROM notebook
+WHERE note_name = $1
+        ",
+        notename,
+        key
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(value.map(|value| parse_value(&value)))
+}
+
+/// Returns all metadata of note.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn all_meta(notename: &str, pool: &PgPool) -> Result<Map<String, Value>, NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+    protect::check_passphrase(notename, pool).await?;
+
+    let metadata = query_scalar!(
+        String,
+        r#"
+SELECT metadata::TEXT AS "metadata!"
+FROM notebook
+WHERE note_name = $1
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    match parse_value(&metadata) {
+        Value::Object(metadata) => Ok(metadata),
+        _ => Ok(Map::new()),
+    }
+}
+
+/// Returns notes whose metadata `key` is equal to `value`, ordered by notename.
+/// Protected notes (read about them in [`protect` module][crate::protect]) are skipped.
+///
+/// Objects and arrays are compared as in JSONB containment: `["a"]` finds notes
+/// whose array contains `"a"`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn find_by_meta(
+    key: &str,
+    value: &Value,
+    pool: &PgPool,
+) -> Result<Vec<Note>, NotebookError> {
+    let rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE metadata @> $1::TEXT::JSONB
+    AND passphrase_hash IS NULL
+    AND can_access_note(id, owner, $2, false)
+ORDER BY note_name
+        ",
+        entry(key, value),
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(StoredNote::into_note).collect()
+}
+
+impl Note {
+    /// Returns value of metadata `key` of note converted into `T`, `None` if note has no such key.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::InvalidMetadata`] error if value can't be converted into `T`
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+    ///       [`sqlx::Error::RowNotFound`] if note was deleted
+    pub async fn meta<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        pool: &PgPool,
+    ) -> Result<Option<T>, NotebookError> {
+        get_meta(&self.note_name, key, pool)
+            .await?
+            .map(|value| {
+                serde_json::from_value(value).map_err(|err| NotebookError::InvalidMetadata {
+                    key: key.to_owned(),
+                    reason: err.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    /// Sets metadata `key` of note to `value` converted into JSON.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::InvalidMetadata`] error if value can't be converted into JSON
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+    ///       [`sqlx::Error::RowNotFound`] if note was deleted
+    pub async fn set_meta<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        pool: &PgPool,
+    ) -> Result<(), NotebookError> {
+        let value = serde_json::to_value(value).map_err(|err| NotebookError::InvalidMetadata {
+            key: key.to_owned(),
+            reason: err.to_string(),
+        })?;
+
+        set_meta(&self.note_name, key, &value, pool).await
+    }
+}
@@ -0,0 +1,423 @@
+//! Timing of commands and their SQL queries (only with `metrics` feature).
+//!
+//! Every request executed by [`Notebook`][crate::notebook::Notebook] (so every CLI command too)
+//! is recorded: how many times it was executed, how long it took and how many of executions
+//! failed. SQL queries are recorded by [`MetricsLayer`] added to the `tracing` subscriber
+//! of your program: number of queries executed by every command, their duration and number
+//! of rows they returned and changed. [`metrics`] returns everything recorded since the program
+//! started, so you can see which command became slow and why.
+//!
+//! Queries are recorded from events that `sqlx` logs for every query, so they are recorded
+//! only if SQL queries are logged (read about it in [`ConnectOptions`][crate::ConnectOptions])
+//! and events of `sqlx::query` are not filtered out before they get to [`MetricsLayer`].
+//! Add filters of other layers to them with [`Layer::with_filter`], not to the whole subscriber.
+//!
+//! CLI prints metrics of command after it with `--timing`. Server mode (read more in
+//! [`server` module][crate::server]) returns them in format of Prometheus at `GET /metrics`,
+//! see [`render_prometheus`].
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::metrics::{self, MetricsLayer};
+//! # use lnotebook::notebook::{CommandRequest, Notebook};
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! # use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
+//! async fn metrics_example(pool: PgPool) -> Result<(), NotebookError> {
+//!     tracing_subscriber::registry().with(MetricsLayer).init();
+//!
+//!     Notebook::new(pool)
+//!         .execute(CommandRequest::List {
+//!             full: false,
+//!             preview_len: 20,
+//!         })
+//!         .await?;
+//!
+//!     println!("{}", metrics::metrics());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+/// Name of span in which [`Notebook`][crate::notebook::Notebook] executes requests.
+pub(crate) const COMMAND_SPAN: &str = "command";
+
+/// Target of events that `sqlx` logs for every query.
+const QUERY_TARGET: &str = "sqlx::query";
+
+/// Upper bounds of buckets of duration histograms in seconds.
+pub const BUCKETS: [f64; 11] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+static METRICS: Mutex<BTreeMap<String, CommandMetrics>> = Mutex::new(BTreeMap::new());
+
+/// This is a `struct` that containing metrics of one command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandMetrics {
+    /// Name of command, as returned by [`CommandRequest::name`][crate::notebook::CommandRequest::name]
+    pub name: String,
+    /// Number of executions
+    pub executions: u64,
+    /// Number of executions that failed
+    pub errors: u64,
+    /// Total duration of all executions
+    pub total: Duration,
+    /// Number of SQL queries executed by all executions
+    pub queries: u64,
+    /// Total duration of SQL queries
+    pub query_time: Duration,
+    /// Duration of the slowest SQL query
+    pub slowest_query: Duration,
+    /// Number of rows returned by SQL queries
+    pub rows_returned: u64,
+    /// Number of rows changed by SQL queries
+    pub rows_affected: u64,
+    /// Number of executions that took no longer than every of [`BUCKETS`]
+    pub duration_histogram: [u64; BUCKETS.len()],
+    /// Number of SQL queries that took no longer than every of [`BUCKETS`]
+    pub query_histogram: [u64; BUCKETS.len()],
+}
+
+impl CommandMetrics {
+    /// Returns average duration of execution.
+    pub fn average(&self) -> Duration {
+        self.total
+            .checked_div(self.executions.try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default()
+    }
+}
+
+/// This is a `struct` that containing metrics of all commands.
+///
+/// It implements [`fmt::Display`], which renders metrics as a table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Metrics of commands ordered by name
+    pub commands: Vec<CommandMetrics>,
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .commands
+            .iter()
+            .map(|command| command.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Command".len());
+
+        write!(
+            f,
+            "{:<name_width$}  {:>6}  {:>6}  {:>10}  {:>7}  {:>10}  {:>10}  {:>8}  {:>8}",
+            "Command",
+            "Count",
+            "Errors",
+            "Total",
+            "Queries",
+            "SQL",
+            "Slowest",
+            "Returned",
+            "Changed"
+        )?;
+        for command in &self.commands {
+            write!(
+                f,
+                "\n{:<name_width$}  {:>6}  {:>6}  {:>8.1}ms  {:>7}  {:>8.1}ms  {:>8.1}ms  {:>8}  {:>8}",
+                command.name,
+                command.executions,
+                command.errors,
+                command.total.as_secs_f64() * 1000.0,
+                command.queries,
+                command.query_time.as_secs_f64() * 1000.0,
+                command.slowest_query.as_secs_f64() * 1000.0,
+                command.rows_returned,
+                command.rows_affected
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds `duration` into cumulative `histogram` with [`BUCKETS`].
+fn observe(histogram: &mut [u64; BUCKETS.len()], duration: Duration) {
+    let secs = duration.as_secs_f64();
+
+    for (count, bound) in histogram.iter_mut().zip(BUCKETS) {
+        if secs <= bound {
+            *count += 1;
+        }
+    }
+}
+
+/// Changes metrics of command `name`.
+fn update(name: &str, change: impl FnOnce(&mut CommandMetrics)) {
+    let mut metrics = METRICS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let command = metrics
+        .entry(name.to_owned())
+        .or_insert_with(|| CommandMetrics {
+            name: name.to_owned(),
+            ..CommandMetrics::default()
+        });
+    change(command);
+}
+
+/// Records one execution of command `name` that took `duration`.
+pub(crate) fn record_command(name: &str, duration: Duration, failed: bool) {
+    update(name, |command| {
+        command.executions += 1;
+        command.errors += u64::from(failed);
+        command.total += duration;
+        observe(&mut command.duration_histogram, duration);
+    });
+}
+
+/// Returns metrics of all commands recorded since the program started.
+///
+/// Read about metrics [here][crate::metrics].
+pub fn metrics() -> Metrics {
+    let metrics = METRICS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    Metrics {
+        commands: metrics.values().cloned().collect(),
+    }
+}
+
+/// Forgets all recorded metrics.
+pub fn reset() {
+    METRICS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// Returns `name` with characters that can't be in label of Prometheus escaped.
+fn escape_label(name: &str) -> String {
+    name.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Writes histogram `name` of every command into `text`.
+fn write_histogram(
+    text: &mut String,
+    name: &str,
+    help: &str,
+    metrics: &Metrics,
+    histogram: impl Fn(&CommandMetrics) -> (&[u64; BUCKETS.len()], u64, Duration),
+) {
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} histogram");
+    for command in &metrics.commands {
+        let label = escape_label(&command.name);
+        let (buckets, count, sum) = histogram(command);
+
+        for (bucket, bound) in buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                text,
+                "{name}_bucket{{command=\"{label}\",le=\"{bound}\"}} {bucket}"
+            );
+        }
+        let _ = writeln!(
+            text,
+            "{name}_bucket{{command=\"{label}\",le=\"+Inf\"}} {count}"
+        );
+        let _ = writeln!(
+            text,
+            "{name}_sum{{command=\"{label}\"}} {}",
+            sum.as_secs_f64()
+        );
+        let _ = writeln!(text, "{name}_count{{command=\"{label}\"}} {count}");
+    }
+}
+
+/// Returns `metrics` in text format of Prometheus.
+///
+/// Every metric has label `command` with name of command:
+/// * `lnotebook_commands_total` - number of executions
+/// * `lnotebook_command_errors_total` - number of executions that failed
+/// * `lnotebook_command_duration_seconds` - histogram of duration of executions
+/// * `lnotebook_query_duration_seconds` - histogram of duration of SQL queries
+/// * `lnotebook_query_rows_returned_total` - number of rows returned by SQL queries
+/// * `lnotebook_query_rows_affected_total` - number of rows changed by SQL queries
+pub fn render_prometheus(metrics: &Metrics) -> String {
+    let mut text = String::new();
+    let mut counter = |name: &str, help: &str, value: fn(&CommandMetrics) -> u64| {
+        let _ = writeln!(text, "# HELP {name} {help}");
+        let _ = writeln!(text, "# TYPE {name} counter");
+        for command in &metrics.commands {
+            let _ = writeln!(
+                text,
+                "{name}{{command=\"{}\"}} {}",
+                escape_label(&command.name),
+                value(command)
+            );
+        }
+    };
+
+    counter(
+        "lnotebook_commands_total",
+        "Number of executed commands.",
+        |command| command.executions,
+    );
+    counter(
+        "lnotebook_command_errors_total",
+        "Number of commands that failed.",
+        |command| command.errors,
+    );
+    counter(
+        "lnotebook_query_rows_returned_total",
+        "Number of rows returned by SQL queries.",
+        |command| command.rows_returned,
+    );
+    counter(
+        "lnotebook_query_rows_affected_total",
+        "Number of rows changed by SQL queries.",
+        |command| command.rows_affected,
+    );
+
+    write_histogram(
+        &mut text,
+        "lnotebook_command_duration_seconds",
+        "Duration of commands.",
+        metrics,
+        |command| {
+            (
+                &command.duration_histogram,
+                command.executions,
+                command.total,
+            )
+        },
+    );
+    write_histogram(
+        &mut text,
+        "lnotebook_query_duration_seconds",
+        "Duration of SQL queries.",
+        metrics,
+        |command| {
+            (
+                &command.query_histogram,
+                command.queries,
+                command.query_time,
+            )
+        },
+    );
+
+    text
+}
+
+/// Name of command saved in extensions of its span.
+struct CommandName(String);
+
+#[derive(Default)]
+struct CommandVisitor(Option<String>);
+
+impl Visit for CommandVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueryVisitor {
+    elapsed: Duration,
+    rows_returned: u64,
+    rows_affected: u64,
+}
+
+impl Visit for QueryVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "rows_returned" => self.rows_returned = value,
+            "rows_affected" => self.rows_affected = value,
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed = Duration::try_from_secs_f64(value).unwrap_or_default();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+/// Layer of `tracing` subscriber that records SQL queries of commands.
+///
+/// Read about it [here][crate::metrics].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+        if metadata.name() != COMMAND_SPAN || !metadata.target().starts_with("lnotebook") {
+            return;
+        }
+
+        let mut visitor = CommandVisitor::default();
+        attrs.record(&mut visitor);
+
+        if let (Some(name), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(CommandName(name));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != QUERY_TARGET {
+            return;
+        }
+
+        // Queries executed outside of commands are not recorded
+        let Some(name) = ctx.event_scope(event).and_then(|scope| {
+            scope.from_root().find_map(|span| {
+                span.extensions()
+                    .get::<CommandName>()
+                    .map(|name| name.0.clone())
+            })
+        }) else {
+            return;
+        };
+
+        let mut visitor = QueryVisitor::default();
+        event.record(&mut visitor);
+
+        update(&name, |command| {
+            command.queries += 1;
+            command.query_time += visitor.elapsed;
+            command.slowest_query = command.slowest_query.max(visitor.elapsed);
+            command.rows_returned += visitor.rows_returned;
+            command.rows_affected += visitor.rows_affected;
+            observe(&mut command.query_histogram, visitor.elapsed);
+        });
+    }
+}
@@ -0,0 +1,112 @@
+//! Migrations of the database.
+//!
+//! Migrations from the `migrations` directory are built into the crate, so database can be
+//! migrated without `sqlx-cli` using [`migrate`]. Server modes (`serve` and `serve-grpc`
+//! commands) migrate database on startup.
+//!
+//! Several instances of the notebook can start at the same time (e.g. during rolling deployment
+//! of the server), so migrations are serialized with Postgres advisory lock [`MIGRATION_LOCK`]:
+//! the first instance applies migrations, and others wait for it and then check that nothing
+//! is left to apply.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::{connect, get_db_url, ConnectOptions, NotebookError};
+//! async fn migrate_example() -> Result<(), NotebookError> {
+//!     let db = connect(&get_db_url().await?, ConnectOptions::default()).await?;
+//!
+//!     lnotebook::migrate::migrate(&db).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+use crate::sql::{query, query_scalar};
+
+use sqlx::{self, migrate::Migrate, PgPool};
+use std::collections::HashSet;
+use tracing::{event, Level};
+
+/// Key of Postgres advisory lock held while migrations are applied.
+pub const MIGRATION_LOCK: i64 = 0x6c6e_6f74_6562_6f6f;
+
+/// Applies migrations that are not applied yet.
+///
+/// If other instance is applying migrations, waits until it's finished.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::Migrate`] if migration failed
+pub async fn migrate(pool: &PgPool) -> Result<(), NotebookError> {
+    let mut conn = pool.acquire().await?;
+
+    let locked = query_scalar!(
+        bool,
+        r#"SELECT pg_try_advisory_lock($1) AS "locked!""#,
+        MIGRATION_LOCK
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if !locked {
+        event!(
+            Level::INFO,
+            "Other instance is migrating database, waiting for it"
+        );
+
+        query!("SELECT pg_advisory_lock($1)", MIGRATION_LOCK)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    // The lock is already held, so migrator must not take its own
+    let mut migrator = sqlx::migrate!();
+    migrator.set_locking(false);
+
+    let res = migrator.run_direct(&mut *conn).await;
+
+    query_scalar!(
+        Option<bool>,
+        "SELECT pg_advisory_unlock($1)",
+        MIGRATION_LOCK
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    res.map_err(sqlx::Error::from)?;
+
+    event!(Level::INFO, "Database is migrated");
+
+    Ok(())
+}
+
+/// Returns `true` if all migrations are applied to database. Database isn't changed.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn is_migrated(pool: &PgPool) -> Result<bool, NotebookError> {
+    let mut conn = pool.acquire().await?;
+
+    // Table of applied migrations is created by the first migration, listing doesn't create it
+    let has_table = query_scalar!(
+        bool,
+        r#"SELECT to_regclass('_sqlx_migrations') IS NOT NULL AS "exists!""#
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !has_table {
+        return Ok(false);
+    }
+
+    let applied: HashSet<i64> = conn
+        .list_applied_migrations()
+        .await
+        .map_err(sqlx::Error::from)?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    Ok(sqlx::migrate!()
+        .iter()
+        .all(|migration| applied.contains(&migration.version)))
+}
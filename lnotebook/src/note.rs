@@ -0,0 +1,141 @@
+//! Notes as they're returned by the notebook.
+//!
+//! [`Note`] and [`NoteSummary`] are returned both by [`commands` module][crate::commands] and by
+//! [`NoteStore`][crate::store::NoteStore]s, and they are the only types of notes in builds
+//! without `postgres` feature, like a web frontend that uses [`Client`][crate::client::Client].
+
+#[cfg(feature = "postgres")]
+use crate::stats::NotePreview;
+
+use chrono::{DateTime, Local};
+
+/// This is a `struct` that containing information about notes.
+///
+/// This `struct` is returned by `functions` from [`command` module][`crate::commands`]:
+/// * [`add`][crate::commands::add]
+/// * [`upd`][crate::commands::upd]
+/// * [`upd_notename`][crate::commands::upd_notename]
+/// * [`append`][crate::commands::append]
+/// * [`prepend`][crate::commands::prepend]
+/// * [`insert_at_line`][crate::commands::insert_at_line]
+/// * [`select_one`][crate::commands::select_one]
+/// * [`del`][crate::commands::del]
+///
+/// and by every [`NoteStore`][crate::store::NoteStore].
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::store::NoteStore;
+/// # use lnotebook::NotebookError;
+/// async fn struct_example(store: &dyn NoteStore) -> Result<(), NotebookError> {
+///     // `add()` returns struct `Note` that we can use later as we wish
+///     let row = store.add("early_sleep", "I'll go to bed early today").await?;
+///
+///     assert_eq!("early_sleep", row.note_name);
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// With `serde` feature it implements `Serialize` and `Deserialize`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Note {
+    pub id: i32,
+    pub note: Option<String>,
+    pub note_name: String,
+}
+
+impl Note {
+    /// Returns content of note, empty `&str` if note has no content.
+    /// ### Example
+    /// ```
+    /// # use lnotebook::note::Note;
+    /// let note = Note {
+    ///     id: 1,
+    ///     note: None,
+    ///     note_name: "empty".to_owned(),
+    /// };
+    ///
+    /// assert_eq!("", note.body());
+    /// ```
+    pub fn body(&self) -> &str {
+        self.note.as_deref().unwrap_or_default()
+    }
+
+    /// Returns first paragraph of note cut to `max_len` characters, its number of words and
+    /// estimated reading time, read more [here][NotePreview] (only with `postgres` feature).
+    /// ### Example
+    /// ```
+    /// # use lnotebook::note::Note;
+    /// let note = Note {
+    ///     id: 1,
+    ///     note: Some("Milk, bread\nand eggs.\n\nFor the weekend.".to_owned()),
+    ///     note_name: "shopping".to_owned(),
+    /// };
+    /// let summary = note.summary(60);
+    ///
+    /// assert_eq!("Milk, bread and eggs.", summary.paragraph);
+    /// assert_eq!(7, summary.words);
+    /// ```
+    #[cfg(feature = "postgres")]
+    pub fn summary(&self, max_len: usize) -> NotePreview {
+        NotePreview::of(self.body(), max_len)
+    }
+}
+
+/// This is a `struct` that containing short information about note.
+///
+/// Unlike [`Note`] it doesn't contain whole content of note, only the preview
+/// of its first line. This `struct` is returned by [`list`][crate::commands::list]
+/// and [`NoteStore::search`][crate::store::NoteStore::search].
+pub struct NoteSummary {
+    pub id: i32,
+    pub note_name: String,
+    /// First line of note, cut to [`PREVIEW_LEN`] characters (or other length
+    /// given to [`list_previews`][crate::commands::list_previews])
+    pub preview: String,
+    /// `true` if note has more content than [`preview`][NoteSummary::preview]
+    pub truncated: bool,
+    /// Number of lines in note
+    pub lines: i64,
+    /// When note was changed or renamed last time, `None` if it's unknown
+    pub updated_at: Option<DateTime<Local>>,
+}
+
+impl NoteSummary {
+    /// Returns summary of note with content `note`, the same as queries of
+    /// [`list_previews`][crate::commands::list_previews] make in the database. It's used for
+    /// compressed notes, that the database can't read.
+    #[cfg(feature = "postgres")]
+    pub(crate) fn of(
+        id: i32,
+        note_name: String,
+        note: &str,
+        preview_len: i32,
+        updated_at: Option<DateTime<Local>>,
+    ) -> NoteSummary {
+        let preview: String = note
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(preview_len.max(0) as usize)
+            .collect();
+
+        NoteSummary {
+            id,
+            note_name,
+            truncated: note.trim_end_matches('\n').chars().count() > preview.chars().count(),
+            lines: if note.is_empty() {
+                0
+            } else {
+                note.split('\n').count() as i64
+            },
+            preview,
+            updated_at,
+        }
+    }
+}
+
+/// Max number of characters in [`NoteSummary::preview`] by default.
+pub const PREVIEW_LEN: i32 = 60;
@@ -0,0 +1,1558 @@
+//! Typed commands for embedding the notebook into other programs.
+//!
+//! Every CLI command (read about them in `execute_commands` module) has [`CommandRequest`]
+//! that does the same thing, but takes everything that CLI asks user for as fields.
+//! Requests are executed by [`Notebook::execute`], which returns [`CommandResponse`]
+//! with data instead of printing it. CLI itself is built on top of this module,
+//! so programs that use it get the same behavior as CLI.
+//!
+//! Hooks added with [`Notebook::on_command`] are called after every executed request,
+//! for example to log requests or refresh user interface.
+//!
+//! Notebook created with [`Notebook::as_user`] acts as user of notebook shared by several people:
+//! it sees only notes of the user and shared notes (read more in [`users` module][crate::users]).
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::notebook::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn notebook_example(pool: PgPool) -> Result<(), NotebookError> {
+//!     let notebook = Notebook::new(pool).on_command(|request, res| {
+//!         println!("`{}` executed, success: {}", request.name(), res.is_ok());
+//!     });
+//!
+//!     notebook
+//!         .execute(CommandRequest::AddNote {
+//!             notename: "shopping".to_owned(),
+//!             note: "milk".to_owned(),
+//!         })
+//!         .await?;
+//!
+//!     let res = notebook
+//!         .execute(CommandRequest::DisplayNote {
+//!             notename: "shopping".to_owned(),
+//!             lines: None,
+//!         })
+//!         .await?;
+//!
+//!     if let CommandResponse::Note(note) = res {
+//!         assert_eq!(Some("milk".to_owned()), note.note);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::attachments::{self, Attachment};
+use crate::audit::{self, AuditEntry};
+use crate::batch::BatchReport;
+use crate::commands::{self, NamePattern, Note, NoteReplacement, NoteSummary, OnConflict};
+use crate::compact::{self, CompactPolicies, CompactProgress};
+use crate::config::Config;
+use crate::dedup::{self, DuplicateGroup};
+use crate::diff::{self, DiffWith};
+use crate::due::{self, DueNote};
+use crate::errors::NotebookError;
+#[cfg(feature = "git")]
+use crate::git::GitRepo;
+use crate::highlights::{self, Highlight};
+use crate::history::{self, HistoryCompaction, HistoryStats, Revision};
+use crate::journal;
+use crate::linkcheck::{self, BrokenLink};
+use crate::links::{self, GraphFormat, Link, LinkGraph};
+use crate::lint::{self, Finding, Linter};
+use crate::lock;
+use crate::notename::NoteName;
+use crate::protect;
+use crate::search::{self, GrepMatch, ProfilesSearch};
+use crate::split::{self, SplitBy};
+use crate::sql::query_scalar;
+use crate::stats::{self, NotePreview, NoteStats, NotebookStats};
+use crate::store::{self, NoteStore, PgStore};
+use crate::sync::{self, ConflictPolicy, RemoteSyncReport};
+use crate::templates::{self, NoteTemplate};
+use crate::users::{self, SharedNote, User};
+#[cfg(feature = "webhooks")]
+use crate::webhook::{self, ChangeKind, WebhookEvent};
+
+use sqlx::PgPool;
+use std::{future::Future, sync::Arc};
+use tracing::{event, Instrument as _, Level};
+
+/// Command that can be executed by [`Notebook`].
+///
+/// Every variant does the same as CLI command with the same name.
+#[derive(Debug, Clone)]
+pub enum CommandRequest {
+    /// Returns short information about all notes with previews cut to `preview_len` characters,
+    /// or whole notes if `full` is `true`
+    List {
+        full: bool,
+        preview_len: i32,
+    },
+    AddNote {
+        notename: String,
+        note: String,
+    },
+    /// Adds note named after its first line or heading, with suffix like `_2` if the name
+    /// is taken (read more in [`NoteName::from_body`]). Name is normalized if `normalize` is `true`
+    AddAutoNamed {
+        note: String,
+        normalize: bool,
+    },
+    /// Adds binary note, read about them in [`add_binary`][commands::add_binary]
+    AddBinary {
+        notename: String,
+        data: Vec<u8>,
+    },
+    DelNote {
+        notename: String,
+    },
+    DelAll,
+    /// Returns names of notes that match pattern
+    SelectMatching {
+        pattern: NamePattern,
+    },
+    DelMatching {
+        pattern: NamePattern,
+    },
+    /// Deletes notes by their names, read about it in [`del_many`][commands::del_many]
+    DelNames {
+        notenames: Vec<String>,
+    },
+    ClearNote {
+        notename: String,
+    },
+    UpdNotename {
+        notename: String,
+        new_notename: String,
+        rewrite_links: bool,
+        on_conflict: OnConflict,
+    },
+    RenameMatching {
+        pattern: String,
+        replacement: String,
+    },
+    ReplaceAll {
+        pattern: String,
+        replacement: String,
+        dry_run: bool,
+    },
+    UpdNote {
+        notename: String,
+        note: String,
+    },
+    /// Replaces content of note only if it wasn't changed since it had hash `base_hash`,
+    /// read more in [`upd_if_unchanged`][commands::upd_if_unchanged]
+    UpdIfUnchanged {
+        notename: String,
+        base_hash: String,
+        note: String,
+    },
+    AppendNote {
+        notename: String,
+        text: String,
+    },
+    PrependNote {
+        notename: String,
+        text: String,
+    },
+    InsertNote {
+        notename: String,
+        line: u32,
+        text: String,
+    },
+    /// Adds `text` into journal note of today with name in `name_format`
+    Journal {
+        text: String,
+        name_format: String,
+    },
+    /// Returns the whole note, or only lines from `start` to `end` if `lines` is `Some((start, end))`
+    DisplayNote {
+        notename: String,
+        lines: Option<(u32, u32)>,
+    },
+    /// Returns all notes whose names start with `prefix`, like `display-note <prefix>*`
+    DisplayPrefix {
+        prefix: String,
+    },
+    /// Returns only content of note, see [`CommandResponse::exit_code`]
+    Cat {
+        notename: String,
+    },
+    /// Returns content of note as bytes, see [`CommandResponse::exit_code`]
+    GetBinary {
+        notename: String,
+    },
+    Search {
+        query: String,
+        all_profiles: bool,
+    },
+    /// Returns lines of notes that match regular expression, `names_only` is used only to display them
+    Grep {
+        pattern: String,
+        ignore_case: bool,
+        names_only: bool,
+    },
+    History {
+        notename: String,
+    },
+    DisplayRevision {
+        revision: i32,
+    },
+    /// Returns unified diff of note and other note or its revision with `context` unchanged lines
+    /// around changes, read more [here][crate::diff]
+    Diff {
+        notename: String,
+        with: DiffWith,
+        context: usize,
+    },
+    Stats,
+    /// Returns number of words, lines, characters and bytes in note
+    CountWords {
+        notename: String,
+    },
+    /// Returns the first paragraph of note cut to `max_len` characters, its number of words
+    /// and reading time
+    Summary {
+        notename: String,
+        max_len: usize,
+    },
+    /// Returns problems in note found by [standard linter][crate::lint::Linter::standard],
+    /// with spellcheck if `dictionary` is given
+    Lint {
+        notename: String,
+        max_line_len: usize,
+        dictionary: Option<std::path::PathBuf>,
+    },
+    /// Checks if note exists, see [`CommandResponse::exit_code`]
+    Exists {
+        notename: String,
+    },
+    Count,
+    RandomNote,
+    /// Returns links from note to other notes
+    Links {
+        notename: String,
+    },
+    /// Returns names of notes that link to note
+    Backlinks {
+        notename: String,
+    },
+    /// Returns links of all notes, `format` is used only to display graph
+    ExportGraph {
+        format: GraphFormat,
+    },
+    /// Returns broken links of all notes, URLs are checked only if `http` is `true`
+    CheckLinks {
+        http: bool,
+    },
+    Attach {
+        notename: String,
+        path: std::path::PathBuf,
+    },
+    Attachments {
+        notename: String,
+    },
+    SaveAttachment {
+        notename: String,
+        file_name: String,
+        dest: std::path::PathBuf,
+    },
+    /// Highlights lines from `start_line` to `end_line` of note, read more [here][crate::highlights]
+    Highlight {
+        notename: String,
+        start_line: u32,
+        end_line: u32,
+    },
+    /// Returns highlights of note, or of all notes if `notename` is `None`
+    Highlights {
+        notename: Option<String>,
+    },
+    Compact {
+        batch_size: i64,
+        /// Size in bytes from which contents of notes and revisions are compressed,
+        /// `None` to not compress
+        compress_threshold: Option<usize>,
+        /// Rename notes to normalized names
+        normalize_names: bool,
+    },
+    Dedup {
+        merge: bool,
+    },
+    SetDue {
+        notename: String,
+        due_at: String,
+    },
+    ClearDue {
+        notename: String,
+    },
+    Due,
+    AddTemplate {
+        template_name: String,
+        template: String,
+    },
+    DelTemplate {
+        template_name: String,
+    },
+    DisplayTemplates,
+    NewFromTemplate {
+        template_name: String,
+        notename: String,
+    },
+    /// Adds template made from note, `vars` are `(key, value)` pairs
+    TemplateFrom {
+        notename: String,
+        template_name: String,
+        vars: Vec<(String, String)>,
+    },
+    #[cfg(feature = "metadata")]
+    SetMeta {
+        notename: String,
+        key: String,
+        value: serde_json::Value,
+    },
+    /// Returns value of `key`, or all metadata if `key` is `None`
+    #[cfg(feature = "metadata")]
+    GetMeta {
+        notename: String,
+        key: Option<String>,
+    },
+    #[cfg(feature = "metadata")]
+    FindByMeta {
+        key: String,
+        value: serde_json::Value,
+    },
+    #[cfg(feature = "crypto")]
+    Protect {
+        notename: String,
+        passphrase: String,
+    },
+    #[cfg(feature = "crypto")]
+    Unprotect {
+        notename: String,
+        passphrase: String,
+    },
+    #[cfg(feature = "export")]
+    ExportMarkdown {
+        dir: std::path::PathBuf,
+        template: Option<std::path::PathBuf>,
+    },
+    #[cfg(feature = "export")]
+    ExportHtml {
+        dir: std::path::PathBuf,
+    },
+    #[cfg(feature = "export")]
+    Export {
+        path: std::path::PathBuf,
+        passphrase: Option<String>,
+    },
+    #[cfg(feature = "export")]
+    Import {
+        path: std::path::PathBuf,
+        passphrase: Option<String>,
+        fail_fast: bool,
+    },
+    /// Imports notes exported by other note app, read more [here][crate::import]
+    #[cfg(feature = "export")]
+    ImportFrom {
+        format: crate::import::ImportFormat,
+        path: std::path::PathBuf,
+        fail_fast: bool,
+    },
+    #[cfg(feature = "export")]
+    ExportArchive {
+        path: std::path::PathBuf,
+        passphrase: Option<String>,
+    },
+    #[cfg(feature = "export")]
+    ImportArchive {
+        path: std::path::PathBuf,
+        passphrase: Option<String>,
+        fail_fast: bool,
+    },
+    Dump {
+        path: std::path::PathBuf,
+    },
+    Restore {
+        path: std::path::PathBuf,
+    },
+    Sync {
+        remote_url: String,
+        policy: ConflictPolicy,
+        dry_run: bool,
+    },
+    #[cfg(feature = "export")]
+    ExportCsv {
+        path: std::path::PathBuf,
+    },
+    #[cfg(feature = "export")]
+    ImportCsv {
+        path: std::path::PathBuf,
+        fail_fast: bool,
+    },
+    LockNote {
+        notename: String,
+    },
+    /// Unlocks note locked by the current user, or by anyone if `force` is `true`
+    UnlockNote {
+        notename: String,
+        force: bool,
+    },
+    /// Returns recorded commands, only ones executed since `since` if it's `Some`
+    Audit {
+        since: Option<String>,
+    },
+    /// Creates user `name`, admin if `admin` is `true`
+    AddUser {
+        name: String,
+        admin: bool,
+    },
+    /// Deletes user `name`, its notes become shared
+    DelUser {
+        name: String,
+    },
+    /// Returns all users
+    Users,
+    /// Makes user `owner` owner of note, or makes note shared if `owner` is `None`
+    Chown {
+        notename: String,
+        owner: Option<String>,
+    },
+    /// Shares note with user `user`, read-only if `read_only` is `true`
+    ShareNote {
+        notename: String,
+        user: String,
+        read_only: bool,
+    },
+    /// Stops sharing note with user `user`
+    UnshareNote {
+        notename: String,
+        user: String,
+    },
+    /// Returns notes shared with user `user`
+    SharedWith {
+        user: String,
+    },
+    /// Splits note into notes with its sections, read more [here][crate::split]
+    SplitNote {
+        notename: String,
+        by: SplitBy,
+        delimiter: String,
+        keep: bool,
+    },
+}
+
+impl CommandRequest {
+    /// Returns name of CLI command of request, like `add-note`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandRequest::List { .. } => "list",
+            CommandRequest::AddNote { .. } | CommandRequest::AddAutoNamed { .. } => "add-note",
+            CommandRequest::AddBinary { .. } => "add-binary",
+            CommandRequest::DelNote { .. } => "del-note",
+            CommandRequest::DelAll => "del-all",
+            CommandRequest::SelectMatching { .. } => "select-matching",
+            CommandRequest::DelMatching { .. } => "del-matching",
+            CommandRequest::DelNames { .. } => "del-names",
+            CommandRequest::ClearNote { .. } => "clear-note",
+            CommandRequest::UpdNotename { .. } => "upd-notename",
+            CommandRequest::RenameMatching { .. } => "rename-matching",
+            CommandRequest::ReplaceAll { .. } => "sed",
+            CommandRequest::UpdNote { .. } | CommandRequest::UpdIfUnchanged { .. } => "upd-note",
+            CommandRequest::AppendNote { .. } => "append-note",
+            CommandRequest::Journal { .. } => "journal",
+            CommandRequest::PrependNote { .. } => "prepend-note",
+            CommandRequest::InsertNote { .. } => "insert-note",
+            CommandRequest::DisplayNote { .. } => "display-note",
+            CommandRequest::DisplayPrefix { .. } => "display-note",
+            CommandRequest::Cat { .. } => "cat",
+            CommandRequest::GetBinary { .. } => "get-binary",
+            CommandRequest::Search { .. } => "search",
+            CommandRequest::Grep { .. } => "grep",
+            CommandRequest::History { .. } => "history",
+            CommandRequest::DisplayRevision { .. } => "display-revision",
+            CommandRequest::Diff { .. } => "diff",
+            CommandRequest::Stats => "stats",
+            CommandRequest::CountWords { .. } => "count-words",
+            CommandRequest::Summary { .. } => "summary",
+            CommandRequest::Lint { .. } => "lint",
+            CommandRequest::Exists { .. } => "exists",
+            CommandRequest::Count => "count",
+            CommandRequest::RandomNote => "random-note",
+            CommandRequest::Links { .. } => "links",
+            CommandRequest::Backlinks { .. } => "backlinks",
+            CommandRequest::ExportGraph { .. } => "export-graph",
+            CommandRequest::CheckLinks { .. } => "check-links",
+            CommandRequest::Attach { .. } => "attach",
+            CommandRequest::Attachments { .. } => "attachments",
+            CommandRequest::SaveAttachment { .. } => "save-attachment",
+            CommandRequest::Highlight { .. } => "highlight",
+            CommandRequest::Highlights { .. } => "highlights",
+            CommandRequest::Compact { .. } => "compact",
+            CommandRequest::Dedup { .. } => "dedup",
+            CommandRequest::SetDue { .. } => "set-due",
+            CommandRequest::ClearDue { .. } => "clear-due",
+            CommandRequest::Due => "due",
+            CommandRequest::AddTemplate { .. } => "add-template",
+            CommandRequest::DelTemplate { .. } => "del-template",
+            CommandRequest::DisplayTemplates => "display-templates",
+            CommandRequest::NewFromTemplate { .. } => "new-from-template",
+            CommandRequest::TemplateFrom { .. } => "template-from",
+            #[cfg(feature = "metadata")]
+            CommandRequest::SetMeta { .. } => "set-meta",
+            #[cfg(feature = "metadata")]
+            CommandRequest::GetMeta { .. } => "get-meta",
+            #[cfg(feature = "metadata")]
+            CommandRequest::FindByMeta { .. } => "find-by-meta",
+            #[cfg(feature = "crypto")]
+            CommandRequest::Protect { .. } => "protect-note",
+            #[cfg(feature = "crypto")]
+            CommandRequest::Unprotect { .. } => "unprotect-note",
+            #[cfg(feature = "export")]
+            CommandRequest::ExportMarkdown { .. } => "export-markdown",
+            #[cfg(feature = "export")]
+            CommandRequest::ExportHtml { .. } => "export-html",
+            #[cfg(feature = "export")]
+            CommandRequest::Export { .. } => "export",
+            #[cfg(feature = "export")]
+            CommandRequest::Import { .. } | CommandRequest::ImportFrom { .. } => "import",
+            #[cfg(feature = "export")]
+            CommandRequest::ExportArchive { .. } => "export-archive",
+            #[cfg(feature = "export")]
+            CommandRequest::ImportArchive { .. } => "import-archive",
+            CommandRequest::Dump { .. } => "dump",
+            CommandRequest::Restore { .. } => "restore",
+            CommandRequest::Sync { .. } => "sync",
+            #[cfg(feature = "export")]
+            CommandRequest::ExportCsv { .. } => "export-csv",
+            #[cfg(feature = "export")]
+            CommandRequest::ImportCsv { .. } => "import-csv",
+            CommandRequest::LockNote { .. } => "lock-note",
+            CommandRequest::UnlockNote { .. } => "unlock-note",
+            CommandRequest::Audit { .. } => "audit",
+            CommandRequest::AddUser { .. } => "add-user",
+            CommandRequest::DelUser { .. } => "del-user",
+            CommandRequest::Users => "list-users",
+            CommandRequest::Chown { .. } => "chown",
+            CommandRequest::ShareNote { .. } => "share-note",
+            CommandRequest::UnshareNote { .. } => "unshare-note",
+            CommandRequest::SharedWith { .. } => "shared-with-me",
+            CommandRequest::SplitNote { .. } => "split-note",
+        }
+    }
+
+    /// Returns `true` if request changes notes, templates or other data of the notebook.
+    /// Such requests are recorded in the audit log (read more [here][crate::audit]).
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            CommandRequest::AddNote { .. }
+            | CommandRequest::AddAutoNamed { .. }
+            | CommandRequest::AddBinary { .. }
+            | CommandRequest::DelNote { .. }
+            | CommandRequest::DelAll
+            | CommandRequest::DelMatching { .. }
+            | CommandRequest::DelNames { .. }
+            | CommandRequest::ClearNote { .. }
+            | CommandRequest::UpdNotename { .. }
+            | CommandRequest::RenameMatching { .. }
+            | CommandRequest::ReplaceAll { dry_run: false, .. }
+            | CommandRequest::UpdNote { .. }
+            | CommandRequest::UpdIfUnchanged { .. }
+            | CommandRequest::AppendNote { .. }
+            | CommandRequest::Journal { .. }
+            | CommandRequest::PrependNote { .. }
+            | CommandRequest::InsertNote { .. }
+            | CommandRequest::Attach { .. }
+            | CommandRequest::Highlight { .. }
+            | CommandRequest::Compact { .. }
+            | CommandRequest::Dedup { merge: true }
+            | CommandRequest::SetDue { .. }
+            | CommandRequest::ClearDue { .. }
+            | CommandRequest::AddTemplate { .. }
+            | CommandRequest::DelTemplate { .. }
+            | CommandRequest::NewFromTemplate { .. }
+            | CommandRequest::TemplateFrom { .. }
+            | CommandRequest::Restore { .. }
+            | CommandRequest::Sync { dry_run: false, .. }
+            | CommandRequest::LockNote { .. }
+            | CommandRequest::UnlockNote { .. }
+            | CommandRequest::AddUser { .. }
+            | CommandRequest::DelUser { .. }
+            | CommandRequest::Chown { .. }
+            | CommandRequest::ShareNote { .. }
+            | CommandRequest::UnshareNote { .. }
+            | CommandRequest::SplitNote { .. } => true,
+            #[cfg(feature = "metadata")]
+            CommandRequest::SetMeta { .. } => true,
+            #[cfg(feature = "crypto")]
+            CommandRequest::Protect { .. } | CommandRequest::Unprotect { .. } => true,
+            #[cfg(feature = "export")]
+            CommandRequest::Import { .. }
+            | CommandRequest::ImportFrom { .. }
+            | CommandRequest::ImportArchive { .. }
+            | CommandRequest::ImportCsv { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns note, template, pattern or file that request is given, if it's given any.
+    pub fn target(&self) -> Option<String> {
+        match self {
+            CommandRequest::AddNote { notename, .. }
+            | CommandRequest::AddBinary { notename, .. }
+            | CommandRequest::DelNote { notename }
+            | CommandRequest::ClearNote { notename }
+            | CommandRequest::UpdNote { notename, .. }
+            | CommandRequest::UpdIfUnchanged { notename, .. }
+            | CommandRequest::AppendNote { notename, .. }
+            | CommandRequest::PrependNote { notename, .. }
+            | CommandRequest::InsertNote { notename, .. }
+            | CommandRequest::DisplayNote { notename, .. }
+            | CommandRequest::Cat { notename }
+            | CommandRequest::GetBinary { notename }
+            | CommandRequest::History { notename }
+            | CommandRequest::Diff { notename, .. }
+            | CommandRequest::CountWords { notename }
+            | CommandRequest::Summary { notename, .. }
+            | CommandRequest::Lint { notename, .. }
+            | CommandRequest::Exists { notename }
+            | CommandRequest::Links { notename }
+            | CommandRequest::Backlinks { notename }
+            | CommandRequest::Attach { notename, .. }
+            | CommandRequest::Attachments { notename }
+            | CommandRequest::SaveAttachment { notename, .. }
+            | CommandRequest::Highlight { notename, .. }
+            | CommandRequest::SetDue { notename, .. }
+            | CommandRequest::ClearDue { notename }
+            | CommandRequest::NewFromTemplate { notename, .. }
+            | CommandRequest::TemplateFrom { notename, .. }
+            | CommandRequest::LockNote { notename }
+            | CommandRequest::UnlockNote { notename, .. }
+            | CommandRequest::Chown { notename, .. }
+            | CommandRequest::SplitNote { notename, .. } => Some(notename.to_owned()),
+
+            CommandRequest::ShareNote { notename, user, .. }
+            | CommandRequest::UnshareNote { notename, user } => {
+                Some(format!("{notename} -> {user}"))
+            }
+            #[cfg(feature = "metadata")]
+            CommandRequest::SetMeta { notename, .. } | CommandRequest::GetMeta { notename, .. } => {
+                Some(notename.to_owned())
+            }
+            #[cfg(feature = "crypto")]
+            CommandRequest::Protect { notename, .. }
+            | CommandRequest::Unprotect { notename, .. } => Some(notename.to_owned()),
+
+            CommandRequest::UpdNotename {
+                notename,
+                new_notename,
+                ..
+            } => Some(format!("{notename} -> {new_notename}")),
+            CommandRequest::DisplayPrefix { prefix } => Some(format!("{prefix}*")),
+            CommandRequest::DelNames { notenames } => Some(notenames.join(", ")),
+            CommandRequest::Highlights { notename } => notename.to_owned(),
+
+            CommandRequest::SelectMatching { pattern }
+            | CommandRequest::DelMatching { pattern } => match pattern {
+                NamePattern::Glob(pattern) | NamePattern::Regex(pattern) => {
+                    Some(pattern.to_owned())
+                }
+            },
+            CommandRequest::RenameMatching { pattern, .. }
+            | CommandRequest::ReplaceAll { pattern, .. }
+            | CommandRequest::Grep { pattern, .. } => Some(pattern.to_owned()),
+
+            CommandRequest::AddTemplate { template_name, .. }
+            | CommandRequest::DelTemplate { template_name } => Some(template_name.to_owned()),
+
+            CommandRequest::AddUser { name, .. } | CommandRequest::DelUser { name } => {
+                Some(name.to_owned())
+            }
+
+            CommandRequest::Dump { path } | CommandRequest::Restore { path } => {
+                Some(path.display().to_string())
+            }
+            CommandRequest::Sync { remote_url, .. } => sync::remote_key(remote_url).ok(),
+            #[cfg(feature = "export")]
+            CommandRequest::ExportMarkdown { dir: path, .. }
+            | CommandRequest::ExportHtml { dir: path }
+            | CommandRequest::Export { path, .. }
+            | CommandRequest::Import { path, .. }
+            | CommandRequest::ImportFrom { path, .. }
+            | CommandRequest::ExportArchive { path, .. }
+            | CommandRequest::ImportArchive { path, .. }
+            | CommandRequest::ExportCsv { path }
+            | CommandRequest::ImportCsv { path, .. } => Some(path.display().to_string()),
+
+            _ => None,
+        }
+    }
+}
+
+/// Result of [`CommandRequest`] executed by [`Notebook`].
+///
+/// It's also returned by [`NoteCommand::execute_command`][crate::NoteCommand::execute_command]
+/// after CLI command is executed.
+pub enum CommandResponse {
+    /// Command doesn't return any data
+    None,
+    /// Note that was added, changed or requested
+    Note(Note),
+    /// Whole notes
+    Notes(Vec<Note>),
+    /// Short information about notes
+    Summaries(Vec<NoteSummary>),
+    /// Names of notes
+    Notenames(Vec<String>),
+    /// Text, like selected lines of note
+    Text(String),
+    /// Content of note, `None` if note doesn't exist
+    Content(Option<String>),
+    /// Content of note as bytes, `None` if note doesn't exist
+    Bytes(Option<Vec<u8>>),
+    /// Renamed note and the number of notes whose links were rewritten
+    Renamed {
+        note: Note,
+        rewritten_links: u64,
+    },
+    /// Old and new names of renamed notes
+    RenamedMany(Vec<(String, String)>),
+    /// Changes of notes whose content was replaced
+    Replaced(Vec<NoteReplacement>),
+    /// Names of deleted notes
+    Deleted(Vec<String>),
+    ProfilesSearch(ProfilesSearch),
+    Grep(Vec<GrepMatch>),
+    History(Vec<Revision>),
+    Stats(NotebookStats),
+    NoteStats(NoteStats),
+    /// Summary of note
+    Preview(NotePreview),
+    /// Whether note exists
+    Exists(bool),
+    /// Number of notes
+    Count(i64),
+    Links(Vec<Link>),
+    Graph(LinkGraph),
+    Attachment(Attachment),
+    Attachments(Vec<Attachment>),
+    Highlight(Highlight),
+    Highlights(Vec<Highlight>),
+    /// Path of saved file
+    Saved(std::path::PathBuf),
+    Compacted {
+        notes: CompactProgress,
+        history: HistoryCompaction,
+        history_stats: HistoryStats,
+    },
+    /// Groups of notes with identical content, merged ones if duplicates were merged
+    Duplicates(Vec<DuplicateGroup>),
+    Due(Vec<DueNote>),
+    Template(NoteTemplate),
+    Templates(Vec<NoteTemplate>),
+    /// Number of exported notes
+    Exported(usize),
+    /// Number of restored notes
+    Restored(usize),
+    /// Recorded commands
+    Audit(Vec<AuditEntry>),
+    /// User that was added or deleted
+    User(User),
+    Users(Vec<User>),
+    SharedNotes(Vec<SharedNote>),
+    Batch(BatchReport),
+    Synced(RemoteSyncReport),
+    BrokenLinks(Vec<BrokenLink>),
+    /// Problems found in note by linter
+    Findings(Vec<Finding>),
+}
+
+impl CommandResponse {
+    /// Returns exit code that program should exit with after printing the response.
+    ///
+    /// It's `1` if note requested by [`CommandRequest::Exists`], [`CommandRequest::Cat`]
+    /// or [`CommandRequest::GetBinary`] doesn't exist, or if [`CommandRequest::CheckLinks`] found
+    /// broken links or [`CommandRequest::Lint`] found problems, so shell scripts can check it
+    /// without parsing output, and `0` otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommandResponse::Exists(false)
+            | CommandResponse::Content(None)
+            | CommandResponse::Bytes(None) => 1,
+            CommandResponse::BrokenLinks(broken) if !broken.is_empty() => 1,
+            CommandResponse::Findings(findings) if !findings.is_empty() => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Runs command `name` in its own span and records it in metrics (only with `metrics` feature,
+/// read more in [`metrics` module][crate::metrics]).
+pub(crate) async fn run_command<T>(
+    name: &'static str,
+    command: impl Future<Output = Result<T, NotebookError>>,
+) -> Result<T, NotebookError> {
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+
+    let res = command
+        .instrument(tracing::info_span!("command", name))
+        .await;
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::record_command(name, start.elapsed(), res.is_err());
+
+    res
+}
+
+type Hook = dyn Fn(&CommandRequest, &Result<CommandResponse, NotebookError>) + Send + Sync;
+
+/// Notebook that executes [`CommandRequest`]s.
+///
+/// Read more [here][crate::notebook].
+#[derive(Clone)]
+pub struct Notebook {
+    pool: PgPool,
+    store: Arc<dyn NoteStore>,
+    hooks: Vec<Arc<Hook>>,
+    #[cfg(feature = "webhooks")]
+    webhooks: Vec<String>,
+    #[cfg(feature = "git")]
+    git: Option<GitRepo>,
+    user: Option<String>,
+    passphrases: Vec<String>,
+}
+
+impl Notebook {
+    /// Creates notebook that stores notes in database `pool`.
+    pub fn new(pool: PgPool) -> Notebook {
+        Notebook {
+            store: Arc::new(PgStore::new(pool.clone())),
+            pool,
+            hooks: Vec::new(),
+            #[cfg(feature = "webhooks")]
+            webhooks: Vec::new(),
+            #[cfg(feature = "git")]
+            git: None,
+            user: None,
+            passphrases: Vec::new(),
+        }
+    }
+
+    /// Makes notebook keep notes in `store` instead of its database.
+    ///
+    /// Only requests that add, read, update, delete, list (with `full`) and search notes use
+    /// the store, read more [here][crate::store].
+    pub fn with_store(mut self, store: impl NoteStore + 'static) -> Notebook {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Returns database of notebook.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Adds hook that is called after every executed request with its result.
+    pub fn on_command(
+        mut self,
+        hook: impl Fn(&CommandRequest, &Result<CommandResponse, NotebookError>) + Send + Sync + 'static,
+    ) -> Notebook {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Makes notebook call webhooks `urls` after notes are changed (only with `webhooks` feature).
+    ///
+    /// Webhooks are called for requests that return changed note, read more
+    /// [here][crate::webhook].
+    #[cfg(feature = "webhooks")]
+    pub fn with_webhooks(mut self, urls: Vec<String>) -> Notebook {
+        self.webhooks = urls;
+        self
+    }
+
+    /// Makes notebook commit notes into git repository `repo` after notes are changed
+    /// (only with `git` feature).
+    ///
+    /// Notes are committed after every request that changes them, read more [here][crate::git].
+    #[cfg(feature = "git")]
+    pub fn with_git(mut self, repo: GitRepo) -> Notebook {
+        self.git = Some(repo);
+        self
+    }
+
+    /// Makes notebook act as user `user`: notes it adds are owned by the user, and notes
+    /// of other users are not listed, found or changed, read more [here][crate::users].
+    ///
+    /// Requests return [`NotebookError::UnknownUser`] if the user doesn't exist.
+    pub fn as_user(mut self, user: impl Into<String>) -> Notebook {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Makes notebook open protected notes with `passphrase`, read more [here][crate::protect].
+    ///
+    /// Can be called several times to open notes protected with different passphrases.
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Notebook {
+        self.passphrases.push(passphrase.into());
+        self
+    }
+
+    /// Returns name of notebook (name of its database).
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+    pub async fn name(&self) -> Result<String, NotebookError> {
+        let name = query_scalar!(String, r#"SELECT current_database() AS "name!""#)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(name)
+    }
+
+    /// Executes `request`.
+    /// ### Returns
+    /// * Ok
+    ///     * [`CommandResponse`] with data returned by command
+    /// * Errors
+    ///     * Errors of functions that execute command, for example [`NotebookError::AlreadyTaken`]
+    ///       if note with the same name already exists
+    pub async fn execute(&self, request: CommandRequest) -> Result<CommandResponse, NotebookError> {
+        event!(Level::DEBUG, "Execute command `{}`", request.name());
+
+        // Future of dispatch holds futures of all commands, so it's boxed to not overflow
+        // the stack of tasks that execute requests
+        let dispatch = Box::pin(self.dispatch_as_user(&request));
+        let res = run_command(
+            request.name(),
+            protect::with_passphrases(self.passphrases.clone(), dispatch),
+        )
+        .await;
+
+        if res.is_ok() && request.is_mutating() {
+            let target = request.target();
+
+            // Command is already done, so it's not failed if it can't be recorded
+            if let Err(err) = audit::record(request.name(), target.as_deref(), &self.pool).await {
+                event!(
+                    Level::WARN,
+                    "Can't record `{}` in audit log: {}",
+                    request.name(),
+                    err
+                );
+            }
+
+            #[cfg(feature = "git")]
+            self.commit_git(&request).await;
+
+            #[cfg(feature = "webhooks")]
+            self.call_webhooks(&request, &res).await;
+        }
+
+        for hook in &self.hooks {
+            hook(&request, &res);
+        }
+
+        res
+    }
+
+    #[cfg(feature = "git")]
+    async fn commit_git(&self, request: &CommandRequest) {
+        let Some(repo) = &self.git else {
+            return;
+        };
+
+        let message = match request.target() {
+            Some(target) => format!("{} {}", request.name(), target),
+            None => request.name().to_owned(),
+        };
+
+        // Command is already done, so it's not failed if notes can't be committed
+        if let Err(err) = repo.commit_notes(&message, &self.pool).await {
+            event!(
+                Level::WARN,
+                "Can't commit notes into {}: {}",
+                repo.dir().display(),
+                err
+            );
+        }
+    }
+
+    #[cfg(feature = "webhooks")]
+    async fn call_webhooks(
+        &self,
+        request: &CommandRequest,
+        res: &Result<CommandResponse, NotebookError>,
+    ) {
+        if self.webhooks.is_empty() {
+            return;
+        }
+
+        let note = match res {
+            Ok(CommandResponse::Note(note)) | Ok(CommandResponse::Renamed { note, .. }) => note,
+            _ => return,
+        };
+        let kind = match request {
+            CommandRequest::AddNote { .. }
+            | CommandRequest::AddAutoNamed { .. }
+            | CommandRequest::AddBinary { .. }
+            | CommandRequest::NewFromTemplate { .. } => ChangeKind::Insert,
+            CommandRequest::DelNote { .. } => ChangeKind::Delete,
+            _ => ChangeKind::Update,
+        };
+
+        let change = WebhookEvent::new(kind, note.id, &note.note_name, request.name());
+        webhook::notify(&self.webhooks, &change).await;
+    }
+
+    /// Dispatches request acting as the user of notebook, so command functions check that the user
+    /// can execute it and skip notes of other users.
+    async fn dispatch_as_user(
+        &self,
+        request: &CommandRequest,
+    ) -> Result<CommandResponse, NotebookError> {
+        let Some(name) = &self.user else {
+            return self.dispatch(request).await;
+        };
+        let user = users::user(name, &self.pool).await?;
+
+        users::as_user(&user.name, self.dispatch(request)).await
+    }
+
+    async fn dispatch(&self, request: &CommandRequest) -> Result<CommandResponse, NotebookError> {
+        let pool = &self.pool;
+        let store = &*self.store;
+
+        let response = match request {
+            CommandRequest::List { full: true, .. } => CommandResponse::Notes(store.list().await?),
+            CommandRequest::List {
+                full: false,
+                preview_len,
+            } => CommandResponse::Summaries(commands::list_previews(*preview_len, pool).await?),
+
+            CommandRequest::AddNote { notename, note } => {
+                CommandResponse::Note(store.add(notename, note).await?)
+            }
+
+            CommandRequest::AddAutoNamed { note, normalize } => {
+                let notename = NoteName::from_body(note)?;
+                let notename = if *normalize {
+                    NoteName::normalized(&notename).unwrap_or(notename)
+                } else {
+                    notename
+                };
+
+                CommandResponse::Note(store::add_with_free_name(store, &notename, note).await?)
+            }
+
+            CommandRequest::AddBinary { notename, data } => {
+                CommandResponse::Note(commands::add_binary(notename, data.to_owned(), pool).await?)
+            }
+
+            CommandRequest::DelNote { notename } => {
+                CommandResponse::Note(store.delete(notename).await?)
+            }
+
+            CommandRequest::DelAll => CommandResponse::Deleted(commands::del_all(pool).await?),
+
+            CommandRequest::SelectMatching { pattern } => {
+                CommandResponse::Notenames(commands::select_matching(pattern, pool).await?)
+            }
+
+            CommandRequest::DelMatching { pattern } => {
+                CommandResponse::Deleted(commands::del_matching(pattern, pool).await?)
+            }
+
+            CommandRequest::DelNames { notenames } => {
+                CommandResponse::Deleted(commands::del_many(notenames, pool).await?)
+            }
+
+            CommandRequest::ClearNote { notename } => {
+                commands::clear(notename, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::UpdNotename {
+                notename,
+                new_notename,
+                rewrite_links: true,
+                on_conflict,
+            } => {
+                let (note, rewritten_links) =
+                    commands::rename_rewrite_links(notename, new_notename, *on_conflict, pool)
+                        .await?;
+
+                CommandResponse::Renamed {
+                    note,
+                    rewritten_links,
+                }
+            }
+            CommandRequest::UpdNotename {
+                notename,
+                new_notename,
+                rewrite_links: false,
+                on_conflict,
+            } => CommandResponse::Renamed {
+                note: commands::rename(notename, new_notename, *on_conflict, pool).await?,
+                rewritten_links: 0,
+            },
+
+            CommandRequest::RenameMatching {
+                pattern,
+                replacement,
+            } => CommandResponse::RenamedMany(
+                commands::upd_notename_bulk(pattern, replacement, pool).await?,
+            ),
+
+            CommandRequest::ReplaceAll {
+                pattern,
+                replacement,
+                dry_run,
+            } => CommandResponse::Replaced(
+                commands::replace_all(pattern, replacement, *dry_run, pool).await?,
+            ),
+
+            CommandRequest::UpdNote { notename, note } => {
+                CommandResponse::Note(store.update(notename, note).await?)
+            }
+
+            CommandRequest::UpdIfUnchanged {
+                notename,
+                base_hash,
+                note,
+            } => CommandResponse::Note(
+                commands::upd_if_unchanged(notename, base_hash, note, pool).await?,
+            ),
+
+            CommandRequest::Journal { text, name_format } => {
+                CommandResponse::Note(journal::journal(text, name_format, pool).await?)
+            }
+
+            CommandRequest::AppendNote { notename, text } => {
+                CommandResponse::Note(commands::append(notename, text, pool).await?)
+            }
+
+            CommandRequest::PrependNote { notename, text } => {
+                CommandResponse::Note(commands::prepend(notename, text, pool).await?)
+            }
+
+            CommandRequest::InsertNote {
+                notename,
+                line,
+                text,
+            } => {
+                CommandResponse::Note(commands::insert_at_line(notename, *line, text, pool).await?)
+            }
+
+            CommandRequest::DisplayNote {
+                notename,
+                lines: None,
+            } => CommandResponse::Note(store.get(notename).await?),
+            CommandRequest::DisplayNote {
+                notename,
+                lines: Some((start_line, end_line)),
+            } => CommandResponse::Text(
+                commands::select_range(notename, *start_line, *end_line, pool).await?,
+            ),
+
+            CommandRequest::DisplayPrefix { prefix } => {
+                CommandResponse::Notes(commands::select_by_prefix(prefix, pool).await?)
+            }
+
+            CommandRequest::Cat { notename } => match store.get(notename).await {
+                Ok(note) => CommandResponse::Content(Some(note.note.unwrap_or_default())),
+                Err(NotebookError::Sqlx(sqlx::Error::RowNotFound)) => {
+                    CommandResponse::Content(None)
+                }
+                Err(err) => return Err(err),
+            },
+
+            CommandRequest::GetBinary { notename } => {
+                match commands::get_binary(notename, pool).await {
+                    Ok(data) => CommandResponse::Bytes(Some(data)),
+                    Err(NotebookError::Sqlx(sqlx::Error::RowNotFound)) => {
+                        CommandResponse::Bytes(None)
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+
+            CommandRequest::Search {
+                query,
+                all_profiles: true,
+            } => CommandResponse::ProfilesSearch(
+                search::search_profiles(query, pool, &Config::from_env().profiles).await,
+            ),
+            CommandRequest::Search {
+                query,
+                all_profiles: false,
+            } => CommandResponse::Summaries(store.search(query).await?),
+
+            CommandRequest::Grep {
+                pattern,
+                ignore_case,
+                ..
+            } => CommandResponse::Grep(search::grep(pattern, *ignore_case, pool).await?),
+
+            CommandRequest::History { notename } => {
+                CommandResponse::History(history::history(notename, pool).await?)
+            }
+
+            CommandRequest::DisplayRevision { revision } => {
+                CommandResponse::Text(history::select_revision(*revision, pool).await?)
+            }
+            CommandRequest::Diff {
+                notename,
+                with,
+                context,
+            } => CommandResponse::Text(diff::diff(notename, with, *context, pool).await?),
+
+            CommandRequest::Stats => CommandResponse::Stats(stats::stats(pool).await?),
+
+            CommandRequest::CountWords { notename } => {
+                CommandResponse::NoteStats(stats::word_count(notename, pool).await?)
+            }
+            CommandRequest::Summary { notename, max_len } => {
+                CommandResponse::Preview(stats::summary(notename, *max_len, pool).await?)
+            }
+
+            CommandRequest::Lint {
+                notename,
+                max_line_len,
+                dictionary,
+            } => {
+                let linter = Linter::standard(*max_line_len, dictionary.as_deref())?;
+
+                CommandResponse::Findings(lint::lint(notename, &linter, pool).await?)
+            }
+
+            CommandRequest::Exists { notename } => {
+                CommandResponse::Exists(commands::exists(notename, pool).await?)
+            }
+
+            CommandRequest::Count => CommandResponse::Count(commands::count(pool).await?),
+
+            CommandRequest::RandomNote => CommandResponse::Note(commands::random(pool).await?),
+
+            CommandRequest::Links { notename } => {
+                CommandResponse::Links(links::links(notename, pool).await?)
+            }
+
+            CommandRequest::Backlinks { notename } => {
+                CommandResponse::Notenames(links::backlinks(notename, pool).await?)
+            }
+
+            CommandRequest::ExportGraph { .. } => CommandResponse::Graph(links::graph(pool).await?),
+            CommandRequest::CheckLinks { http } => {
+                CommandResponse::BrokenLinks(linkcheck::check_links(*http, pool).await?)
+            }
+
+            CommandRequest::Attach { notename, path } => {
+                CommandResponse::Attachment(attachments::attach(notename, path, pool).await?)
+            }
+
+            CommandRequest::Attachments { notename } => {
+                CommandResponse::Attachments(attachments::attachments(notename, pool).await?)
+            }
+
+            CommandRequest::SaveAttachment {
+                notename,
+                file_name,
+                dest,
+            } => CommandResponse::Saved(
+                attachments::save_attachment(notename, file_name, dest, pool).await?,
+            ),
+
+            CommandRequest::Highlight {
+                notename,
+                start_line,
+                end_line,
+            } => CommandResponse::Highlight(
+                highlights::highlight(notename, *start_line, *end_line, pool).await?,
+            ),
+
+            CommandRequest::Highlights { notename } => CommandResponse::Highlights(
+                highlights::highlights(notename.as_deref(), pool).await?,
+            ),
+
+            CommandRequest::Compact {
+                batch_size,
+                compress_threshold,
+                normalize_names,
+            } => {
+                let policies = CompactPolicies {
+                    compress_threshold: *compress_threshold,
+                    normalize_names: *normalize_names,
+                };
+                let notes = compact::compact(
+                    policies,
+                    *batch_size,
+                    |p| {
+                        event!(
+                            Level::INFO,
+                            "Checked {}/{} notes, rewritten {}, renamed {}",
+                            p.scanned,
+                            p.total,
+                            p.rewritten,
+                            p.renamed
+                        )
+                    },
+                    pool,
+                )
+                .await?;
+
+                CommandResponse::Compacted {
+                    notes,
+                    history: history::compact_history(*compress_threshold, pool).await?,
+                    history_stats: history::history_stats(pool).await?,
+                }
+            }
+
+            CommandRequest::Dedup { merge } => {
+                CommandResponse::Duplicates(dedup::dedup(*merge, pool).await?)
+            }
+
+            CommandRequest::SetDue { notename, due_at } => {
+                due::set_due(notename, due::parse_due(due_at)?, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::ClearDue { notename } => {
+                due::clear_due(notename, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::Due => CommandResponse::Due(due::due(pool).await?),
+
+            CommandRequest::AddTemplate {
+                template_name,
+                template,
+            } => {
+                templates::add_template(template_name, template, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::DelTemplate { template_name } => {
+                templates::del_template(template_name, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::DisplayTemplates => {
+                CommandResponse::Templates(templates::select_all_templates(pool).await?)
+            }
+
+            CommandRequest::NewFromTemplate {
+                template_name,
+                notename,
+            } => CommandResponse::Note(
+                templates::new_from_template(template_name, notename, pool).await?,
+            ),
+
+            CommandRequest::TemplateFrom {
+                notename,
+                template_name,
+                vars,
+            } => {
+                let vars: Vec<(&str, &str)> = vars
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect();
+
+                CommandResponse::Template(
+                    templates::template_from(notename, template_name, &vars, pool).await?,
+                )
+            }
+
+            #[cfg(feature = "metadata")]
+            CommandRequest::SetMeta {
+                notename,
+                key,
+                value,
+            } => {
+                crate::metadata::set_meta(notename, key, value, pool).await?;
+
+                CommandResponse::None
+            }
+
+            #[cfg(feature = "metadata")]
+            CommandRequest::GetMeta { notename, key } => {
+                let value = match key {
+                    Some(key) => crate::metadata::get_meta(notename, key, pool)
+                        .await?
+                        .unwrap_or_default(),
+                    None => crate::metadata::all_meta(notename, pool).await?.into(),
+                };
+
+                CommandResponse::Text(serde_json::to_string_pretty(&value).unwrap_or_default())
+            }
+
+            #[cfg(feature = "metadata")]
+            CommandRequest::FindByMeta { key, value } => {
+                CommandResponse::Notes(crate::metadata::find_by_meta(key, value, pool).await?)
+            }
+
+            #[cfg(feature = "crypto")]
+            CommandRequest::Protect {
+                notename,
+                passphrase,
+            } => {
+                protect::protect(notename, passphrase, pool).await?;
+
+                CommandResponse::None
+            }
+
+            #[cfg(feature = "crypto")]
+            CommandRequest::Unprotect {
+                notename,
+                passphrase,
+            } => {
+                protect::unprotect(notename, passphrase, pool).await?;
+
+                CommandResponse::None
+            }
+
+            #[cfg(feature = "export")]
+            CommandRequest::ExportMarkdown { dir, template } => {
+                let template = match template {
+                    Some(path) => Some(crate::export::Template::from_file(path)?),
+                    None => None,
+                };
+
+                CommandResponse::Exported(
+                    crate::export::export_markdown(dir, template.as_ref(), pool).await?,
+                )
+            }
+
+            #[cfg(feature = "export")]
+            CommandRequest::ExportHtml { dir } => {
+                CommandResponse::Exported(crate::export::export_html(dir, pool).await?)
+            }
+
+            #[cfg(feature = "export")]
+            CommandRequest::Export { path, passphrase } => CommandResponse::Exported(
+                crate::export::export_archive(path, passphrase.as_deref(), pool).await?,
+            ),
+
+            #[cfg(feature = "export")]
+            CommandRequest::Import {
+                path,
+                passphrase,
+                fail_fast,
+            } => CommandResponse::Batch(
+                crate::export::import_archive(path, passphrase.as_deref(), *fail_fast, pool)
+                    .await?,
+            ),
+
+            #[cfg(feature = "export")]
+            CommandRequest::ImportFrom {
+                format,
+                path,
+                fail_fast,
+            } => CommandResponse::Batch(
+                crate::import::import_from(*format, path, *fail_fast, pool).await?,
+            ),
+
+            #[cfg(feature = "export")]
+            CommandRequest::ExportArchive { path, passphrase } => CommandResponse::Exported(
+                crate::export::export_tar(path, passphrase.as_deref(), pool).await?,
+            ),
+
+            #[cfg(feature = "export")]
+            CommandRequest::ImportArchive {
+                path,
+                passphrase,
+                fail_fast,
+            } => CommandResponse::Batch(
+                crate::export::import_tar(path, passphrase.as_deref(), *fail_fast, pool).await?,
+            ),
+
+            CommandRequest::Dump { path } => {
+                CommandResponse::Exported(crate::dump::dump(path, pool).await?)
+            }
+
+            CommandRequest::Restore { path } => {
+                CommandResponse::Restored(crate::dump::restore(path, pool).await?)
+            }
+
+            CommandRequest::Sync {
+                remote_url,
+                policy,
+                dry_run,
+            } => CommandResponse::Synced(sync::sync(remote_url, *policy, *dry_run, pool).await?),
+
+            #[cfg(feature = "export")]
+            CommandRequest::ExportCsv { path } => {
+                CommandResponse::Exported(crate::export::export_csv(path, pool).await?)
+            }
+
+            #[cfg(feature = "export")]
+            CommandRequest::ImportCsv { path, fail_fast } => {
+                CommandResponse::Batch(crate::export::import_csv(path, *fail_fast, pool).await?)
+            }
+
+            CommandRequest::LockNote { notename } => {
+                lock::lock_note(notename, pool).await?;
+
+                CommandResponse::None
+            }
+            CommandRequest::UnlockNote { notename, force } => {
+                lock::unlock_note(notename, *force, pool).await?;
+
+                CommandResponse::None
+            }
+
+            CommandRequest::Audit { since } => {
+                let since = since.as_deref().map(audit::parse_since).transpose()?;
+
+                CommandResponse::Audit(audit::audit(since, pool).await?)
+            }
+
+            CommandRequest::AddUser { name, admin } => {
+                CommandResponse::User(users::add_user(name, *admin, pool).await?)
+            }
+            CommandRequest::DelUser { name } => {
+                CommandResponse::User(users::del_user(name, pool).await?)
+            }
+            CommandRequest::Users => CommandResponse::Users(users::users(pool).await?),
+            CommandRequest::Chown { notename, owner } => {
+                users::set_owner(notename, owner.as_deref(), pool).await?;
+
+                CommandResponse::None
+            }
+            CommandRequest::ShareNote {
+                notename,
+                user,
+                read_only,
+            } => {
+                users::share_note(notename, user, *read_only, pool).await?;
+
+                CommandResponse::None
+            }
+            CommandRequest::UnshareNote { notename, user } => {
+                users::unshare_note(notename, user, pool).await?;
+
+                CommandResponse::None
+            }
+            CommandRequest::SharedWith { user } => {
+                CommandResponse::SharedNotes(users::shared_with(user, pool).await?)
+            }
+            CommandRequest::SplitNote {
+                notename,
+                by,
+                delimiter,
+                keep,
+            } => CommandResponse::Notes(
+                split::split_note(notename, *by, delimiter, *keep, pool).await?,
+            ),
+        };
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,213 @@
+//! Validation and normalization of notenames.
+//!
+//! [`NoteName`] is notename that can be given to a note: it's not empty, not longer than
+//! [`MAX_LEN`] characters, has no spaces at the start and at the end and has no control
+//! characters (like line breaks) and [`FORBIDDEN_CHARS`]. Functions that create notes or
+//! rename them (like [`add`][crate::commands::add] and [`rename`][crate::commands::rename])
+//! return [`NotebookError::InvalidNoteName`] for other names.
+//!
+//! [`NoteName::normalized`] also trims notename, makes it lowercase and replaces spaces
+//! with `_`. CLI normalizes names of new notes if `LNOTEBOOK_NORMALIZE_NAMES` is `true`
+//! (read more in [`config` module][crate::config]). Names of existing notes are changed only
+//! by [`compact`][crate::compact::compact], so notes are still found by names they were
+//! created with until the notebook is compacted.
+//!
+//! [`NoteName::from_body`] derives notename from content of note, for notes added without name
+//! (`add-note --auto-name`), and [`NoteName::with_suffix`] makes names like `todo_2` when
+//! the derived name is already taken.
+//! ### Example
+//! ```
+//! # use lnotebook::notename::NoteName;
+//! # use lnotebook::NotebookError;
+//! # fn main() -> Result<(), NotebookError> {
+//! assert_eq!("shopping list", NoteName::new("shopping list")?.as_str());
+//! assert_eq!("shopping_list", NoteName::normalized("  Shopping List ")?.as_str());
+//!
+//! assert!(NoteName::new("shopping ").is_err());
+//! assert!(NoteName::new("").is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::NotebookError;
+use crate::split;
+
+use std::{fmt, ops::Deref, str::FromStr};
+
+/// Max length of notename in characters.
+pub const MAX_LEN: usize = 255;
+
+/// Characters that can't be used in notenames.
+///
+/// `*` selects notes by prefix in `display-note`, and `[` with `]` make links
+/// (read about links in [`links` module][crate::links]).
+pub const FORBIDDEN_CHARS: [char; 3] = ['*', '[', ']'];
+
+/// Max length of notename derived by [`NoteName::from_body`] in characters.
+pub const AUTO_NAME_LEN: usize = 60;
+
+/// Notename that can be given to a note.
+///
+/// Read what notenames are valid [here][crate::notename].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NoteName(String);
+
+impl NoteName {
+    /// Returns `name` as [`NoteName`] if it's valid.
+    /// ### Returns
+    /// * Ok
+    ///     * [`NoteName`] with `name` as it is
+    /// * Errors
+    ///     * [`NotebookError::InvalidNoteName`] error if `name` is not valid
+    pub fn new(name: &str) -> Result<NoteName, NotebookError> {
+        let invalid = |reason: &str| {
+            Err(NotebookError::InvalidNoteName {
+                notename: name.to_owned(),
+                reason: reason.to_owned(),
+            })
+        };
+
+        if name.is_empty() {
+            return invalid("notename is empty");
+        }
+        if name.chars().count() > MAX_LEN {
+            return invalid(&format!("notename is longer than {MAX_LEN} characters"));
+        }
+        if name.trim() != name {
+            return invalid("notename starts or ends with space");
+        }
+        if name.chars().any(char::is_control) {
+            return invalid("notename contains control characters");
+        }
+        if let Some(c) = name.chars().find(|c| FORBIDDEN_CHARS.contains(c)) {
+            return invalid(&format!("notename contains `{c}`"));
+        }
+
+        Ok(NoteName(name.to_owned()))
+    }
+
+    /// Returns `name` trimmed, in lowercase and with `_` instead of spaces as [`NoteName`].
+    ///
+    /// Several spaces in a row are replaced with one `_`.
+    /// ### Returns
+    /// * Ok
+    ///     * Normalized [`NoteName`]
+    /// * Errors
+    ///     * [`NotebookError::InvalidNoteName`] error if `name` is not valid after normalization
+    pub fn normalized(name: &str) -> Result<NoteName, NotebookError> {
+        NoteName::new(
+            &name
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_")
+                .to_lowercase(),
+        )
+    }
+
+    /// Returns notename derived from `body` of note: its first line that isn't blank,
+    /// or text of the line if it's Markdown heading.
+    ///
+    /// [`FORBIDDEN_CHARS`] and control characters are removed from the line, several spaces
+    /// in a row are replaced with one, and it's cut to [`AUTO_NAME_LEN`] characters.
+    /// ### Returns
+    /// * Ok
+    ///     * [`NoteName`] derived from `body`
+    /// * Errors
+    ///     * [`NotebookError::InvalidNoteName`] error if `body` has no text to derive name from
+    /// ### Example
+    /// ```
+    /// # use lnotebook::notename::NoteName;
+    /// # use lnotebook::NotebookError;
+    /// # fn main() -> Result<(), NotebookError> {
+    /// assert_eq!("Trip to Oslo", NoteName::from_body("\n## Trip  to [Oslo]\nPack the bags")?.as_str());
+    /// assert_eq!("buy milk", NoteName::from_body("buy milk\nand bread")?.as_str());
+    ///
+    /// assert!(NoteName::from_body(" \n# \n").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_body(body: &str) -> Result<NoteName, NotebookError> {
+        let name = body
+            .lines()
+            .map(|line| {
+                let line = split::heading(line.trim()).unwrap_or(line);
+                let line: String = line
+                    .chars()
+                    .filter(|c| !FORBIDDEN_CHARS.contains(c))
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+
+                line.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .find(|line| !line.is_empty())
+            .ok_or_else(|| NotebookError::InvalidNoteName {
+                notename: String::new(),
+                reason: "note has no text to derive notename from".to_owned(),
+            })?;
+
+        let name: String = name.chars().take(AUTO_NAME_LEN).collect();
+
+        NoteName::new(name.trim_end())
+    }
+
+    /// Returns notename with `_<n>` at the end, like `todo_2`.
+    ///
+    /// Notename is cut if it's too long, so the name with suffix is not longer than [`MAX_LEN`].
+    /// ### Example
+    /// ```
+    /// # use lnotebook::notename::NoteName;
+    /// # use lnotebook::NotebookError;
+    /// # fn main() -> Result<(), NotebookError> {
+    /// assert_eq!("todo_2", NoteName::new("todo")?.with_suffix(2).as_str());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_suffix(&self, n: u32) -> NoteName {
+        let suffix = format!("_{n}");
+        let name: String = self.0.chars().take(MAX_LEN - suffix.len()).collect();
+
+        NoteName(format!("{}{}", name.trim_end(), suffix))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for NoteName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Deref for NoteName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for NoteName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for NoteName {
+    type Err = NotebookError;
+
+    fn from_str(name: &str) -> Result<NoteName, NotebookError> {
+        NoteName::new(name)
+    }
+}
+
+impl From<NoteName> for String {
+    fn from(name: NoteName) -> String {
+        name.0
+    }
+}
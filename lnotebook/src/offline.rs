@@ -0,0 +1,631 @@
+//! Queue of changes made while the database is unreachable.
+//!
+//! If `LNOTEBOOK_OFFLINE_QUEUE` is enabled (read about configuration in
+//! [`config` module][crate::config]) and CLI can't connect to the database, `add-note`
+//! and `upd-note` don't fail: their changes are appended to file [`QUEUE_FILE`] in the config
+//! directory. When the database is reachable again, `replay-queue` command applies queued
+//! changes in the order they were made with [`replay`].
+//!
+//! Queued update is applied only if note wasn't changed since the content it replaces was seen:
+//! every change records hash of that content ([`QueuedChange::base_hash`]), and [`replay`]
+//! updates note with [`upd_if_unchanged`][commands::upd_if_unchanged]. CLI remembers hashes
+//! of notes it reads or changes while the database is reachable in file [`BASES_FILE`], and
+//! takes base of update from there or from the previous queued change of the same note.
+//! Changes that can't be applied are not lost: they are moved into file [`REJECTED_FILE`]
+//! next to the queue.
+//!
+//! Changes are applied by [`Notebook`] like commands of CLI, so they are recorded in audit log
+//! (read more in [`audit` module][crate::audit]) and sent to webhooks and git repository
+//! of the notebook.
+//!
+//! Every line of the file contains time when change was queued, operation (`add` or `upd`),
+//! notename, content of note and base hash (empty if it's unknown), separated by tabs. Tabs,
+//! line breaks and backslashes in notename and content are escaped with backslash.
+//!
+//! Content of notes is stored in the queue as plain text, even if the note is protected with
+//! passphrase in the database, so files of the queue are created readable only by their owner
+//! (on Unix). Don't enable the queue on machines where the config directory isn't private.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::offline::*;
+//! # use lnotebook::NotebookError;
+//! # use lnotebook::notebook::Notebook;
+//! # use std::path::Path;
+//! async fn offline_example(notebook: &Notebook) -> Result<(), NotebookError> {
+//!     let path = Path::new("offline.tsv");
+//!
+//!     // Database is unreachable
+//!     push(path, &QueuedChange::new(Operation::Add, "train", "Ideas from the trip"))?;
+//!
+//!     // Later, when it's reachable again
+//!     let report = replay(path, notebook).await?;
+//!     println!("{report}");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands;
+use crate::config::Config;
+use crate::errors::NotebookError;
+use crate::notebook::{CommandRequest, Notebook};
+use crate::pool;
+use crate::protect;
+use crate::sql::query_scalar;
+
+use chrono::{DateTime, Local};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{event, Level};
+
+/// Name of file with queued changes in the config directory.
+pub const QUEUE_FILE: &str = "offline.tsv";
+
+/// Name of file with changes that [`replay`] couldn't apply, next to the queue.
+pub const REJECTED_FILE: &str = "offline-rejected.tsv";
+
+/// Name of file with hashes of notes as CLI saw them last time, in the config directory.
+pub const BASES_FILE: &str = "offline-bases.tsv";
+
+/// Operations that can be queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Add a new note, like [`commands::add`]
+    Add,
+    /// Replace content of note, like [`commands::upd`]
+    Upd,
+}
+
+impl Operation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Add => "add",
+            Operation::Upd => "upd",
+        }
+    }
+}
+
+/// This is a `struct` that containing one change waiting in the queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedChange {
+    /// Time when change was queued
+    pub queued_at: DateTime<Local>,
+    pub operation: Operation,
+    pub note_name: String,
+    pub note: String,
+    /// Hash of content that update replaces (made by [`hash_content`][commands::hash_content]),
+    /// `None` if it's unknown
+    pub base_hash: Option<String>,
+}
+
+impl QueuedChange {
+    /// Creates change queued now.
+    pub fn new(operation: Operation, notename: &str, note: &str) -> QueuedChange {
+        QueuedChange {
+            queued_at: Local::now(),
+            operation,
+            note_name: notename.to_owned(),
+            note: note.to_owned(),
+            base_hash: None,
+        }
+    }
+
+    /// Makes update replace only content with hash `hash`, read more [here][crate::offline].
+    pub fn based_on(mut self, hash: impl Into<String>) -> QueuedChange {
+        self.base_hash = Some(hash.into());
+        self
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            self.queued_at.to_rfc3339(),
+            self.operation.as_str(),
+            escape(&self.note_name),
+            escape(&self.note),
+            self.base_hash.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<QueuedChange> {
+        let mut fields = line.split('\t');
+
+        let queued_at = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let operation = match fields.next()? {
+            "add" => Operation::Add,
+            "upd" => Operation::Upd,
+            _ => return None,
+        };
+        let note_name = unescape(fields.next()?)?;
+        let note = unescape(fields.next()?)?;
+        // Lines queued before base hashes were recorded have no hash
+        let base_hash = fields.next().filter(|hash| !hash.is_empty());
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(QueuedChange {
+            queued_at: queued_at.with_timezone(&Local),
+            operation,
+            note_name,
+            note,
+            base_hash: base_hash.map(str::to_owned),
+        })
+    }
+}
+
+impl fmt::Display for QueuedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} `{}`",
+            self.queued_at.format("%Y-%m-%d %H:%M"),
+            self.operation.as_str(),
+            self.note_name
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => res += "\\\\",
+            '\t' => res += "\\t",
+            '\n' => res += "\\n",
+            '\r' => res += "\\r",
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => res.push('\\'),
+            't' => res.push('\t'),
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(res)
+}
+
+/// Returns path of file with queued changes, `None` if config directory is unknown.
+pub fn queue_path(config: &Config) -> Option<PathBuf> {
+    config.config_dir.as_ref().map(|dir| dir.join(QUEUE_FILE))
+}
+
+/// Returns path of file with changes that [`replay`] couldn't apply from queue `path`.
+pub fn rejected_path(path: &Path) -> PathBuf {
+    path.with_file_name(REJECTED_FILE)
+}
+
+/// Returns path of file with hashes of notes seen by CLI, `None` if config directory is unknown.
+pub fn bases_path(config: &Config) -> Option<PathBuf> {
+    config.config_dir.as_ref().map(|dir| dir.join(BASES_FILE))
+}
+
+/// Opens file `path` to write, creating it and its directory if needed. New file is readable
+/// only by its owner on Unix, because it contains notes.
+fn open_private(path: &Path, append: bool) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)
+}
+
+/// Returns `true` if `err` means that the database can't be reached right now,
+/// so changes should be queued.
+pub fn is_unreachable(err: &NotebookError) -> bool {
+    matches!(err, NotebookError::Sqlx(err) if pool::is_transient(err))
+}
+
+/// Appends `change` to queue in file `path`, creating file and its directory if needed.
+///
+/// If other change of the same note is already queued, update is based on content of the last
+/// of them instead of [`QueuedChange::base_hash`], because it replaces that content.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read or written
+pub fn push(path: &Path, change: &QueuedChange) -> Result<(), NotebookError> {
+    let mut change = change.clone();
+    if change.operation == Operation::Upd {
+        let queued = load(path)?
+            .into_iter()
+            .rev()
+            .find(|queued| queued.note_name == change.note_name);
+        if let Some(queued) = queued {
+            change.base_hash = Some(commands::hash_content(&queued.note));
+        }
+    }
+
+    open_private(path, true)?.write_all(change.to_line().as_bytes())?;
+
+    event!(Level::INFO, "Queue `{}` into `{}`", change, path.display());
+
+    Ok(())
+}
+
+fn load_bases(path: &Path) -> Result<BTreeMap<String, String>, NotebookError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    // Damaged lines are skipped: without base the update is checked by time of the last change
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (notename, hash) = line.split_once('\t')?;
+            Some((unescape(notename)?, hash.to_owned()))
+        })
+        .collect())
+}
+
+/// Remembers in file `path` that note `notename` had content with hash `hash` when CLI saw it,
+/// so update of note queued later is based on it.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read or written
+pub fn remember_base(path: &Path, notename: &str, hash: &str) -> Result<(), NotebookError> {
+    let mut bases = load_bases(path)?;
+    if bases.get(notename).is_some_and(|known| known == hash) {
+        return Ok(());
+    }
+    bases.insert(notename.to_owned(), hash.to_owned());
+
+    let text: String = bases
+        .iter()
+        .map(|(notename, hash)| format!("{}\t{hash}\n", escape(notename)))
+        .collect();
+    open_private(path, false)?.write_all(text.as_bytes())?;
+
+    Ok(())
+}
+
+/// Returns hash of note `notename` remembered in file `path` by [`remember_base`].
+/// ### Returns
+/// * Ok
+///     * Hash, `None` if CLI didn't see note
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read
+pub fn remembered_base(path: &Path, notename: &str) -> Result<Option<String>, NotebookError> {
+    Ok(load_bases(path)?.remove(notename))
+}
+
+/// Reads queued changes from file `path` in the order they were queued.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`QueuedChange`], empty if file doesn't exist
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read or is damaged
+pub fn load(path: &Path) -> Result<Vec<QueuedChange>, NotebookError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            QueuedChange::from_line(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {} of `{}` is damaged", i + 1, path.display()),
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Writes `changes` into file `path` instead of its content, deleting file if there are none.
+fn save(path: &Path, changes: &[QueuedChange]) -> Result<(), NotebookError> {
+    if changes.is_empty() {
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        };
+    }
+
+    let text: String = changes.iter().map(QueuedChange::to_line).collect();
+    open_private(path, false)?.write_all(text.as_bytes())?;
+
+    Ok(())
+}
+
+/// This is a `struct` that containing results of [`replay`].
+///
+/// It implements [`fmt::Display`], which renders results line by line.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Changes that were applied
+    pub applied: Vec<QueuedChange>,
+    /// Changes that can't be applied with the errors why, they are moved from queue
+    /// into [`rejected_path`]
+    pub failed: Vec<(QueuedChange, NotebookError)>,
+    /// Number of changes left in queue because database became unreachable
+    pub left: usize,
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.applied {
+            writeln!(f, "Applied: {change}")?;
+        }
+        for (change, err) in &self.failed {
+            writeln!(f, "Failed: {change}: {err}")?;
+        }
+        write!(
+            f,
+            "Applied {} changes, {} failed (kept in `{}`), {} left in queue",
+            self.applied.len(),
+            self.failed.len(),
+            REJECTED_FILE,
+            self.left
+        )
+    }
+}
+
+/// Replaces content of note like [`commands::upd_if_unchanged`], unless note is protected:
+/// passphrase of protected note can't be asked when it was queued.
+///
+/// Without base hash note is updated only if it wasn't changed since the change was queued.
+async fn upd_queued(change: &QueuedChange, notebook: &Notebook) -> Result<(), NotebookError> {
+    let notename = &change.note_name;
+    let pool = notebook.pool();
+    if protect::is_protected(notename, pool).await? {
+        return Err(NotebookError::NoteProtected {
+            notename: notename.to_owned(),
+        });
+    }
+
+    let base_hash = match &change.base_hash {
+        Some(hash) => hash.to_owned(),
+        None => {
+            let changed = query_scalar!(
+                bool,
+                r#"
+SELECT updated_at > $2 AS "changed!"
+FROM notebook
+WHERE note_name = $1
+                "#,
+                notename,
+                change.queued_at
+            )
+            .fetch_one(pool)
+            .await?;
+            if changed {
+                return Err(NotebookError::Conflict {
+                    notename: notename.to_owned(),
+                });
+            }
+
+            commands::content_hash(notename, pool).await?
+        }
+    };
+
+    let request = CommandRequest::UpdIfUnchanged {
+        notename: notename.to_owned(),
+        base_hash,
+        note: change.note.to_owned(),
+    };
+    notebook.execute(request).await?;
+
+    Ok(())
+}
+
+/// Applies changes queued in file `path` in the order they were queued.
+///
+/// Changes that can't be applied (for example, note to add already exists, note to update
+/// was changed since its base or is protected with passphrase) are reported and moved
+/// into file [`rejected_path`], where they can be looked at and applied by hand. If
+/// the database becomes unreachable again, replaying stops and the rest of changes are left
+/// in queue.
+/// ### Returns
+/// * Ok
+///     * [`SyncReport`] with results of all changes
+/// * Errors
+///     * [`NotebookError::Io`] error if file can't be read, is damaged or can't be written
+pub async fn replay(path: &Path, notebook: &Notebook) -> Result<SyncReport, NotebookError> {
+    let mut changes = load(path)?.into_iter();
+    let mut report = SyncReport::default();
+    let mut left = Vec::new();
+
+    while let Some(change) = changes.next() {
+        let res = match change.operation {
+            Operation::Add => {
+                let request = CommandRequest::AddNote {
+                    notename: change.note_name.to_owned(),
+                    note: change.note.to_owned(),
+                };
+                notebook.execute(request).await.map(|_| ())
+            }
+            Operation::Upd => upd_queued(&change, notebook).await,
+        };
+
+        match res {
+            Ok(_) => report.applied.push(change),
+            Err(err) if is_unreachable(&err) => {
+                left = std::iter::once(change).chain(changes).collect();
+                break;
+            }
+            Err(err) => report.failed.push((change, err)),
+        }
+    }
+
+    // Rejected changes are saved before they're removed from queue, so they're never lost
+    if !report.failed.is_empty() {
+        let rejected = rejected_path(path);
+        let text: String = report
+            .failed
+            .iter()
+            .map(|(change, _)| change.to_line())
+            .collect();
+        open_private(&rejected, true)?.write_all(text.as_bytes())?;
+
+        event!(
+            Level::WARN,
+            "{} queued changes can't be applied, they are kept in `{}`",
+            report.failed.len(),
+            rejected.display()
+        );
+    }
+
+    report.left = left.len();
+    save(path, &left)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit;
+    use sqlx::PgPool;
+
+    #[test]
+    fn escaped_text_is_unescaped_back() {
+        for text in [
+            "",
+            "plain",
+            "tab\there",
+            "lines\nand\r\nbreaks\n",
+            "back\\slash \\n not a break",
+            "\\\t\\\n\\",
+            "заметка ✓",
+        ] {
+            let escaped = escape(text);
+
+            assert!(!escaped.contains(['\t', '\n', '\r']));
+            assert_eq!(Some(text.to_owned()), unescape(&escaped));
+        }
+    }
+
+    #[test]
+    fn invalid_escapes_are_rejected() {
+        assert_eq!(None, unescape("ends with \\"));
+        assert_eq!(None, unescape("unknown \\x escape"));
+    }
+
+    #[test]
+    fn change_is_read_from_its_line() {
+        let change = QueuedChange::new(Operation::Upd, "to\tdo", "milk\n\tbread\\eggs\n")
+            .based_on(commands::hash_content("milk"));
+        let line = change.to_line();
+
+        assert_eq!(1, line.lines().count());
+        assert_eq!(
+            Some(change),
+            QueuedChange::from_line(line.trim_end_matches('\n'))
+        );
+    }
+
+    #[test]
+    fn line_without_base_hash_is_read() {
+        let change = QueuedChange::new(Operation::Add, "todo", "milk");
+        let line = change.to_line();
+        let old_line = line.trim_end_matches("\t\n");
+
+        assert_eq!(Some(change.clone()), QueuedChange::from_line(old_line));
+        assert_eq!(
+            Some(change),
+            QueuedChange::from_line(line.trim_end_matches('\n'))
+        );
+    }
+
+    #[test]
+    fn damaged_lines_are_rejected() {
+        let line = QueuedChange::new(Operation::Add, "todo", "milk").to_line();
+
+        assert_eq!(None, QueuedChange::from_line("not a change"));
+        assert_eq!(
+            None,
+            QueuedChange::from_line(&line.replace("\tadd\t", "\tdel\t"))
+        );
+        assert_eq!(
+            None,
+            QueuedChange::from_line(&format!("{}\textra", line.trim_end_matches('\n')))
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn changed_notes_are_not_overwritten(pool: PgPool) {
+        let dir = std::env::temp_dir().join(format!("lnotebook-{}-offline", std::process::id()));
+        let path = dir.join(QUEUE_FILE);
+        commands::add("shopping", "milk", &pool).await.unwrap();
+        commands::add("todo", "call mom", &pool).await.unwrap();
+
+        let base = commands::hash_content("milk");
+        let change = QueuedChange::new(Operation::Upd, "shopping", "milk\neggs").based_on(&base);
+        push(&path, &change).unwrap();
+        push(
+            &path,
+            &QueuedChange::new(Operation::Upd, "shopping", "eggs"),
+        )
+        .unwrap();
+        push(
+            &path,
+            &QueuedChange::new(Operation::Upd, "todo", "call dad").based_on(&base),
+        )
+        .unwrap();
+
+        let report = replay(&path, &Notebook::new(pool.clone())).await.unwrap();
+        assert_eq!(2, report.applied.len());
+        assert!(matches!(
+            report.failed[..],
+            [(_, NotebookError::Conflict { .. })]
+        ));
+        assert!(!path.exists());
+
+        let rejected = load(&rejected_path(&path)).unwrap();
+        assert_eq!(
+            vec!["todo"],
+            rejected.iter().map(|c| &c.note_name).collect::<Vec<_>>()
+        );
+        let note = commands::select_one("todo", &pool).await.unwrap();
+        assert_eq!("call mom", note.body());
+        let note = commands::select_one("shopping", &pool).await.unwrap();
+        assert_eq!("eggs", note.body());
+        let audited = audit::audit(None, &pool).await.unwrap();
+        assert_eq!(
+            vec!["upd-note", "upd-note"],
+            audited.iter().map(|e| &e.command).collect::<Vec<_>>()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
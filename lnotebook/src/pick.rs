@@ -0,0 +1,292 @@
+//! Fuzzy picker of notes.
+//!
+//! [`pick`] shows names of all notes in the terminal and filters them while you type,
+//! so you don't need to remember exact notenames. Letters of the query must appear in notename
+//! in the same order, but not necessarily next to each other: `mtg` finds `meeting 2024-07-20`.
+//! Names where letters are closer together or start words are shown first (read more in
+//! [`fuzzy_score`]).
+//! ##### Keybindings
+//! * letters - filter notes
+//! * `↑`/`↓` - select note
+//! * `Tab` - change action that will be run with the selected note
+//! * `Enter` - run action with the selected note
+//! * `Esc` - cancel
+//!
+//! Notes are listed by [`Notebook`], so notebook acting as user shows only notes the user
+//! can see (read more in [`users` module][crate::users]).
+//!
+//! CLI runs picker with `pick [--action <display|edit|delete>]`.
+
+use crate::commands::PREVIEW_LEN;
+use crate::errors::NotebookError;
+use crate::notebook::{CommandRequest, CommandResponse, Notebook};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState},
+    DefaultTerminal, Frame,
+};
+use std::{fmt, time::Duration};
+
+/// Actions that can be run with picked note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PickAction {
+    /// Display note
+    #[default]
+    Display,
+    /// Replace content of note
+    Edit,
+    /// Delete note
+    Delete,
+}
+
+impl PickAction {
+    fn next(self) -> PickAction {
+        match self {
+            PickAction::Display => PickAction::Edit,
+            PickAction::Edit => PickAction::Delete,
+            PickAction::Delete => PickAction::Display,
+        }
+    }
+}
+
+impl fmt::Display for PickAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PickAction::Display => "display",
+            PickAction::Edit => "edit",
+            PickAction::Delete => "delete",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// Returns how well `candidate` matches `query`, `None` if it doesn't match.
+///
+/// `candidate` matches if it contains all characters of `query` in the same order, ignoring case.
+/// Every matched character adds to the score, with bonuses for characters that follow the previous
+/// matched one or start a word, and a small penalty for characters skipped between them.
+/// ### Example
+/// ```
+/// # use lnotebook::pick::fuzzy_score;
+/// assert!(fuzzy_score("mtg", "meeting 2024-07-20").is_some());
+/// assert!(fuzzy_score("gtm", "meeting 2024-07-20").is_none());
+///
+/// // Consecutive characters are better than scattered ones
+/// assert!(fuzzy_score("meet", "meeting").unwrap() > fuzzy_score("meet", "my electric toaster").unwrap());
+/// ```
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    let mut chars = candidate.chars().enumerate();
+    let mut before = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (i, c) = chars.next()?;
+            let word_start = before.is_none_or(|b: char| !b.is_alphanumeric());
+            before = Some(c);
+
+            if !c.to_lowercase().eq(std::iter::once(q)) {
+                continue;
+            }
+
+            score += 1;
+            match prev {
+                Some(prev) if prev + 1 == i => score += 5,
+                Some(prev) => score -= (i - prev - 1).min(3) as i64,
+                None => {}
+            }
+            if word_start {
+                score += 3;
+            }
+            prev = Some(i);
+            break;
+        }
+    }
+
+    Some(score)
+}
+
+/// Returns `candidates` that match `query`, the best matches first.
+///
+/// Matches with the same score are ordered by length and then by name.
+/// If `query` is empty, all candidates are returned ordered by name.
+/// ### Example
+/// ```
+/// # use lnotebook::pick::fuzzy_filter;
+/// let names = ["shopping".to_owned(), "meeting notes".to_owned(), "my todo".to_owned()];
+///
+/// assert_eq!(vec!["my todo", "meeting notes"], fuzzy_filter("mt", &names));
+/// ```
+pub fn fuzzy_filter<'a>(query: &str, candidates: &'a [String]) -> Vec<&'a str> {
+    let mut matches: Vec<(i64, &str)> = candidates
+        .iter()
+        .filter_map(|candidate| Some((fuzzy_score(query, candidate)?, candidate.as_str())))
+        .collect();
+
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then(a.len().cmp(&b.len()))
+            .then(a.cmp(b))
+    });
+
+    matches
+        .into_iter()
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+struct Picker {
+    notenames: Vec<String>,
+    query: String,
+    action: PickAction,
+    list_state: ListState,
+}
+
+impl Picker {
+    fn visible(&self) -> Vec<&str> {
+        fuzzy_filter(&self.query, &self.notenames)
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.visible().len();
+        if len == 0 {
+            self.list_state.select(None);
+            return;
+        }
+
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let next = (current + offset).clamp(0, len as isize - 1);
+        self.list_state.select(Some(next as usize));
+    }
+}
+
+/// Shows fuzzy picker of notes with `action` selected first.
+/// ### Returns
+/// * Ok
+///     * Picked notename and action to run with it, `None` if picking was cancelled
+/// * Errors
+///     * [`NotebookError::Io`] error if terminal can't be used
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+///       if notes can't be loaded
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::pick::*;
+/// # use lnotebook::NotebookError;
+/// # use lnotebook::notebook::Notebook;
+/// async fn pick_example(notebook: &Notebook) -> Result<(), NotebookError> {
+///     if let Some((notename, action)) = pick(PickAction::Display, notebook).await? {
+///         println!("Picked `{notename}` to {action}");
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn pick(
+    action: PickAction,
+    notebook: &Notebook,
+) -> Result<Option<(String, PickAction)>, NotebookError> {
+    let request = CommandRequest::List {
+        full: true,
+        preview_len: PREVIEW_LEN,
+    };
+    let mut notenames: Vec<String> = match notebook.execute(request).await? {
+        CommandResponse::Notes(notes) => notes.into_iter().map(|note| note.note_name).collect(),
+        _ => unreachable!("request returns notes"),
+    };
+    notenames.sort();
+
+    let mut picker = Picker {
+        notenames,
+        query: String::new(),
+        action,
+        list_state: ListState::default().with_selected(Some(0)),
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let res = event_loop(&mut terminal, &mut picker);
+    ratatui::try_restore()?;
+
+    res
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    picker: &mut Picker,
+) -> Result<Option<(String, PickAction)>, NotebookError> {
+    loop {
+        terminal.draw(|frame| draw(frame, picker))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => {
+                let selected = picker
+                    .list_state
+                    .selected()
+                    .and_then(|i| picker.visible().get(i).map(|name| name.to_string()));
+
+                if let Some(notename) = selected {
+                    return Ok(Some((notename, picker.action)));
+                }
+            }
+            KeyCode::Tab => picker.action = picker.action.next(),
+            KeyCode::Up => picker.select(-1),
+            KeyCode::Down => picker.select(1),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.list_state.select(Some(0));
+            }
+            KeyCode::Char(c) => {
+                picker.query.push(c);
+                picker.list_state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, picker: &mut Picker) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+
+    frame.render_widget(Line::from(format!("> {}", picker.query)), rows[0]);
+
+    let visible = picker.visible();
+    let title = format!("Notes {}/{}", visible.len(), picker.notenames.len());
+    let items: Vec<ListItem> = visible
+        .into_iter()
+        .map(|name| ListItem::new(name.to_owned()))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, rows[1], &mut picker.list_state);
+
+    let status = format!(
+        "Action: {} | Enter run | Tab change action | Esc cancel",
+        picker.action
+    );
+    frame.render_widget(Line::from(status), rows[2]);
+}
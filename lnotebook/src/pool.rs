@@ -0,0 +1,150 @@
+//! Connection to the database.
+//!
+//! [`connect`] creates pool of connections with limits from [`ConnectOptions`] and retries
+//! connection with exponential backoff if database is temporarily unavailable (for example, it's
+//! still starting, or network is down for a moment), so the notebook doesn't fail right away.
+//! Errors that won't go away by themselves, like wrong password or missing database,
+//! are returned without retrying.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::{connect, get_db_url, ConnectOptions, NotebookError};
+//! # use std::time::Duration;
+//! async fn connect_example() -> Result<(), NotebookError> {
+//!     let options = ConnectOptions {
+//!         max_connections: 2,
+//!         retries: 10,
+//!         ..ConnectOptions::default()
+//!     };
+//!
+//!     let db = connect(&get_db_url().await?, options).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    ConnectOptions as _, PgPool,
+};
+use std::time::Duration;
+use tracing::{event, level_filters::LevelFilter, Level};
+
+/// Contains options of [`connect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectOptions {
+    /// Max number of connections in pool, 5 by default
+    pub max_connections: u32,
+    /// How long to wait for free connection or for database to accept connection,
+    /// 10 seconds by default
+    pub acquire_timeout: Duration,
+    /// How many times to retry failed connection, 5 by default.
+    /// Every attempt itself waits up to `acquire_timeout` for database
+    pub retries: u32,
+    /// Delay before the first retry, 200 milliseconds by default.
+    /// Every next delay is two times longer
+    pub initial_backoff: Duration,
+    /// Max delay between retries, 5 seconds by default
+    pub max_backoff: Duration,
+    /// Level of log of executed SQL queries and their timing, `DEBUG` by default.
+    /// `OFF` disables it
+    pub log_statements: LevelFilter,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions {
+            max_connections: 5,
+            acquire_timeout: Duration::from_secs(10),
+            retries: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            log_statements: LevelFilter::DEBUG,
+        }
+    }
+}
+
+impl ConnectOptions {
+    /// Returns delay before retry number `retry` (numbered from 0).
+    /// ### Example
+    /// ```
+    /// # use lnotebook::ConnectOptions;
+    /// # use std::time::Duration;
+    /// let options = ConnectOptions::default();
+    ///
+    /// assert_eq!(Duration::from_millis(200), options.backoff(0));
+    /// assert_eq!(Duration::from_millis(800), options.backoff(2));
+    /// assert_eq!(Duration::from_secs(5), options.backoff(10));
+    /// ```
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// Converts level of `tracing` into level of `log` used by `sqlx`.
+fn log_level(level: LevelFilter) -> log::LevelFilter {
+    match level.into_level() {
+        None => log::LevelFilter::Off,
+        Some(Level::ERROR) => log::LevelFilter::Error,
+        Some(Level::WARN) => log::LevelFilter::Warn,
+        Some(Level::INFO) => log::LevelFilter::Info,
+        Some(Level::DEBUG) => log::LevelFilter::Debug,
+        Some(Level::TRACE) => log::LevelFilter::Trace,
+    }
+}
+
+/// Returns `true` if connection failed for reason that can go away by itself.
+pub(crate) fn is_transient(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        // `cannot_connect_now` (database is starting or shutting down)
+        // and `too_many_connections`
+        sqlx::Error::Database(err) => matches!(err.code().as_deref(), Some("57P03" | "53300")),
+        _ => false,
+    }
+}
+
+/// Connects to database `url`, retrying if database is temporarily unavailable.
+/// ### Returns
+/// * Ok
+///     * [`PgPool`] with at least one open connection
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`] if connection
+///       failed for a reason that can't go away by itself, or all retries failed
+pub async fn connect(url: &str, options: ConnectOptions) -> Result<PgPool, NotebookError> {
+    let pool_options = PgPoolOptions::new()
+        .max_connections(options.max_connections)
+        .acquire_timeout(options.acquire_timeout);
+    let connect_options = url
+        .parse::<PgConnectOptions>()?
+        .log_statements(log_level(options.log_statements));
+
+    let mut retry = 0;
+    loop {
+        match pool_options
+            .clone()
+            .connect_with(connect_options.clone())
+            .await
+        {
+            Ok(pool) => return Ok(pool),
+            Err(err) if retry < options.retries && is_transient(&err) => {
+                let backoff = options.backoff(retry);
+                event!(
+                    Level::WARN,
+                    "Can't connect to database: {}; retrying in {:?} ({}/{})",
+                    err,
+                    backoff,
+                    retry + 1,
+                    options.retries
+                );
+
+                tokio::time::sleep(backoff).await;
+                retry += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
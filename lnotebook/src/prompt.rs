@@ -0,0 +1,231 @@
+//! Interactive input used by the CLI.
+//!
+//! All reads from user (note contents, confirmations, passphrases) go through [`Prompt`],
+//! so the notebook never hangs forever when it's run without input, for example from cron or CI:
+//! * if input is closed, reading fails with [`NotebookError::InputClosed`], except reading
+//!   of notes, which are finished by the end of input (see [`Prompt::read_note`])
+//! * confirmations and passphrases can be asked only in terminal, otherwise they fail
+//!   with [`NotebookError::NotInteractive`]
+//! * if timeout is set and user doesn't enter anything in time, reading fails
+//!   with [`NotebookError::PromptTimeout`]
+//!
+//! Timeout can be set with `LNOTEBOOK_PROMPT_TIMEOUT` enivroment variable (in seconds),
+//! read more in [`config` module][crate::config].
+//!
+//! In script mode (see [`Prompt::script`]) prompt doesn't print any messages,
+//! confirmations are accepted without asking and passphrases are read from input as usual lines.
+
+use crate::config::Config;
+use crate::errors::NotebookError;
+use crate::input::NoteInput;
+
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Lines read from stdin by the background thread.
+///
+/// Stdin is read in a separate thread, so prompts can stop waiting for it after timeout.
+fn stdin_lines() -> &'static Mutex<Receiver<io::Result<String>>> {
+    static LINES: OnceLock<Mutex<Receiver<io::Result<String>>>> = OnceLock::new();
+
+    LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            loop {
+                let mut line = String::new();
+                let res = stdin.lock().read_line(&mut line);
+                let closed = !matches!(res, Ok(n) if n > 0);
+
+                if tx.send(res.map(|_| line)).is_err() || closed {
+                    break;
+                }
+            }
+        });
+
+        Mutex::new(rx)
+    })
+}
+
+/// Reads input from user.
+///
+/// Read about its behaviour [here][crate::prompt].
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    timeout: Option<Duration>,
+    script: bool,
+}
+
+impl Prompt {
+    /// Creates prompt with timeout from [`Config`].
+    pub fn new() -> Prompt {
+        Prompt {
+            timeout: Config::from_env().prompt_timeout,
+            script: false,
+        }
+    }
+
+    /// Sets how long prompt waits for every line of input. `None` means waiting forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Prompt {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Enables script mode: messages are not printed and confirmations are not asked,
+    /// so the notebook can be run by other programs.
+    pub fn script(mut self, script: bool) -> Prompt {
+        self.script = script;
+        self
+    }
+
+    fn next_line(&self) -> Result<String, NotebookError> {
+        let lines = stdin_lines()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let line = match self.timeout {
+            Some(timeout) => match lines.recv_timeout(timeout) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(NotebookError::PromptTimeout {
+                        secs: timeout.as_secs(),
+                    })
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(NotebookError::InputClosed),
+            },
+            None => lines.recv().map_err(|_| NotebookError::InputClosed)?,
+        }?;
+
+        if line.is_empty() {
+            return Err(NotebookError::InputClosed);
+        }
+
+        Ok(line)
+    }
+
+    /// Prints `message` and reads one line without the line break.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::InputClosed`] error if input is closed
+    ///     * [`NotebookError::PromptTimeout`] error if nothing was entered in time
+    pub fn read_line(&self, message: &str) -> Result<String, NotebookError> {
+        if !self.script {
+            print!("{message}");
+            io::stdout().flush()?;
+        }
+
+        let line = self.next_line()?;
+
+        Ok(line.trim_end_matches(['\n', '\r']).to_owned())
+    }
+
+    /// Reads lines until `terminator` is entered or input is closed (Ctrl+D)
+    /// and returns everything before it.
+    ///
+    /// Read about rules of input in [`input` module][crate::input].
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::PromptTimeout`] error if nothing was entered in time
+    pub fn read_note(&self, terminator: &str) -> Result<String, NotebookError> {
+        let mut input = NoteInput::new(terminator);
+        loop {
+            let line = match self.next_line() {
+                Ok(line) => line,
+                Err(NotebookError::InputClosed) => return Ok(input.into_body()),
+                Err(err) => return Err(err),
+            };
+
+            if input.push_line(&line) {
+                return Ok(input.into_body());
+            }
+        }
+    }
+
+    /// Asks user to answer `y` or `n` to `message`.
+    /// ### Returns
+    /// * Ok
+    ///     * `true` if user answered `y` or `yes`, always `true` in script mode
+    /// * Errors
+    ///     * [`NotebookError::NotInteractive`] error if input is not a terminal
+    ///     * [`NotebookError::InputClosed`] error if input is closed
+    ///     * [`NotebookError::PromptTimeout`] error if nothing was entered in time
+    pub fn confirm(&self, message: &str) -> Result<bool, NotebookError> {
+        if self.script {
+            return Ok(true);
+        }
+        require_terminal("confirmation")?;
+
+        let answer = self.read_line(&format!("{message} [y/N]: "))?;
+
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+
+    /// Asks user to type `expected` to confirm `message`, for operations that can't be undone.
+    /// ### Returns
+    /// * Ok
+    ///     * `true` if user typed exactly `expected`, always `true` in script mode
+    /// * Errors
+    ///     * [`NotebookError::NotInteractive`] error if input is not a terminal
+    ///     * [`NotebookError::InputClosed`] error if input is closed
+    ///     * [`NotebookError::PromptTimeout`] error if nothing was entered in time
+    pub fn confirm_typed(&self, message: &str, expected: &str) -> Result<bool, NotebookError> {
+        if self.script {
+            return Ok(true);
+        }
+        require_terminal("confirmation")?;
+
+        let answer = self.read_line(&format!("{message}\nType `{expected}` to confirm: "))?;
+
+        Ok(answer.trim() == expected)
+    }
+
+    /// Asks user to enter passphrase without displaying it.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::NotInteractive`] error if input is not a terminal (except script mode)
+    ///     * [`NotebookError::InputClosed`] error if input is closed in script mode
+    ///     * [`NotebookError::PromptTimeout`] error if nothing was entered in time
+    ///     * [`NotebookError::Io`] error if terminal can't be read
+    pub fn passphrase(&self, message: &str) -> Result<String, NotebookError> {
+        if self.script {
+            return self.read_line(message);
+        }
+        require_terminal("passphrase")?;
+
+        let message = message.to_owned();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(rpassword::prompt_password(message));
+        });
+
+        let passphrase = match self.timeout {
+            Some(timeout) => rx.recv_timeout(timeout).map_err(|_| {
+                println!();
+                NotebookError::PromptTimeout {
+                    secs: timeout.as_secs(),
+                }
+            })?,
+            None => rx.recv().map_err(|_| NotebookError::InputClosed)?,
+        }?;
+
+        Ok(passphrase)
+    }
+}
+
+fn require_terminal(what: &str) -> Result<(), NotebookError> {
+    if io::stdin().is_terminal() {
+        Ok(())
+    } else {
+        Err(NotebookError::NotInteractive {
+            what: what.to_owned(),
+        })
+    }
+}
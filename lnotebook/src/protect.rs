@@ -0,0 +1,355 @@
+//! Protection of notes with passphrase.
+//!
+//! Protected note keeps hash of its passphrase (made by
+//! [`hash_passphrase`][crate::crypto::hash_passphrase], only with `crypto` feature).
+//! Functions that read or change one note (like [`select_one`][crate::commands::select_one],
+//! [`upd`][crate::commands::upd], [`diff`][crate::diff::diff] or
+//! [`history`][crate::history::history]) return [`NotebookError::NoteProtected`] for protected
+//! notes, unless they run with the passphrase given by [`with_passphrases`]
+//! (like all requests of [`Notebook`] created with [`Notebook::with_passphrase`]).
+//! Functions that read or change many notes at once (like
+//! [`select_all`][crate::commands::select_all], `search`, `export`, `sed` or `del-all`)
+//! skip protected notes, and [`list`][crate::commands::list] shows them without preview.
+//! Without `crypto` feature protected notes can't be opened at all.
+//!
+//! CLI asks for the passphrase before commands like `display-note`, `upd-note` or `cat` open
+//! protected note; server, TUI and gRPC service can't open them. Backups made by `dump` and
+//! `sync` keep protected notes with their passphrases.
+//!
+//! Protection doesn't encrypt content of note: it's still stored in the database as usual,
+//! so it keeps the note away from other people using the notebook, but not from anyone who
+//! can read the database. Use `export --encrypt` to encrypt notes.
+//!
+//! [`Notebook`]: crate::notebook::Notebook
+//! [`Notebook::with_passphrase`]: crate::notebook::Notebook::with_passphrase
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::protect::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn protect_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     if !is_protected("shopping", pool).await? {
+//!         println!("{}", select_one("shopping", pool).await?.note.unwrap_or_default());
+//!     }
+//!
+//!     let note = with_passphrases(vec!["secret".to_owned()], select_one("passwords", pool)).await?;
+//!     println!("{}", note.note.unwrap_or_default());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "crypto")]
+use crate::crypto;
+use crate::errors::NotebookError;
+#[cfg(feature = "crypto")]
+use crate::sql::query;
+use crate::sql::query_scalar;
+#[cfg(feature = "crypto")]
+use crate::users::{self, Access};
+
+use sqlx::{self, PgExecutor, PgPool};
+use std::future::Future;
+#[cfg(feature = "crypto")]
+use tracing::{event, Level};
+
+tokio::task_local! {
+    static PASSPHRASES: Vec<String>;
+}
+
+/// Runs `f` with `passphrases`, which open protected notes for functions called by `f`.
+///
+/// Passphrases given by outer [`with_passphrases`] open notes in `f` too.
+pub async fn with_passphrases<F: Future>(mut passphrases: Vec<String>, f: F) -> F::Output {
+    passphrases.extend(PASSPHRASES.try_with(Vec::clone).unwrap_or_default());
+
+    PASSPHRASES.scope(passphrases, f).await
+}
+
+/// Returns [`NotebookError::NoteProtected`] error if note is protected and none of passphrases
+/// given by [`with_passphrases`] opens it. Note that doesn't exist is not checked.
+pub(crate) async fn check_passphrase<'c>(
+    notename: &str,
+    conn: impl PgExecutor<'c>,
+) -> Result<(), NotebookError> {
+    let hash = query_scalar!(
+        Option<String>,
+        "
+SELECT passphrase_hash
+FROM notebook
+WHERE note_name = $1
+        ",
+        notename
+    )
+    .fetch_optional(conn)
+    .await?
+    .flatten();
+
+    let Some(hash) = hash else {
+        return Ok(());
+    };
+
+    #[cfg(feature = "crypto")]
+    for passphrase in PASSPHRASES.try_with(Vec::clone).unwrap_or_default() {
+        if crypto::verify_passphrase(&passphrase, &hash)? {
+            return Ok(());
+        }
+    }
+    #[cfg(not(feature = "crypto"))]
+    let _ = hash;
+
+    Err(NotebookError::NoteProtected {
+        notename: notename.to_owned(),
+    })
+}
+
+/// Returns `false` instead of [`NotebookError::NoteProtected`] error of [`check_passphrase`],
+/// for commands that skip notes that aren't opened.
+pub(crate) async fn is_opened<'c>(
+    notename: &str,
+    conn: impl PgExecutor<'c>,
+) -> Result<bool, NotebookError> {
+    match check_passphrase(notename, conn).await {
+        Ok(()) => Ok(true),
+        Err(NotebookError::NoteProtected { .. }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns `true` if note is protected with passphrase.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn is_protected(notename: &str, pool: &PgPool) -> Result<bool, NotebookError> {
+    let protected = query_scalar!(
+        bool,
+        r#"
+SELECT passphrase_hash IS NOT NULL AS "protected!"
+FROM notebook
+WHERE note_name = $1
+        "#,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(protected)
+}
+
+#[cfg(feature = "crypto")]
+async fn passphrase_hash(notename: &str, pool: &PgPool) -> Result<Option<String>, NotebookError> {
+    let hash = query_scalar!(
+        Option<String>,
+        "
+SELECT passphrase_hash
+FROM notebook
+WHERE note_name = $1
+        ",
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(hash)
+}
+
+/// Protects note with `passphrase` (only with `crypto` feature).
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NoteProtected`] error if note is already protected
+///       (remove protection with [`unprotect`] to change passphrase)
+///     * [`NotebookError::Crypto`] error if passphrase can't be hashed
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::protect::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn protect_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("passwords", "login: krutoy_4el\npassword: 123", pool).await?;
+///     protect("passwords", "secret", pool).await?;
+///
+///     assert!(is_protected("passwords", pool).await?);
+///     assert!(unlock("passwords", "wrong", pool).await.is_err());
+///
+///     unlock("passwords", "secret", pool).await?;
+///     let note = with_passphrases(vec!["secret".to_owned()], select_one("passwords", pool)).await?;
+///     println!("{}", note.note.unwrap_or_default());
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "crypto")]
+pub async fn protect(notename: &str, passphrase: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Write, pool).await?;
+
+    let hash = crypto::hash_passphrase(passphrase)?;
+
+    let protected = query_scalar!(
+        bool,
+        r#"
+UPDATE notebook
+SET passphrase_hash = COALESCE(passphrase_hash, $1), updated_at = now()
+WHERE note_name = $2
+RETURNING passphrase_hash = $1 AS "protected!"
+        "#,
+        hash,
+        notename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !protected {
+        return Err(NotebookError::NoteProtected {
+            notename: notename.to_owned(),
+        });
+    }
+
+    event!(Level::INFO, "Protect `{}` with passphrase", notename);
+
+    Ok(())
+}
+
+/// Checks that `passphrase` opens note (only with `crypto` feature).
+/// Notes that are not protected are opened by any passphrase.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::WrongPassphrase`] error if passphrase is wrong
+///     * [`NotebookError::Crypto`] error if stored hash is damaged
+///     * [`NotebookError::NotOwner`] error if note is owned by other user and isn't shared
+///       with the acting user (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+#[cfg(feature = "crypto")]
+pub async fn unlock(notename: &str, passphrase: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Read, pool).await?;
+
+    match passphrase_hash(notename, pool).await? {
+        Some(hash) if !crypto::verify_passphrase(passphrase, &hash)? => {
+            Err(NotebookError::WrongPassphrase)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Removes protection of note after checking `passphrase` (only with `crypto` feature).
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::WrongPassphrase`] error if passphrase is wrong
+///     * [`NotebookError::Crypto`] error if stored hash is damaged
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't change note (read more in [`users` module][crate::users])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+#[cfg(feature = "crypto")]
+pub async fn unprotect(
+    notename: &str,
+    passphrase: &str,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    users::check_access(notename, Access::Write, pool).await?;
+    unlock(notename, passphrase, pool).await?;
+
+    query!(
+        "
+UPDATE notebook
+SET passphrase_hash = NULL, updated_at = now()
+WHERE note_name = $1
+        ",
+        notename
+    )
+    .execute(pool)
+    .await?;
+
+    event!(Level::INFO, "Remove protection of `{}`", notename);
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::*;
+    use crate::commands;
+
+    fn assert_protected<T: std::fmt::Debug>(res: Result<T, NotebookError>) {
+        assert!(
+            matches!(res, Err(NotebookError::NoteProtected { .. })),
+            "{res:?}"
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn protected_note_is_opened_only_with_passphrase(pool: PgPool) {
+        commands::add("passwords", "password: 123", &pool)
+            .await
+            .unwrap();
+        commands::add("shopping", "milk", &pool).await.unwrap();
+        protect("passwords", "secret", &pool).await.unwrap();
+
+        assert_protected(commands::select_one("passwords", &pool).await);
+        assert_protected(commands::upd("passwords", "hacked", &pool).await);
+        assert_protected(commands::del("passwords", &pool).await);
+        assert_protected(
+            with_passphrases(
+                vec!["wrong".to_owned()],
+                commands::select_one("passwords", &pool),
+            )
+            .await,
+        );
+
+        let passphrases = vec!["wrong".to_owned(), "secret".to_owned()];
+        let note = with_passphrases(passphrases, async {
+            commands::append("passwords", "\npin: 0000", &pool).await?;
+            commands::select_one("passwords", &pool).await
+        })
+        .await
+        .unwrap();
+        assert_eq!("password: 123\npin: 0000", note.body());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn protected_note_is_skipped_by_bulk_commands(pool: PgPool) {
+        commands::add("passwords", "password: 123", &pool)
+            .await
+            .unwrap();
+        commands::add("shopping", "password: milk", &pool)
+            .await
+            .unwrap();
+        protect("passwords", "secret", &pool).await.unwrap();
+
+        let notes = commands::select_all(&pool).await.unwrap();
+        assert_eq!(
+            vec!["shopping"],
+            notes.iter().map(|note| &note.note_name).collect::<Vec<_>>()
+        );
+
+        let summaries = commands::list(&pool).await.unwrap();
+        assert_eq!("(protected)", summaries[0].preview);
+
+        let found = crate::search::search("password", &pool).await.unwrap();
+        assert_eq!(1, found.len());
+
+        let replaced = commands::replace_all("password", "pass", false, &pool)
+            .await
+            .unwrap();
+        assert_eq!(1, replaced.len());
+        assert_eq!(vec!["shopping"], commands::del_all(&pool).await.unwrap());
+
+        unprotect("passwords", "secret", &pool).await.unwrap();
+        assert_eq!(
+            "password: 123",
+            commands::select_one("passwords", &pool)
+                .await
+                .unwrap()
+                .body()
+        );
+    }
+}
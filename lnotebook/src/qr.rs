@@ -0,0 +1,94 @@
+//! QR codes of notes (only with `qr` feature).
+//!
+//! CLI uses it in `qr <notename>`, which prints content of note as QR code in terminal,
+//! or saves it into PNG image with `--png <path>`. This is a simple way to move short notes
+//! (like Wi-Fi passwords) to a phone: scan the code with its camera.
+//!
+//! QR code can hold only about 2900 bytes, longer notes return [`NotebookError::QrCode`].
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::qr;
+//! # use lnotebook::NotebookError;
+//! fn qr_example() -> Result<(), NotebookError> {
+//!     println!("{}", qr::render_terminal("WIFI:S:home;T:WPA;P:secret;;")?);
+//!
+//!     qr::write_png("WIFI:S:home;T:WPA;P:secret;;", "wifi.png")?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+
+use qrcode::{render::unicode::Dense1x2, Color, QrCode};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Width of empty border around QR code in modules (squares of QR code).
+const QUIET_ZONE: usize = 4;
+
+/// Size of one module in PNG image in pixels.
+const PNG_SCALE: usize = 8;
+
+fn qr_error(err: impl std::fmt::Display) -> NotebookError {
+    NotebookError::QrCode {
+        reason: err.to_string(),
+    }
+}
+
+/// Returns QR code of `text`.
+fn encode(text: &str) -> Result<QrCode, NotebookError> {
+    QrCode::new(text.as_bytes()).map_err(qr_error)
+}
+
+/// Returns QR code of `text` drawn with Unicode blocks, two rows of modules in one line.
+///
+/// Colors are inverted, so the code is read correctly from terminals with dark background.
+/// ### Returns
+/// * Ok
+///     * QR code that can be printed
+/// * Errors
+///     * [`NotebookError::QrCode`] error if `text` is too long for QR code
+pub fn render_terminal(text: &str) -> Result<String, NotebookError> {
+    Ok(encode(text)?
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Saves QR code of `text` into PNG image `path`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::QrCode`] error if `text` is too long for QR code or image can't
+///       be encoded
+///     * [`NotebookError::Io`] error if file can't be created
+pub fn write_png(text: &str, path: impl AsRef<Path>) -> Result<(), NotebookError> {
+    let code = encode(text)?;
+    let width = code.width();
+    let size = (width + 2 * QUIET_ZONE) * PNG_SCALE;
+
+    let mut pixels = vec![u8::MAX; size * size];
+    for (i, color) in code.to_colors().iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+
+        let (x, y) = (i % width + QUIET_ZONE, i / width + QUIET_ZONE);
+        for dy in 0..PNG_SCALE {
+            let row = (y * PNG_SCALE + dy) * size;
+            pixels[row + x * PNG_SCALE..row + (x + 1) * PNG_SCALE].fill(0);
+        }
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(qr_error)?;
+    writer.write_image_data(&pixels).map_err(qr_error)?;
+    writer.finish().map_err(qr_error)?;
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Reminders about deadlines of notes.
+//!
+//! [`remind_daemon`] (and `remind-daemon` command of CLI) checks deadlines of notes
+//! (read about them in [`due` module][crate::due]) every few seconds and sends reminder
+//! when deadline of note has passed. Every deadline is reminded once: it's recorded in the
+//! database, so reminders are not repeated after the daemon is restarted, and changing
+//! deadline with [`set_due`][crate::due::set_due] makes note be reminded again.
+//!
+//! Reminder is sent by [`Notifier`]:
+//! * [`Notifier::Desktop`] - desktop notification, shown with `notify-send` on Linux and BSD
+//!   and with `osascript` on macOS
+//! * [`Notifier::Command`] - shell command, for example to send an email. Note is passed
+//!   in enivroment variables `LNOTEBOOK_NOTE_ID`, `LNOTEBOOK_NOTE_NAME` and `LNOTEBOOK_DUE_AT`
+//!   (in RFC 3339 format)
+//!
+//! Command can be set in `LNOTEBOOK_REMIND_COMMAND` enivroment variable
+//! (read more in [`config` module][crate::config]). Reminder that can't be sent is sent
+//! again on next check.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::remind::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! # use std::time::Duration;
+//! async fn remind_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let notifier = Notifier::Command("mail -s \"$LNOTEBOOK_NOTE_NAME is due\" me".to_owned());
+//!
+//!     // Send reminders about notes that are already overdue
+//!     for note in remind(&notifier, pool).await? {
+//!         println!("Reminded about `{}`", note.note_name);
+//!     }
+//!
+//!     // Keep sending reminders until program is stopped
+//!     remind_daemon(&notifier, Duration::from_secs(60), pool, |note| {
+//!         println!("Reminded about `{}`", note.note_name);
+//!     })
+//!     .await;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::due::DueNote;
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as};
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{self, PgPool};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{event, Level};
+
+/// How often deadlines are checked if other interval is not set.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How reminders are sent, read more [here][crate::remind].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Notifier {
+    /// Desktop notification
+    #[default]
+    Desktop,
+    /// Shell command run for every reminder
+    Command(String),
+}
+
+impl Notifier {
+    /// Returns notifier that runs `command`, or shows desktop notifications if it's `None`.
+    pub fn new(command: Option<String>) -> Notifier {
+        command.map_or(Notifier::Desktop, Notifier::Command)
+    }
+
+    /// Sends reminder about `note`.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Reminder`] error if command can't be started or failed
+    pub async fn notify(&self, note: &DueNote) -> Result<(), NotebookError> {
+        let due_at = note.due_at.format("%Y-%m-%d %H:%M").to_string();
+
+        let mut cmd = match self {
+            Notifier::Desktop if cfg!(target_os = "macos") => {
+                let script = format!(
+                    "display notification {:?} with title {:?}",
+                    format!("Due {due_at}"),
+                    note.note_name
+                );
+
+                let mut cmd = Command::new("osascript");
+                cmd.arg("-e").arg(script);
+                cmd
+            }
+            Notifier::Desktop => {
+                let mut cmd = Command::new("notify-send");
+                cmd.arg("--app-name=lnotebook")
+                    .arg(&note.note_name)
+                    .arg(format!("Due {due_at}"));
+                cmd
+            }
+            Notifier::Command(command) if cfg!(windows) => {
+                let mut cmd = Command::new("cmd");
+                cmd.arg("/C").arg(command);
+                cmd
+            }
+            Notifier::Command(command) => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(command);
+                cmd
+            }
+        };
+
+        let failed = |reason: String| NotebookError::Reminder {
+            notename: note.note_name.to_owned(),
+            reason,
+        };
+
+        let output = cmd
+            .env("LNOTEBOOK_NOTE_ID", note.id.to_string())
+            .env("LNOTEBOOK_NOTE_NAME", &note.note_name)
+            .env("LNOTEBOOK_DUE_AT", note.due_at.to_rfc3339())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|err| {
+                let program = cmd.as_std().get_program().to_string_lossy();
+                failed(format!("can't run `{program}`: {err}"))
+            })?;
+
+        if !output.status.success() {
+            return Err(failed(format!(
+                "{} ({})",
+                String::from_utf8_lossy(&output.stderr).trim(),
+                output.status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns notes whose deadline has passed, but which were not reminded yet.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`DueNote`] sorted by deadline
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn pending_reminders(pool: &PgPool) -> Result<Vec<DueNote>, NotebookError> {
+    let rows = query_as!(
+        { id: i32, note_name: String, due_at: DateTime<Utc> },
+        r#"
+SELECT id, note_name, due_at AS "due_at!"
+FROM notebook
+WHERE due_at <= now() AND reminded_at IS NULL
+ORDER BY due_at, note_name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DueNote {
+            id: row.id,
+            note_name: row.note_name,
+            due_at: row.due_at.with_timezone(&Local),
+        })
+        .collect())
+}
+
+/// Sends reminders about all notes whose deadline has passed with `notifier`, once.
+///
+/// Reminders that can't be sent are logged and are not marked as sent.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`DueNote`] that were reminded
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn remind(notifier: &Notifier, pool: &PgPool) -> Result<Vec<DueNote>, NotebookError> {
+    let mut reminded = Vec::new();
+
+    for note in pending_reminders(pool).await? {
+        if let Err(err) = notifier.notify(&note).await {
+            event!(Level::WARN, "{}", err);
+            continue;
+        }
+
+        // Deadline is checked, so reminder is not lost if deadline was changed meanwhile
+        query!(
+            "
+UPDATE notebook
+SET reminded_at = now()
+WHERE id = $1 AND due_at = $2
+            ",
+            note.id,
+            note.due_at
+        )
+        .execute(pool)
+        .await?;
+
+        event!(Level::INFO, "Remind about note `{}`", note.note_name);
+
+        reminded.push(note);
+    }
+
+    Ok(reminded)
+}
+
+/// Checks deadlines every `interval` and sends reminders with `notifier` until it's stopped.
+///
+/// `on_remind` is called with every note that was reminded. Errors of the database are logged,
+/// and deadlines are checked again after `interval`, so the daemon survives restart
+/// of the database.
+pub async fn remind_daemon(
+    notifier: &Notifier,
+    interval: Duration,
+    pool: &PgPool,
+    mut on_remind: impl FnMut(&DueNote),
+) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticks.tick().await;
+
+        match remind(notifier, pool).await {
+            Ok(reminded) => reminded.iter().for_each(&mut on_remind),
+            Err(err) => event!(Level::WARN, "Can't check deadlines of notes: {}", err),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Rendering of notes into text.
+//!
+//! Functions from [`commands` module][crate::commands] return data, and [`NoteRenderer`]
+//! turns it into text in one of [`RenderFormat`]s:
+//! * [`Plain`] - the usual output of the notebook, short information about notes is rendered
+//!   as table by [`summaries_table`][crate::format::summaries_table]
+//! * [`Table`] - aligned table with one row for every note
+//! * [`Json`] - JSON objects with the same fields as [`Note`] and [`NoteSummary`]
+//! * [`Markdown`] - headers with content for notes and table for short information
+//!
+//! CLI selects format with `--format`.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::*;
+//! # use lnotebook::render::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn render_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let notes = list(pool).await?;
+//!
+//!     println!("{}", RenderFormat::Json.renderer().render_summaries(&notes));
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{Note, NoteSummary};
+use crate::format;
+
+/// Renders notes into text.
+///
+/// Read about implementations [here][crate::render].
+pub trait NoteRenderer {
+    /// Renders one note with its whole content.
+    fn render_note(&self, note: &Note) -> String;
+    /// Renders all notes with their whole content.
+    fn render_notes(&self, notes: &[Note]) -> String;
+    /// Renders short information about notes.
+    fn render_summaries(&self, notes: &[NoteSummary]) -> String;
+}
+
+/// Formats that notes can be rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RenderFormat {
+    #[default]
+    Plain,
+    Table,
+    Json,
+    Markdown,
+}
+
+impl RenderFormat {
+    /// Returns renderer of the format.
+    pub fn renderer(&self) -> Box<dyn NoteRenderer> {
+        match self {
+            RenderFormat::Plain => Box::new(Plain),
+            RenderFormat::Table => Box::new(Table),
+            RenderFormat::Json => Box::new(Json),
+            RenderFormat::Markdown => Box::new(Markdown),
+        }
+    }
+}
+
+/// Returns preview of note ending with `…` if note has more content.
+pub(crate) fn preview(note: &NoteSummary) -> String {
+    if note.truncated {
+        format!("{}…", note.preview)
+    } else {
+        note.preview.to_owned()
+    }
+}
+
+/// Returns time of the last change of note, or `-` if it's unknown.
+pub(crate) fn updated(note: &NoteSummary) -> String {
+    note.updated_at.map_or("-".to_owned(), |updated_at| {
+        updated_at.format("%Y-%m-%d %H:%M").to_string()
+    })
+}
+
+/// The usual output of the notebook.
+pub struct Plain;
+
+impl NoteRenderer for Plain {
+    fn render_note(&self, note: &Note) -> String {
+        format!(
+            "ID: {}\nName: {}\nData:\n{}",
+            note.id,
+            note.note_name,
+            note.body()
+        )
+    }
+
+    fn render_notes(&self, notes: &[Note]) -> String {
+        let mut res = format!("All notes in notebook ({}):", notes.len());
+
+        for (number, note) in notes.iter().enumerate() {
+            res += &format!(
+                "\n{}. ID: {}; Name: {}\nData:\n{}",
+                number + 1,
+                note.id,
+                note.note_name,
+                note.body()
+            );
+        }
+
+        res
+    }
+
+    fn render_summaries(&self, notes: &[NoteSummary]) -> String {
+        format::summaries_table(notes, false)
+    }
+}
+
+/// Aligned table with one row for every note.
+///
+/// Line breaks and tabs in content of notes are displayed as `\n` and `\t`,
+/// so every note takes one row.
+pub struct Table;
+
+impl Table {
+    fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+        format::table(header, &rows, &[], false)
+    }
+}
+
+impl NoteRenderer for Table {
+    fn render_note(&self, note: &Note) -> String {
+        self.render_notes(std::slice::from_ref(note))
+    }
+
+    fn render_notes(&self, notes: &[Note]) -> String {
+        Table::table(
+            &["ID", "NAME", "CONTENT"],
+            notes
+                .iter()
+                .map(|note| {
+                    vec![
+                        note.id.to_string(),
+                        note.note_name.to_owned(),
+                        note.note
+                            .as_deref()
+                            .unwrap_or_default()
+                            .replace('\n', "\\n")
+                            .replace('\t', "\\t"),
+                    ]
+                })
+                .collect(),
+        )
+    }
+
+    fn render_summaries(&self, notes: &[NoteSummary]) -> String {
+        Table::table(
+            &["ID", "NAME", "LINES", "UPDATED", "PREVIEW"],
+            notes
+                .iter()
+                .map(|note| {
+                    vec![
+                        note.id.to_string(),
+                        note.note_name.to_owned(),
+                        note.lines.to_string(),
+                        updated(note),
+                        preview(note),
+                    ]
+                })
+                .collect(),
+        )
+    }
+}
+
+/// JSON objects with the same fields as [`Note`] and [`NoteSummary`].
+///
+/// Note is rendered as object, notes are rendered as array of objects.
+pub struct Json;
+
+impl Json {
+    /// Returns `text` as JSON string.
+    pub(crate) fn string(text: &str) -> String {
+        let mut res = String::with_capacity(text.len() + 2);
+
+        res.push('"');
+        for c in text.chars() {
+            match c {
+                '"' => res += "\\\"",
+                '\\' => res += "\\\\",
+                '\n' => res += "\\n",
+                '\r' => res += "\\r",
+                '\t' => res += "\\t",
+                c if c.is_control() => res += &format!("\\u{:04x}", c as u32),
+                c => res.push(c),
+            }
+        }
+        res.push('"');
+
+        res
+    }
+
+    fn array(items: impl Iterator<Item = String>) -> String {
+        format!("[{}]", items.collect::<Vec<_>>().join(","))
+    }
+}
+
+impl NoteRenderer for Json {
+    fn render_note(&self, note: &Note) -> String {
+        format!(
+            r#"{{"id":{},"note_name":{},"note":{}}}"#,
+            note.id,
+            Json::string(&note.note_name),
+            note.note.as_deref().map_or("null".to_owned(), Json::string)
+        )
+    }
+
+    fn render_notes(&self, notes: &[Note]) -> String {
+        Json::array(notes.iter().map(|note| self.render_note(note)))
+    }
+
+    fn render_summaries(&self, notes: &[NoteSummary]) -> String {
+        Json::array(notes.iter().map(|note| {
+            format!(
+                r#"{{"id":{},"note_name":{},"preview":{},"truncated":{},"lines":{},"updated_at":{}}}"#,
+                note.id,
+                Json::string(&note.note_name),
+                Json::string(&note.preview),
+                note.truncated,
+                note.lines,
+                note.updated_at.map_or("null".to_owned(), |updated_at| {
+                    Json::string(&updated_at.to_rfc3339())
+                })
+            )
+        }))
+    }
+}
+
+/// Header with content for every note, and table for short information about notes.
+pub struct Markdown;
+
+impl Markdown {
+    /// Escapes `|` so text can be put into cell of table.
+    fn cell(text: &str) -> String {
+        text.replace('|', "\\|")
+    }
+}
+
+impl NoteRenderer for Markdown {
+    fn render_note(&self, note: &Note) -> String {
+        format!("## {}\n\n{}", note.note_name, note.body().trim_end())
+    }
+
+    fn render_notes(&self, notes: &[Note]) -> String {
+        notes
+            .iter()
+            .map(|note| self.render_note(note))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn render_summaries(&self, notes: &[NoteSummary]) -> String {
+        let mut res =
+            "| ID | Name | Lines | Updated | Preview |\n| --- | --- | --- | --- | --- |".to_owned();
+
+        for note in notes {
+            res += &format!(
+                "\n| {} | {} | {} | {} | {} |",
+                note.id,
+                Markdown::cell(&note.note_name),
+                note.lines,
+                updated(note),
+                Markdown::cell(&preview(note))
+            );
+        }
+
+        res
+    }
+}
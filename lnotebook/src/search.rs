@@ -0,0 +1,283 @@
+//! Search of notes.
+//!
+//! [`search`] looks for notes in one notebook. Notes can also be searched in all notebooks
+//! configured as profiles (read about profiles in [`config` module][crate::config])
+//! with [`search_profiles`]: notebooks are searched at the same time and results
+//! are merged and tagged with the name of profile they were found in.
+//!
+//! [`grep`] looks for lines of notes that match regular expression.
+
+use crate::commands::{NoteSummary, PREVIEW_LEN};
+use crate::compression::{self, StoredNote};
+use crate::config::Profile;
+use crate::errors::NotebookError;
+use crate::sql::query_as;
+use crate::users;
+
+use chrono::{DateTime, Local, Utc};
+use futures_util::{future, TryStreamExt};
+use regex::RegexBuilder;
+use sqlx::{self, PgPool};
+use std::fmt;
+
+/// Name of profile that is used for notebook the command was run with.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Returns notes whose name or content contains `query`, ordered by name.
+///
+/// Search is case-insensitive. Protected notes (read about them in
+/// [`protect` module][crate::protect]) are not searched.
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`NoteSummary`] of found notes
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::search::search;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn search_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("shopping", "Milk\nBread", pool).await?;
+///
+///     let notes = search("bread", pool).await?;
+///
+///     assert_eq!("shopping", notes[0].note_name);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn search(query: &str, pool: &PgPool) -> Result<Vec<NoteSummary>, NotebookError> {
+    let rows = query_as!(
+        {
+            id: i32,
+            note_name: String,
+            preview: String,
+            truncated: bool,
+            lines: i64,
+            updated_at: DateTime<Utc>,
+            packed: Option<Vec<u8>>,
+        },
+        r#"
+SELECT
+    id,
+    note_name,
+    preview AS "preview!",
+    char_length(rtrim(COALESCE(note, ''), E'\n')) > char_length(preview) AS "truncated!",
+    COALESCE(array_length(string_to_array(note, E'\n'), 1), 0)::INT8 AS "lines!",
+    updated_at,
+    CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook,
+    LATERAL substring(
+        split_part(COALESCE(note, '(binary, ' || octet_length(note_bytes) || ' bytes)', ''), E'\n', 1)
+        FROM 1 FOR $1
+    ) AS preview
+WHERE passphrase_hash IS NULL
+    AND can_access_note(id, owner, $3, false)
+    AND (
+        strpos(lower(note_name), lower($2)) > 0
+        OR strpos(lower(COALESCE(note, '')), lower($2)) > 0
+        OR compressed
+    )
+ORDER BY note_name
+        "#,
+        PREVIEW_LEN,
+        query,
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Compressed notes are searched here, the database can't read them
+    let query = query.to_lowercase();
+    let mut notes = Vec::with_capacity(rows.len());
+    for row in rows {
+        let updated_at = Some(row.updated_at.with_timezone(&Local));
+        match compression::content(&row.note_name, None, row.packed)? {
+            Some(note) => {
+                if row.note_name.to_lowercase().contains(&query)
+                    || note.to_lowercase().contains(&query)
+                {
+                    notes.push(NoteSummary::of(
+                        row.id,
+                        row.note_name,
+                        &note,
+                        PREVIEW_LEN,
+                        updated_at,
+                    ));
+                }
+            }
+            None => notes.push(NoteSummary {
+                id: row.id,
+                note_name: row.note_name,
+                preview: row.preview,
+                truncated: row.truncated,
+                lines: row.lines,
+                updated_at,
+            }),
+        }
+    }
+
+    Ok(notes)
+}
+
+/// This is a `struct` that containing note found by [`search_profiles`].
+pub struct SearchHit {
+    /// Name of profile the note was found in
+    pub profile: String,
+    pub note: NoteSummary,
+}
+
+/// This is a `struct` that containing results of [`search_profiles`].
+#[derive(Default)]
+pub struct ProfilesSearch {
+    /// Found notes ordered by notename and profile
+    pub hits: Vec<SearchHit>,
+    /// Profiles that can't be searched with the errors why
+    pub failed: Vec<(String, NotebookError)>,
+}
+
+async fn search_profile(query: &str, profile: &Profile) -> Result<Vec<NoteSummary>, NotebookError> {
+    let pool = PgPool::connect(&profile.url).await?;
+    let notes = search(query, &pool).await;
+    pool.close().await;
+
+    notes
+}
+
+/// Searches notes in notebook `pool` (as [`DEFAULT_PROFILE`]) and in notebooks of all `profiles`
+/// at the same time.
+///
+/// If some notebook can't be searched, for example it's unavailable, other notebooks are still
+/// searched and the error is returned in [`ProfilesSearch::failed`].
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::config::Config;
+/// # use lnotebook::search::search_profiles;
+/// # use sqlx::PgPool;
+/// async fn search_profiles_example(pool: &PgPool) {
+///     let res = search_profiles("bread", pool, &Config::from_env().profiles).await;
+///
+///     for hit in res.hits {
+///         println!("[{}] {}", hit.profile, hit.note.note_name);
+///     }
+/// }
+/// ```
+pub async fn search_profiles(query: &str, pool: &PgPool, profiles: &[Profile]) -> ProfilesSearch {
+    let (default, others) = future::join(
+        search(query, pool),
+        future::join_all(
+            profiles
+                .iter()
+                .map(|profile| async move { (profile, search_profile(query, profile).await) }),
+        ),
+    )
+    .await;
+
+    let mut res = ProfilesSearch::default();
+    let results = std::iter::once((DEFAULT_PROFILE, default)).chain(
+        others
+            .into_iter()
+            .map(|(p, notes)| (p.name.as_str(), notes)),
+    );
+    for (profile, notes) in results {
+        match notes {
+            Ok(notes) => res.hits.extend(notes.into_iter().map(|note| SearchHit {
+                profile: profile.to_owned(),
+                note,
+            })),
+            Err(err) => res.failed.push((profile.to_owned(), err)),
+        }
+    }
+    res.hits
+        .sort_by(|a, b| (&a.note.note_name, &a.profile).cmp(&(&b.note.note_name, &b.profile)));
+
+    res
+}
+
+/// This is a `struct` that containing line of note found by [`grep`].
+pub struct GrepMatch {
+    pub note_name: String,
+    /// Number of line, lines are numbered from 1
+    pub line_no: u32,
+    pub line: String,
+}
+
+impl fmt::Display for GrepMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.note_name, self.line_no, self.line)
+    }
+}
+
+/// Returns lines of notes that match regular expression `pattern`, ordered by notename
+/// and number of line.
+///
+/// Notes are read from database one by one, so all notes are never loaded at the same time.
+/// Protected notes are not searched, like in [`search`].
+/// ### Returns
+/// * Ok
+///     * `Vec` of [`GrepMatch`]
+/// * Errors
+///     * [`NotebookError::InvalidPattern`] error if `pattern` is not a valid regular expression
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::search::grep;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn grep_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("todo", "buy milk\ncall mom\nTODO: fix bike", pool).await?;
+///
+///     let matches = grep("^todo", true, pool).await?;
+///
+///     assert_eq!(3, matches[0].line_no);
+///     assert_eq!("TODO: fix bike", matches[0].line);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn grep(
+    pattern: &str,
+    ignore_case: bool,
+    pool: &PgPool,
+) -> Result<Vec<GrepMatch>, NotebookError> {
+    let re = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| NotebookError::InvalidPattern {
+            pattern: pattern.to_owned(),
+            reason: err.to_string(),
+        })?;
+
+    let mut rows = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE (note IS NOT NULL OR compressed)
+    AND passphrase_hash IS NULL
+    AND can_access_note(id, owner, $1, false)
+ORDER BY note_name
+        ",
+        users::acting_user()
+    )
+    .fetch(pool);
+
+    let mut matches = Vec::new();
+    while let Some(row) = rows.try_next().await? {
+        let row = row.into_note()?;
+        for (i, line) in row.body().lines().enumerate() {
+            if re.is_match(line) {
+                matches.push(GrepMatch {
+                    note_name: row.note_name.to_owned(),
+                    line_no: i as u32 + 1,
+                    line: line.to_owned(),
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
@@ -0,0 +1,611 @@
+//! HTTP server mode of the notebook.
+//!
+//! Server gives access to the notebook over REST API:
+//! * `GET /notes` - returns all notes
+//! * `GET /notes/:name` - returns the requested note
+//! * `POST /notes` - adds a new note from `{"note_name": "...", "note": "..."}`
+//! * `PUT /notes/:name` - updates content of note from `{"note": "..."}`
+//! * `DELETE /notes/:name` - deletes the requested note
+//!
+//! Notes are returned as `{"id": 1, "note_name": "...", "note": "..."}`. `GET /` returns
+//! a simple HTML page with all notes, so the notebook can be read from a browser.
+//!
+//! Requests are executed by [`Notebook`] like commands of CLI, so changes made over the server
+//! are recorded in audit log (read more in [`audit` module][crate::audit]) and sent to webhooks
+//! and git repository of the notebook given to [`serve_with`].
+//!
+//! With `metrics` feature `GET /metrics` returns number of executed requests, errors and
+//! histograms of their duration and duration of their SQL queries in format of Prometheus
+//! (read more in [`metrics` module][crate::metrics]). Requests are recorded with names of CLI
+//! commands that do the same: `list`, `display-note`, `add-note`, `upd-note` and `del-note`.
+//!
+//! ### Authentication and rate limiting
+//! Server started with [`serve_with`] can be protected by [`ServerOptions`]:
+//! * with `token` every request must have `Authorization: Bearer <token>` header, other requests
+//!   get `401 Unauthorized` ([`NotebookError::Unauthorized`]);
+//! * with `rate_limit` one IP address can send only so many requests in a minute, other requests
+//!   get `429 Too Many Requests` with `Retry-After` header ([`NotebookError::RateLimited`]).
+//!
+//! CLI reads them from `LNOTEBOOK_API_TOKEN` and `LNOTEBOOK_RATE_LIMIT` (read more in
+//! [`config` module][crate::config]). Tokens are sent in plain text, so put the server behind
+//! HTTPS proxy before exposing it to the internet.
+//!
+//! Error messages and the HTML page are localized using the `Accept-Language` header
+//! (read more in [`i18n` module][crate::i18n]).
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::{connect, get_db_url, ConnectOptions, NotebookError};
+//! # use lnotebook::notebook::Notebook;
+//! # use lnotebook::server::ServerOptions;
+//! async fn server_example() -> Result<(), NotebookError> {
+//!     let db = connect(&get_db_url().await?, ConnectOptions::default()).await?;
+//!
+//!     let options = ServerOptions {
+//!         token: Some("secret".to_owned()),
+//!         rate_limit: Some(60),
+//!     };
+//!     lnotebook::server::serve_with("0.0.0.0:8080", Notebook::new(db), options).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::{Note, PREVIEW_LEN};
+use crate::config::Config;
+use crate::errors::NotebookError;
+use crate::i18n::{self, Lang, Message};
+use crate::notebook::{CommandRequest, CommandResponse, Notebook};
+
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::net::TcpListener;
+use tracing::{event, Level};
+
+/// Period in which requests from one IP address are counted by rate limit.
+const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(60);
+
+/// Protection of the server, read more [here][crate::server#authentication-and-rate-limiting].
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Token that clients must send in `Authorization: Bearer <token>` header,
+    /// `None` to allow all clients
+    pub token: Option<String>,
+    /// Max requests from one IP address in a minute, `None` to not limit requests
+    pub rate_limit: Option<u32>,
+}
+
+impl ServerOptions {
+    /// Returns options set in [`Config`] (`LNOTEBOOK_API_TOKEN` and `LNOTEBOOK_RATE_LIMIT`).
+    pub fn from_config(config: &Config) -> ServerOptions {
+        ServerOptions {
+            token: config.api_token.clone(),
+            rate_limit: config.rate_limit,
+        }
+    }
+}
+
+/// Note as it is sent and received by the server.
+#[derive(Serialize)]
+struct NoteJson {
+    id: i32,
+    note_name: String,
+    note: String,
+}
+
+impl From<Note> for NoteJson {
+    fn from(note: Note) -> Self {
+        NoteJson {
+            id: note.id,
+            note: note.note.unwrap_or_default(),
+            note_name: note.note_name,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NewNote {
+    note_name: String,
+    #[serde(default)]
+    note: String,
+}
+
+#[derive(Deserialize)]
+struct UpdNote {
+    note: String,
+}
+
+/// [`NotebookError`] that will be sent in the language of the client.
+struct ApiError {
+    err: NotebookError,
+    lang: Lang,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.err {
+            NotebookError::Unauthorized => StatusCode::UNAUTHORIZED,
+            NotebookError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            NotebookError::NotOwner { .. }
+            | NotebookError::ReadOnly { .. }
+            | NotebookError::NotAdmin { .. }
+            | NotebookError::NoteProtected { .. } => StatusCode::FORBIDDEN,
+            NotebookError::AlreadyTaken { .. }
+            | NotebookError::UserTaken { .. }
+            | NotebookError::Conflict { .. }
+            | NotebookError::BinaryNote { .. } => StatusCode::CONFLICT,
+            NotebookError::Locked { .. } => StatusCode::LOCKED,
+            NotebookError::NoteTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            NotebookError::InvalidLineNumber { .. }
+            | NotebookError::InvalidTemplate { .. }
+            | NotebookError::InvalidDate { .. }
+            | NotebookError::InvalidDateFormat { .. }
+            | NotebookError::InvalidPattern { .. }
+            | NotebookError::InvalidNoteName { .. } => StatusCode::BAD_REQUEST,
+            NotebookError::Sqlx(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            event!(Level::ERROR, "Request failed: {}", self.err);
+        }
+
+        let body = serde_json::json!({ "error": i18n::error_message(&self.err, self.lang) });
+        let mut response = (status, Json(body)).into_response();
+
+        match &self.err {
+            NotebookError::Unauthorized => {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+            }
+            NotebookError::RateLimited { retry_after } => {
+                response.headers_mut().insert(
+                    header::RETRY_AFTER,
+                    retry_after.to_string().parse().unwrap(),
+                );
+            }
+            _ => {}
+        }
+
+        response
+    }
+}
+
+/// Returns language requested by the client in `Accept-Language` header.
+fn request_lang(headers: &HeaderMap) -> Lang {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Lang::from_accept_language)
+        .unwrap_or_default()
+}
+
+/// Returns [`Router`] with all routes of the server.
+///
+/// Use it if you want to serve notebook by yourself or merge it with your own routes.
+pub fn router(pool: PgPool) -> Router {
+    router_with(Notebook::new(pool), ServerOptions::default())
+}
+
+/// Returns [`Router`] with all routes of the server protected by `options`.
+///
+/// Rate limit needs addresses of clients, so the router must be served with
+/// [`into_make_service_with_connect_info::<SocketAddr>`][Router::into_make_service_with_connect_info],
+/// otherwise requests are not limited.
+pub fn router_with(notebook: Notebook, options: ServerOptions) -> Router {
+    let router = routes(notebook);
+
+    let router = match options.token {
+        Some(token) => router.layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token),
+            check_token,
+        )),
+        None => router,
+    };
+
+    match options.rate_limit {
+        Some(limit) => router.layer(middleware::from_fn_with_state(
+            RateLimiter::new(limit),
+            limit_rate,
+        )),
+        None => router,
+    }
+}
+
+fn routes(notebook: Notebook) -> Router {
+    let router = Router::new();
+    #[cfg(feature = "metrics")]
+    let router = router.route("/metrics", get(metrics));
+
+    router
+        .route("/", get(index))
+        .route("/notes", get(list_notes).post(create_note))
+        .route(
+            "/notes/:name",
+            get(get_note).put(update_note).delete(delete_note),
+        )
+        .with_state(notebook)
+}
+
+/// Starts server on the given address and serves notebook until error occurs.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if server can't listen on the given address
+pub async fn serve(addr: &str, pool: PgPool) -> Result<(), NotebookError> {
+    serve_with(addr, Notebook::new(pool), ServerOptions::default()).await
+}
+
+/// Starts server protected by `options` on the given address and serves `notebook`
+/// until error occurs.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if server can't listen on the given address
+pub async fn serve_with(
+    addr: &str,
+    notebook: Notebook,
+    options: ServerOptions,
+) -> Result<(), NotebookError> {
+    let listener = TcpListener::bind(addr).await?;
+
+    if options.token.is_none() {
+        event!(
+            Level::WARN,
+            "Server is open to everyone, set `LNOTEBOOK_API_TOKEN` to check clients"
+        );
+    }
+    event!(Level::INFO, "Serving notebook on http://{}", addr);
+
+    let router = router_with(notebook, options);
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Counts requests from every IP address in the current period of [`RATE_LIMIT_PERIOD`].
+#[derive(Clone)]
+struct RateLimiter {
+    limit: u32,
+    clients: Arc<Mutex<HashMap<IpAddr, (Instant, u32)>>>,
+}
+
+impl RateLimiter {
+    fn new(limit: u32) -> RateLimiter {
+        RateLimiter {
+            limit,
+            clients: Arc::default(),
+        }
+    }
+
+    /// Counts request from `ip`, returns [`NotebookError::RateLimited`] if limit is reached.
+    fn check(&self, ip: IpAddr) -> Result<(), NotebookError> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        clients.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_PERIOD);
+
+        let (start, count) = clients.entry(ip).or_insert((now, 0));
+        if *count >= self.limit {
+            let retry_after = RATE_LIMIT_PERIOD.saturating_sub(now.duration_since(*start));
+            return Err(NotebookError::RateLimited {
+                retry_after: retry_after.as_secs().max(1),
+            });
+        }
+        *count += 1;
+
+        Ok(())
+    }
+}
+
+async fn limit_rate(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(ip) = ip {
+        limiter.check(ip).map_err(|err| ApiError {
+            err,
+            lang: request_lang(request.headers()),
+        })?;
+    }
+
+    Ok(next.run(request).await)
+}
+
+async fn check_token(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match given {
+        Some(given) if same_token(given.trim().as_bytes(), token.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(ApiError {
+            err: NotebookError::Unauthorized,
+            lang: request_lang(request.headers()),
+        }),
+    }
+}
+
+/// Compares tokens in constant time, so the token can't be guessed by time of responses.
+fn same_token(given: &[u8], token: &[u8]) -> bool {
+    given.len() == token.len()
+        && given
+            .iter()
+            .zip(token)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Executes `request` in `notebook`, errors are sent in the language of `headers`.
+async fn execute(
+    notebook: &Notebook,
+    request: CommandRequest,
+    headers: &HeaderMap,
+) -> Result<CommandResponse, ApiError> {
+    notebook.execute(request).await.map_err(|err| ApiError {
+        err,
+        lang: request_lang(headers),
+    })
+}
+
+/// Returns all notes that can be read over the server.
+async fn all_notes(notebook: &Notebook, headers: &HeaderMap) -> Result<Vec<Note>, ApiError> {
+    let request = CommandRequest::List {
+        full: true,
+        preview_len: PREVIEW_LEN,
+    };
+
+    match execute(notebook, request, headers).await? {
+        CommandResponse::Notes(notes) => Ok(notes),
+        _ => unreachable!("request returns notes"),
+    }
+}
+
+/// Returns note from response of request that returns note.
+fn note_of(response: CommandResponse) -> NoteJson {
+    match response {
+        CommandResponse::Note(note) => note.into(),
+        _ => unreachable!("request returns note"),
+    }
+}
+
+async fn index(
+    State(notebook): State<Notebook>,
+    headers: HeaderMap,
+) -> Result<Html<String>, ApiError> {
+    let lang = request_lang(&headers);
+    let notes = all_notes(&notebook, &headers).await?;
+
+    let title = i18n::message(Message::AllNotes, lang);
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+
+    if notes.is_empty() {
+        page += &format!("<p>{}</p>\n", i18n::message(Message::NoNotes, lang));
+    }
+    for note in notes.iter() {
+        page += &format!(
+            "<details>\n<summary>{}</summary>\n<pre>{}</pre>\n</details>\n",
+            escape_html(&note.note_name),
+            escape_html(note.body())
+        );
+    }
+    page += "</body>\n</html>\n";
+
+    Ok(Html(page))
+}
+
+async fn list_notes(
+    State(notebook): State<Notebook>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<NoteJson>>, ApiError> {
+    let notes = all_notes(&notebook, &headers).await?;
+
+    Ok(Json(notes.into_iter().map(NoteJson::from).collect()))
+}
+
+async fn get_note(
+    State(notebook): State<Notebook>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<NoteJson>, ApiError> {
+    let request = CommandRequest::DisplayNote {
+        notename: name,
+        lines: None,
+    };
+
+    Ok(Json(note_of(execute(&notebook, request, &headers).await?)))
+}
+
+async fn create_note(
+    State(notebook): State<Notebook>,
+    headers: HeaderMap,
+    Json(new_note): Json<NewNote>,
+) -> Result<(StatusCode, Json<NoteJson>), ApiError> {
+    let request = CommandRequest::AddNote {
+        notename: new_note.note_name,
+        note: new_note.note,
+    };
+    let note = note_of(execute(&notebook, request, &headers).await?);
+
+    Ok((StatusCode::CREATED, Json(note)))
+}
+
+async fn update_note(
+    State(notebook): State<Notebook>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(upd_note): Json<UpdNote>,
+) -> Result<Json<NoteJson>, ApiError> {
+    let request = CommandRequest::UpdNote {
+        notename: name,
+        note: upd_note.note,
+    };
+
+    Ok(Json(note_of(execute(&notebook, request, &headers).await?)))
+}
+
+async fn delete_note(
+    State(notebook): State<Notebook>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    execute(
+        &notebook,
+        CommandRequest::DelNote { notename: name },
+        &headers,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(feature = "metrics")]
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render_prometheus(&crate::metrics::metrics()),
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Serves notebook protected by `options` on a free port and returns its address.
+    async fn server(options: ServerOptions, pool: PgPool) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let router = router_with(Notebook::new(pool), options);
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        addr
+    }
+
+    /// Sends `GET /notes` with `authorization` header and returns the response head.
+    async fn get_notes(addr: SocketAddr, authorization: Option<&str>) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let authorization = authorization
+            .map(|value| format!("Authorization: {value}\r\n"))
+            .unwrap_or_default();
+        let request = format!(
+            "GET /notes HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n{authorization}\r\n"
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.split("\r\n\r\n").next().unwrap().to_owned()
+    }
+
+    fn status(head: &str) -> &str {
+        head.split(' ').nth(1).unwrap()
+    }
+
+    #[test]
+    fn limit_is_counted_for_every_ip() {
+        let limiter = RateLimiter::new(2);
+        let first = IpAddr::from([10, 0, 0, 1]);
+        let second = IpAddr::from([10, 0, 0, 2]);
+
+        assert!(limiter.check(first).is_ok());
+        assert!(limiter.check(first).is_ok());
+        assert!(matches!(
+            limiter.check(first),
+            Err(NotebookError::RateLimited {
+                retry_after: 1..=60
+            })
+        ));
+        assert!(limiter.check(second).is_ok());
+    }
+
+    #[test]
+    fn tokens_are_compared() {
+        assert!(same_token(b"secret", b"secret"));
+        assert!(!same_token(b"Secret", b"secret"));
+        assert!(!same_token(b"secret2", b"secret"));
+        assert!(!same_token(b"", b"secret"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn requests_without_right_token_are_rejected(pool: PgPool) {
+        let options = ServerOptions {
+            token: Some("secret".to_owned()),
+            rate_limit: None,
+        };
+        let addr = server(options, pool).await;
+
+        for authorization in [None, Some("Bearer wrong"), Some("secret"), Some("Bearer ")] {
+            assert_eq!("401", status(&get_notes(addr, authorization).await));
+        }
+        assert_eq!("200", status(&get_notes(addr, Some("Bearer secret")).await));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn requests_over_limit_are_rejected(pool: PgPool) {
+        let options = ServerOptions {
+            token: None,
+            rate_limit: Some(2),
+        };
+        let addr = server(options, pool).await;
+
+        assert_eq!("200", status(&get_notes(addr, None).await));
+        assert_eq!("200", status(&get_notes(addr, None).await));
+
+        let head = get_notes(addr, None).await;
+        assert_eq!("429", status(&head));
+        assert!(head.to_lowercase().contains("retry-after: "));
+    }
+}
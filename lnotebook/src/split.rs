@@ -0,0 +1,320 @@
+//! Splitting one note into several notes.
+//!
+//! [`split_note`] divides content of note into sections and adds one note per section named
+//! `<notename>_<slug>`, where slug is title of the section in lowercase with `_` instead of spaces
+//! and punctuation. Notes with the same slug get `_2`, `_3` and so on. New notes have the same
+//! owner as the original (read about owners in [`users` module][crate::users]). The original note
+//! is deleted, unless it's kept. Everything is done in one transaction, so if any of the new names
+//! is already taken, nothing is changed.
+//!
+//! Sections are found by [`SplitBy`]:
+//! * [`SplitBy::Heading`] - every Markdown heading (`# Title`, `## Title` and so on, but not inside
+//!   code blocks) starts a section titled by it. Heading stays in the content of the section.
+//!   Text before the first heading becomes section `intro`.
+//! * [`SplitBy::Delimiter`] - sections are separated by lines that contain only the delimiter,
+//!   [`DEFAULT_DELIMITER`] by default. Section is titled by its first line.
+//!
+//! Empty sections are skipped. Note with less than two sections is not split.
+//!
+//! CLI splits notes with `split-note <notename> [--by <heading|delimiter>] [--delimiter <text>]
+//! [--keep]`.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::commands::add;
+//! # use lnotebook::split::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn split_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     add("ideas", "# Work\nAutomate reports\n# Home\nPaint the fence", pool).await?;
+//!
+//!     let notes = split_note("ideas", SplitBy::Heading, DEFAULT_DELIMITER, false, pool).await?;
+//!
+//!     assert_eq!("ideas_work", notes[0].note_name);
+//!     assert_eq!("ideas_home", notes[1].note_name);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::Note;
+use crate::compression::{self, StoredNote};
+use crate::config;
+use crate::errors::{self, NotebookError};
+use crate::lock;
+use crate::notename::{NoteName, MAX_LEN};
+use crate::protect;
+use crate::sql::{query, query_as};
+use crate::users::{self, Access};
+
+use sqlx::{self, PgPool};
+use std::collections::HashSet;
+use tracing::{event, Level};
+
+/// Delimiter of sections used if other delimiter is not given.
+pub const DEFAULT_DELIMITER: &str = "---";
+
+/// Max length of slug in characters.
+const MAX_SLUG_LEN: usize = 60;
+
+/// How content of note is divided into sections, read more [here][crate::split].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SplitBy {
+    /// Every Markdown heading starts a section
+    #[default]
+    Heading,
+    /// Sections are separated by lines with delimiter
+    Delimiter,
+}
+
+/// This is a `struct` that containing section of note found by [`sections`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub title: String,
+    pub content: String,
+}
+
+/// Returns sections of `text` found by `by`, read more [here][crate::split].
+///
+/// `delimiter` is used only with [`SplitBy::Delimiter`].
+/// ### Example
+/// ```
+/// # use lnotebook::split::*;
+/// let parts = sections("Todo\n## Work\nReports\n## Home\nFence", SplitBy::Heading, "");
+///
+/// assert_eq!(3, parts.len());
+/// assert_eq!("intro", parts[0].title);
+/// assert_eq!("Work", parts[1].title);
+/// assert_eq!("## Home\nFence", parts[2].content);
+///
+/// let parts = sections("milk\n---\nbread\nbutter", SplitBy::Delimiter, "---");
+///
+/// assert_eq!("bread", parts[1].title);
+/// assert_eq!("bread\nbutter", parts[1].content);
+/// ```
+pub fn sections(text: &str, by: SplitBy, delimiter: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut title = match by {
+        SplitBy::Heading => Some("intro".to_owned()),
+        SplitBy::Delimiter => None,
+    };
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_code = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+
+        let starts_section = match by {
+            SplitBy::Heading if !in_code => heading(line).map(str::to_owned),
+            SplitBy::Delimiter if line.trim() == delimiter.trim() => Some(String::new()),
+            _ => None,
+        };
+        let Some(next_title) = starts_section else {
+            lines.push(line);
+            continue;
+        };
+
+        push_section(&mut sections, title.take(), &lines);
+        lines.clear();
+        match by {
+            SplitBy::Heading => {
+                title = Some(next_title);
+                lines.push(line);
+            }
+            SplitBy::Delimiter => {}
+        }
+    }
+    push_section(&mut sections, title, &lines);
+
+    sections
+}
+
+/// Returns text of Markdown heading `line`, `None` if it's not a heading.
+pub(crate) fn heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    Some(text.trim().trim_end_matches('#').trim_end())
+}
+
+fn push_section(sections: &mut Vec<Section>, title: Option<String>, lines: &[&str]) {
+    let content = lines.join("\n").trim_matches('\n').to_owned();
+    if content.trim().is_empty() {
+        return;
+    }
+
+    // Section without title is titled by its first line
+    let title = title.filter(|title| !title.is_empty()).unwrap_or_else(|| {
+        content
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default()
+            .trim()
+            .to_owned()
+    });
+
+    sections.push(Section { title, content });
+}
+
+/// Returns `title` in lowercase with `_` instead of spaces and punctuation.
+///
+/// It's cut to 60 characters and is `section` if `title` has no letters and digits.
+/// ### Example
+/// ```
+/// # use lnotebook::split::slug;
+/// assert_eq!("work_plans_2024", slug("Work: plans (2024)!"));
+/// assert_eq!("section", slug("???"));
+/// ```
+pub fn slug(title: &str) -> String {
+    let slug = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase();
+
+    match slug.char_indices().nth(MAX_SLUG_LEN) {
+        _ if slug.is_empty() => "section".to_owned(),
+        Some((end, _)) => slug[..end].trim_end_matches('_').to_owned(),
+        None => slug,
+    }
+}
+
+/// Splits note `notename` into notes with sections of its content found by `by`,
+/// read more [here][crate::split].
+///
+/// `delimiter` is used only with [`SplitBy::Delimiter`]. The original note is deleted,
+/// unless `keep` is `true`.
+/// ### Returns
+/// * Ok
+///     * `Vec` of added [Note]s in order of sections, empty if note has less than two sections
+/// * Errors
+///     * [`NotebookError::AlreadyTaken`] error if note with name of section already exists
+///     * [`NotebookError::Locked`] error if note is locked by other user and it's not kept
+///     * [`NotebookError::NotOwner`] or [`NotebookError::ReadOnly`] error if the acting user
+///       can't read note, or can't change it and it's not kept (read more in
+///       [`users` module][crate::users])
+///     * [`NotebookError::NoteProtected`] error if note is protected and its passphrase isn't
+///       given (read more in [`protect` module][crate::protect])
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn split_note(
+    notename: &str,
+    by: SplitBy,
+    delimiter: &str,
+    keep: bool,
+    pool: &PgPool,
+) -> Result<Vec<Note>, NotebookError> {
+    let mut tx = pool.begin().await?;
+
+    if keep {
+        users::check_access(notename, Access::Read, &mut *tx).await?;
+        protect::check_passphrase(notename, &mut *tx).await?;
+    } else {
+        lock::check_unlocked(notename, &mut *tx).await?;
+        users::check_access(notename, Access::Write, &mut *tx).await?;
+        protect::check_passphrase(notename, &mut *tx).await?;
+    }
+
+    let original = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE note_name = $1
+FOR UPDATE
+        ",
+        notename
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .into_note()?;
+
+    let sections = sections(original.body(), by, delimiter);
+    if sections.len() < 2 {
+        return Ok(Vec::new());
+    }
+
+    let mut taken = HashSet::new();
+    let mut notes = Vec::new();
+    for section in sections {
+        let name = section_name(notename, &section.title, &mut taken);
+        NoteName::new(&name)?;
+        config::check_size(section.content.len())?;
+
+        let note = query_as!(
+            Note { id: i32, note_name: String, note: Option<String> },
+            "
+INSERT INTO notebook (note_name, note, owner)
+SELECT $1, $2, owner
+FROM notebook
+WHERE id = $3
+RETURNING id, note_name, note
+            ",
+            name,
+            section.content,
+            original.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| {
+            if errors::is_unique_violation(&err) {
+                NotebookError::AlreadyTaken {
+                    notename: name.to_owned(),
+                }
+            } else {
+                err.into()
+            }
+        })?;
+        compression::pack(note.id, note.note.as_deref(), &mut tx).await?;
+
+        notes.push(note);
+    }
+
+    if !keep {
+        query!("DELETE FROM notebook WHERE id = $1", original.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    event!(
+        Level::INFO,
+        "Split `{}` into {} notes{}",
+        notename,
+        notes.len(),
+        if keep { "" } else { " and delete it" }
+    );
+
+    Ok(notes)
+}
+
+/// Returns `<notename>_<slug>` that is not in `taken` and adds it there.
+fn section_name(notename: &str, title: &str, taken: &mut HashSet<String>) -> String {
+    let slug = slug(title);
+
+    let mut n = 1;
+    loop {
+        let suffix = match n {
+            1 => format!("_{slug}"),
+            n => format!("_{slug}_{n}"),
+        };
+        let prefix: String = notename
+            .chars()
+            .take(MAX_LEN.saturating_sub(suffix.chars().count()))
+            .collect();
+        let name = format!("{}{suffix}", prefix.trim_end());
+
+        if taken.insert(name.clone()) {
+            return name;
+        }
+        n += 1;
+    }
+}
@@ -0,0 +1,88 @@
+//! Queries to the database that are checked at compile time or built at runtime.
+//!
+//! By default queries are `sqlx` macros ([`sqlx::query!`] and others) that are checked against
+//! the database from `DATABASE_URL` or against offline data in `.sqlx` directory when the crate
+//! is compiled. With `runtime-queries` feature queries are built at runtime with [`sqlx::query`],
+//! and columns of rows are mapped to fields by their position, so the crate compiles without
+//! a database. Mistakes in queries are found then only when they run.
+//!
+//! Every query is written once with macros of this module, that expand to one or the other:
+//! * `query!(sql, args..)` - query whose rows are not read
+//! * `query_as!({ field: Type, .. }, sql, args..)` - query that returns records with the fields,
+//!   or `query_as!(Struct { field: Type, .. }, sql, args..)` that returns `Struct`s
+//! * `query_scalar!(Type, sql, args..)` - query that returns one column
+//!
+//! Fields are listed in order of columns, and their types are types of columns: `Option` if
+//! column is nullable.
+
+#[cfg(not(feature = "runtime-queries"))]
+macro_rules! query {
+    ($sql:literal $(, $arg:expr)* $(,)?) => {
+        sqlx::query!($sql $(, $arg)*)
+    };
+}
+
+#[cfg(feature = "runtime-queries")]
+macro_rules! query {
+    ($sql:literal $(, $arg:expr)* $(,)?) => {
+        sqlx::query($sql)$(.bind(&$arg))*
+    };
+}
+
+#[cfg(not(feature = "runtime-queries"))]
+macro_rules! query_as {
+    ({ $($field:ident: $ty:ty),* $(,)? }, $sql:literal $(, $arg:expr)* $(,)?) => {{
+        #[allow(dead_code)]
+        struct Record {
+            $($field: $ty),*
+        }
+
+        sqlx::query_as!(Record, $sql $(, $arg)*)
+    }};
+    ($out:ident { $($field:ident: $ty:ty),* $(,)? }, $sql:literal $(, $arg:expr)* $(,)?) => {
+        sqlx::query_as!($out, $sql $(, $arg)*)
+    };
+}
+
+#[cfg(feature = "runtime-queries")]
+macro_rules! query_as {
+    ({ $($field:ident: $ty:ty),* $(,)? }, $sql:literal $(, $arg:expr)* $(,)?) => {{
+        #[allow(dead_code)]
+        struct Record {
+            $($field: $ty),*
+        }
+
+        $crate::sql::query_as!(Record { $($field: $ty),* }, $sql $(, $arg)*)
+    }};
+    ($out:ident { $($field:ident: $ty:ty),* $(,)? }, $sql:literal $(, $arg:expr)* $(,)?) => {
+        sqlx::query($sql)$(.bind(&$arg))*.try_map(|row: sqlx::postgres::PgRow| {
+            let ($($field,)*) = <($($ty,)*) as sqlx::FromRow<'_, _>>::from_row(&row)?;
+
+            Ok($out { $($field),* })
+        })
+    };
+}
+
+#[cfg(not(feature = "runtime-queries"))]
+macro_rules! query_scalar {
+    ($ty:ty, $sql:literal $(, $arg:expr)* $(,)?) => {
+        $crate::sql::scalar::<$ty, _>(sqlx::query_scalar!($sql $(, $arg)*))
+    };
+}
+
+#[cfg(feature = "runtime-queries")]
+macro_rules! query_scalar {
+    ($ty:ty, $sql:literal $(, $arg:expr)* $(,)?) => {
+        sqlx::query_scalar::<_, $ty>($sql)$(.bind(&$arg))*
+    };
+}
+
+pub(crate) use {query, query_as, query_scalar};
+
+/// Returns `query`, so type of its column is checked to be the same in both modes.
+#[cfg(not(feature = "runtime-queries"))]
+pub(crate) fn scalar<O, A>(
+    query: sqlx::query::QueryScalar<'_, sqlx::Postgres, O, A>,
+) -> sqlx::query::QueryScalar<'_, sqlx::Postgres, O, A> {
+    query
+}
@@ -0,0 +1,371 @@
+//! Statistics about the notebook.
+
+use crate::commands;
+use crate::compression::StoredNote;
+use crate::errors::NotebookError;
+use crate::split;
+use crate::sql::query_as;
+use crate::users;
+
+use sqlx::{self, PgPool};
+use std::{fmt, time::Duration};
+use tracing::{event, Level};
+
+/// This is a `struct` that containing statistics about all notes in notebook.
+///
+/// This `struct` is returned by [`stats`]. It implements [`fmt::Display`],
+/// which renders statistics as a table.
+pub struct NotebookStats {
+    /// Number of notes in notebook
+    pub total_notes: i64,
+    /// Size of all notes content in bytes
+    pub total_bytes: i64,
+    /// Name of the largest note, `None` if notebook is empty
+    pub largest_note: Option<String>,
+    /// Size of the largest note content in bytes
+    pub largest_note_bytes: i64,
+    /// Average length of notes content in characters
+    pub average_length: f64,
+}
+
+impl fmt::Display for NotebookStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let largest = match &self.largest_note {
+            Some(name) => format!("{} ({} bytes)", name, self.largest_note_bytes),
+            None => "-".to_owned(),
+        };
+
+        table(
+            f,
+            &[
+                ("Total notes", self.total_notes.to_string()),
+                ("Total bytes", self.total_bytes.to_string()),
+                ("Largest note", largest),
+                ("Average length", format!("{:.1}", self.average_length)),
+            ],
+        )
+    }
+}
+
+/// This is a `struct` that containing size of content of one note.
+///
+/// This `struct` is returned by [`word_count`]. It implements [`fmt::Display`],
+/// which renders statistics as a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoteStats {
+    /// Number of words separated by whitespace
+    pub words: usize,
+    pub lines: usize,
+    /// Number of characters (Unicode scalar values)
+    pub chars: usize,
+    pub bytes: usize,
+}
+
+impl NoteStats {
+    /// Counts words, lines, characters and bytes in `text`.
+    /// ### Example
+    /// ```
+    /// # use lnotebook::stats::NoteStats;
+    /// let stats = NoteStats::of("Купить молоко\nand bread\n");
+    ///
+    /// assert_eq!(4, stats.words);
+    /// assert_eq!(2, stats.lines);
+    /// assert_eq!(24, stats.chars);
+    /// assert_eq!(36, stats.bytes);
+    /// ```
+    pub fn of(text: &str) -> NoteStats {
+        NoteStats {
+            words: text.split_whitespace().count(),
+            lines: text.lines().count(),
+            chars: text.chars().count(),
+            bytes: text.len(),
+        }
+    }
+}
+
+impl fmt::Display for NoteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        table(
+            f,
+            &[
+                ("Words", self.words.to_string()),
+                ("Lines", self.lines.to_string()),
+                ("Characters", self.chars.to_string()),
+                ("Bytes", self.bytes.to_string()),
+            ],
+        )
+    }
+}
+
+/// Number of words read in a minute, used to estimate reading time of notes.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Max number of characters in [`NotePreview::paragraph`] by default.
+pub const SUMMARY_LEN: usize = 280;
+
+/// This is a `struct` that containing summary of note: its first paragraph, number of words
+/// and estimated reading time.
+///
+/// This `struct` is returned by [`Note::summary`][crate::commands::Note::summary]
+/// and [`summary`]. It implements [`fmt::Display`], which renders paragraph ending with `…`
+/// if note has more content, and reading time below it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NotePreview {
+    /// First paragraph of note with its lines joined by spaces, Markdown headings before it
+    /// are skipped. Cut to max number of characters
+    pub paragraph: String,
+    /// `true` if note has more content than [`paragraph`][NotePreview::paragraph]
+    pub truncated: bool,
+    /// Number of words separated by whitespace
+    pub words: usize,
+    /// Time it takes to read note at [`WORDS_PER_MINUTE`]
+    pub reading_time: Duration,
+}
+
+impl NotePreview {
+    /// Makes summary of `text` with first paragraph cut to `max_len` characters.
+    /// ### Example
+    /// ```
+    /// # use lnotebook::stats::NotePreview;
+    /// let preview = NotePreview::of("# Trip\n\nPack the bags\nand go.\n\nDay one", 100);
+    ///
+    /// assert_eq!("Pack the bags and go.", preview.paragraph);
+    /// assert!(preview.truncated);
+    /// assert_eq!(9, preview.words);
+    /// assert_eq!(3, preview.reading_time.as_secs());
+    ///
+    /// assert_eq!("Pack", NotePreview::of("Pack the bags", 4).paragraph);
+    /// ```
+    pub fn of(text: &str, max_len: usize) -> NotePreview {
+        let mut lines = text.lines().map(str::trim);
+        let mut paragraph = Vec::new();
+        let mut truncated = false;
+
+        for line in lines.by_ref() {
+            if line.is_empty() || split::heading(line).is_some() {
+                if paragraph.is_empty() {
+                    continue;
+                }
+                truncated = !line.is_empty();
+                break;
+            }
+            paragraph.push(line);
+        }
+        truncated = truncated || lines.any(|line| !line.is_empty());
+
+        let paragraph = paragraph.join(" ");
+        let cut: String = paragraph.chars().take(max_len).collect();
+        let words = NoteStats::of(text).words;
+
+        NotePreview {
+            truncated: truncated || cut.len() < paragraph.len(),
+            paragraph: cut,
+            words,
+            reading_time: Duration::from_secs((words * 60).div_ceil(WORDS_PER_MINUTE) as u64),
+        }
+    }
+
+    /// Returns reading time in whole minutes, rounded up.
+    pub fn reading_minutes(&self) -> u64 {
+        self.reading_time.as_secs().div_ceil(60)
+    }
+}
+
+impl fmt::Display for NotePreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.paragraph.is_empty() {
+            let ellipsis = if self.truncated { "…" } else { "" };
+            writeln!(f, "{}{}\n", self.paragraph, ellipsis)?;
+        }
+
+        write!(
+            f,
+            "{} words, {} min read",
+            self.words,
+            self.reading_minutes()
+        )
+    }
+}
+
+/// Writes `rows` of keys and values as a table.
+fn table(f: &mut fmt::Formatter<'_>, rows: &[(&str, String)]) -> fmt::Result {
+    let key_width = rows.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    let value_width = rows.iter().map(|(_, v)| v.len()).max().unwrap_or(0);
+    let border = format!(
+        "+-{}-+-{}-+",
+        "-".repeat(key_width),
+        "-".repeat(value_width)
+    );
+
+    writeln!(f, "{border}")?;
+    for (key, value) in rows.iter() {
+        writeln!(f, "| {key:<key_width$} | {value:<value_width$} |")?;
+    }
+    write!(f, "{border}")
+}
+
+/// Returns statistics about all notes in notebook.
+/// ### Returns
+/// * Ok
+///     * [`NotebookStats`]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::stats::stats;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn stats_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("short", "meow", pool).await?;
+///     add("long", "meow meow meow meow", pool).await?;
+///
+///     let stats = stats(pool).await?;
+///
+///     assert_eq!(Some("long".to_owned()), stats.largest_note);
+///     println!("{stats}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn stats(pool: &PgPool) -> Result<NotebookStats, NotebookError> {
+    let mut totals = query_as!(
+        { total_notes: i64, total_bytes: i64, total_chars: i64, text_notes: i64 },
+        r#"
+SELECT
+    COUNT(*) AS "total_notes!",
+    COALESCE(SUM(octet_length(note)), 0)::INT8 AS "total_bytes!",
+    COALESCE(SUM(char_length(note)), 0)::INT8 AS "total_chars!",
+    COUNT(note) AS "text_notes!"
+FROM notebook
+WHERE can_access_note(id, owner, $1, false)
+        "#,
+        users::acting_user()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let largest = query_as!(
+        { id: i32, note_name: String, bytes: i32 },
+        r#"
+SELECT id, note_name, COALESCE(octet_length(note), 0) AS "bytes!"
+FROM notebook
+WHERE can_access_note(id, owner, $1, false)
+ORDER BY octet_length(note) DESC NULLS LAST, id
+LIMIT 1
+        "#,
+        users::acting_user()
+    )
+    .fetch_optional(pool)
+    .await?;
+    let mut largest = largest.map(|row| (i64::from(row.bytes), row.id, row.note_name));
+
+    // Compressed notes are measured here, the database can't read them
+    let compressed = query_as!(
+        StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+        "
+SELECT id, note_name, note, note_bytes AS packed
+FROM notebook
+WHERE compressed AND can_access_note(id, owner, $1, false)
+ORDER BY id
+        ",
+        users::acting_user()
+    )
+    .fetch_all(pool)
+    .await?;
+    for row in compressed {
+        let note = row.into_note()?;
+        let bytes = note.body().len() as i64;
+        totals.total_bytes += bytes;
+        totals.total_chars += note.body().chars().count() as i64;
+        totals.text_notes += 1;
+        if largest
+            .as_ref()
+            .is_none_or(|(max, id, _)| (bytes, -note.id) > (*max, -id))
+        {
+            largest = Some((bytes, note.id, note.note_name));
+        }
+    }
+
+    event!(
+        Level::DEBUG,
+        "Collected stats of {} notes",
+        totals.total_notes
+    );
+
+    let (largest_note, largest_note_bytes) = match largest {
+        Some((bytes, _, note_name)) => (Some(note_name), bytes),
+        None => (None, 0),
+    };
+
+    Ok(NotebookStats {
+        total_notes: totals.total_notes,
+        total_bytes: totals.total_bytes,
+        largest_note,
+        largest_note_bytes,
+        average_length: if totals.text_notes > 0 {
+            totals.total_chars as f64 / totals.text_notes as f64
+        } else {
+            0.0
+        },
+    })
+}
+
+/// Returns number of words, lines, characters and bytes in note `notename`.
+/// ### Returns
+/// * Ok
+///     * [`NoteStats`]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::stats::word_count;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn word_count_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("novel", "It was a dark and stormy night", pool).await?;
+///
+///     assert_eq!(7, word_count("novel", pool).await?.words);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn word_count(notename: &str, pool: &PgPool) -> Result<NoteStats, NotebookError> {
+    let note = commands::select_one(notename, pool).await?;
+
+    Ok(NoteStats::of(note.body()))
+}
+
+/// Returns summary of note `notename` with first paragraph cut to `max_len` characters.
+/// ### Returns
+/// * Ok
+///     * [`NotePreview`]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::stats::{summary, SUMMARY_LEN};
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn summary_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("novel", "It was a dark and stormy night.\n\nThe end.", pool).await?;
+///
+///     let summary = summary("novel", SUMMARY_LEN, pool).await?;
+///
+///     assert_eq!("It was a dark and stormy night.", summary.paragraph);
+///     assert_eq!(1, summary.reading_minutes());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn summary(
+    notename: &str,
+    max_len: usize,
+    pool: &PgPool,
+) -> Result<NotePreview, NotebookError> {
+    let note = commands::select_one(notename, pool).await?;
+
+    Ok(note.summary(max_len))
+}
@@ -0,0 +1,514 @@
+//! Storage of notes behind a trait.
+//!
+//! [`NoteStore`] contains basic operations with notes: adding, reading, updating, deleting,
+//! listing and searching. The notebook stores notes in PostgreSQL with [`PgStore`], but
+//! programs can implement [`NoteStore`] for their own storage (in memory, REST API, sled) and
+//! give it to [`Notebook::with_store`][crate::notebook::Notebook::with_store]. Then CLI and
+//! [`CommandRequest`][crate::notebook::CommandRequest]s that do these operations
+//! (`add-note`, `display-note`, `upd-note`, `del-note`, `search` and displaying all notes
+//! with `--full`) use the store, and other commands still use the database of notebook.
+//!
+//! [`MemoryStore`] keeps notes in memory, so programs can be tested without PostgreSQL,
+//! and `MySqlStore` (only with `mysql` feature) keeps them in MySQL or MariaDB.
+//!
+//! Methods return boxed futures, so store can be used as `dyn NoteStore`.
+//!
+//! Without `postgres` feature the module contains only [`NoteStore`], that is implemented
+//! by [`Client`][crate::client::Client].
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::store::*;
+//! # use lnotebook::NotebookError;
+//! async fn store_example(store: &dyn NoteStore) -> Result<(), NotebookError> {
+//!     store.add("shopping", "milk").await?;
+//!     store.update("shopping", "milk\nbread").await?;
+//!
+//!     assert_eq!(Some("milk\nbread".to_owned()), store.get("shopping").await?.note);
+//!     assert_eq!(1, store.search("bread").await?.len());
+//!
+//!     store.delete("shopping").await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+#[cfg(any(feature = "postgres", feature = "client"))]
+use crate::note::PREVIEW_LEN;
+use crate::note::{Note, NoteSummary};
+#[cfg(feature = "postgres")]
+use crate::{commands, config, errors, notename::NoteName, search};
+
+#[cfg(feature = "postgres")]
+use futures_util::future;
+use futures_util::future::BoxFuture;
+#[cfg(feature = "postgres")]
+use sqlx::PgPool;
+#[cfg(feature = "postgres")]
+use std::{collections::BTreeMap, sync::Mutex};
+
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "mysql")]
+pub use mysql::MySqlStore;
+
+/// Storage of notes.
+///
+/// Read more [here][crate::store].
+pub trait NoteStore: Send + Sync {
+    /// Adds note and returns it.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::InvalidNoteName`] error if notename is not valid
+    ///     * [`NotebookError::NoteTooLarge`] error if note is larger than
+    ///       [`max_note_size`][crate::config::max_note_size]
+    ///     * [`NotebookError::AlreadyTaken`] error if notename is already taken
+    fn add<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>>;
+
+    /// Returns note.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error with [`sqlx::Error::RowNotFound`]
+    ///       if note doesn't exist
+    fn get<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>>;
+
+    /// Replaces content of note and returns updated note.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::NoteTooLarge`] error if note is larger than
+    ///       [`max_note_size`][crate::config::max_note_size]
+    ///     * [`NotebookError::Sqlx`][NotebookError] error with [`sqlx::Error::RowNotFound`]
+    ///       if note doesn't exist
+    fn update<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>>;
+
+    /// Deletes note and returns it.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error with [`sqlx::Error::RowNotFound`]
+    ///       if note doesn't exist
+    fn delete<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>>;
+
+    /// Returns all notes ordered by ID.
+    fn list(&self) -> BoxFuture<'_, Result<Vec<Note>, NotebookError>>;
+
+    /// Returns [`NoteSummary`] of notes whose name or content contains `query`
+    /// (case-insensitive), ordered by name.
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<NoteSummary>, NotebookError>>;
+}
+
+/// Adds note into `store` under `notename`, or under `notename_2`, `notename_3` and so on
+/// if the name is already taken (read more in [`NoteName::with_suffix`]).
+///
+/// Names are tried by adding the note, so it's not added twice under the same name even if
+/// other program adds notes at the same time.
+/// ### Returns
+/// * Ok
+///     * [`Note`] that was added
+/// * Errors
+///     * Errors of [`NoteStore::add`] other than [`NotebookError::AlreadyTaken`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::notename::NoteName;
+/// # use lnotebook::store::*;
+/// # use lnotebook::NotebookError;
+/// async fn add_with_free_name_example(store: &dyn NoteStore) -> Result<(), NotebookError> {
+///     let notename = NoteName::from_body("Groceries\nmilk")?;
+///
+///     add_with_free_name(store, &notename, "Groceries\nmilk").await?;
+///     let note = add_with_free_name(store, &notename, "Groceries\nbread").await?;
+///
+///     assert_eq!("Groceries_2", note.note_name);
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "postgres")]
+pub async fn add_with_free_name<S: NoteStore + ?Sized>(
+    store: &S,
+    notename: &NoteName,
+    note: &str,
+) -> Result<Note, NotebookError> {
+    let mut candidate = notename.to_owned();
+
+    for n in 2.. {
+        match store.add(&candidate, note).await {
+            Err(NotebookError::AlreadyTaken { .. }) => candidate = notename.with_suffix(n),
+            res => return res,
+        }
+    }
+
+    unreachable!("all suffixes of `{notename}` are taken")
+}
+
+/// This is a `struct` that containing database where notes are stored in PostgreSQL.
+///
+/// Every method calls function from [`commands` module][crate::commands] with the same name
+/// (and [`search`][crate::search::search]), so it behaves the same way.
+#[cfg(feature = "postgres")]
+#[derive(Debug, Clone)]
+pub struct PgStore {
+    pool: PgPool,
+}
+
+#[cfg(feature = "postgres")]
+impl PgStore {
+    /// Creates store that keeps notes in database `pool`.
+    pub fn new(pool: PgPool) -> PgStore {
+        PgStore { pool }
+    }
+
+    /// Returns database of store.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl NoteStore for PgStore {
+    fn add<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(commands::add(notename, note, &self.pool))
+    }
+
+    fn get<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(commands::select_one(notename, &self.pool))
+    }
+
+    fn update<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(commands::upd(notename, note, &self.pool))
+    }
+
+    fn delete<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(commands::del(notename, &self.pool))
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<Note>, NotebookError>> {
+        Box::pin(commands::select_all(&self.pool))
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<NoteSummary>, NotebookError>> {
+        Box::pin(search::search(query, &self.pool))
+    }
+}
+
+/// This is a `struct` that containing notes kept in memory.
+///
+/// It behaves like [`PgStore`] (with the same errors), but notes are lost when it's dropped.
+/// Use it in tests of programs that use the notebook, so they don't need running PostgreSQL.
+/// ### Example
+/// ```
+/// # use lnotebook::store::*;
+/// # use lnotebook::NotebookError;
+/// # async fn memory_example() -> Result<(), NotebookError> {
+/// let store = MemoryStore::new();
+///
+/// store.add("shopping", "milk").await?;
+///
+/// assert_eq!(Some("milk".to_owned()), store.get("shopping").await?.note);
+/// assert!(store.add("shopping", "bread").await.is_err());
+/// # Ok(())
+/// # }
+/// # tokio::runtime::Runtime::new().unwrap().block_on(memory_example()).unwrap();
+/// ```
+#[cfg(feature = "postgres")]
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    notes: Mutex<MemoryNotes>,
+}
+
+#[cfg(feature = "postgres")]
+#[derive(Debug, Default)]
+struct MemoryNotes {
+    /// ID and content of notes by their names
+    by_name: BTreeMap<String, (i32, String)>,
+    last_id: i32,
+}
+
+#[cfg(feature = "postgres")]
+impl MemoryStore {
+    /// Creates empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn with_notes<T>(&self, f: impl FnOnce(&mut MemoryNotes) -> T) -> T {
+        // Notes are never left half-changed, so they can be used after panic in other thread
+        let mut notes = self.notes.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut notes)
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn memory_note(notename: &str, id: i32, note: &str) -> Note {
+    Note {
+        id,
+        note: Some(note.to_owned()),
+        note_name: notename.to_owned(),
+    }
+}
+
+/// Returns [`NoteSummary`] of note like the one made by the database in [`search`][crate::search::search].
+#[cfg(any(feature = "postgres", feature = "client"))]
+pub(crate) fn summary(id: i32, note_name: String, note: &str) -> NoteSummary {
+    let preview: String = note
+        .split('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(PREVIEW_LEN as usize)
+        .collect();
+
+    NoteSummary {
+        id,
+        note_name,
+        truncated: note.trim_end_matches('\n').chars().count() > preview.chars().count(),
+        preview,
+        lines: if note.is_empty() {
+            0
+        } else {
+            note.split('\n').count() as i64
+        },
+        updated_at: None,
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl NoteStore for MemoryStore {
+    fn add<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        let res = self.with_notes(|notes| {
+            NoteName::new(notename)?;
+            config::check_size(note.len())?;
+            if notes.by_name.contains_key(notename) {
+                return Err(NotebookError::AlreadyTaken {
+                    notename: notename.to_owned(),
+                });
+            }
+
+            notes.last_id += 1;
+            notes
+                .by_name
+                .insert(notename.to_owned(), (notes.last_id, note.to_owned()));
+
+            Ok(memory_note(notename, notes.last_id, note))
+        });
+
+        Box::pin(future::ready(res))
+    }
+
+    fn get<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        let res = self.with_notes(|notes| match notes.by_name.get(notename) {
+            Some((id, note)) => Ok(memory_note(notename, *id, note)),
+            None => Err(errors::not_found()),
+        });
+
+        Box::pin(future::ready(res))
+    }
+
+    fn update<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        if let Err(err) = config::check_size(note.len()) {
+            return Box::pin(future::ready(Err(err)));
+        }
+
+        let res = self.with_notes(|notes| match notes.by_name.get_mut(notename) {
+            Some((id, old)) => {
+                note.clone_into(old);
+                Ok(memory_note(notename, *id, note))
+            }
+            None => Err(errors::not_found()),
+        });
+
+        Box::pin(future::ready(res))
+    }
+
+    fn delete<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        let res = self.with_notes(|notes| match notes.by_name.remove(notename) {
+            Some((id, note)) => Ok(memory_note(notename, id, &note)),
+            None => Err(errors::not_found()),
+        });
+
+        Box::pin(future::ready(res))
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<Note>, NotebookError>> {
+        let mut list = self.with_notes(|notes| {
+            notes
+                .by_name
+                .iter()
+                .map(|(notename, (id, note))| memory_note(notename, *id, note))
+                .collect::<Vec<_>>()
+        });
+        list.sort_by_key(|note| note.id);
+
+        Box::pin(future::ready(Ok(list)))
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<NoteSummary>, NotebookError>> {
+        let query = query.to_lowercase();
+
+        // Notes are ordered by name, like in `search`
+        let found = self.with_notes(|notes| {
+            notes
+                .by_name
+                .iter()
+                .filter(|(notename, (_, note))| {
+                    notename.to_lowercase().contains(&query) || note.to_lowercase().contains(&query)
+                })
+                .map(|(notename, (id, note))| summary(*id, notename.to_owned(), note))
+                .collect()
+        });
+
+        Box::pin(future::ready(Ok(found)))
+    }
+}
+
+#[cfg(all(test, feature = "postgres"))]
+mod tests {
+    use super::*;
+    use crate::notebook::{CommandRequest, CommandResponse, Notebook};
+
+    #[tokio::test]
+    async fn add_get_update_delete() {
+        let store = MemoryStore::new();
+
+        assert_eq!(1, store.add("a", "first").await.unwrap().id);
+        assert_eq!(2, store.add("b", "").await.unwrap().id);
+
+        store.update("a", "changed").await.unwrap();
+        assert_eq!(
+            Some("changed".to_owned()),
+            store.get("a").await.unwrap().note
+        );
+
+        assert_eq!(1, store.delete("a").await.unwrap().id);
+        assert!(store.get("a").await.is_err());
+        assert!(store.update("a", "").await.is_err());
+        assert!(store.delete("a").await.is_err());
+
+        // IDs of deleted notes are not reused
+        assert_eq!(3, store.add("a", "").await.unwrap().id);
+    }
+
+    #[tokio::test]
+    async fn taken_notename_is_error() {
+        let store = MemoryStore::new();
+
+        store.add("a", "first").await.unwrap();
+
+        assert!(matches!(
+            store.add("a", "second").await,
+            Err(NotebookError::AlreadyTaken { notename }) if notename == "a"
+        ));
+        assert_eq!(Some("first".to_owned()), store.get("a").await.unwrap().note);
+    }
+
+    #[tokio::test]
+    async fn taken_free_name_gets_suffix() {
+        let store = MemoryStore::new();
+        let notename = NoteName::from_body("# Groceries\nmilk").unwrap();
+
+        store.add("Groceries_2", "").await.unwrap();
+
+        let first = add_with_free_name(&store, &notename, "milk").await.unwrap();
+        let second = add_with_free_name(&store, &notename, "bread")
+            .await
+            .unwrap();
+
+        assert_eq!("Groceries", first.note_name);
+        assert_eq!("Groceries_3", second.note_name);
+        assert_eq!(Some("bread".to_owned()), second.note);
+    }
+
+    #[tokio::test]
+    async fn list_is_ordered_by_id() {
+        let store = MemoryStore::new();
+
+        store.add("b", "").await.unwrap();
+        store.add("a", "").await.unwrap();
+
+        let names: Vec<_> = store
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|note| note.note_name)
+            .collect();
+
+        assert_eq!(vec!["b", "a"], names);
+    }
+
+    #[tokio::test]
+    async fn search_is_case_insensitive() {
+        let store = MemoryStore::new();
+
+        store.add("shopping", "Milk\nBread").await.unwrap();
+        store.add("Bread recipe", "").await.unwrap();
+        store.add("work", "report").await.unwrap();
+
+        let found = store.search("bread").await.unwrap();
+
+        assert_eq!(2, found.len());
+        assert_eq!("Bread recipe", found[0].note_name);
+        assert_eq!(0, found[0].lines);
+        assert_eq!("Milk", found[1].preview);
+        assert_eq!(2, found[1].lines);
+    }
+
+    #[tokio::test]
+    async fn notebook_uses_store() {
+        // Database is never connected, because requests are executed by the store
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let notebook = Notebook::new(pool).with_store(MemoryStore::new());
+
+        notebook
+            .execute(CommandRequest::AddNote {
+                notename: "a".to_owned(),
+                note: "text".to_owned(),
+            })
+            .await
+            .unwrap();
+
+        let res = notebook
+            .execute(CommandRequest::DisplayNote {
+                notename: "a".to_owned(),
+                lines: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(matches!(res, CommandResponse::Note(note) if note.note_name == "a"));
+    }
+}
@@ -0,0 +1,252 @@
+use super::{summary, NoteStore};
+use crate::commands::{Note, NoteSummary};
+use crate::config;
+use crate::errors::{self, NotebookError};
+use crate::notename::NoteName;
+
+use futures_util::future::BoxFuture;
+use sqlx::{self, MySqlConnection, MySqlPool};
+use tracing::{event, Level};
+
+/// Table of notes. Names are compared byte by byte, like in PostgreSQL,
+/// so `Shopping` and `shopping` are different notes.
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS notebook (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    note_name VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL UNIQUE,
+    note MEDIUMTEXT CHARACTER SET utf8mb4
+)";
+
+type Row = (i32, String, Option<String>);
+
+fn note((id, note_name, note): Row) -> Note {
+    Note {
+        id,
+        note,
+        note_name,
+    }
+}
+
+/// This is a `struct` that containing MySQL or MariaDB database where notes are stored
+/// (only with `mysql` feature).
+///
+/// Only notes are stored: history, attachments and other data of the notebook need PostgreSQL.
+/// Table of notes is created by [`MySqlStore::migrate`].
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::store::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::{MySqlPool, PgPool};
+/// # use lnotebook::notebook::Notebook;
+/// async fn mysql_example(pool: PgPool) -> Result<(), NotebookError> {
+///     let store = MySqlStore::new(MySqlPool::connect("mysql://user@localhost/notebook").await?);
+///     store.migrate().await?;
+///
+///     let notebook = Notebook::new(pool).with_store(store);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MySqlStore {
+    pool: MySqlPool,
+}
+
+impl MySqlStore {
+    /// Creates store that keeps notes in database `pool`.
+    pub fn new(pool: MySqlPool) -> MySqlStore {
+        MySqlStore { pool }
+    }
+
+    /// Returns database of store.
+    pub fn pool(&self) -> &MySqlPool {
+        &self.pool
+    }
+
+    /// Creates table of notes if it doesn't exist.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+    pub async fn migrate(&self) -> Result<(), NotebookError> {
+        sqlx::query(CREATE_TABLE).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn select_for_update(
+        notename: &str,
+        conn: &mut MySqlConnection,
+    ) -> Result<Note, NotebookError> {
+        let row: Row = sqlx::query_as(
+            "
+SELECT id, note_name, note
+FROM notebook
+WHERE note_name = ?
+FOR UPDATE
+            ",
+        )
+        .bind(notename)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(note(row))
+    }
+
+    async fn add_note(&self, notename: &str, text: &str) -> Result<Note, NotebookError> {
+        NoteName::new(notename)?;
+        config::check_size(text.len())?;
+
+        let res = sqlx::query("INSERT INTO notebook (note_name, note) VALUES (?, ?)")
+            .bind(notename)
+            .bind(text)
+            .execute(&self.pool)
+            .await;
+
+        match res {
+            Ok(done) => {
+                event!(
+                    Level::INFO,
+                    "Insert note with name `{}` into notebook",
+                    notename
+                );
+
+                Ok(Note {
+                    id: done.last_insert_id() as i32,
+                    note: Some(text.to_owned()),
+                    note_name: notename.to_owned(),
+                })
+            }
+            Err(err) if errors::is_unique_violation(&err) => Err(NotebookError::AlreadyTaken {
+                notename: notename.to_owned(),
+            }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn get_note(&self, notename: &str) -> Result<Note, NotebookError> {
+        let row: Row = sqlx::query_as(
+            "
+SELECT id, note_name, note
+FROM notebook
+WHERE note_name = ?
+            ",
+        )
+        .bind(notename)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(note(row))
+    }
+
+    async fn update_note(&self, notename: &str, text: &str) -> Result<Note, NotebookError> {
+        config::check_size(text.len())?;
+
+        // MySQL can't return updated rows, so note is locked to return the row that was updated
+        let mut tx = self.pool.begin().await?;
+
+        let mut updated = MySqlStore::select_for_update(notename, &mut tx).await?;
+
+        sqlx::query("UPDATE notebook SET note = ? WHERE id = ?")
+            .bind(text)
+            .bind(updated.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        event!(Level::INFO, "Update note `{}`", notename);
+
+        updated.note = Some(text.to_owned());
+        Ok(updated)
+    }
+
+    async fn delete_note(&self, notename: &str) -> Result<Note, NotebookError> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted = MySqlStore::select_for_update(notename, &mut tx).await?;
+
+        sqlx::query("DELETE FROM notebook WHERE id = ?")
+            .bind(deleted.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        event!(Level::INFO, "Delete note `{}`", notename);
+
+        Ok(deleted)
+    }
+
+    async fn list_notes(&self) -> Result<Vec<Note>, NotebookError> {
+        let rows: Vec<Row> = sqlx::query_as(
+            "
+SELECT id, note_name, note
+FROM notebook
+ORDER BY id
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(note).collect())
+    }
+
+    async fn search_notes(&self, query: &str) -> Result<Vec<NoteSummary>, NotebookError> {
+        let rows: Vec<Row> = sqlx::query_as(
+            "
+SELECT id, note_name, note
+FROM notebook
+WHERE LOCATE(LOWER(?), LOWER(note_name)) > 0
+    OR LOCATE(LOWER(?), LOWER(COALESCE(note, ''))) > 0
+ORDER BY note_name
+            ",
+        )
+        .bind(query)
+        .bind(query)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Previews are made here, so they are the same as in PostgreSQL
+        Ok(rows
+            .into_iter()
+            .map(|(id, note_name, note)| summary(id, note_name, note.as_deref().unwrap_or("")))
+            .collect())
+    }
+}
+
+impl NoteStore for MySqlStore {
+    fn add<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(self.add_note(notename, note))
+    }
+
+    fn get<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(self.get_note(notename))
+    }
+
+    fn update<'a>(
+        &'a self,
+        notename: &'a str,
+        note: &'a str,
+    ) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(self.update_note(notename, note))
+    }
+
+    fn delete<'a>(&'a self, notename: &'a str) -> BoxFuture<'a, Result<Note, NotebookError>> {
+        Box::pin(self.delete_note(notename))
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<Note>, NotebookError>> {
+        Box::pin(self.list_notes())
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<NoteSummary>, NotebookError>> {
+        Box::pin(self.search_notes(query))
+    }
+}
@@ -0,0 +1,317 @@
+//! Templates of notes.
+//!
+//! Template is a text that new notes can be created from with [`new_from_template`].
+//! Template can contain placeholders that are replaced when a note is created:
+//! * `{{date}}` - today's date (`2024-07-19`)
+//! * `{{time}}` - current time (`13:03`)
+//! * `{{name}}` - notename of the created note
+//!
+//! For example, template for meeting notes could look like:
+//! ```text
+//! # {{name}} ({{date}})
+//! Participants:
+//! Decisions:
+//! ```
+//!
+//! Template can also be made from existing note with [`template_from`]: chosen values
+//! in the note are replaced with placeholders.
+
+use crate::commands::{self, Note};
+use crate::errors::{self, NotebookError};
+use crate::sql::query_as;
+
+use chrono::Local;
+use sqlx::{self, PgPool};
+use tracing::{event, Level};
+
+/// This is a `struct` that containing information about template.
+pub struct NoteTemplate {
+    pub id: i32,
+    pub template_name: String,
+    pub template: String,
+}
+
+/// Replaces placeholders `{{key}}` (spaces inside braces are allowed) with values.
+///
+/// Unknown placeholders are left as is.
+/// ### Example
+/// ```
+/// # use lnotebook::templates::fill_placeholders;
+/// let text = fill_placeholders("{{ name }} at {{date}} {{unknown}}", &[("name", "standup"), ("date", "2024-07-19")]);
+///
+/// assert_eq!("standup at 2024-07-19 {{unknown}}", text);
+/// ```
+pub fn fill_placeholders(text: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let key = rest[start + 2..start + len].trim();
+
+        filled += &rest[..start];
+        match values.iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => filled += value,
+            None => filled += &rest[start..start + len + 2],
+        }
+
+        rest = &rest[start + len + 2..];
+    }
+    filled += rest;
+
+    filled
+}
+
+/// Replaces every value in `text` with placeholder `{{key}}`, the opposite of [`fill_placeholders`].
+///
+/// Longer values are replaced first, so value that contains other value is replaced as a whole.
+/// Empty values are ignored.
+/// ### Example
+/// ```
+/// # use lnotebook::templates::extract_placeholders;
+/// let text = extract_placeholders("# standup (2024-07-19)", &[("date", "2024-07-19"), ("name", "standup")]);
+///
+/// assert_eq!("# {{name}} ({{date}})", text);
+/// ```
+pub fn extract_placeholders(text: &str, values: &[(&str, &str)]) -> String {
+    let mut values: Vec<&(&str, &str)> = values.iter().filter(|(_, v)| !v.is_empty()).collect();
+    values.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+    // Parts of text that are not replaced yet, and placeholders between them
+    let mut parts: Vec<(String, bool)> = vec![(text.to_owned(), false)];
+    for (key, value) in values {
+        parts = parts
+            .into_iter()
+            .flat_map(|(part, placeholder)| {
+                if placeholder {
+                    return vec![(part, true)];
+                }
+
+                let mut split = Vec::new();
+                for (i, piece) in part.split(value).enumerate() {
+                    if i > 0 {
+                        split.push((format!("{{{{{key}}}}}"), true));
+                    }
+                    split.push((piece.to_owned(), false));
+                }
+                split
+            })
+            .collect();
+    }
+
+    parts.into_iter().map(|(part, _)| part).collect()
+}
+
+/// Adds and returns a new template.
+/// ### Returns
+/// * Ok
+///     * [`NoteTemplate`] that was added
+/// * Errors
+///     * [`NotebookError::AlreadyTaken`] error if a template with the same name already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::templates::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn templates_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add_template("meeting", "# {{name}} ({{date}})\nParticipants:\n", pool).await?;
+///
+///     let note = new_from_template("meeting", "standup", pool).await?;
+///
+///     assert!(note.note.unwrap().starts_with("# standup ("));
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn add_template(
+    template_name: &str,
+    template: &str,
+    pool: &PgPool,
+) -> Result<NoteTemplate, NotebookError> {
+    match query_as!(
+        NoteTemplate {
+            id: i32,
+            template_name: String,
+            template: String
+        },
+        "
+INSERT INTO templates (template_name, template)
+VALUES ( $1, $2 )
+RETURNING id, template_name, template
+        ",
+        template_name,
+        template
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(row) => {
+            event!(
+                Level::INFO,
+                "Add template `{}`:\n{}",
+                template_name,
+                template
+            );
+
+            Ok(row)
+        }
+        Err(err) => {
+            if errors::is_unique_violation(&err) {
+                return Err(NotebookError::AlreadyTaken {
+                    notename: template_name.to_owned(),
+                });
+            }
+            Err(err.into())
+        }
+    }
+}
+
+/// Returns the requested template.
+/// ### Returns
+/// * Ok
+///     * [`NoteTemplate`]
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_template(
+    template_name: &str,
+    pool: &PgPool,
+) -> Result<NoteTemplate, NotebookError> {
+    let row = query_as!(
+        NoteTemplate {
+            id: i32,
+            template_name: String,
+            template: String
+        },
+        "
+SELECT id, template_name, template
+FROM templates
+WHERE template_name = $1
+        ",
+        template_name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Returns all templates ordered by name.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn select_all_templates(pool: &PgPool) -> Result<Vec<NoteTemplate>, NotebookError> {
+    let rows = query_as!(
+        NoteTemplate {
+            id: i32,
+            template_name: String,
+            template: String
+        },
+        "
+SELECT id, template_name, template
+FROM templates
+ORDER BY template_name
+        "
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Deletes the requested template.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn del_template(template_name: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    query_as!(
+        { id: i32 },
+        "
+DELETE FROM templates
+WHERE template_name = $1
+RETURNING id
+        ",
+        template_name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(Level::INFO, "Delete template `{}`", template_name);
+
+    Ok(())
+}
+
+/// Creates a new note from template, filling placeholders in it.
+///
+/// Read about placeholders [here][crate::templates].
+/// ### Returns
+/// * Ok
+///     * [Note] that was added into notebook
+/// * Errors
+///     * [`NotebookError::InvalidNoteName`] error if `notename` is not valid
+///     * [`NotebookError::AlreadyTaken`] error if a note with the same name already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn new_from_template(
+    template_name: &str,
+    notename: &str,
+    pool: &PgPool,
+) -> Result<Note, NotebookError> {
+    let template = select_template(template_name, pool).await?;
+
+    let now = Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    let time = now.format("%H:%M").to_string();
+    let note = fill_placeholders(
+        &template.template,
+        &[("date", &date), ("time", &time), ("name", notename)],
+    );
+
+    commands::add(notename, &note, pool).await
+}
+
+/// Adds and returns a new template made from note `notename`.
+///
+/// Every value from `values` in the note is replaced with placeholder `{{key}}`
+/// (see [`extract_placeholders`]).
+/// ### Returns
+/// * Ok
+///     * [`NoteTemplate`] that was added
+/// * Errors
+///     * [`NotebookError::AlreadyTaken`] error if a template with the same name already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::templates::*;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn template_from_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     add("standup", "# standup (2024-07-19)\nParticipants:\n", pool).await?;
+///
+///     let template = template_from(
+///         "standup",
+///         "meeting",
+///         &[("name", "standup"), ("date", "2024-07-19")],
+///         pool,
+///     )
+///     .await?;
+///
+///     assert_eq!("# {{name}} ({{date}})\nParticipants:\n", template.template);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn template_from(
+    notename: &str,
+    template_name: &str,
+    values: &[(&str, &str)],
+    pool: &PgPool,
+) -> Result<NoteTemplate, NotebookError> {
+    let note = commands::select_one(notename, pool).await?;
+
+    let template = extract_placeholders(note.body(), values);
+
+    add_template(template_name, &template, pool).await
+}
@@ -0,0 +1,524 @@
+//! Terminal user interface of the notebook.
+//!
+//! Interface shows the list of notes on the left and content of the selected note on the right.
+//! ##### Keybindings
+//! * `↑`/`↓` or `k`/`j` - select note
+//! * `a` - add a new note: enter notename, then content of note
+//! * `e` - edit content of the selected note
+//! * `d` - delete the selected note (asks for confirmation)
+//! * `/` - search notes by name and content; `Esc` clears the search
+//! * `q` or `Esc` - quit
+//!
+//! With `watch` feature notes are reloaded when someone else changes them (read more in
+//! [`watch` module][crate::watch]), while you are not editing a note.
+//!
+//! When writing content of note, `Enter` starts a new line, `Ctrl+S` saves note
+//! and `Esc` cancels editing.
+//!
+//! ##### Conflicts
+//! If note was changed by someone else while you were editing it, note is not saved.
+//! Instead your version and the current version are shown side by side:
+//! * `m` - keep mine: save your version over the current one
+//! * `t` - keep theirs: discard your changes
+//! * `e` - merge by hand: edit note where lines that differ are marked as
+//!   `<<<<<<< mine`, `=======` and `>>>>>>> theirs`
+//! * `Esc` - return to editing
+//!
+//! Notes are read and changed by [`Notebook`] like commands of CLI, so only notes of the user
+//! of notebook are shown and changes are recorded in audit log (read more in
+//! [`audit` module][crate::audit]) and sent to webhooks and git repository of the notebook.
+
+use crate::commands::{self, Note, PREVIEW_LEN};
+use crate::diff;
+use crate::errors::NotebookError;
+use crate::notebook::{CommandRequest, CommandResponse, Notebook};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::time::Duration;
+
+enum Mode {
+    Browse,
+    Search,
+    NewName,
+    Edit {
+        notename: String,
+        is_new: bool,
+        /// Content of note when editing started
+        base: Option<String>,
+    },
+    Conflict {
+        notename: String,
+        /// Current content of note
+        theirs: String,
+    },
+    ConfirmDelete,
+}
+
+struct App {
+    notes: Vec<Note>,
+    list_state: ListState,
+    mode: Mode,
+    search: String,
+    input: String,
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    /// Notes that match the search query.
+    fn visible(&self) -> Vec<&Note> {
+        let query = self.search.to_lowercase();
+
+        self.notes
+            .iter()
+            .filter(|note| {
+                query.is_empty()
+                    || note.note_name.to_lowercase().contains(&query)
+                    || note
+                        .note
+                        .as_deref()
+                        .is_some_and(|n| n.to_lowercase().contains(&query))
+            })
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&Note> {
+        let visible = self.visible();
+
+        self.list_state
+            .selected()
+            .and_then(|i| visible.get(i).copied())
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.visible().len();
+        if len == 0 {
+            self.list_state.select(None);
+            return;
+        }
+
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let next = (current + offset).clamp(0, len as isize - 1);
+        self.list_state.select(Some(next as usize));
+    }
+
+    fn reset_selection(&mut self) {
+        self.list_state.select(Some(0));
+        self.select(0);
+    }
+
+    async fn reload(&mut self, notebook: &Notebook) -> Result<(), NotebookError> {
+        let request = CommandRequest::List {
+            full: true,
+            preview_len: PREVIEW_LEN,
+        };
+        self.notes = match notebook.execute(request).await? {
+            CommandResponse::Notes(notes) => notes,
+            _ => unreachable!("request returns notes"),
+        };
+        self.select(0);
+        if self.list_state.selected().is_none() {
+            self.reset_selection();
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs terminal user interface until user quits it.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Io`] error if terminal can't be used
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+///       if notes can't be loaded
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::NotebookError;
+/// # use lnotebook::notebook::Notebook;
+/// async fn tui_example(notebook: &Notebook) -> Result<(), NotebookError> {
+///     lnotebook::tui::run(notebook).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn run(notebook: &Notebook) -> Result<(), NotebookError> {
+    let mut app = App {
+        notes: Vec::new(),
+        list_state: ListState::default(),
+        mode: Mode::Browse,
+        search: String::new(),
+        input: String::new(),
+        status: String::new(),
+        quit: false,
+    };
+    app.reload(notebook).await?;
+
+    let mut terminal = ratatui::try_init()?;
+    let res = event_loop(&mut terminal, &mut app, notebook).await;
+    ratatui::try_restore()?;
+
+    res
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    notebook: &Notebook,
+) -> Result<(), NotebookError> {
+    // Interface works without changes if they can't be received
+    #[cfg(feature = "watch")]
+    let mut changes = crate::watch::watch(notebook.pool()).await.ok();
+    #[cfg(feature = "watch")]
+    let mut stale = false;
+
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        if !event::poll(Duration::from_millis(250))? {
+            #[cfg(feature = "watch")]
+            {
+                stale |= received_changes(&mut changes);
+                if stale && matches!(app.mode, Mode::Browse | Mode::Search) {
+                    stale = false;
+                    if let Err(err) = app.reload(notebook).await {
+                        app.status = err.to_string();
+                    }
+                }
+            }
+
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                handle_key(app, key, notebook).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if any change of notes was received, without waiting for changes.
+#[cfg(feature = "watch")]
+fn received_changes<S>(changes: &mut Option<S>) -> bool
+where
+    S: futures_util::Stream<Item = Result<crate::watch::NoteChange, NotebookError>> + Unpin,
+{
+    use futures_util::{FutureExt, StreamExt};
+
+    let mut received = false;
+    while let Some(stream) = changes {
+        match stream.next().now_or_never() {
+            Some(Some(Ok(_))) => received = true,
+            // Connection can't be opened again, so changes are not received anymore
+            Some(Some(Err(_)) | None) => *changes = None,
+            None => break,
+        }
+    }
+
+    received
+}
+
+async fn handle_key(app: &mut App, key: KeyEvent, notebook: &Notebook) {
+    match &app.mode {
+        Mode::Browse => match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => app.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => app.select(1),
+            KeyCode::Up | KeyCode::Char('k') => app.select(-1),
+            KeyCode::Char('/') => {
+                app.mode = Mode::Search;
+                app.status.clear();
+            }
+            KeyCode::Char('a') => {
+                app.input.clear();
+                app.mode = Mode::NewName;
+                app.status.clear();
+            }
+            KeyCode::Char('e') => {
+                if let Some(note) = app.selected() {
+                    let notename = note.note_name.to_owned();
+                    let base = note.note.to_owned();
+                    app.input = base.to_owned().unwrap_or_default();
+                    app.mode = Mode::Edit {
+                        notename,
+                        is_new: false,
+                        base,
+                    };
+                    app.status.clear();
+                }
+            }
+            KeyCode::Char('d') if app.selected().is_some() => app.mode = Mode::ConfirmDelete,
+            _ => {}
+        },
+
+        Mode::Search => match key.code {
+            KeyCode::Esc => {
+                app.search.clear();
+                app.mode = Mode::Browse;
+                app.reset_selection();
+            }
+            KeyCode::Enter => app.mode = Mode::Browse,
+            KeyCode::Backspace => {
+                app.search.pop();
+                app.reset_selection();
+            }
+            KeyCode::Char(c) => {
+                app.search.push(c);
+                app.reset_selection();
+            }
+            _ => {}
+        },
+
+        Mode::NewName => match key.code {
+            KeyCode::Esc => app.mode = Mode::Browse,
+            KeyCode::Enter if !app.input.trim().is_empty() => {
+                let notename = app.input.trim().to_owned();
+                app.input.clear();
+                app.mode = Mode::Edit {
+                    notename,
+                    is_new: true,
+                    base: None,
+                };
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Char(c) => app.input.push(c),
+            _ => {}
+        },
+
+        Mode::Edit {
+            notename,
+            is_new,
+            base,
+        } => match key.code {
+            KeyCode::Esc => {
+                app.mode = Mode::Browse;
+                app.status = "Editing cancelled".to_owned();
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let notename = notename.to_owned();
+                let request = if *is_new {
+                    CommandRequest::AddNote {
+                        notename: notename.to_owned(),
+                        note: app.input.to_owned(),
+                    }
+                } else {
+                    CommandRequest::UpdIfUnchanged {
+                        notename: notename.to_owned(),
+                        base_hash: commands::hash_content(base.as_deref().unwrap_or_default()),
+                        note: app.input.to_owned(),
+                    }
+                };
+                let res = notebook.execute(request).await;
+
+                match res {
+                    Ok(_) => app.status = format!("Saved `{notename}`"),
+                    Err(NotebookError::Conflict { .. }) => {
+                        // Note was changed by someone else, so user decides what to keep
+                        show_conflict(app, notename, notebook).await;
+                        return;
+                    }
+                    Err(err) => app.status = err.to_string(),
+                }
+
+                app.mode = Mode::Browse;
+                if let Err(err) = app.reload(notebook).await {
+                    app.status = err.to_string();
+                }
+            }
+            KeyCode::Enter => app.input.push('\n'),
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Tab => app.input.push('\t'),
+            KeyCode::Char(c) => app.input.push(c),
+            _ => {}
+        },
+
+        Mode::Conflict { notename, theirs } => {
+            let notename = notename.to_owned();
+
+            match key.code {
+                KeyCode::Char('m') => {
+                    let request = CommandRequest::UpdIfUnchanged {
+                        notename: notename.to_owned(),
+                        base_hash: commands::hash_content(theirs),
+                        note: app.input.to_owned(),
+                    };
+                    app.status = match notebook.execute(request).await {
+                        Ok(_) => format!("Saved `{notename}`, their changes are overwritten"),
+                        Err(NotebookError::Conflict { .. }) => {
+                            // Note was changed again while user was deciding
+                            show_conflict(app, notename, notebook).await;
+                            return;
+                        }
+                        Err(err) => err.to_string(),
+                    };
+                }
+                KeyCode::Char('t') => {
+                    app.status = format!("Your changes of `{notename}` are discarded");
+                }
+                KeyCode::Char('e') => {
+                    app.input = conflict_markers(&app.input, theirs);
+                    app.mode = Mode::Edit {
+                        notename,
+                        is_new: false,
+                        base: Some(theirs.to_owned()),
+                    };
+                    return;
+                }
+                KeyCode::Esc => {
+                    // Saving again will show conflict again, unless note is merged by hand
+                    app.mode = Mode::Edit {
+                        notename,
+                        is_new: false,
+                        base: Some(theirs.to_owned()),
+                    };
+                    return;
+                }
+                _ => return,
+            }
+
+            app.mode = Mode::Browse;
+            if let Err(err) = app.reload(notebook).await {
+                app.status = err.to_string();
+            }
+        }
+
+        Mode::ConfirmDelete => {
+            if let KeyCode::Char('y') = key.code {
+                if let Some(notename) = app.selected().map(|n| n.note_name.to_owned()) {
+                    let request = CommandRequest::DelNote {
+                        notename: notename.to_owned(),
+                    };
+                    app.status = match notebook.execute(request).await {
+                        Ok(_) => format!("Deleted `{notename}`"),
+                        Err(err) => err.to_string(),
+                    };
+                    if let Err(err) = app.reload(notebook).await {
+                        app.status = err.to_string();
+                    }
+                }
+            }
+            app.mode = Mode::Browse;
+        }
+    }
+}
+
+/// Shows current content of note `notename` next to content edited by user.
+async fn show_conflict(app: &mut App, notename: String, notebook: &Notebook) {
+    let request = CommandRequest::DisplayNote {
+        notename: notename.to_owned(),
+        lines: None,
+    };
+    match notebook.execute(request).await {
+        Ok(CommandResponse::Note(note)) => {
+            app.mode = Mode::Conflict {
+                notename,
+                theirs: note.note.unwrap_or_default(),
+            };
+            app.status.clear();
+        }
+        Ok(_) => unreachable!("request returns note"),
+        Err(err) => app.status = err.to_string(),
+    }
+}
+
+/// Returns `mine` and `theirs` merged with conflict markers around lines that differ.
+fn conflict_markers(mine: &str, theirs: &str) -> String {
+    let mine: Vec<&str> = mine.lines().collect();
+    let theirs: Vec<&str> = theirs.lines().collect();
+
+    let (prefix, suffix) = diff::common_lines(&mine, &theirs);
+
+    let mut lines: Vec<&str> = mine[..prefix].to_vec();
+    lines.push("<<<<<<< mine");
+    lines.extend(&mine[prefix..mine.len() - suffix]);
+    lines.push("=======");
+    lines.extend(&theirs[prefix..theirs.len() - suffix]);
+    lines.push(">>>>>>> theirs");
+    lines.extend(&mine[mine.len() - suffix..]);
+
+    lines.join("\n") + "\n"
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(frame.area());
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(rows[0]);
+
+    let items: Vec<ListItem> = app
+        .visible()
+        .iter()
+        .map(|note| ListItem::new(note.note_name.to_owned()))
+        .collect();
+    let list_title = if app.search.is_empty() {
+        "Notes".to_owned()
+    } else {
+        format!("Notes /{}", app.search)
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(list_title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, panes[0], &mut app.list_state);
+
+    if let Mode::Conflict { theirs, .. } = &app.mode {
+        let sides = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(panes[1]);
+
+        for (side, title, content) in [(sides[0], "Mine", &app.input), (sides[1], "Theirs", theirs)]
+        {
+            let content = Paragraph::new(content.to_owned())
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .wrap(Wrap { trim: false });
+            frame.render_widget(content, side);
+        }
+    } else {
+        let (title, content) = match &app.mode {
+            Mode::Edit { notename, .. } => (format!("Editing `{notename}`"), app.input.to_owned()),
+            Mode::NewName => ("New note".to_owned(), String::new()),
+            _ => match app.selected() {
+                Some(note) => (
+                    note.note_name.to_owned(),
+                    note.note.to_owned().unwrap_or_default(),
+                ),
+                None => (String::new(), String::new()),
+            },
+        };
+        let content = Paragraph::new(content)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(content, panes[1]);
+    }
+
+    let status = match &app.mode {
+        Mode::Browse if !app.status.is_empty() => app.status.to_owned(),
+        Mode::Browse => "q quit | a add | e edit | d delete | / search".to_owned(),
+        Mode::Search => format!("Search: {}", app.search),
+        Mode::NewName => format!("Notename: {}", app.input),
+        Mode::Edit { .. } if !app.status.is_empty() => {
+            format!("{} | Ctrl+S save | Esc cancel", app.status)
+        }
+        Mode::Edit { .. } => "Ctrl+S save | Esc cancel".to_owned(),
+        Mode::Conflict { notename, .. } => format!(
+            "`{notename}` was changed by someone else: m keep mine | t keep theirs | e merge by hand | Esc back"
+        ),
+        Mode::ConfirmDelete => "Delete the selected note? (y/n)".to_owned(),
+    };
+    frame.render_widget(Line::from(status), rows[1]);
+}
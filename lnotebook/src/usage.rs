@@ -0,0 +1,212 @@
+//! Usage statistics of CLI commands.
+//!
+//! If `LNOTEBOOK_USAGE` is enabled (read about configuration in [`config` module][crate::config]),
+//! CLI records how many times every command was executed and how long it took.
+//! Statistics are kept only in file [`USAGE_FILE`] in the config directory and are never sent
+//! anywhere. They can be displayed with `stats --usage`, and shared by you if you want.
+//!
+//! Every line of the file contains name of command, number of executions and their total
+//! duration in microseconds, separated by tabs.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::usage::*;
+//! # use lnotebook::NotebookError;
+//! # use std::{path::Path, time::Duration};
+//! fn usage_example() -> Result<(), NotebookError> {
+//!     let path = Path::new("usage.tsv");
+//!
+//!     let mut usage = UsageStats::load(path)?;
+//!     usage.record("add-note", Duration::from_millis(12));
+//!     usage.save(path)?;
+//!
+//!     println!("{usage}");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::config::Config;
+use crate::errors::NotebookError;
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{event, Level};
+
+/// Name of file with usage statistics in the config directory.
+pub const USAGE_FILE: &str = "usage.tsv";
+
+/// This is a `struct` that containing usage statistics of one command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandUsage {
+    /// Name of command, as returned by [`CommandRequest::name`][crate::notebook::CommandRequest::name]
+    pub name: String,
+    /// Number of executions
+    pub count: u64,
+    /// Total duration of all executions
+    pub total: Duration,
+}
+
+impl CommandUsage {
+    /// Returns average duration of execution.
+    pub fn average(&self) -> Duration {
+        self.total
+            .checked_div(self.count.try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default()
+    }
+}
+
+/// This is a `struct` that containing usage statistics of all commands.
+///
+/// It implements [`fmt::Display`], which renders statistics as a table
+/// with the most used commands first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageStats {
+    /// Statistics of commands ordered by name
+    pub commands: Vec<CommandUsage>,
+}
+
+impl UsageStats {
+    /// Reads statistics from file `path`. Lines that can't be read are skipped.
+    /// ### Returns
+    /// * Ok
+    ///     * [`UsageStats`], empty if file doesn't exist
+    /// * Errors
+    ///     * [`NotebookError::Io`] error if file can't be read
+    pub fn load(path: &Path) -> Result<UsageStats, NotebookError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(UsageStats::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut usage = UsageStats::default();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+
+            if let (Some(name), Some(Ok(count)), Some(Ok(micros)), None) = (
+                fields.next(),
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+                fields.next(),
+            ) {
+                usage.add(name, count, Duration::from_micros(micros));
+            }
+        }
+
+        Ok(usage)
+    }
+
+    /// Writes statistics into file `path`, creating its directory if needed.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Io`] error if file can't be written
+    pub fn save(&self, path: &Path) -> Result<(), NotebookError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let text: String = self
+            .commands
+            .iter()
+            .map(|command| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    command.name,
+                    command.count,
+                    command.total.as_micros()
+                )
+            })
+            .collect();
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+
+    /// Records one execution of command `name` that took `duration`.
+    pub fn record(&mut self, name: &str, duration: Duration) {
+        self.add(name, 1, duration);
+    }
+
+    fn add(&mut self, name: &str, count: u64, total: Duration) {
+        match self
+            .commands
+            .binary_search_by(|command| command.name.as_str().cmp(name))
+        {
+            Ok(i) => {
+                self.commands[i].count += count;
+                self.commands[i].total += total;
+            }
+            Err(i) => self.commands.insert(
+                i,
+                CommandUsage {
+                    name: name.to_owned(),
+                    count,
+                    total,
+                },
+            ),
+        }
+    }
+}
+
+impl fmt::Display for UsageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut commands: Vec<&CommandUsage> = self.commands.iter().collect();
+        commands.sort_by_key(|command| std::cmp::Reverse(command.count));
+
+        let name_width = commands
+            .iter()
+            .map(|command| command.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Command".len());
+
+        write!(
+            f,
+            "{:<name_width$}  {:>8}  {:>10}  {:>10}",
+            "Command", "Count", "Total", "Average"
+        )?;
+        for command in commands {
+            write!(
+                f,
+                "\n{:<name_width$}  {:>8}  {:>8.1}ms  {:>8.1}ms",
+                command.name,
+                command.count,
+                command.total.as_secs_f64() * 1000.0,
+                command.average().as_secs_f64() * 1000.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns path of file with usage statistics, `None` if config directory is unknown.
+pub fn usage_path(config: &Config) -> Option<PathBuf> {
+    config.config_dir.as_ref().map(|dir| dir.join(USAGE_FILE))
+}
+
+/// Records one execution of command `name` if usage statistics are enabled in `config`.
+///
+/// Statistics are not important, so errors are only logged.
+pub fn record_usage(config: &Config, name: &str, duration: Duration) {
+    let Some(path) = usage_path(config).filter(|_| config.usage) else {
+        return;
+    };
+
+    let res = UsageStats::load(&path).and_then(|mut usage| {
+        usage.record(name, duration);
+        usage.save(&path)
+    });
+
+    if let Err(err) = res {
+        event!(
+            Level::WARN,
+            "Can't record usage into `{}`: {}",
+            path.display(),
+            err
+        );
+    }
+}
@@ -0,0 +1,849 @@
+//! Users and owners of notes for notebooks shared by several people.
+//!
+//! Users are created with [`add_user`] and stored in table `users` of the notebook database.
+//! Functions run with [`as_user`] (like all requests of [`Notebook`] created with
+//! [`Notebook::as_user`]) act as the user:
+//! * notes they add are owned by the user;
+//! * functions that list, search, count or export notes (like `list`, `search`, `grep`, `due`,
+//!   `stats`, `random-note`, `backlinks` and `export`) return only notes of the user, notes shared
+//!   with the user and notes without owner;
+//! * functions that change many notes at once (like `del-all`, `sed` and `rename-matching`)
+//!   skip notes the user can't change;
+//! * functions that read or change content of one note (like [`select_one`][crate::commands::select_one],
+//!   [`upd`][crate::commands::upd] and [`del`][crate::commands::del]) return
+//!   [`NotebookError::NotOwner`] for notes of other users, unless the note is shared with the user
+//!   by [`share_note`]. Notes shared read-only can be read, but changing them returns
+//!   [`NotebookError::ReadOnly`].
+//!
+//! Notes without owner (like all notes added before users were created, or by notebook that
+//! doesn't act as any user) are shared by everyone. Admins see and change notes of all users and
+//! are the only ones who can create and delete users and change owners of notes ([`set_owner`]),
+//! others get [`NotebookError::NotAdmin`]. Only the owner of note (and admins) can share it,
+//! and only admins can [`dump`][crate::dump::dump] and [`restore`][crate::dump::restore]
+//! the whole notebook.
+//! Code that doesn't act as any user can do everything, so the first admin is created without
+//! `--as-user`. Notes of deleted user become shared.
+//!
+//! CLI acts as user selected with `--as-user <name>`, manages users with `add-user`, `del-user`,
+//! `list-users` and `chown`, and shares notes with `share-note`, `unshare-note` and
+//! `shared-with-me`. `watch` and `remind-daemon` serve notes of all users, so they refuse
+//! to run with `--as-user`. Users are not authenticated: they keep members of household from seeing
+//! and changing notes of each other by mistake, but anyone who can connect to the database can
+//! act as any user. Lists of notes are filtered in SQL by function `can_access_note`
+//! of the notebook database, so notes of other users are never sent to the notebook.
+//!
+//! [`Notebook`]: crate::notebook::Notebook
+//! [`Notebook::as_user`]: crate::notebook::Notebook::as_user
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::notebook::*;
+//! # use lnotebook::users::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! async fn users_example(pool: PgPool) -> Result<(), NotebookError> {
+//!     add_user("alice", false, &pool).await?;
+//!
+//!     let notebook = Notebook::new(pool).as_user("alice");
+//!     notebook
+//!         .execute(CommandRequest::AddNote {
+//!             notename: "diary".to_owned(),
+//!             note: "Dear diary".to_owned(),
+//!         })
+//!         .await?;
+//!
+//!     // `diary` is not listed for other users
+//!     assert_eq!(Some("alice".to_owned()), owner("diary", notebook.pool()).await?);
+//!
+//!     // But `bob` can read it
+//!     as_user("alice", share_note("diary", "bob", true, notebook.pool())).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+use crate::sql::{query, query_as, query_scalar};
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{self, PgExecutor, PgPool};
+use std::{fmt, future::Future};
+use tracing::{event, Level};
+
+tokio::task_local! {
+    static ACTING_USER: String;
+}
+
+/// This is a `struct` that containing user of the notebook.
+///
+/// It implements [`fmt::Display`], which renders it as `name  created_at`, with `(admin)`
+/// after the name of admins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    /// Admin sees and changes notes of all users and manages users
+    pub admin: bool,
+    pub created_at: DateTime<Local>,
+}
+
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.admin {
+            write!(f, " (admin)")?;
+        }
+
+        write!(f, "  {}", self.created_at.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+/// This is a `struct` that containing note shared with user.
+///
+/// It implements [`fmt::Display`], which renders it as `name  owner`, with `(read-only)`
+/// after notes shared read-only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedNote {
+    pub note_name: String,
+    /// Owner that shared the note, `None` if the note became shared by everyone
+    pub owner: Option<String>,
+    /// Note can be read, but not changed
+    pub read_only: bool,
+    pub shared_at: DateTime<Local>,
+}
+
+impl fmt::Display for SharedNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}",
+            self.note_name,
+            self.owner.as_deref().unwrap_or("-")
+        )?;
+        if self.read_only {
+            write!(f, "  (read-only)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether function reads or changes note, see [`check_access`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// Runs `f` acting as user `user`, read more [here][crate::users].
+/// ### Example
+/// ```rust,no_run
+/// # use lnotebook::commands::*;
+/// # use lnotebook::users::as_user;
+/// # use lnotebook::NotebookError;
+/// # use sqlx::PgPool;
+/// async fn as_user_example(pool: &PgPool) -> Result<(), NotebookError> {
+///     // `NotebookError::NotOwner` if `diary` is owned by other user and isn't shared with `bob`
+///     let note = as_user("bob", select_one("diary", pool)).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn as_user<F: Future>(user: &str, f: F) -> F::Output {
+    ACTING_USER.scope(user.to_owned(), f).await
+}
+
+/// Returns user that code acts as with [`as_user`], `None` if it doesn't act as any user.
+pub fn acting_user() -> Option<String> {
+    ACTING_USER.try_with(String::clone).ok()
+}
+
+/// Returns [`NotebookError::NotOwner`] error if note is owned by other user than the acting one
+/// and isn't shared with it, or [`NotebookError::ReadOnly`] error if it's shared read-only
+/// and `access` is [`Access::Write`]. Note that doesn't exist is not checked.
+pub(crate) async fn check_access<'c>(
+    notename: &str,
+    access: Access,
+    conn: impl PgExecutor<'c>,
+) -> Result<(), NotebookError> {
+    let Some(user) = acting_user() else {
+        return Ok(());
+    };
+
+    let row = query_as!(
+        { owner: String, read_only: Option<bool>, admin: bool },
+        r#"
+SELECT
+    notebook.owner AS "owner!",
+    note_shares.read_only AS "read_only?",
+    COALESCE((SELECT admin FROM users WHERE name = $2), false) AS "admin!"
+FROM notebook
+LEFT JOIN note_shares ON note_shares.note_id = notebook.id AND note_shares.user_name = $2
+WHERE note_name = $1 AND owner <> $2
+        "#,
+        notename,
+        user
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    match row {
+        Some(row) if !row.admin => match (row.read_only, access) {
+            (None, _) => Err(NotebookError::NotOwner {
+                notename: notename.to_owned(),
+                owner: row.owner,
+            }),
+            (Some(true), Access::Write) => Err(NotebookError::ReadOnly {
+                notename: notename.to_owned(),
+            }),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Returns `false` instead of [`NotebookError::NotOwner`] and [`NotebookError::ReadOnly`] errors
+/// of [`check_access`], for commands that skip notes the acting user can't access.
+pub(crate) async fn has_access<'c>(
+    notename: &str,
+    access: Access,
+    conn: impl PgExecutor<'c>,
+) -> Result<bool, NotebookError> {
+    match check_access(notename, access, conn).await {
+        Ok(()) => Ok(true),
+        Err(NotebookError::NotOwner { .. } | NotebookError::ReadOnly { .. }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns [`NotebookError::NotAdmin`] error if the acting user is not an admin.
+pub(crate) async fn check_admin(pool: &PgPool) -> Result<(), NotebookError> {
+    match acting_user() {
+        Some(name) if !user(&name, pool).await?.admin => {
+            Err(NotebookError::NotAdmin { user: name })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Creates user `name`, admin if `admin` is `true`.
+/// ### Returns
+/// * Ok
+///     * Created user
+/// * Errors
+///     * [`NotebookError::NotAdmin`] error if the acting user is not an admin
+///     * [`NotebookError::UserTaken`] error if user with the same name already exists
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn add_user(name: &str, admin: bool, pool: &PgPool) -> Result<User, NotebookError> {
+    check_admin(pool).await?;
+
+    let user = query_as!(
+        { name: String, admin: bool, created_at: DateTime<Utc> },
+        "
+INSERT INTO users (name, admin)
+VALUES ($1, $2)
+ON CONFLICT (name) DO NOTHING
+RETURNING name, admin, created_at
+        ",
+        name.trim(),
+        admin
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| NotebookError::UserTaken {
+        user: name.trim().to_owned(),
+    })?;
+
+    event!(Level::INFO, "Add user `{}`", user.name);
+
+    Ok(User {
+        name: user.name,
+        admin: user.admin,
+        created_at: user.created_at.with_timezone(&Local),
+    })
+}
+
+/// Deletes user `name`, its notes become shared.
+/// ### Returns
+/// * Ok
+///     * Deleted user
+/// * Errors
+///     * [`NotebookError::NotAdmin`] error if the acting user is not an admin
+///     * [`NotebookError::UnknownUser`] error if user doesn't exist
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn del_user(name: &str, pool: &PgPool) -> Result<User, NotebookError> {
+    check_admin(pool).await?;
+
+    let user = query_as!(
+        { name: String, admin: bool, created_at: DateTime<Utc> },
+        "
+DELETE FROM users
+WHERE name = $1
+RETURNING name, admin, created_at
+        ",
+        name
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| NotebookError::UnknownUser {
+        user: name.to_owned(),
+    })?;
+
+    event!(Level::INFO, "Delete user `{}`", user.name);
+
+    Ok(User {
+        name: user.name,
+        admin: user.admin,
+        created_at: user.created_at.with_timezone(&Local),
+    })
+}
+
+/// Returns user `name`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::UnknownUser`] error if user doesn't exist
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn user(name: &str, pool: &PgPool) -> Result<User, NotebookError> {
+    let user = query_as!(
+        { name: String, admin: bool, created_at: DateTime<Utc> },
+        "
+SELECT name, admin, created_at
+FROM users
+WHERE name = $1
+        ",
+        name
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| NotebookError::UnknownUser {
+        user: name.to_owned(),
+    })?;
+
+    Ok(User {
+        name: user.name,
+        admin: user.admin,
+        created_at: user.created_at.with_timezone(&Local),
+    })
+}
+
+/// Returns all users ordered by name.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn users(pool: &PgPool) -> Result<Vec<User>, NotebookError> {
+    let rows = query_as!(
+        { name: String, admin: bool, created_at: DateTime<Utc> },
+        "
+SELECT name, admin, created_at
+FROM users
+ORDER BY name
+        "
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| User {
+            name: row.name,
+            admin: row.admin,
+            created_at: row.created_at.with_timezone(&Local),
+        })
+        .collect())
+}
+
+/// Returns owner of note `notename`, `None` if note is shared or doesn't exist.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn owner(notename: &str, pool: &PgPool) -> Result<Option<String>, NotebookError> {
+    let owner = query_scalar!(
+        Option<String>,
+        "
+SELECT owner
+FROM notebook
+WHERE note_name = $1
+        ",
+        notename
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(owner.flatten())
+}
+
+/// Makes user `owner` owner of note `notename`, or makes note shared if `owner` is `None`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotAdmin`] error if the acting user is not an admin
+///     * [`NotebookError::UnknownUser`] error if user `owner` doesn't exist
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn set_owner(
+    notename: &str,
+    owner: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    check_admin(pool).await?;
+    if let Some(owner) = owner {
+        user(owner, pool).await?;
+    }
+
+    query_as!(
+        { id: i32 },
+        "
+UPDATE notebook
+SET owner = $2
+WHERE note_name = $1
+RETURNING id
+        ",
+        notename,
+        owner
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Set owner of `{}` to `{}`",
+        notename,
+        owner.unwrap_or("nobody")
+    );
+
+    Ok(())
+}
+
+/// Shares note `notename` with user `user_name`, read-only if `read_only` is `true`.
+/// Note that is already shared with the user is shared again with new `read_only`.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if the acting user is not an owner of note
+///       or an admin
+///     * [`NotebookError::UnknownUser`] error if user `user_name` doesn't exist
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`],
+///       [`sqlx::Error::RowNotFound`] if note doesn't exist
+pub async fn share_note(
+    notename: &str,
+    user_name: &str,
+    read_only: bool,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    check_owner(notename, pool).await?;
+    user(user_name, pool).await?;
+
+    query_as!(
+        { note_id: i32 },
+        "
+INSERT INTO note_shares (note_id, user_name, read_only)
+SELECT id, $2, $3
+FROM notebook
+WHERE note_name = $1
+ON CONFLICT (note_id, user_name) DO UPDATE
+SET read_only = EXCLUDED.read_only
+RETURNING note_id
+        ",
+        notename,
+        user_name,
+        read_only
+    )
+    .fetch_one(pool)
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Share `{}` with `{}`{}",
+        notename,
+        user_name,
+        if read_only { " (read-only)" } else { "" }
+    );
+
+    Ok(())
+}
+
+/// Stops sharing note `notename` with user `user_name`. Note that isn't shared with the user
+/// is left as it is.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::NotOwner`] error if the acting user is not an owner of note
+///       or an admin
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn unshare_note(
+    notename: &str,
+    user_name: &str,
+    pool: &PgPool,
+) -> Result<(), NotebookError> {
+    check_owner(notename, pool).await?;
+
+    query!(
+        "
+DELETE FROM note_shares
+USING notebook
+WHERE note_shares.note_id = notebook.id AND note_name = $1 AND user_name = $2
+        ",
+        notename,
+        user_name
+    )
+    .execute(pool)
+    .await?;
+
+    event!(
+        Level::INFO,
+        "Stop sharing `{}` with `{}`",
+        notename,
+        user_name
+    );
+
+    Ok(())
+}
+
+/// Returns notes shared with user `user` ordered by name.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+pub async fn shared_with(user: &str, pool: &PgPool) -> Result<Vec<SharedNote>, NotebookError> {
+    let rows = query_as!(
+        { note_name: String, owner: Option<String>, read_only: bool, shared_at: DateTime<Utc> },
+        "
+SELECT note_name, owner, read_only, shared_at
+FROM note_shares
+JOIN notebook ON notebook.id = note_shares.note_id
+WHERE user_name = $1
+ORDER BY note_name
+        ",
+        user
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SharedNote {
+            note_name: row.note_name,
+            owner: row.owner,
+            read_only: row.read_only,
+            shared_at: row.shared_at.with_timezone(&Local),
+        })
+        .collect())
+}
+
+/// Returns [`NotebookError::NotOwner`] error if note is owned by other user than the acting one
+/// and the acting user is not an admin.
+async fn check_owner(notename: &str, pool: &PgPool) -> Result<(), NotebookError> {
+    let Some(name) = acting_user() else {
+        return Ok(());
+    };
+
+    match owner(notename, pool).await? {
+        Some(owner) if owner != name && !user(&name, pool).await?.admin => {
+            Err(NotebookError::NotOwner {
+                notename: notename.to_owned(),
+                owner,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{attachments, commands, dedup, due, history};
+
+    /// Adds users `alice` and `bob`, and note `diary` owned by `alice` with two revisions.
+    async fn diary_of_alice(pool: &PgPool) -> i32 {
+        add_user("alice", false, pool).await.unwrap();
+        add_user("bob", false, pool).await.unwrap();
+
+        as_user("alice", commands::add("diary", "Dear diary", pool))
+            .await
+            .unwrap();
+        commands::append("diary", "\nToday", pool).await.unwrap();
+
+        history::history("diary", pool).await.unwrap()[0].id
+    }
+
+    fn attachment_file(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("lnotebook-{}-{name}", std::process::id()));
+        std::fs::write(&path, "attached").unwrap();
+
+        path
+    }
+
+    fn assert_not_owner<T>(res: Result<T, NotebookError>) {
+        match res {
+            Err(NotebookError::NotOwner { owner, .. }) => assert_eq!("alice", owner),
+            Err(err) => panic!("unexpected error: {err:?}"),
+            Ok(_) => panic!("note of other user is accessed"),
+        }
+    }
+
+    fn assert_read_only<T>(res: Result<T, NotebookError>) {
+        match res {
+            Err(NotebookError::ReadOnly { .. }) => {}
+            Err(err) => panic!("unexpected error: {err:?}"),
+            Ok(_) => panic!("read-only note is changed"),
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn note_of_other_user_is_not_read(pool: PgPool) {
+        let revision_id = diary_of_alice(&pool).await;
+        let path = attachment_file("not-read");
+        attachments::attach("diary", &path, &pool).await.unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        as_user("bob", async {
+            assert_not_owner(history::history("diary", &pool).await);
+            assert_not_owner(history::select_revision(revision_id, &pool).await);
+            assert_not_owner(history::select_note_revision("diary", revision_id, &pool).await);
+            assert_not_owner(attachments::attachments("diary", &pool).await);
+            assert_not_owner(
+                attachments::save_attachment("diary", &file_name, &std::env::temp_dir(), &pool)
+                    .await,
+            );
+            #[cfg(feature = "metadata")]
+            assert_not_owner(crate::metadata::get_meta("diary", "mood", &pool).await);
+        })
+        .await;
+        std::fs::remove_file(&path).unwrap();
+
+        // The owner still reads it
+        as_user("alice", async {
+            assert_eq!(2, history::history("diary", &pool).await.unwrap().len());
+            assert_eq!(
+                "Dear diary",
+                history::select_revision(revision_id, &pool).await.unwrap()
+            );
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn note_of_other_user_is_not_changed(pool: PgPool) {
+        diary_of_alice(&pool).await;
+        let path = attachment_file("not-changed");
+        let due_at = due::parse_due("2030-01-01").unwrap();
+
+        as_user("bob", async {
+            assert_not_owner(attachments::attach("diary", &path, &pool).await);
+            assert_not_owner(due::set_due("diary", due_at, &pool).await);
+            assert_not_owner(due::clear_due("diary", &pool).await);
+            #[cfg(feature = "metadata")]
+            assert_not_owner(
+                crate::metadata::set_meta("diary", "mood", &serde_json::json!("sad"), &pool).await,
+            );
+            #[cfg(feature = "crypto")]
+            assert_not_owner(crate::protect::protect("diary", "secret", &pool).await);
+        })
+        .await;
+
+        // Shared read-only, note can be read but not changed
+        as_user("alice", share_note("diary", "bob", true, &pool))
+            .await
+            .unwrap();
+        as_user("bob", async {
+            assert_eq!(2, history::history("diary", &pool).await.unwrap().len());
+            assert_read_only(attachments::attach("diary", &path, &pool).await);
+            assert_read_only(due::set_due("diary", due_at, &pool).await);
+            assert_read_only(due::clear_due("diary", &pool).await);
+            #[cfg(feature = "metadata")]
+            assert_read_only(
+                crate::metadata::set_meta("diary", "mood", &serde_json::json!("sad"), &pool).await,
+            );
+            #[cfg(feature = "crypto")]
+            assert_read_only(crate::protect::protect("diary", "secret", &pool).await);
+        })
+        .await;
+        std::fs::remove_file(&path).unwrap();
+
+        let note: (Option<DateTime<Utc>>, Option<String>) = sqlx::query_as(
+            "SELECT due_at, passphrase_hash FROM notebook WHERE note_name = 'diary'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((None, None), note);
+        assert!(attachments::attachments("diary", &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn duplicates_of_other_user_are_skipped(pool: PgPool) {
+        diary_of_alice(&pool).await;
+        as_user(
+            "bob",
+            commands::add("diary copy", "Dear diary\nToday", &pool),
+        )
+        .await
+        .unwrap();
+
+        as_user("bob", async {
+            assert!(dedup::dedup(false, &pool).await.unwrap().is_empty());
+            assert!(dedup::dedup(true, &pool).await.unwrap().is_empty());
+        })
+        .await;
+        assert!(commands::exists("diary", &pool).await.unwrap());
+        assert!(commands::exists("diary copy", &pool).await.unwrap());
+
+        // Read-only share lets `bob` find duplicates, but not delete `diary` when merging
+        as_user("alice", share_note("diary", "bob", true, &pool))
+            .await
+            .unwrap();
+        as_user("bob", async {
+            let groups = dedup::dedup(false, &pool).await.unwrap();
+            assert_eq!("diary", groups[0].kept);
+            assert_eq!(vec!["diary copy".to_owned()], groups[0].duplicates);
+
+            assert!(dedup::dedup(true, &pool).await.unwrap().is_empty());
+        })
+        .await;
+        assert!(commands::exists("diary", &pool).await.unwrap());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn notes_added_as_user_are_owned_by_it(pool: PgPool) {
+        add_user("alice", false, &pool).await.unwrap();
+
+        as_user("alice", async {
+            commands::add("diary", "Dear diary", &pool).await.unwrap();
+            commands::add_binary("photo", vec![0xff], &pool)
+                .await
+                .unwrap();
+            crate::batch::add_many([("plans", Some("travel"))], false, &pool)
+                .await
+                .unwrap();
+        })
+        .await;
+        commands::add("shared", "for everyone", &pool)
+            .await
+            .unwrap();
+
+        for notename in ["diary", "photo", "plans"] {
+            assert_eq!(
+                Some("alice".to_owned()),
+                owner(notename, &pool).await.unwrap()
+            );
+        }
+        assert_eq!(None, owner("shared", &pool).await.unwrap());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn notes_of_other_user_are_not_listed(pool: PgPool) {
+        diary_of_alice(&pool).await;
+        as_user(
+            "alice",
+            commands::add("plans", "Dear me, see [[index]]", &pool),
+        )
+        .await
+        .unwrap();
+        commands::add("index", "Dear reader", &pool).await.unwrap();
+
+        let names = |notes: Vec<commands::Note>| -> Vec<String> {
+            notes.into_iter().map(|note| note.note_name).collect()
+        };
+        as_user("bob", async {
+            let listed = commands::list(&pool).await.unwrap();
+            assert_eq!(
+                vec!["index"],
+                listed
+                    .into_iter()
+                    .map(|note| note.note_name)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                vec!["index"],
+                names(commands::select_all(&pool).await.unwrap())
+            );
+            assert_eq!(
+                vec!["index"],
+                names(commands::select_by_prefix("", &pool).await.unwrap())
+            );
+            assert_eq!(1, commands::count(&pool).await.unwrap());
+            assert_eq!("index", commands::random(&pool).await.unwrap().note_name);
+            assert_eq!(
+                vec!["index"],
+                commands::select_matching(&commands::NamePattern::Glob("*".to_owned()), &pool)
+                    .await
+                    .unwrap()
+            );
+
+            let found = crate::search::search("dear", &pool).await.unwrap();
+            assert_eq!(
+                vec!["index"],
+                found
+                    .into_iter()
+                    .map(|note| note.note_name)
+                    .collect::<Vec<_>>()
+            );
+            let grep = crate::search::grep("Dear", false, &pool).await.unwrap();
+            assert_eq!(
+                vec!["index"],
+                grep.into_iter()
+                    .map(|found| found.note_name)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(1, crate::stats::stats(&pool).await.unwrap().total_notes);
+            assert!(crate::links::backlinks("index", &pool)
+                .await
+                .unwrap()
+                .is_empty());
+
+            assert!(matches!(
+                crate::dump::dump(std::env::temp_dir().join("lnotebook-not-dumped.sql"), &pool)
+                    .await,
+                Err(NotebookError::NotAdmin { .. })
+            ));
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn notes_of_other_user_are_not_changed_in_bulk(pool: PgPool) {
+        diary_of_alice(&pool).await;
+        as_user("alice", commands::add("plans", "Dear me", &pool))
+            .await
+            .unwrap();
+        as_user("alice", share_note("diary", "bob", true, &pool))
+            .await
+            .unwrap();
+        commands::add("index", "Dear reader", &pool).await.unwrap();
+
+        as_user("bob", async {
+            // Notes shared read-only are listed, but not changed
+            assert_eq!(2, commands::count(&pool).await.unwrap());
+
+            let replaced = commands::replace_all("Dear", "Hi", false, &pool)
+                .await
+                .unwrap();
+            assert_eq!(
+                vec!["index"],
+                replaced
+                    .into_iter()
+                    .map(|note| note.note_name)
+                    .collect::<Vec<_>>()
+            );
+            assert!(commands::upd_notename_bulk("^(.*)$", "old $1", &pool)
+                .await
+                .unwrap()
+                .iter()
+                .all(|(old, _)| old == "index"));
+            assert_eq!(vec!["old index"], commands::del_all(&pool).await.unwrap());
+        })
+        .await;
+
+        assert_eq!(
+            "Dear diary\nToday",
+            commands::select_one("diary", &pool).await.unwrap().body()
+        );
+        assert_eq!(
+            "Dear me",
+            commands::select_one("plans", &pool).await.unwrap().body()
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Changes of notes sent by the database.
+//!
+//! Every time note is added, changed or deleted, database sends notification to channel
+//! [`CHANNEL`] (using Postgres `NOTIFY`) with JSON payload that is parsed into [`NoteChange`]:
+//! ```json
+//! {"event": "update", "id": 1, "name": "shopping", "version": 3, "actor": "postgres"}
+//! ```
+//! * `event` - what happened with note, see [`ChangeKind`]
+//! * `id` and `name` - ID and name of note (name after the change)
+//! * `version` - number of revisions of note (read about revisions in [`history` module][crate::history]),
+//!   missing for deleted notes
+//! * `actor` - database user that changed note
+//!
+//! Payload of notification must be shorter than [`MAX_PAYLOAD`] bytes. If it would be longer
+//! (because of very long name), only `event` and `id` are sent with `"refetch": true`,
+//! and note should be read with [`NoteChange::fetch`].
+//!
+//! [`watch`] returns stream of changes, and CLI prints them as they happen with `watch`
+//! command. With `tui` feature terminal user interface also reloads notes when they change.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::watch::*;
+//! # use lnotebook::NotebookError;
+//! # use sqlx::PgPool;
+//! use futures_util::TryStreamExt;
+//!
+//! async fn watch_example(pool: &PgPool) -> Result<(), NotebookError> {
+//!     let mut changes = watch(pool).await?;
+//!
+//!     while let Some(change) = changes.try_next().await? {
+//!         println!("{:?} of note {}", change.event, change.id);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::commands::Note;
+use crate::compression::StoredNote;
+use crate::errors::NotebookError;
+use crate::sql::query_as;
+
+use futures_util::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::{self, postgres::PgListener, PgPool};
+use std::fmt;
+
+/// Channel that changes of notes are sent to.
+pub const CHANNEL: &str = "notebook_changes";
+
+/// Max size of notification payload in bytes, it's limited by Postgres.
+pub const MAX_PAYLOAD: usize = 8000;
+
+/// What happened with note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// This is a `struct` that containing change of note sent by the database.
+///
+/// Read about fields [here][crate::watch].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NoteChange {
+    pub event: ChangeKind,
+    pub id: i32,
+    /// Name of note, `None` if payload was too long
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Number of revisions of note, `None` for deleted notes or if payload was too long
+    #[serde(default)]
+    pub version: Option<i64>,
+    /// Database user that changed note, `None` if payload was too long
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// `true` if payload was too long and note should be read with [`NoteChange::fetch`]
+    #[serde(default)]
+    pub refetch: bool,
+}
+
+impl NoteChange {
+    /// Parses payload of notification.
+    /// ### Returns
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error with [`sqlx::Error::Decode`]
+    ///       if payload is not a valid change
+    /// ### Example
+    /// ```
+    /// # use lnotebook::watch::*;
+    /// let change = NoteChange::parse(r#"{"event": "delete", "id": 7, "refetch": true}"#).unwrap();
+    ///
+    /// assert_eq!(ChangeKind::Delete, change.event);
+    /// assert!(change.refetch);
+    /// ```
+    pub fn parse(payload: &str) -> Result<NoteChange, NotebookError> {
+        serde_json::from_str(payload).map_err(|err| {
+            NotebookError::Sqlx(sqlx::Error::Decode(
+                format!("invalid change of note `{payload}`: {err}").into(),
+            ))
+        })
+    }
+
+    /// Reads the changed note from the database.
+    /// ### Returns
+    /// * Ok
+    ///     * [Note] as it's now, `None` if it was deleted or it's protected (read about
+    ///       protection in [`protect` module][crate::protect])
+    /// * Errors
+    ///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+    pub async fn fetch(&self, pool: &PgPool) -> Result<Option<Note>, NotebookError> {
+        let note = query_as!(
+            StoredNote { id: i32, note_name: String, note: Option<String>, packed: Option<Vec<u8>> },
+            "
+SELECT id, note_name, note, CASE WHEN compressed THEN note_bytes END AS packed
+FROM notebook
+WHERE id = $1 AND passphrase_hash IS NULL
+            ",
+            self.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        note.map(StoredNote::into_note).transpose()
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ChangeKind::Insert => "added",
+            ChangeKind::Update => "updated",
+            ChangeKind::Delete => "deleted",
+        };
+
+        f.pad(kind)
+    }
+}
+
+/// It renders change as `updated  shopping (ID 1, version 3) by postgres`.
+impl fmt::Display for NoteChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<7}  ", self.event)?;
+
+        match (&self.name, self.version) {
+            (Some(name), Some(version)) => write!(f, "{name} (ID {}, version {version})", self.id)?,
+            (Some(name), None) => write!(f, "{name} (ID {})", self.id)?,
+            (None, _) => write!(f, "note with ID {}", self.id)?,
+        }
+
+        if let Some(actor) = &self.actor {
+            write!(f, " by {actor}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns stream of changes of notes made after it was called.
+///
+/// Changes are received with separate connection, which is opened again if it's lost
+/// (changes made while it's lost are not received).
+/// ### Returns
+/// * Ok
+///     * Stream of [`NoteChange`], its items are errors if connection can't be opened again
+///       or notification can't be parsed
+/// * Errors
+///     * [`NotebookError::Sqlx`][NotebookError] error from [`sqlx::Error`]
+///       if connection can't be opened
+pub async fn watch(
+    pool: &PgPool,
+) -> Result<impl Stream<Item = Result<NoteChange, NotebookError>> + Unpin, NotebookError> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    Ok(listener.into_stream().map(|notification| {
+        let notification = notification?;
+        NoteChange::parse(notification.payload())
+    }))
+}
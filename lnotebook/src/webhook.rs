@@ -0,0 +1,271 @@
+//! Webhooks called when notes change (only with `webhooks` feature).
+//!
+//! URLs of webhooks are set in `LNOTEBOOK_WEBHOOKS` enivroment variable separated by commas
+//! (read more in [`config` module][crate::config]). After note is added, updated or deleted
+//! by [`Notebook`][crate::notebook::Notebook] (so by CLI, HTTP server, gRPC service, terminal
+//! interface and replayed [offline queue][crate::offline] too), every webhook gets `POST` request
+//! with JSON body describing the change:
+//! ```json
+//! {"event": "update", "id": 1, "name": "shopping", "command": "append-note", "at": "2024-07-20T18:30:00+03:00"}
+//! ```
+//! * `event` - `insert`, `update` or `delete`, like in [`watch` module][crate::watch]
+//! * `id` and `name` - ID and name of note
+//! * `command` - CLI command that changed note
+//! * `at` - when note was changed, in RFC 3339 format
+//!
+//! Content of note is not sent, webhook can read it with HTTP server of the notebook.
+//! Request that fails (or gets response with status other than `2xx`) is sent again up to
+//! [`ATTEMPTS`] times with growing pauses. Webhooks that still fail are logged and don't make
+//! the command fail.
+//!
+//! Only `http://` URLs are supported, to call HTTPS webhooks (like ones of Slack or Matrix)
+//! use a local proxy that forwards requests to them.
+//! ### Example
+//! ```rust,no_run
+//! # use lnotebook::webhook::*;
+//! # use lnotebook::NotebookError;
+//! async fn webhook_example() -> Result<(), NotebookError> {
+//!     let change = WebhookEvent::new(ChangeKind::Update, 1, "shopping", "upd-note");
+//!
+//!     send("http://127.0.0.1:9000/notes", &change).await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::errors::NotebookError;
+
+use chrono::{DateTime, Local};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, header, Request, Uri};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::{event, Level};
+
+/// How many times request to webhook is sent before it's considered failed.
+pub const ATTEMPTS: u32 = 3;
+
+/// How long one attempt to call webhook can take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What happened with note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// This is a `struct` that containing change of note sent to webhooks.
+///
+/// Read about fields [here][crate::webhook].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebhookEvent {
+    pub event: ChangeKind,
+    pub id: i32,
+    pub name: String,
+    pub command: String,
+    pub at: DateTime<Local>,
+}
+
+impl WebhookEvent {
+    /// Creates change of note made now by `command`.
+    pub fn new(event: ChangeKind, id: i32, name: &str, command: &str) -> WebhookEvent {
+        WebhookEvent {
+            event,
+            id,
+            name: name.to_owned(),
+            command: command.to_owned(),
+            at: Local::now(),
+        }
+    }
+}
+
+fn webhook_error(url: &str, reason: impl std::fmt::Display) -> NotebookError {
+    NotebookError::Webhook {
+        url: url.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Sends `change` to webhook `url` once.
+async fn post(url: &str, uri: &Uri, body: &str) -> Result<(), NotebookError> {
+    let host = uri.host().unwrap_or_default();
+    let port = uri.port_u16().unwrap_or(80);
+
+    let stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|err| webhook_error(url, err))?;
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|err| webhook_error(url, err))?;
+    tokio::spawn(conn);
+
+    let authority = uri
+        .authority()
+        .map(|authority| authority.as_str())
+        .unwrap_or(host);
+    let request = Request::post(uri.path_and_query().map_or("/", |path| path.as_str()))
+        .header(header::HOST, authority)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            header::USER_AGENT,
+            concat!("lnotebook/", env!("CARGO_PKG_VERSION")),
+        )
+        .body(Full::new(Bytes::from(body.to_owned())))
+        .map_err(|err| webhook_error(url, err))?;
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|err| webhook_error(url, err))?;
+    let status = response.status();
+
+    // Response is read, so connection is closed properly
+    let _ = response.into_body().collect().await;
+
+    if !status.is_success() {
+        return Err(webhook_error(url, format!("response status is {status}")));
+    }
+
+    Ok(())
+}
+
+/// Sends `change` to webhook `url`, retrying [`ATTEMPTS`] times.
+/// ### Returns
+/// * Errors
+///     * [`NotebookError::Webhook`] error if URL is not a valid `http://` URL,
+///       or the last attempt failed
+pub async fn send(url: &str, change: &WebhookEvent) -> Result<(), NotebookError> {
+    let uri: Uri = url.parse().map_err(|err| webhook_error(url, err))?;
+    if uri.scheme_str() != Some("http") || uri.host().is_none() {
+        return Err(webhook_error(url, "only `http://` URLs are supported"));
+    }
+
+    let body = serde_json::to_string(change).map_err(|err| webhook_error(url, err))?;
+
+    let mut attempt = 1;
+    loop {
+        let res = match tokio::time::timeout(TIMEOUT, post(url, &uri, &body)).await {
+            Ok(res) => res,
+            Err(_) => Err(webhook_error(url, "timed out")),
+        };
+
+        match res {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt >= ATTEMPTS => return Err(err),
+            Err(err) => {
+                event!(
+                    Level::DEBUG,
+                    "Attempt {} to call webhook failed: {}",
+                    attempt,
+                    err
+                );
+
+                tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt - 1))).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Sends `change` to all webhooks in `urls` at the same time.
+///
+/// Webhooks that fail are logged, so this function never fails.
+pub async fn notify(urls: &[String], change: &WebhookEvent) {
+    let calls = urls.iter().map(|url| async move {
+        if let Err(err) = send(url, change).await {
+            event!(Level::WARN, "{}", err);
+        }
+    });
+
+    futures_util::future::join_all(calls).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands;
+    use crate::notebook::{CommandRequest, Notebook};
+
+    use sqlx::PgPool;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Starts webhook that accepts one request and returns its URL and the body of request.
+    async fn webhook() -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/notes", listener.local_addr().unwrap());
+
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            // Body is JSON object, so request is read until its end
+            while !request.ends_with(b"}") {
+                let read = stream.read(&mut buf).await.unwrap();
+                assert_ne!(0, read, "request was closed before its body");
+                request.extend_from_slice(&buf[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+
+            let request = String::from_utf8(request).unwrap();
+            request.split("\r\n\r\n").nth(1).unwrap().to_owned()
+        });
+
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn change_is_posted() {
+        let (url, received) = webhook().await;
+        let change = WebhookEvent::new(ChangeKind::Update, 1, "shopping", "append-note");
+
+        send(&url, &change).await.unwrap();
+
+        let body: serde_json::Value = serde_json::from_str(&received.await.unwrap()).unwrap();
+        assert_eq!("update", body["event"]);
+        assert_eq!("shopping", body["name"]);
+        assert_eq!("append-note", body["command"]);
+    }
+
+    #[tokio::test]
+    async fn only_http_urls_are_called() {
+        let change = WebhookEvent::new(ChangeKind::Delete, 1, "shopping", "del-note");
+
+        for url in ["https://127.0.0.1/notes", "not a url"] {
+            assert!(matches!(
+                send(url, &change).await,
+                Err(NotebookError::Webhook { .. })
+            ));
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs PostgreSQL in DATABASE_URL"]
+    async fn conditional_updates_are_posted(pool: PgPool) {
+        // Server, terminal interface and offline queue update notes only if they weren't changed
+        let (url, received) = webhook().await;
+        commands::add("shopping", "milk", &pool).await.unwrap();
+        let notebook = Notebook::new(pool).with_webhooks(vec![url]);
+
+        notebook
+            .execute(CommandRequest::UpdIfUnchanged {
+                notename: "shopping".to_owned(),
+                base_hash: commands::hash_content("milk"),
+                note: "milk\neggs".to_owned(),
+            })
+            .await
+            .unwrap();
+
+        let body: serde_json::Value = serde_json::from_str(&received.await.unwrap()).unwrap();
+        assert_eq!("update", body["event"]);
+        assert_eq!("upd-note", body["command"]);
+    }
+}
@@ -1,36 +1,56 @@
 //! # Notebook_example
 //! `Notebook_example` is simple example of use [`LNotebook`][crate].
 
-use anyhow;
-use sqlx::{self, PgPool};
-use tokio;
-
 use tracing::{event, Level};
-use tracing_subscriber::{fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter, Layer as _,
+};
 
-use lnotebook::{get_db_url, NoteCommand};
+use lnotebook::{connect, get_db_url, metrics::MetricsLayer, NoteCommand, Verbosity};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // Converting CLI command to `enum` and save it in `NoteCommand`
+    // (help and invalid commands are printed by `clap` before exit)
+    let c = NoteCommand::new().await.unwrap_or_else(|err| err.exit());
+
+    // With `--script` and `--quiet` only results of command are printed, so logging is disabled
+    let level = match c.verbosity() {
+        Verbosity::Quiet => None,
+        Verbosity::Normal => Some("info"),
+        Verbosity::Verbose => Some("debug"),
+    };
+    // Filter is added only to logging, so `MetricsLayer` gets all SQL queries for `--timing`
     tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::new("debug"))
+        .with(level.map(|level| fmt::layer().with_filter(EnvFilter::new(level))))
+        .with(MetricsLayer)
         .init();
 
     // Get database URL from enivroment variable
     let db_url = get_db_url().await?;
 
-    // Connecting to database
-    let db = PgPool::connect(&db_url).await?;
+    // Connecting to database, retrying if it's temporarily unavailable
+    let db = match connect(&db_url, c.connect_options()).await {
+        Ok(db) => db,
+        // Changes can be queued while database is unreachable
+        // (read about it in `lnotebook::offline` module)
+        Err(err) => {
+            c.execute_offline(err).await?;
+            return Ok(());
+        }
+    };
 
     event!(Level::INFO, "Connect to db");
 
-    // Converting CLI command to `enum` and save it in `NoteCommand`
-    let c = NoteCommand::new().await?;
     // Execute the selected command
-    c.execute_command(&db).await?;
+    let response = c.execute_command(&db).await?;
 
     event!(Level::INFO, "Command executed");
 
+    // For example `exists` exits with code 1 if note doesn't exist
+    if response.exit_code() != 0 {
+        std::process::exit(response.exit_code());
+    }
+
     Ok(())
 }